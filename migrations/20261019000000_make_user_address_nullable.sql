@@ -0,0 +1,2 @@
+-- An address is optional, PATCH clears it with an explicit null.
+ALTER TABLE user_system ALTER COLUMN address DROP NOT NULL;
@@ -0,0 +1,3 @@
+-- Bumped on every write, exposed as the ETag checked against If-Match.
+ALTER TABLE category ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE user_system ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
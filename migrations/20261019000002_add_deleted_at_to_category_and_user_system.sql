@@ -0,0 +1,3 @@
+-- Soft delete, rows stay for reporting until the purge job removes them after the retention period.
+ALTER TABLE category ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE user_system ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
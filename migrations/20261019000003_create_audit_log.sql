@@ -0,0 +1,15 @@
+-- Who changed what, written by the handlers after every successful mutation and listed by /api/audit.
+CREATE TABLE IF NOT EXISTS audit_log (
+	id SERIAL PRIMARY KEY,
+	actor_id INTEGER,
+	actor_username VARCHAR(255),
+	action VARCHAR(50) NOT NULL,
+	entity_type VARCHAR(50) NOT NULL,
+	entity_id VARCHAR(255),
+	changes JSONB NOT NULL DEFAULT '{}',
+	ip_address VARCHAR(64),
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_entity_idx ON audit_log (entity_type, entity_id);
+CREATE INDEX IF NOT EXISTS audit_log_created_at_idx ON audit_log (created_at);
@@ -0,0 +1,14 @@
+-- Responses of POST requests sent with an Idempotency-Key, replayed when a client retries within the TTL.
+CREATE TABLE IF NOT EXISTS idempotency_key (
+	user_id INTEGER NOT NULL,
+	key VARCHAR(255) NOT NULL,
+	request_hash CHAR(64) NOT NULL,
+	-- NULL until the first request has been answered.
+	response_status INTEGER,
+	response_headers JSONB NOT NULL DEFAULT '[]',
+	response_body BYTEA,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	PRIMARY KEY (user_id, key)
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_key_created_at_idx ON idempotency_key (created_at);
@@ -0,0 +1,4 @@
+-- Nested categories, e.g. Minuman > Kopi > Kemasan Botol. Purging a parent turns its children into roots.
+ALTER TABLE category ADD COLUMN IF NOT EXISTS parent_id INTEGER REFERENCES category(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS category_parent_id_idx ON category (parent_id);
@@ -0,0 +1,15 @@
+-- URL slug, manual display order for the POS touchscreen and an optional icon stored under uploads/category.
+ALTER TABLE category ADD COLUMN IF NOT EXISTS slug VARCHAR(120);
+ALTER TABLE category ADD COLUMN IF NOT EXISTS sort_order INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE category ADD COLUMN IF NOT EXISTS icon VARCHAR(255);
+
+-- Existing rows get an ASCII slug, the application spells out symbols and folds accents for new ones.
+UPDATE category SET slug = COALESCE(NULLIF(TRIM(BOTH '-' FROM LOWER(REGEXP_REPLACE(name, '[^a-zA-Z0-9]+', '-', 'g'))), ''), 'kategori')
+WHERE slug IS NULL;
+
+UPDATE category SET slug = category.slug || '-' || category.id
+FROM (SELECT id, ROW_NUMBER() OVER (PARTITION BY slug ORDER BY id) AS position FROM category) duplicate
+WHERE duplicate.id = category.id AND duplicate.position > 1;
+
+ALTER TABLE category ALTER COLUMN slug SET NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS category_slug_idx ON category (slug);
@@ -0,0 +1,13 @@
+-- Live category names are unique ignoring case and whitespace, "Minuman" and " minuman " are the same category.
+UPDATE category SET name = BTRIM(REGEXP_REPLACE(name, '\s+', ' ', 'g'))
+WHERE name <> BTRIM(REGEXP_REPLACE(name, '\s+', ' ', 'g'));
+
+-- Existing duplicates keep their rows under a suffixed name, merge them afterwards through the merge route.
+UPDATE category SET name = category.name || ' (' || category.id || ')'
+FROM (
+	SELECT id, ROW_NUMBER() OVER (PARTITION BY LOWER(name) ORDER BY id) AS position FROM category WHERE deleted_at IS NULL
+) duplicate
+WHERE duplicate.id = category.id AND duplicate.position > 1;
+
+CREATE UNIQUE INDEX IF NOT EXISTS category_name_unique_idx ON category (LOWER(BTRIM(REGEXP_REPLACE(name, '\s+', ' ', 'g'))))
+WHERE deleted_at IS NULL;
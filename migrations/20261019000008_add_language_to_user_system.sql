@@ -0,0 +1,2 @@
+-- Interface language each user picked for themselves, Indonesian unless changed through /api/user/me.
+ALTER TABLE user_system ADD COLUMN IF NOT EXISTS language VARCHAR(10) NOT NULL DEFAULT 'id';
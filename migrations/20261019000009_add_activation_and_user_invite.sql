@@ -0,0 +1,28 @@
+-- Deactivated users can neither log in nor use a token issued before, invited users stay inactive until they set a password.
+ALTER TABLE user_system ADD COLUMN IF NOT EXISTS is_active BOOLEAN NOT NULL DEFAULT TRUE;
+-- NULL while an invite is pending, users created with a password are activated right away.
+ALTER TABLE user_system ADD COLUMN IF NOT EXISTS activated_at TIMESTAMPTZ DEFAULT NOW();
+UPDATE user_system SET activated_at = created_at WHERE activated_at IS NULL;
+
+-- One-time activation links, only the SHA-256 of the token is stored.
+CREATE TABLE IF NOT EXISTS user_invite (
+	id SERIAL PRIMARY KEY,
+	user_id INTEGER NOT NULL REFERENCES user_system (id) ON DELETE CASCADE,
+	token_hash CHAR(64) NOT NULL UNIQUE,
+	expires_at TIMESTAMPTZ NOT NULL,
+	-- Set once the link was used, a used link is never accepted again.
+	used_at TIMESTAMPTZ,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS user_invite_user_id_idx ON user_invite (user_id);
+
+-- Live usernames are unique, the username of a soft-deleted user may be taken again. Existing duplicates keep their
+-- rows under a suffixed username.
+UPDATE user_system SET username = user_system.username || '_' || user_system.id
+FROM (
+	SELECT id, ROW_NUMBER() OVER (PARTITION BY username ORDER BY id) AS position FROM user_system WHERE deleted_at IS NULL
+) duplicate
+WHERE duplicate.id = user_system.id AND duplicate.position > 1;
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_system_username_unique_idx ON user_system (username) WHERE deleted_at IS NULL;
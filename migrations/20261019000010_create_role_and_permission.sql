@@ -0,0 +1,57 @@
+-- Roles are rows now, `user_system.role` names one of them and follows renames.
+CREATE TABLE IF NOT EXISTS role (
+	id SERIAL PRIMARY KEY,
+	-- As wide as `user_system.role`, whose values are copied below. New names are kept shorter by the server.
+	name VARCHAR(255) NOT NULL UNIQUE,
+	description TEXT,
+	-- Built-in roles cannot be renamed or deleted, `admin` is granted every permission.
+	is_system BOOLEAN NOT NULL DEFAULT FALSE,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	version INTEGER NOT NULL DEFAULT 1
+);
+
+-- Catalog of what can be granted, codes are checked by the server so new ones only arrive with a migration.
+CREATE TABLE IF NOT EXISTS permission (
+	code VARCHAR(100) PRIMARY KEY,
+	description TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS role_permission (
+	role_id INTEGER NOT NULL REFERENCES role (id) ON DELETE CASCADE,
+	permission_code VARCHAR(100) NOT NULL REFERENCES permission (code) ON DELETE CASCADE,
+	PRIMARY KEY (role_id, permission_code)
+);
+
+INSERT INTO permission (code, description) VALUES
+	('category.create', 'Create categories'),
+	('category.update', 'Edit, move and reorder categories'),
+	('category.delete', 'Delete categories'),
+	('category.manage', 'Restore, merge, import and export categories and list deleted ones'),
+	('user.create', 'Create users'),
+	('user.update', 'Edit users'),
+	('user.delete', 'Delete users'),
+	('user.manage', 'Read any user, change usernames and roles, invite, deactivate, restore, import and export users'),
+	('audit.view', 'Read the audit log'),
+	('role.manage', 'Create roles and assign permissions'),
+	('sale.void', 'Void a completed sale'),
+	('report.view', 'Read sales reports')
+ON CONFLICT (code) DO NOTHING;
+
+INSERT INTO role (name, description, is_system) VALUES
+	('admin', 'Full access', TRUE),
+	('cashier', 'Front counter staff', TRUE)
+ON CONFLICT (name) DO NOTHING;
+
+-- Every role already assigned to a user becomes a row, without permissions unless it is one of the above.
+INSERT INTO role (name) SELECT DISTINCT role FROM user_system ON CONFLICT (name) DO NOTHING;
+
+-- Cashiers keep managing categories as before roles had a meaning. Managing users is left to roles granted it
+-- explicitly, with `user.*` a cashier could create an admin or take over another account.
+INSERT INTO role_permission (role_id, permission_code)
+SELECT role.id, permission.code FROM role, permission
+WHERE role.name = 'cashier' AND permission.code IN ('category.create', 'category.update', 'category.delete')
+ON CONFLICT DO NOTHING;
+
+ALTER TABLE user_system DROP CONSTRAINT IF EXISTS user_system_role_fkey;
+ALTER TABLE user_system ADD CONSTRAINT user_system_role_fkey FOREIGN KEY (role) REFERENCES role (name) ON UPDATE CASCADE;
@@ -0,0 +1,79 @@
+-- Uploads are stored once per content, named after the SHA-256 of the stored bytes. `ref_count` follows the columns
+-- holding file names through the triggers below, objects left unreferenced past a grace period are removed with their
+-- blobs by the purge job. Files uploaded before this table existed have no row and are never removed.
+CREATE TABLE IF NOT EXISTS file_object (
+	-- Key prefix in the storage, `user` or `category`, each has its own variant sizes.
+	prefix VARCHAR(32) NOT NULL,
+	-- `{sha256}.{extension}`, returned by the upload and stored as is by the referencing columns.
+	file_name VARCHAR(80) NOT NULL,
+	sha256 CHAR(64) NOT NULL,
+	content_type VARCHAR(100) NOT NULL,
+	size BIGINT NOT NULL,
+	ref_count INTEGER NOT NULL DEFAULT 0 CHECK (ref_count >= 0),
+	created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+	-- When `ref_count` last dropped to zero or the content was last uploaded again, NULL while referenced.
+	unreferenced_since TIMESTAMPTZ DEFAULT NOW(),
+	PRIMARY KEY (prefix, file_name)
+);
+
+CREATE INDEX IF NOT EXISTS file_object_unreferenced_since_idx ON file_object (unreferenced_since) WHERE ref_count = 0;
+
+-- Moves one reference from `old_name` to `new_name`, either may be NULL or a name without a row such as
+-- `default_user.png`. Tables storing file names, product images included, call it from their own trigger.
+-- A content-addressed `new_name` must have a row: the purge job may have removed it while the write waited on its
+-- lock, and the write fails instead of referencing a blob that is gone.
+CREATE OR REPLACE FUNCTION file_object_move_reference(file_prefix TEXT, old_name TEXT, new_name TEXT) RETURNS VOID AS $$
+BEGIN
+	IF old_name IS NOT DISTINCT FROM new_name THEN
+		RETURN;
+	END IF;
+
+	UPDATE file_object SET ref_count = ref_count - 1, unreferenced_since = CASE WHEN ref_count = 1 THEN NOW() END
+	WHERE prefix = file_prefix AND file_name = old_name AND ref_count > 0;
+
+	UPDATE file_object SET ref_count = ref_count + 1, unreferenced_since = NULL
+	WHERE prefix = file_prefix AND file_name = new_name;
+
+	IF NOT FOUND AND new_name ~ '^[0-9a-f]{64}\.' THEN
+		RAISE EXCEPTION 'File ''%'' Belum Diupload Atau Sudah Dihapus, Upload Ulang Lalu Coba Lagi.', new_name
+		USING ERRCODE = 'foreign_key_violation', CONSTRAINT = 'file_object_reference';
+	END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Soft-deleted rows keep their reference, they may be restored, purging them releases it.
+CREATE OR REPLACE FUNCTION user_system_photo_reference() RETURNS TRIGGER AS $$
+BEGIN
+	IF TG_OP = 'INSERT' THEN
+		PERFORM file_object_move_reference('user', NULL, NEW.photo);
+	ELSIF TG_OP = 'UPDATE' THEN
+		PERFORM file_object_move_reference('user', OLD.photo, NEW.photo);
+	ELSE
+		PERFORM file_object_move_reference('user', OLD.photo, NULL);
+	END IF;
+
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS user_system_photo_reference ON user_system;
+CREATE TRIGGER user_system_photo_reference AFTER INSERT OR UPDATE OF photo OR DELETE ON user_system
+FOR EACH ROW EXECUTE FUNCTION user_system_photo_reference();
+
+CREATE OR REPLACE FUNCTION category_icon_reference() RETURNS TRIGGER AS $$
+BEGIN
+	IF TG_OP = 'INSERT' THEN
+		PERFORM file_object_move_reference('category', NULL, NEW.icon);
+	ELSIF TG_OP = 'UPDATE' THEN
+		PERFORM file_object_move_reference('category', OLD.icon, NEW.icon);
+	ELSE
+		PERFORM file_object_move_reference('category', OLD.icon, NULL);
+	END IF;
+
+	RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS category_icon_reference ON category;
+CREATE TRIGGER category_icon_reference AFTER INSERT OR UPDATE OF icon OR DELETE ON category
+FOR EACH ROW EXECUTE FUNCTION category_icon_reference();
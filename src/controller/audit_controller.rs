@@ -0,0 +1,58 @@
+use axum::{
+	extract::State,
+	http::StatusCode
+};
+
+use serde_json::json;
+
+use crate::model::audit_model::{ AuditData, AuditPaginate, AUDIT_SORT_FIELDS, AUDIT_FILTER_FIELDS };
+use crate::model::utils_model::{ ErrorResponse, PaginateResponse, PaginationBody, PaginationResponse };
+use crate::state::DynAuditRepository;
+use crate::utils::extractor::Query;
+use crate::utils::pagination::PageRequest;
+
+#[utoipa::path(
+	get,
+	path = "/api/audit",
+	tag = "Audit",
+	params(PaginationBody),
+	responses(
+		(status = 200, description = "Paginated audit events whose actor username or entity id match the term and filters, newest last unless sort_dir is desc", body = PaginateResponse<AuditData>),
+		(status = 400, description = "Invalid page, per_page, sort_by, cursor or filter", body = ErrorResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks audit.view", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn search_paginate(
+	State(audit_repository): State<DynAuditRepository>,
+	Query(body): Query<PaginationBody>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let page_request = PageRequest::new(body, AUDIT_SORT_FIELDS, AUDIT_FILTER_FIELDS)
+	.map_err(|message| {
+		(
+			StatusCode::BAD_REQUEST,
+			json!({ "success": false, "message": message }).to_string()
+		)
+	})?;
+
+	let query_search = audit_repository.search_paginate(&page_request)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let pagination_response = AuditPaginate {
+		paginate: PaginationResponse::new(&page_request, &query_search),
+		data: query_search.data
+	};
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "data": pagination_response.data, "paginate": pagination_response.paginate }).to_string()
+	))
+}
@@ -1,32 +1,49 @@
 use axum:: {
 	extract::State,
-	http:: {HeaderMap, StatusCode},
-	Json
+	http::StatusCode,
+	Extension
 };
 
-use sqlx::{postgres::PgPool, query, query_as};
 use serde_json::json;
 
 use bcrypt::{hash, verify, DEFAULT_COST};
 
-use jsonwebtoken::{ decode, encode, DecodingKey, EncodingKey, Header, Validation };
+use jsonwebtoken::{ encode, EncodingKey, Header };
 
-use crate::model::auth_model::{ LoginBody, ChangePasswordBody };
-use crate::model::user_model::{ UserData, JwtClaims };
+use crate::model::auth_model::{ ActivateBody, AuthResponse, LoginBody, ChangePasswordBody };
+use crate::model::user_model::{ UserData, UserView, JwtClaims };
+use crate::model::utils_model::{ ErrorResponse, MessageResponse, ValidationErrorResponse };
 
+use crate::repository::RepositoryError;
+use crate::state::DynUserRepository;
+use crate::utils::audit::Audit;
+use crate::utils::extractor::ValidatedJson;
+use crate::utils::route_guard::INACTIVE_MESSAGE;
+use crate::utils::token::token_hash;
 use crate::utils::utils::JWT_SECRET;
 
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use time::OffsetDateTime;
+
+#[utoipa::path(
+	post,
+	path = "/api/auth/login",
+	tag = "Auth",
+	request_body = LoginBody,
+	responses(
+		(status = 202, description = "Credentials accepted, returns the user and a bearer token", body = AuthResponse),
+		(status = 400, description = "Unknown username", body = ErrorResponse),
+		(status = 401, description = "Wrong password", body = ErrorResponse),
+		(status = 403, description = "The user was deactivated", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field", body = ValidationErrorResponse)
+	)
+)]
 pub async fn login(
-	State(pg_pool): State<PgPool>,
-	Json(body): Json<LoginBody>
+	State(user_repository): State<DynUserRepository>,
+	ValidatedJson(body): ValidatedJson<LoginBody>
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-	let query_find_first = sqlx::query_as!(
-		UserData,
-		"SELECT * FROM user_system WHERE username = $1 LIMIT 1",
-		body.username
-	).fetch_one(&pg_pool)
+	let query_find_first = user_repository.find_by_username(&body.username)
 	.await
 	.map_err(|_| {
 		(
@@ -35,116 +52,152 @@ pub async fn login(
 		)
 	})?;
 
-	let compared_password = verify(&body.password, &query_find_first.password);
+	// Invited users have an empty hash that fails to parse, they count as a wrong password until activated.
+	let compared_password = verify(&body.password, &query_find_first.password).unwrap_or(false);
 
-	if compared_password.unwrap() == false {
+	if !compared_password {
 		Err(
 			(
 				StatusCode::UNAUTHORIZED,
 				json!({ "success": false, "message": "Data User Tidak Ditemukan." }).to_string()
 			)
 		)
+	} else if !query_find_first.is_active {
+		Err((
+			StatusCode::FORBIDDEN,
+			json!({ "success": false, "message": INACTIVE_MESSAGE }).to_string()
+		))
 	} else {
-	    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-        let user_data_clone = query_find_first.clone();
-
-        let jwt_claim = JwtClaims {
-        	user_data: UserData {
-        		password: String::from(""),
-        		..user_data_clone
-        	},
-        	exp: (now + 360000) as usize
-        };
-
-		let jwt_token = encode(&Header::default(), &jwt_claim, &EncodingKey::from_secret(JWT_SECRET.as_ref()))
-		.expect("Failed to Create Token");
-
 		Ok((
 			StatusCode::ACCEPTED,
-			json!({ "success": true, "data": query_find_first, "token": jwt_token }).to_string()
+			json!({ "success": true, "data": UserView::from(&query_find_first), "token": token(&query_find_first, 360000) }).to_string()
 		))
 	}
 }
 
+#[utoipa::path(
+	post,
+	path = "/api/auth/authenticated",
+	tag = "Auth",
+	responses(
+		(status = 200, description = "Token is valid, returns the user and a refreshed token", body = AuthResponse),
+		(status = 401, description = "Missing, invalid or expired token", body = ErrorResponse),
+		(status = 404, description = "The user of the token was deleted", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
 pub async fn authenticated(
-	headers: HeaderMap
+	State(user_repository): State<DynUserRepository>,
+	Extension(claims): Extension<JwtClaims>
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-	if let Some(auth_header) = headers.get("Authorization") {
-		if let Ok(header_value) = auth_header.to_str() {
-			
-			if let Some(jwt_token) = header_value.strip_prefix("Bearer ") {
-
-			    let now = SystemTime::now()
-		        .duration_since(UNIX_EPOCH)
-		        .unwrap()
-		        .as_secs();
-
-		        match decode::<JwtClaims>(
-		        	jwt_token, 
-		        	&DecodingKey::from_secret(JWT_SECRET.as_ref()), 
-		        	&Validation::default()
-		        ) {
-		            Ok(value) =>{
-		            	let jwt_claim = JwtClaims{
-		            		user_data: UserData {
-		            			password: String::from(""),
-		            			..value.claims.user_data.clone()
-		            		},
-		            		exp: (now + 36000000)as usize
-		            	};
-
-    	    			let jwt_token = encode(&Header::default(), &jwt_claim, &EncodingKey::from_secret(JWT_SECRET.as_ref()))
-						.expect("Failed to Create Token");
-
-						Ok((
-							StatusCode::OK,
-							json!({ "success": true,  "data": value.claims.user_data, "token": jwt_token }).to_string()
-						))
-		            },
-		            Err(_) => Err((
-						StatusCode::UNAUTHORIZED,
-						json!({ "success": false, "message": "Session Was Expired" }).to_string()
-					))
-		        }
-
-			} else {
-				Err((
-					StatusCode::UNAUTHORIZED,
-					json!({ "success": false, "message": "Failed to Strip Bearer Prefix" }).to_string()
-				))
-			}
-
-		} else {
-			Err((
-				StatusCode::UNAUTHORIZED,
-				json!({ "success": false, "message": "Invalid Credentials" }).to_string()
-			))
-		}
-	} else {
-		Err((
-			StatusCode::UNAUTHORIZED,
-			json!({ "success": false, "message": "Invalid Credentials" }).to_string()
-		))
+	// The token only carries the id, role and name, the rest of the user is read fresh.
+	let query_find_first = user_repository.find_by_id(claims.id)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "data": UserView::from(&query_find_first), "token": token(&query_find_first, 36000000) }).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/auth/activate",
+	tag = "Auth",
+	request_body = ActivateBody,
+	responses(
+		(status = 200, description = "Password set and user activated, returns the user and a bearer token", body = AuthResponse),
+		(status = 404, description = "No invite has this token", body = ErrorResponse),
+		(status = 410, description = "The invite was already used or has expired, an admin can resend it", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field", body = ValidationErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	)
+)]
+pub async fn activate(
+	State(user_repository): State<DynUserRepository>,
+	audit: Audit,
+	ValidatedJson(body): ValidatedJson<ActivateBody>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let token_hash = token_hash(&body.token);
+
+	let invite = user_repository.find_invite(&token_hash)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": "Link Aktivasi Tidak Valid." }).to_string()
+		)
+	})?;
+
+	let gone = |message: &str| (StatusCode::GONE, json!({ "success": false, "message": message }).to_string());
+
+	if invite.used_at.is_some() {
+		return Err(gone("Link Aktivasi Sudah Dipakai."));
 	}
+	if invite.expires_at <= OffsetDateTime::now_utc() {
+		return Err(gone("Link Aktivasi Sudah Kedaluwarsa, Minta Admin Mengirim Ulang Undangan."));
+	}
+
+	let before = user_repository.find_by_id(invite.user_id).await.ok().map(|user| user.audit_snapshot());
+	let password_hash = hash(&body.password, DEFAULT_COST).unwrap();
+
+	// Another request may have used or replaced the link since it was read.
+	let query_update = user_repository.accept_invite(&token_hash, &password_hash)
+	.await
+	.map_err(|e| match e {
+		RepositoryError::NotFound => gone("Link Aktivasi Sudah Tidak Berlaku."),
+		e => (e.status_code(), json!({ "success": false, "message": e.to_string() }).to_string())
+	})?;
+
+	audit.record("activate", "user", query_update.id, before.as_ref(), Some(&query_update.audit_snapshot())).await;
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "data": UserView::from(&query_update), "token": token(&query_update, 360000) }).to_string()
+	))
 }
 
+/// Signed bearer token for `user` valid for `lifetime` seconds.
+fn token(user: &UserData, lifetime: u64) -> String {
+	let now = SystemTime::now()
+	.duration_since(UNIX_EPOCH)
+	.unwrap()
+	.as_secs();
+
+	encode(&Header::default(), &JwtClaims::new(user, (now + lifetime) as usize), &EncodingKey::from_secret(JWT_SECRET.as_ref()))
+	.expect("Failed to Create Token")
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/auth/change-password",
+	tag = "Auth",
+	request_body = ChangePasswordBody,
+	responses(
+		(status = 200, description = "Password changed", body = MessageResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Old password does not match", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field", body = ValidationErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
 pub async fn change_password(
-	State(pg_pool): State<PgPool>,
-	Json(body): Json<ChangePasswordBody>
+	State(user_repository): State<DynUserRepository>,
+	audit: Audit,
+	ValidatedJson(body): ValidatedJson<ChangePasswordBody>
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-	let query_find_first = query_as!(
-		UserData,
-		"SELECT * FROM user_system WHERE username = $1",
-		body.username
-	).fetch_one(&pg_pool)
+	let query_find_first = user_repository.find_by_username(&body.username)
 	.await
 	.map_err(|e| {
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status_code(),
             json!({ "success": false, "message": e.to_string() }).to_string()
         )
 	})?;
@@ -153,22 +206,21 @@ pub async fn change_password(
 
 	match compare_password {
 	    Ok(value) => {
-	    	if value == true {
+	    	if value {
 	    		let new_password = hash(body.new_password, DEFAULT_COST).unwrap();
 
-	    		query!(
-	    			"UPDATE user_system SET password = $1 WHERE username = $2",
-	    			new_password,
-	    			body.username
-	    		).execute(&pg_pool)
+	    		user_repository.update_password(&body.username, &new_password)
 	    		.await
 	    		.map_err(|e| {
 			        (
-			            StatusCode::INTERNAL_SERVER_ERROR,
+			            e.status_code(),
 			            json!({ "success": false, "message": e.to_string() }).to_string()
 			        )
 	    		})?;
 
+	    		let after = user_repository.find_by_username(&body.username).await.ok().map(|user| user.audit_snapshot());
+	    		audit.record("change_password", "user", query_find_first.id, Some(&query_find_first.audit_snapshot()), after.as_ref()).await;
+
 		        Ok(
 		        	(
 			            StatusCode::OK,
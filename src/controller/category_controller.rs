@@ -1,54 +1,103 @@
+use std::collections::{ BTreeMap, HashMap, HashSet };
+
 use axum::{
-	extract::{Path, State},
-	http::StatusCode,
-	Json
+	extract::{ Multipart, State },
+	http::{ header, HeaderName, StatusCode },
+	response::Response,
+	Extension
 };
 
-use sqlx::postgres::PgPool;
 use serde_json::json;
+use validator::Validate;
 
-use crate::model::category_model::{ CategoryCreateBody, CategoryData, CategoryUpdateBody, CategoryPaginate };
-use crate::model::utils_model::{ PaginationBody, PaginationResponse };
+use crate::model::category_model::{ CategoryCreateBody, CategoryData, CategoryImportRow, CategoryMergeBody, CategoryMoveBody, CategoryNode, CategoryReorderBody, CategoryReplaceBody, CategoryUpdateBody, CategoryPaginate, CategorySubtreeQuery, CATEGORY_EXPORT_COLUMNS, CATEGORY_SORT_FIELDS, CATEGORY_FILTER_FIELDS };
+use crate::model::utils_model::{ DataResponse, ErrorResponse, ImportReport, ImportResponse, ListQuery, MessageResponse, PaginateResponse, PaginationBody, PaginationResponse, ValidationErrorResponse };
+use crate::state::DynCategoryRepository;
+use crate::controller::file_controller::category_icon_exists;
+use crate::storage::DynStorage;
+use crate::utils::audit::Audit;
+use crate::utils::extractor::{ etag, field_errors, IfMatch, Json, Path, Query, ValidatedJson };
+use crate::utils::pagination::PageRequest;
+use crate::utils::permission::Permissions;
+use crate::utils::tabular::{ self, push_row_error, ExportQuery, ImportQuery };
 
-pub async fn search_paginate(State(pg_pool): State<PgPool>,
+#[utoipa::path(
+	post,
+	path = "/api/category/search-paginate",
+	tag = "Category",
+	params(CategorySubtreeQuery),
+	request_body = PaginationBody,
+	responses(
+		(status = 200, description = "Paginated categories matching the term", body = PaginateResponse<CategoryData>),
+		(status = 400, description = "Invalid page, per_page, sort_by, cursor or filter", body = ErrorResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "include_deleted requested without category.manage", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn search_paginate(
+	State(category_repository): State<DynCategoryRepository>,
+	Extension(permissions): Extension<Permissions>,
+	Query(subtree): Query<CategorySubtreeQuery>,
 	Json(body): Json<PaginationBody>
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-	const PAGE_TAKE: i64 = 10;
+	paginate(category_repository, &permissions, body, subtree.subtree_of).await
+}
 
-	let query_count: i64 = sqlx::query_scalar(
-		"SELECT COUNT(id) from category"
-	).fetch_one(&pg_pool)
-	.await
-	.map_err(|e| {
+#[utoipa::path(
+	get,
+	path = "/api/category/search-paginate",
+	tag = "Category",
+	params(PaginationBody, CategorySubtreeQuery),
+	responses(
+		(status = 200, description = "Paginated categories matching the term and filters", body = PaginateResponse<CategoryData>),
+		(status = 400, description = "Invalid page, per_page, sort_by, cursor or filter", body = ErrorResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "include_deleted requested without category.manage", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn filter_paginate(
+	State(category_repository): State<DynCategoryRepository>,
+	Extension(permissions): Extension<Permissions>,
+	Query(subtree): Query<CategorySubtreeQuery>,
+	Query(body): Query<PaginationBody>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	paginate(category_repository, &permissions, body, subtree.subtree_of).await
+}
+
+async fn paginate(
+	category_repository: DynCategoryRepository,
+	permissions: &Permissions,
+	body: PaginationBody,
+	subtree_of: Option<i32>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	if body.include_deleted {
+		permissions.require("category.manage")?;
+	}
+
+	let page_request = PageRequest::new(body, CATEGORY_SORT_FIELDS, CATEGORY_FILTER_FIELDS)
+	.map_err(|message| {
 		(
-			StatusCode::INTERNAL_SERVER_ERROR,
-			json!({ "success": false, "message": e.to_string() }).to_string()
+			StatusCode::BAD_REQUEST,
+			json!({ "success": false, "message": message }).to_string()
 		)
 	})?;
 
-	let query_search = sqlx::query_as!(
-		CategoryData,
-		"SELECT * FROM category WHERE name ILIKE $1 LIMIT($2) OFFSET($3)",
-		format!("%{}%", body.term),
-		PAGE_TAKE,
-		(&body.page - 1) * PAGE_TAKE
-	).fetch_all(&pg_pool)
+	let query_search = category_repository.search_paginate(&page_request, subtree_of)
 	.await
 	.map_err(|e| {
 		(
-			StatusCode::INTERNAL_SERVER_ERROR,
+			e.status_code(),
 			json!({ "success": false, "message": e.to_string() }).to_string()
 		)
 	})?;
 
 	let pagination_response = CategoryPaginate {
-		data: query_search,
-		paginate: PaginationResponse {
-			per_page: PAGE_TAKE,
-			total_page: ((query_count as f64 / PAGE_TAKE as f64) * 0.4).round() as i64,
-			count: query_count,
-			current_page: body.page
-		}
+		paginate: PaginationResponse::new(&page_request, &query_search),
+		data: query_search.data
 	};
 
 	Ok((
@@ -57,16 +106,33 @@ pub async fn search_paginate(State(pg_pool): State<PgPool>,
 	))
 }
 
+#[utoipa::path(
+	get,
+	path = "/api/category",
+	tag = "Category",
+	params(ListQuery),
+	responses(
+		(status = 200, description = "Every category ordered by sort_order, then by name", body = DataResponse<Vec<CategoryData>>),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "include_deleted requested without category.manage", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn find_many(
+	State(category_repository): State<DynCategoryRepository>,
+	Extension(permissions): Extension<Permissions>,
+	Query(query): Query<ListQuery>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	if query.include_deleted {
+		permissions.require("category.manage")?;
+	}
 
-pub async fn find_many(State(pg_pool): State<PgPool>) -> Result<(StatusCode, String), (StatusCode, String)> {
-	let query_find_many = sqlx::query_as!(
-		CategoryData, 
-		"SELECT * FROM category ORDER BY name ASC"
-	).fetch_all(&pg_pool)
+	let query_find_many = category_repository.find_many(query.include_deleted)
 	.await
 	.map_err(|e| {
 		(
-			StatusCode::INTERNAL_SERVER_ERROR,
+			e.status_code(),
 			json!({ "success": false, "message": e.to_string()}).to_string()
 		)
 	})?;
@@ -78,22 +144,135 @@ pub async fn find_many(State(pg_pool): State<PgPool>) -> Result<(StatusCode, Str
 	))
 }
 
+#[utoipa::path(
+	get,
+	path = "/api/category/tree",
+	tag = "Category",
+	responses(
+		(status = 200, description = "Live categories nested under their parents, siblings ordered by sort_order then name", body = DataResponse<Vec<CategoryNode>>),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn tree(
+	State(category_repository): State<DynCategoryRepository>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let query_find_many = category_repository.find_many(false)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "data": CategoryNode::tree(query_find_many) }).to_string()
+	))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/category/{id}/breadcrumbs",
+	tag = "Category",
+	params(("id" = i32, Path, description = "Category id")),
+	responses(
+		(status = 200, description = "The category and its ancestors, top-level category first", body = DataResponse<Vec<CategoryData>>),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 404, description = "Category not found", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn breadcrumbs(
+	State(category_repository): State<DynCategoryRepository>,
+	Path(id): Path<i32>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let query_ancestors = category_repository.ancestors(id)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "data": query_ancestors }).to_string()
+	))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/category/{id}",
+	tag = "Category",
+	params(("id" = i32, Path, description = "Category id")),
+	responses(
+		(status = 200, description = "The category, its version is also sent as ETag", body = DataResponse<CategoryData>, headers(("ETag" = String, description = "Version to send back in If-Match"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 404, description = "Category not found", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn find_by_id(
+	State(category_repository): State<DynCategoryRepository>,
+	Path(id): Path<i32>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	let query_find_first = category_repository.find_by_id(id)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(query_find_first.version))],
+		json!({ "success": true, "data": query_find_first }).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/category",
+	tag = "Category",
+	request_body = CategoryCreateBody,
+	params(("Idempotency-Key" = Option<String>, Header, description = "Retries with the same key and body replay the first response for 24 hours")),
+	responses(
+		(status = 201, description = "Category created", body = MessageResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.create", body = ErrorResponse),
+		(status = 409, description = "A live category already has the name, ignoring case and whitespace, the message names it, the parent is already at the deepest level, a concurrent write took its slug, or a request with the same Idempotency-Key is still running", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field, parent_id is not a live category, icon was not uploaded, or the Idempotency-Key was used for another body", body = ValidationErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
 pub async fn create(
-    State(pg_pool): State<PgPool>,
-    Json(body): Json<CategoryCreateBody>
+    State(category_repository): State<DynCategoryRepository>,
+    State(storage): State<DynStorage>,
+    audit: Audit,
+    ValidatedJson(body): ValidatedJson<CategoryCreateBody>
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-    sqlx::query_as!(CategoryData, 
-    	"INSERT INTO category (name) VALUES($1) RETURNING *",
-        body.name,
-    ).fetch_one(&pg_pool)
+    check_icon(&storage, body.icon.as_deref()).await?;
+
+    let query_insert = category_repository.create(&body)
     .await
     .map_err(|e| {
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
+            e.status_code(),
             json!({ "success": false, "message": e.to_string() }).to_string()
         )
     })?;
 
+    audit.record("create", "category", query_insert.id, None, Some(&query_insert)).await;
+
     Ok((
         StatusCode::CREATED,
         // json!({ "success": true, "data": query_insert, "message": "" }).to_string()
@@ -101,48 +280,729 @@ pub async fn create(
     ))
 }
 
+#[utoipa::path(
+	put,
+	path = "/api/category/{id}",
+	tag = "Category",
+	params(
+		("id" = i32, Path, description = "Category id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	request_body = CategoryReplaceBody,
+	responses(
+		(status = 200, description = "Category updated", body = MessageResponse, headers(("ETag" = String, description = "New version of the category"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.update", body = ErrorResponse),
+		(status = 404, description = "Category not found", body = ErrorResponse),
+		(status = 409, description = "Another live category already has the name, the message names it, or a concurrent write took its slug", body = ErrorResponse),
+		(status = 412, description = "Category was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field", body = ValidationErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
 pub async fn update(
-	State(pg_pool): State<PgPool>,
+	State(category_repository): State<DynCategoryRepository>,
+	State(storage): State<DynStorage>,
+	audit: Audit,
 	Path(id): Path<i32>,
-	Json(body): Json<CategoryUpdateBody>
-) -> Result<(StatusCode, String), (StatusCode, String)> {
-	sqlx::query!(
-		"UPDATE category set name = $1 WHERE id = $2",
-		body.name,
-		id
-	).execute(&pg_pool)
+	IfMatch(version): IfMatch,
+	ValidatedJson(body): ValidatedJson<CategoryReplaceBody>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	check_icon(&storage, body.icon.as_deref()).await?;
+
+	let before = category_repository.find_by_id(id).await.ok();
+
+	let version = category_repository.update(id, &body.name, body.icon.as_deref(), version)
 	.await
 	.map_err(|e| {
 		(
-			StatusCode::INTERNAL_SERVER_ERROR,
+			e.status_code(),
 			json!({ "success": false, "message": e.to_string() }).to_string()
 		)
 	})?;
 
+	let after = category_repository.find_by_id(id).await.ok();
+	audit.record("update", "category", id, before.as_ref(), after.as_ref()).await;
+
 	Ok((
 		StatusCode::OK,
+		[(header::ETAG, etag(version))],
 		json!({ "success": true, "message": "Data Category Berhasil Diupdate." }).to_string()
 	))
 }
 
+#[utoipa::path(
+	patch,
+	path = "/api/category/{id}",
+	tag = "Category",
+	params(
+		("id" = i32, Path, description = "Category id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	request_body = CategoryUpdateBody,
+	responses(
+		(status = 200, description = "Fields present in the body updated, the rest left untouched", body = MessageResponse, headers(("ETag" = String, description = "New version of the category"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.update", body = ErrorResponse),
+		(status = 404, description = "Category not found", body = ErrorResponse),
+		(status = 409, description = "Another live category already has the name, the message names it, or a concurrent write took its slug", body = ErrorResponse),
+		(status = 412, description = "Category was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 422, description = "Body fails validation or sets name to null", body = ValidationErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn patch(
+	State(category_repository): State<DynCategoryRepository>,
+	State(storage): State<DynStorage>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch,
+	ValidatedJson(body): ValidatedJson<CategoryUpdateBody>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	check_icon(&storage, body.icon.clone().flatten().as_deref()).await?;
+
+	let before = category_repository.find_by_id(id).await.ok();
+
+	let version = category_repository.patch(id, &body, version)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let after = category_repository.find_by_id(id).await.ok();
+	audit.record("update", "category", id, before.as_ref(), after.as_ref()).await;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": "Data Category Berhasil Diupdate." }).to_string()
+	))
+}
+
+#[utoipa::path(
+	delete,
+	path = "/api/category/{id}",
+	tag = "Category",
+	params(
+		("id" = i32, Path, description = "Category id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	responses(
+		(status = 200, description = "Category soft-deleted, it can be restored until the purge job removes it", body = MessageResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.delete", body = ErrorResponse),
+		(status = 404, description = "Category not found", body = ErrorResponse),
+		(status = 409, description = "Category still has sub categories", body = ErrorResponse),
+		(status = 412, description = "Category was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
 pub async fn delete(
-	State(pg_pool): State<PgPool>,
-	Path(id): Path<i32>
+	State(category_repository): State<DynCategoryRepository>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-	sqlx::query!(
-		"DELETE from category WHERE id = $1",
-		id
-	).execute(&pg_pool)
+	let before = category_repository.find_by_id(id).await.ok();
+
+	category_repository.delete(id, version)
 	.await
 	.map_err(|e| {
 		(
-			StatusCode::INTERNAL_SERVER_ERROR,
+			e.status_code(),
 			json!({ "success": false, "message": e.to_string() }).to_string()
 		)
 	})?;
 
+	audit.record("delete", "category", id, before.as_ref(), None).await;
+
 	Ok((
 		StatusCode::OK,
 		json!({ "success": true, "message": "Data Category Berhasil Dihapus." }).to_string()
 	))
 }
+
+#[utoipa::path(
+	post,
+	path = "/api/category/reorder",
+	tag = "Category",
+	request_body = CategoryReorderBody,
+	responses(
+		(status = 200, description = "sort_order of each category set to its position in ids, starting at 1", body = MessageResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.update", body = ErrorResponse),
+		(status = 422, description = "ids is empty, repeats an id or names categories that do not exist", body = ValidationErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn reorder(
+	State(category_repository): State<DynCategoryRepository>,
+	audit: Audit,
+	ValidatedJson(body): ValidatedJson<CategoryReorderBody>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	if body.ids.iter().collect::<HashSet<_>>().len() != body.ids.len() {
+		return Err((
+			StatusCode::UNPROCESSABLE_ENTITY,
+			json!({ "success": false, "message": "Id Category Tidak Boleh Berulang." }).to_string()
+		));
+	}
+
+	let before = categories_by_id(&category_repository).await;
+
+	category_repository.reorder(&body.ids)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let after = categories_by_id(&category_repository).await;
+	for id in &body.ids {
+		audit.record("reorder", "category", id, before.get(id), after.get(id)).await;
+	}
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "message": "Urutan Category Berhasil Disimpan." }).to_string()
+	))
+}
+
+/// Every category, soft-deleted ones included, ordered by id, for the audit trail of writes touching several of them.
+async fn categories_by_id(category_repository: &DynCategoryRepository) -> BTreeMap<i32, CategoryData> {
+	category_repository.find_many(true)
+	.await
+	.unwrap_or_default()
+	.into_iter()
+	.map(|category| (category.id, category))
+	.collect()
+}
+
+/// Fails with 422 unless the icon was stored through `/api/files/category`.
+async fn check_icon(storage: &DynStorage, icon: Option<&str>) -> Result<(), (StatusCode, String)> {
+	match icon {
+		Some(icon) if !category_icon_exists(storage, icon).await => Err((
+			StatusCode::UNPROCESSABLE_ENTITY,
+			json!({ "success": false, "message": format!("Icon '{icon}' Belum Diupload.") }).to_string()
+		)),
+		_ => Ok(())
+	}
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/category/{id}/move",
+	tag = "Category",
+	params(
+		("id" = i32, Path, description = "Category id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	request_body = CategoryMoveBody,
+	responses(
+		(status = 200, description = "Category moved with its whole subtree", body = MessageResponse, headers(("ETag" = String, description = "New version of the category"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.update", body = ErrorResponse),
+		(status = 404, description = "Category not found", body = ErrorResponse),
+		(status = 409, description = "The new parent is the category itself or one of its descendants, or the category and its descendants would not fit below it in the deepest level allowed", body = ErrorResponse),
+		(status = 412, description = "Category was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 422, description = "parent_id is not a live category", body = ErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn move_to(
+	State(category_repository): State<DynCategoryRepository>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch,
+	Json(body): Json<CategoryMoveBody>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	let before = category_repository.find_by_id(id).await.ok();
+
+	let version = category_repository.move_to(id, body.parent_id, version)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let after = category_repository.find_by_id(id).await.ok();
+	audit.record("move", "category", id, before.as_ref(), after.as_ref()).await;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": "Category Berhasil Dipindahkan." }).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/category/{id}/merge",
+	tag = "Category",
+	params(
+		("id" = i32, Path, description = "Category that is kept"),
+		("If-Match" = String, Header, description = "ETag of the kept category, `*` skips the check")
+	),
+	request_body = CategoryMergeBody,
+	responses(
+		(status = 200, description = "Sub categories of the sources moved under the kept category and the sources soft-deleted, in one transaction", body = MessageResponse, headers(("ETag" = String, description = "New version of the kept category"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.manage", body = ErrorResponse),
+		(status = 404, description = "Category not found", body = ErrorResponse),
+		(status = 409, description = "The kept category is below one of the sources", body = ErrorResponse),
+		(status = 412, description = "Category was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 422, description = "source_ids is empty, repeats an id, contains the kept category or names categories that do not exist", body = ValidationErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn merge(
+	State(category_repository): State<DynCategoryRepository>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch,
+	ValidatedJson(body): ValidatedJson<CategoryMergeBody>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	if body.source_ids.iter().collect::<HashSet<_>>().len() != body.source_ids.len() {
+		return Err((
+			StatusCode::UNPROCESSABLE_ENTITY,
+			json!({ "success": false, "message": "Id Category Tidak Boleh Berulang." }).to_string()
+		));
+	}
+
+	let before = categories_by_id(&category_repository).await;
+
+	let version = category_repository.merge(id, &body.source_ids, version)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let merged_ids = |ids: &[i32]| json!({ "merged_ids": ids });
+	audit.record("merge", "category", id, Some(&merged_ids(&[])), Some(&merged_ids(&body.source_ids))).await;
+
+	// The sources are soft-deleted and their sub categories change parent, each gets its own entry.
+	let after = categories_by_id(&category_repository).await;
+	for (category_id, category) in &after {
+		let previous = before.get(category_id);
+		if *category_id != id && previous.is_none_or(|previous| previous.version != category.version) {
+			audit.record("merge", "category", category_id, previous, Some(category)).await;
+		}
+	}
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": format!("{} Category Berhasil Digabungkan.", body.source_ids.len()) }).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/category/import",
+	tag = "Category",
+	params(ImportQuery),
+	request_body(content_type = "multipart/form-data", description = "A csv or xlsx `file` with the columns name, parent (name of an existing category or of one on an earlier row) and sort_order, other columns are ignored"),
+	responses(
+		(status = 200, description = "Dry run, every row is valid and nothing was written", body = ImportResponse),
+		(status = 201, description = "Every row was created in one transaction", body = ImportResponse),
+		(status = 400, description = "The file field is missing or the body is not multipart", body = ErrorResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.manage", body = ErrorResponse),
+		(status = 422, description = "The file cannot be read or rows are invalid, lists the errors of each row and nothing was written", body = ImportResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn import(
+	State(category_repository): State<DynCategoryRepository>,
+	audit: Audit,
+	Query(query): Query<ImportQuery>,
+	multipart: Multipart
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let (format, bytes) = tabular::read_upload(multipart, query.format).await?;
+	let rows = tabular::read_rows(format, &bytes).map_err(|message| {
+		(
+			StatusCode::UNPROCESSABLE_ENTITY,
+			json!({ "success": false, "message": message }).to_string()
+		)
+	})?;
+
+	let mut errors = vec![];
+	let mut import_rows = vec![];
+
+	for row in &rows {
+		let body = CategoryCreateBody { name: row.get("name").unwrap_or_default().to_string(), parent_id: None, icon: None };
+		let mut valid = true;
+
+		if let Err(e) = body.validate() {
+			for error in field_errors(&e) {
+				push_row_error(&mut errors, row.line, &error.field, error.message);
+			}
+			valid = false;
+		}
+
+		let sort_order = match row.get("sort_order").map(str::parse::<i32>) {
+			Some(Ok(sort_order)) => sort_order,
+			Some(Err(_)) => {
+				push_row_error(&mut errors, row.line, "sort_order", "Harus berupa bilangan bulat.");
+				valid = false;
+				0
+			},
+			None => 0
+		};
+
+		if valid {
+			import_rows.push(CategoryImportRow { line: row.line, name: body.name, parent: row.get("parent").map(str::to_string), sort_order });
+		}
+	}
+
+	// Rows that passed validation are still checked against the stored data so the report lists every problem at once.
+	let dry_run = query.dry_run || !errors.is_empty();
+	let outcome = category_repository.import(&import_rows, dry_run)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+	errors.extend(outcome.errors);
+	errors.sort_by_key(|error| error.row);
+
+	for category in &outcome.created {
+		audit.record("import", "category", category.id, None, Some(category)).await;
+	}
+
+	Ok(tabular::import_response(ImportReport { dry_run: query.dry_run, rows: rows.len(), created: outcome.created.len(), errors }))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/category/export",
+	tag = "Category",
+	params(ExportQuery),
+	responses(
+		(status = 200, description = "Live categories ordered by sort_order then name, as a csv or xlsx attachment, the parent column holds the parent name", content_type = "text/csv"),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.manage", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn export(
+	State(category_repository): State<DynCategoryRepository>,
+	Query(query): Query<ExportQuery>
+) -> Result<Response, (StatusCode, String)> {
+	// Categories number in the hundreds at most, they are read at once so each row can carry its parent name.
+	let batch = async move {
+		let categories = category_repository.find_many(false).await?;
+		let names: HashMap<i32, String> = categories.iter().map(|category| (category.id, category.name.clone())).collect();
+
+		Ok(categories.iter()
+			.map(|category| category.export_row(category.parent_id.and_then(|parent_id| names.get(&parent_id)).map(String::as_str)))
+			.collect())
+	};
+
+	tabular::export(query.format, "category", CATEGORY_EXPORT_COLUMNS, futures_util::stream::once(batch)).await
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/category/{id}/restore",
+	tag = "Category",
+	params(("id" = i32, Path, description = "Category id")),
+	responses(
+		(status = 200, description = "Category restored", body = MessageResponse, headers(("ETag" = String, description = "New version of the category"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks category.manage", body = ErrorResponse),
+		(status = 404, description = "No soft-deleted category with this id", body = ErrorResponse),
+		(status = 409, description = "Its parent is still deleted, or a live category took its name meanwhile and the message names it", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn restore(
+	State(category_repository): State<DynCategoryRepository>,
+	audit: Audit,
+	Path(id): Path<i32>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	let version = category_repository.restore(id)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let after = category_repository.find_by_id(id).await.ok();
+	audit.record("restore", "category", id, None, after.as_ref()).await;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": "Data Category Berhasil Dipulihkan." }).to_string()
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+
+	use super::*;
+	use crate::repository::audit_repository::InMemoryAuditRepository;
+	use crate::repository::category_repository::InMemoryCategoryRepository;
+	use crate::storage::memory::InMemoryStorage;
+
+	fn repository() -> DynCategoryRepository {
+		Arc::new(InMemoryCategoryRepository::default())
+	}
+
+	fn permissions(role: &str) -> Extension<Permissions> {
+		Extension(Permissions::new(role, Vec::new()))
+	}
+
+	fn storage() -> State<DynStorage> {
+		State(Arc::new(InMemoryStorage::default()))
+	}
+
+	fn audit() -> Audit {
+		Audit::new(Arc::new(InMemoryAuditRepository::default()), None, None)
+	}
+
+	fn category_body(name: &str, parent_id: Option<i32>) -> CategoryCreateBody {
+		CategoryCreateBody { name: name.to_string(), parent_id, icon: None }
+	}
+
+	fn list(include_deleted: bool) -> Query<ListQuery> {
+		Query(ListQuery { include_deleted })
+	}
+
+	fn body_json(body: &str) -> serde_json::Value {
+		serde_json::from_str(body).unwrap()
+	}
+
+	#[tokio::test]
+	async fn find_many_orders_created_categories_by_name() {
+		let category_repository = repository();
+
+		for name in ["Snack", "Makanan", "Minuman"] {
+			let (status, _) = create(State(category_repository.clone()), storage(), audit(), ValidatedJson(category_body(name, None))).await.unwrap();
+			assert_eq!(status, StatusCode::CREATED);
+		}
+
+		let (_, body) = find_many(State(category_repository), permissions("cashier"), list(false)).await.unwrap();
+		let names: Vec<String> = body_json(&body)["data"].as_array().unwrap().iter().map(|category| category["name"].as_str().unwrap().to_string()).collect();
+
+		assert_eq!(names, ["Makanan", "Minuman", "Snack"]);
+	}
+
+	#[tokio::test]
+	async fn update_and_delete_unknown_category_is_not_found() {
+		let category_repository = repository();
+
+		let (status, _) = update(State(category_repository.clone()), storage(), audit(), Path(42), IfMatch(None), ValidatedJson(CategoryReplaceBody { name: "Snack".to_string(), icon: None })).await.unwrap_err();
+		assert_eq!(status, StatusCode::NOT_FOUND);
+
+		let (status, _) = delete(State(category_repository), audit(), Path(42), IfMatch(None)).await.unwrap_err();
+		assert_eq!(status, StatusCode::NOT_FOUND);
+	}
+
+	#[tokio::test]
+	async fn stale_version_is_rejected() {
+		let category_repository = repository();
+		let category = category_repository.create(&category_body("Minuman", None)).await.unwrap();
+		let body = || ValidatedJson(CategoryReplaceBody { name: "Snack".to_string(), icon: None });
+
+		let (_, [(_, etag)], _) = update(State(category_repository.clone()), storage(), audit(), Path(category.id), IfMatch(Some(category.version)), body()).await.unwrap();
+		assert_eq!(etag, format!("\"{}\"", category.version + 1));
+
+		let (status, _) = update(State(category_repository.clone()), storage(), audit(), Path(category.id), IfMatch(Some(category.version)), body()).await.unwrap_err();
+		assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+		let (status, _) = delete(State(category_repository), audit(), Path(category.id), IfMatch(Some(category.version))).await.unwrap_err();
+		assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+	}
+
+	#[tokio::test]
+	async fn patch_without_name_keeps_it() {
+		let category_repository = repository();
+		let category = category_repository.create(&category_body("Minuman", None)).await.unwrap();
+
+		let (status, _, _) = patch(State(category_repository.clone()), storage(), audit(), Path(category.id), IfMatch(None), ValidatedJson(CategoryUpdateBody { name: None, icon: None })).await.unwrap();
+
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(category_repository.find_many(false).await.unwrap()[0].name, "Minuman");
+	}
+
+	#[tokio::test]
+	async fn deleted_category_is_listed_for_admins_only_and_can_be_restored() {
+		let category_repository = repository();
+		let category = category_repository.create(&category_body("Minuman", None)).await.unwrap();
+
+		delete(State(category_repository.clone()), audit(), Path(category.id), IfMatch(None)).await.unwrap();
+
+		let (_, body) = find_many(State(category_repository.clone()), permissions("cashier"), list(false)).await.unwrap();
+		assert_eq!(body_json(&body)["data"].as_array().unwrap().len(), 0);
+
+		let (status, _) = find_many(State(category_repository.clone()), permissions("cashier"), list(true)).await.unwrap_err();
+		assert_eq!(status, StatusCode::FORBIDDEN);
+
+		let (_, body) = find_many(State(category_repository.clone()), permissions("admin"), list(true)).await.unwrap();
+		assert!(body_json(&body)["data"][0]["deleted_at"].is_string());
+
+		restore(State(category_repository.clone()), audit(), Path(category.id)).await.unwrap();
+		assert_eq!(category_repository.find_many(false).await.unwrap().len(), 1);
+	}
+
+	#[tokio::test]
+	async fn tree_nests_children_and_breadcrumbs_walk_up() {
+		let category_repository = repository();
+		let minuman = category_repository.create(&category_body("Minuman", None)).await.unwrap();
+		let kopi = category_repository.create(&category_body("Kopi", Some(minuman.id))).await.unwrap();
+		let botol = category_repository.create(&category_body("Kemasan Botol", Some(kopi.id))).await.unwrap();
+		category_repository.create(&category_body("Snack", None)).await.unwrap();
+
+		let (_, body) = tree(State(category_repository.clone())).await.unwrap();
+		let tree = body_json(&body)["data"].clone();
+		assert_eq!(tree[0]["name"], "Minuman");
+		assert_eq!(tree[0]["children"][0]["children"][0]["name"], "Kemasan Botol");
+		assert_eq!(tree[1]["name"], "Snack");
+
+		let (_, body) = breadcrumbs(State(category_repository), Path(botol.id)).await.unwrap();
+		let names: Vec<String> = body_json(&body)["data"].as_array().unwrap().iter().map(|category| category["name"].as_str().unwrap().to_string()).collect();
+		assert_eq!(names, ["Minuman", "Kopi", "Kemasan Botol"]);
+	}
+
+	#[tokio::test]
+	async fn move_rejects_cycles_and_unknown_parents() {
+		let category_repository = repository();
+		let minuman = category_repository.create(&category_body("Minuman", None)).await.unwrap();
+		let kopi = category_repository.create(&category_body("Kopi", Some(minuman.id))).await.unwrap();
+		let move_body = |parent_id| Json(CategoryMoveBody { parent_id });
+
+		for parent_id in [minuman.id, kopi.id] {
+			let (status, _) = move_to(State(category_repository.clone()), audit(), Path(minuman.id), IfMatch(None), move_body(Some(parent_id))).await.unwrap_err();
+			assert_eq!(status, StatusCode::CONFLICT);
+		}
+
+		let (status, _) = move_to(State(category_repository.clone()), audit(), Path(kopi.id), IfMatch(None), move_body(Some(42))).await.unwrap_err();
+		assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+		move_to(State(category_repository.clone()), audit(), Path(kopi.id), IfMatch(None), move_body(None)).await.unwrap();
+		assert_eq!(category_repository.find_by_id(kopi.id).await.unwrap().parent_id, None);
+	}
+
+	#[tokio::test]
+	async fn category_with_children_cannot_be_deleted() {
+		let category_repository = repository();
+		let minuman = category_repository.create(&category_body("Minuman", None)).await.unwrap();
+		category_repository.create(&category_body("Kopi", Some(minuman.id))).await.unwrap();
+
+		let (status, _) = delete(State(category_repository), audit(), Path(minuman.id), IfMatch(None)).await.unwrap_err();
+
+		assert_eq!(status, StatusCode::CONFLICT);
+	}
+
+	#[tokio::test]
+	async fn reorder_sets_the_order_of_find_many() {
+		let category_repository = repository();
+		let mut ids = vec![];
+		for name in ["Makanan", "Minuman", "Snack"] {
+			ids.push(category_repository.create(&category_body(name, None)).await.unwrap().id);
+		}
+
+		reorder(State(category_repository.clone()), audit(), ValidatedJson(CategoryReorderBody { ids: vec![ids[2], ids[0]] })).await.unwrap();
+
+		let names: Vec<String> = category_repository.find_many(false).await.unwrap().into_iter().map(|category| category.name).collect();
+		assert_eq!(names, ["Minuman", "Snack", "Makanan"]);
+
+		let (status, _) = reorder(State(category_repository.clone()), audit(), ValidatedJson(CategoryReorderBody { ids: vec![ids[0], ids[0]] })).await.unwrap_err();
+		assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+		let (status, _) = reorder(State(category_repository), audit(), ValidatedJson(CategoryReorderBody { ids: vec![ids[0], 42] })).await.unwrap_err();
+		assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+	}
+
+	#[tokio::test]
+	async fn names_are_unique_ignoring_case_and_whitespace() {
+		let category_repository = repository();
+		let minuman = category_repository.create(&category_body("  Minuman   Dingin ", None)).await.unwrap();
+		assert_eq!(minuman.name, "Minuman Dingin");
+
+		let (status, body) = create(State(category_repository.clone()), storage(), audit(), ValidatedJson(category_body("minuman dingin", None))).await.unwrap_err();
+		assert_eq!(status, StatusCode::CONFLICT);
+		assert!(body_json(&body)["message"].as_str().unwrap().contains("\"Minuman Dingin\""));
+
+		let snack = category_repository.create(&category_body("Snack", None)).await.unwrap();
+		let body = ValidatedJson(CategoryUpdateBody { name: Some("MINUMAN dingin".to_string()), icon: None });
+		let (status, _) = patch(State(category_repository.clone()), storage(), audit(), Path(snack.id), IfMatch(None), body).await.unwrap_err();
+		assert_eq!(status, StatusCode::CONFLICT);
+
+		delete(State(category_repository.clone()), audit(), Path(minuman.id), IfMatch(None)).await.unwrap();
+		category_repository.create(&category_body("Minuman Dingin", None)).await.unwrap();
+
+		let (status, _) = restore(State(category_repository), audit(), Path(minuman.id)).await.unwrap_err();
+		assert_eq!(status, StatusCode::CONFLICT);
+	}
+
+	#[tokio::test]
+	async fn merge_moves_children_and_deletes_the_sources() {
+		let category_repository = repository();
+		let minuman = category_repository.create(&category_body("Minuman", None)).await.unwrap();
+		let drinks = category_repository.create(&category_body("Drinks", None)).await.unwrap();
+		let kopi = category_repository.create(&category_body("Kopi", Some(drinks.id))).await.unwrap();
+		let merge_body = |source_ids| ValidatedJson(CategoryMergeBody { source_ids });
+
+		let (status, _) = merge(State(category_repository.clone()), audit(), Path(kopi.id), IfMatch(None), merge_body(vec![drinks.id])).await.unwrap_err();
+		assert_eq!(status, StatusCode::CONFLICT);
+
+		for source_ids in [vec![minuman.id], vec![drinks.id, drinks.id], vec![42]] {
+			let (status, _) = merge(State(category_repository.clone()), audit(), Path(minuman.id), IfMatch(None), merge_body(source_ids)).await.unwrap_err();
+			assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+		}
+
+		let (_, [(_, etag)], _) = merge(State(category_repository.clone()), audit(), Path(minuman.id), IfMatch(Some(minuman.version)), merge_body(vec![drinks.id])).await.unwrap();
+		assert_eq!(etag, format!("\"{}\"", minuman.version + 1));
+
+		assert_eq!(category_repository.find_by_id(kopi.id).await.unwrap().parent_id, Some(minuman.id));
+		assert!(matches!(category_repository.find_by_id(drinks.id).await, Err(crate::repository::RepositoryError::NotFound)));
+	}
+
+	#[tokio::test]
+	async fn renaming_regenerates_a_unique_slug() {
+		let category_repository = repository();
+		let kopi = category_repository.create(&category_body("Kopi & Teh", None)).await.unwrap();
+		let minuman = category_repository.create(&category_body("Minuman", None)).await.unwrap();
+		assert_eq!(kopi.slug, "kopi-dan-teh");
+
+		let body = ValidatedJson(CategoryUpdateBody { name: Some("Kopi + Teh".to_string()), icon: None });
+		patch(State(category_repository.clone()), storage(), audit(), Path(minuman.id), IfMatch(None), body).await.unwrap();
+		assert_eq!(category_repository.find_by_id(minuman.id).await.unwrap().slug, "kopi-plus-teh");
+
+		let body = ValidatedJson(CategoryUpdateBody { name: Some("Kopi dan Teh".to_string()), icon: None });
+		patch(State(category_repository.clone()), storage(), audit(), Path(minuman.id), IfMatch(None), body).await.unwrap();
+		assert_eq!(category_repository.find_by_id(minuman.id).await.unwrap().slug, "kopi-dan-teh-2");
+	}
+}
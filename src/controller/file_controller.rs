@@ -1,86 +1,200 @@
+use std::sync::Arc;
+
 use axum:: {
-	extract::{ Multipart, Path },
-	http::{StatusCode}, response::{IntoResponse, Response},
+	extract::{ Multipart, State },
+	http::{header, HeaderMap, HeaderValue, StatusCode}, response::Response,
 };
-use reqwest::header;
 use serde_json::json;
 
-use std::{ fs, path::PathBuf };
-use tokio::{fs::File, io::AsyncReadExt};
-use tokio::io::AsyncWriteExt;
+use crate::model::file_model::{ ImageQuery, UploadFileForm, UploadFileResponse };
+use crate::model::utils_model::ErrorResponse;
+use crate::state::DynFileObjectRepository;
+use crate::storage::DynStorage;
+use crate::utils::extractor::{ Path, Query };
+use crate::utils::serve_file;
+use crate::utils::thumbnail;
+use crate::utils::upload::{ self, UploadConfig, UploadPolicy };
+
+/// Key prefixes in the storage, the local backend keeps them as directories under its root.
+const USER_IMAGE_PREFIX: &str = "user";
+const CATEGORY_ICON_PREFIX: &str = "category";
+
+#[utoipa::path(
+	post,
+	path = "/api/files/user",
+	tag = "File",
+	request_body(content = UploadFileForm, content_type = "multipart/form-data"),
+	responses(
+		(status = 200, description = "File stored under the SHA-256 of its content, identical uploads get the same name. Removed once no user photo refers to it for UNREFERENCED_FILE_GRACE_HOURS, a day by default", body = UploadFileResponse),
+		(status = 400, description = "No file part in the form, the file is empty or the form is malformed", body = ErrorResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 413, description = "File is larger than USER_IMAGE_MAX_BYTES, 2 MiB by default", body = ErrorResponse),
+		(status = 415, description = "Content is not one of USER_IMAGE_TYPES, PNG, JPEG or WebP by default", body = ErrorResponse),
+		(status = 500, description = "File could not be written", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn upload_user_image(
+	State(storage): State<DynStorage>,
+	State(file_object_repository): State<DynFileObjectRepository>,
+	State(upload_config): State<Arc<UploadConfig>>,
+	multipart: Multipart
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	store_upload(multipart, &storage, &file_object_repository, USER_IMAGE_PREFIX, &upload_config.user_image).await
+}
 
-pub async fn upload_user_image(mut multipart: Multipart) -> Result<(StatusCode, String), (StatusCode, String)> {
-	let upload_dir = "uploads/user";
+#[utoipa::path(
+	post,
+	path = "/api/files/category",
+	tag = "File",
+	request_body(content = UploadFileForm, content_type = "multipart/form-data"),
+	responses(
+		(status = 200, description = "Icon stored under the SHA-256 of its content, pass the name as the category icon or it is removed after UNREFERENCED_FILE_GRACE_HOURS, a day by default", body = UploadFileResponse),
+		(status = 400, description = "No file part in the form, the file is empty or the form is malformed", body = ErrorResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 413, description = "File is larger than CATEGORY_ICON_MAX_BYTES, 512 KiB by default", body = ErrorResponse),
+		(status = 415, description = "Content is not one of CATEGORY_ICON_TYPES, PNG, JPEG or WebP by default", body = ErrorResponse),
+		(status = 500, description = "File could not be written", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn upload_category_icon(
+	State(storage): State<DynStorage>,
+	State(file_object_repository): State<DynFileObjectRepository>,
+	State(upload_config): State<Arc<UploadConfig>>,
+	multipart: Multipart
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	store_upload(multipart, &storage, &file_object_repository, CATEGORY_ICON_PREFIX, &upload_config.category_icon).await
+}
 
-	fs::create_dir_all(upload_dir).unwrap();
+/// Whether `file_name` was stored by `upload_user_image`, `file_name` must already be a bare file name.
+pub async fn user_image_exists(storage: &DynStorage, file_name: &str) -> bool {
+	storage.stat(&format!("{USER_IMAGE_PREFIX}/{file_name}")).await.is_ok()
+}
 
-	while let Some(mut field) = multipart.next_field().await.unwrap() {
+/// Whether `file_name` was stored by `upload_category_icon`, `file_name` must already be a bare file name.
+pub async fn category_icon_exists(storage: &DynStorage, file_name: &str) -> bool {
+	storage.stat(&format!("{CATEGORY_ICON_PREFIX}/{file_name}")).await.is_ok()
+}
 
-		let file_name = field.file_name().map(|name| name.to_string());
+/// Stores the first part of the form that carries a file name, other parts are skipped.
+async fn store_upload(
+	mut multipart: Multipart,
+	storage: &DynStorage,
+	file_object_repository: &DynFileObjectRepository,
+	prefix: &str,
+	policy: &UploadPolicy
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	while let Some(field) = multipart.next_field().await.map_err(|e| upload::multipart_error(e, policy))? {
+		if field.file_name().is_none() {
+			continue;
+		}
 
-		if let Some(file_name) = file_name {
-			let ext = file_name.split(".").last().unwrap_or("");
-			let timestamp = chrono::Utc::now().timestamp();
+		let (file_name, file_extension) = upload::store(field, storage, file_object_repository, prefix, policy).await?;
 
-			let new_file_name = if ext == "jfif" {
-				format!("{timestamp}_{}.jpg", file_name.split(".").next().unwrap())
-			} else {
-				format!("{timestamp}_{file_name}")
-			};
+		return Ok((
+			StatusCode::OK,
+			json!({ "file_name": file_name, "file_extension": file_extension }).to_string()
+		))
+	}
 
-			let file_path = format!("{}/{}", upload_dir, new_file_name);
+	Err(upload::error(StatusCode::BAD_REQUEST, "Tidak Ada File Yang Diunggah.".to_string()))
+}
 
-			let mut file = File::create(&file_path).await.unwrap();
+#[utoipa::path(
+	get,
+	path = "/api/files/user/image/{filename}",
+	tag = "File",
+	params(
+		("filename" = String, Path, description = "Stored file name returned by the upload"),
+		ImageQuery,
+		("Accept" = Option<String>, Header, description = "A variant is sent as WebP when `image/webp` is listed"),
+		("Range" = Option<String>, Header, description = "A single `bytes=` range"),
+		("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+		("If-Modified-Since" = Option<String>, Header, description = "Last-Modified of a cached copy")
+	),
+	responses(
+		(status = 200, description = "Image bytes, typed by their content rather than the extension", content_type = "application/octet-stream", headers(
+			("ETag" = String, description = "Validator for If-None-Match and If-Range"),
+			("Last-Modified" = String, description = "Validator for If-Modified-Since"),
+			("Cache-Control" = String, description = "How long the copy may be reused"),
+			("Vary" = String, description = "Always `Accept`, the same URL may be sent as WebP")
+		)),
+		(status = 206, description = "The requested range", content_type = "application/octet-stream", headers(("Content-Range" = String, description = "Range sent and full size"))),
+		(status = 304, description = "The cached copy is still current"),
+		(status = 404, description = "File not found or outside the upload directory", body = String, content_type = "text/plain"),
+		(status = 416, description = "The range starts past the end of the file"),
+		(status = 500, description = "Storage backend error", body = String, content_type = "text/plain")
+	)
+)]
+pub async fn get_user_image(
+	State(storage): State<DynStorage>,
+	State(upload_config): State<Arc<UploadConfig>>,
+	Path(filename): Path<String>,
+	Query(query): Query<ImageQuery>,
+	headers: HeaderMap
+) -> Response {
+	serve_image(&storage, USER_IMAGE_PREFIX, &upload_config.user_image, &filename, query.size, &headers).await
+}
 
-			while let Some(chunk) = field.chunk().await.unwrap() {
-				file.write_all(&chunk).await.unwrap();
-			}
+#[utoipa::path(
+	get,
+	path = "/api/files/category/image/{filename}",
+	tag = "File",
+	params(
+		("filename" = String, Path, description = "Stored file name returned by the upload"),
+		ImageQuery,
+		("Accept" = Option<String>, Header, description = "A variant is sent as WebP when `image/webp` is listed"),
+		("Range" = Option<String>, Header, description = "A single `bytes=` range"),
+		("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+		("If-Modified-Since" = Option<String>, Header, description = "Last-Modified of a cached copy")
+	),
+	responses(
+		(status = 200, description = "Icon bytes, typed by their content rather than the extension", content_type = "application/octet-stream", headers(
+			("ETag" = String, description = "Validator for If-None-Match and If-Range"),
+			("Last-Modified" = String, description = "Validator for If-Modified-Since"),
+			("Cache-Control" = String, description = "How long the copy may be reused"),
+			("Vary" = String, description = "Always `Accept`, the same URL may be sent as WebP")
+		)),
+		(status = 206, description = "The requested range", content_type = "application/octet-stream", headers(("Content-Range" = String, description = "Range sent and full size"))),
+		(status = 304, description = "The cached copy is still current"),
+		(status = 404, description = "File not found or outside the upload directory", body = String, content_type = "text/plain"),
+		(status = 416, description = "The range starts past the end of the file"),
+		(status = 500, description = "Storage backend error", body = String, content_type = "text/plain")
+	)
+)]
+pub async fn get_category_icon(
+	State(storage): State<DynStorage>,
+	State(upload_config): State<Arc<UploadConfig>>,
+	Path(filename): Path<String>,
+	Query(query): Query<ImageQuery>,
+	headers: HeaderMap
+) -> Response {
+	serve_image(&storage, CATEGORY_ICON_PREFIX, &upload_config.category_icon, &filename, query.size, &headers).await
+}
 
-			return Ok((
-				StatusCode::OK,
-				json!({ "file_name": new_file_name, "file_extension": ext }).to_string()
-			))
+/// Serves the variant closest to `size`, as WebP when the client takes it, and falls back to the
+/// same size in the original format, then to the original itself.
+async fn serve_image(storage: &DynStorage, prefix: &str, policy: &UploadPolicy, file_name: &str, size: Option<u32>, headers: &HeaderMap) -> Response {
+	let mut keys = vec![];
+	if let Some(size) = size.and_then(|size| thumbnail::pick_size(&policy.variants, size)) {
+		if thumbnail::accepts_webp(headers) {
+			keys.push(thumbnail::variant_key(prefix, file_name, size, "webp"));
+		}
+		if let Some((_, ext)) = file_name.rsplit_once('.') {
+			keys.push(thumbnail::variant_key(prefix, file_name, size, ext));
 		}
 	}
+	keys.push(format!("{prefix}/{file_name}"));
 
-	Err((
-		StatusCode::BAD_REQUEST,
-		"No File Uploaded.".to_string()
-	))
-}
-
-pub async fn get_user_image(Path(filename): Path<String>) -> Response {
-	let file_path = PathBuf::from(format!("uploads/user/{}", filename));
-
-	if file_path.exists() {
-		match File::open(&file_path).await {
-	    	Ok(mut file) => {
-	    		let mut contents = Vec::new();
-
-	    		if file.read_to_end(&mut contents).await.is_ok() {
-	    			return Response::builder()
-	    			.status(StatusCode::OK)
-	    			.header(header::CONTENT_TYPE, "image/*")
-	    			.body(axum::body::Body::from(contents))
-	    			.unwrap();
-	    		}
-	    	},
-	    	Err(_) => {}
+	let mut response = serve_file::not_found();
+	for key in keys {
+		response = serve_file::serve(storage, &key, headers).await;
+		if response.status() != StatusCode::NOT_FOUND {
+			break;
 		}
 	}
 
-	(StatusCode::NOT_FOUND, "File Not Found.").into_response()
+	response.headers_mut().insert(header::VARY, HeaderValue::from_static("Accept"));
+	response
 }
-
-
-pub async fn delete_user_image(Path(filename): Path<String>) -> impl IntoResponse {
-	let file_path = format!("uploads/user/{}", filename);
-
-	if filename != "default_user.png" && std::path::Path::new(&file_path).exists() {
-		fs::remove_file(file_path).unwrap();
-
-		(StatusCode::OK, "File was deleted.")
-	} else {
-		(StatusCode::NOT_FOUND, "File not found or cannot deleted the default file.")
-	}
-}
\ No newline at end of file
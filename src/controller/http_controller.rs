@@ -6,8 +6,18 @@ use axum::{
 
 use serde_json::json;
 
+use crate::model::utils_model::DataResponse;
 use crate::utils::utils::CLIENT;
 
+#[utoipa::path(
+	get,
+	path = "/api/http",
+	tag = "Http Example",
+	responses(
+		(status = 200, description = "Posts proxied from jsonplaceholder", body = DataResponse<serde_json::Value>),
+		(status = 500, description = "Upstream request failed", body = String, content_type = "text/plain")
+	)
+)]
 pub async fn get_http_example() -> Result<(StatusCode, String), (StatusCode, String)> {
 
 	let response = CLIENT.get("https://jsonplaceholder.typicode.com/posts")
@@ -28,6 +38,15 @@ pub async fn get_http_example() -> Result<(StatusCode, String), (StatusCode, Str
     ))
 }
 
+#[utoipa::path(
+	post,
+	path = "/api/http",
+	tag = "Http Example",
+	responses(
+		(status = 200, description = "Todo echoed back by jsonplaceholder", body = DataResponse<serde_json::Value>),
+		(status = 500, description = "Upstream request failed", body = String, content_type = "text/plain")
+	)
+)]
 pub async fn post_http_example() -> Result<(StatusCode, String), (StatusCode, String)> {
 
 	let mut map_body = HashMap::new();
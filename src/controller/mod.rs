@@ -1,5 +1,7 @@
+pub mod audit_controller;
 pub mod auth_controller;
 pub mod category_controller;
+pub mod role_controller;
 pub mod user_controller;
 pub mod http_controller;
 pub mod file_controller;
\ No newline at end of file
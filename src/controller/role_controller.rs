@@ -0,0 +1,271 @@
+use std::sync::Arc;
+
+use axum::{
+	extract::State,
+	http::{ header, HeaderName, StatusCode },
+	Extension
+};
+
+use serde_json::json;
+
+use crate::model::role_model::{ PermissionData, PermissionSummary, RoleBody, RoleData };
+use crate::model::utils_model::{ DataResponse, ErrorResponse, MessageResponse, ValidationErrorResponse };
+use crate::state::DynRoleRepository;
+use crate::utils::audit::Audit;
+use crate::utils::extractor::{ etag, IfMatch, Path, ValidatedJson };
+use crate::utils::permission::{ PermissionCache, Permissions };
+
+#[utoipa::path(
+	get,
+	path = "/api/role",
+	tag = "Role",
+	responses(
+		(status = 200, description = "Every role with its permissions, ordered by name", body = DataResponse<Vec<RoleData>>),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks role.manage", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn find_all(
+	State(role_repository): State<DynRoleRepository>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let query_find_many = role_repository.find_all()
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "data": query_find_many }).to_string()
+	))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/role/{id}",
+	tag = "Role",
+	params(("id" = i32, Path, description = "Role id")),
+	responses(
+		(status = 200, description = "The role, its version is also sent as ETag", body = DataResponse<RoleData>, headers(("ETag" = String, description = "Version to send back in If-Match"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks role.manage", body = ErrorResponse),
+		(status = 404, description = "Role not found", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn find_by_id(
+	State(role_repository): State<DynRoleRepository>,
+	Path(id): Path<i32>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	let query_find_first = role_repository.find_by_id(id)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(query_find_first.version))],
+		json!({ "success": true, "data": query_find_first }).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/role",
+	tag = "Role",
+	request_body = RoleBody,
+	params(("Idempotency-Key" = Option<String>, Header, description = "Retries with the same key and body replay the first response for 24 hours")),
+	responses(
+		(status = 201, description = "Role created", body = DataResponse<RoleData>, headers(("ETag" = String, description = "Version of the new role"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks role.manage", body = ErrorResponse),
+		(status = 409, description = "A role already has the name, or a request with the same Idempotency-Key is still running", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field, names unknown permissions, or the Idempotency-Key was used for another body", body = ValidationErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn create(
+	State(role_repository): State<DynRoleRepository>,
+	State(permission_cache): State<Arc<PermissionCache>>,
+	audit: Audit,
+	ValidatedJson(body): ValidatedJson<RoleBody>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	let query_insert = role_repository.create(&body)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	permission_cache.clear();
+	audit.record("create", "role", query_insert.id, None, Some(&query_insert)).await;
+
+	Ok((
+		StatusCode::CREATED,
+		[(header::ETAG, etag(query_insert.version))],
+		json!({ "success": true, "data": query_insert }).to_string()
+	))
+}
+
+#[utoipa::path(
+	put,
+	path = "/api/role/{id}",
+	tag = "Role",
+	params(
+		("id" = i32, Path, description = "Role id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	request_body = RoleBody,
+	responses(
+		(status = 200, description = "Name, description and permissions replaced, users of a renamed role keep it", body = MessageResponse, headers(("ETag" = String, description = "New version of the role"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks role.manage", body = ErrorResponse),
+		(status = 404, description = "Role not found", body = ErrorResponse),
+		(status = 409, description = "Another role already has the name, or a built-in role was renamed", body = ErrorResponse),
+		(status = 412, description = "Role was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field, or names unknown permissions", body = ValidationErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn update(
+	State(role_repository): State<DynRoleRepository>,
+	State(permission_cache): State<Arc<PermissionCache>>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch,
+	ValidatedJson(body): ValidatedJson<RoleBody>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	let before = role_repository.find_by_id(id).await.ok();
+
+	let version = role_repository.update(id, &body, version)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	permission_cache.clear();
+	let after = role_repository.find_by_id(id).await.ok();
+	audit.record("update", "role", id, before.as_ref(), after.as_ref()).await;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": "Data Role Berhasil Diupdate." }).to_string()
+	))
+}
+
+#[utoipa::path(
+	delete,
+	path = "/api/role/{id}",
+	tag = "Role",
+	params(
+		("id" = i32, Path, description = "Role id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	responses(
+		(status = 200, description = "Role deleted with its permissions", body = MessageResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks role.manage", body = ErrorResponse),
+		(status = 404, description = "Role not found", body = ErrorResponse),
+		(status = 409, description = "The role is built in or still assigned to users, deleted ones included", body = ErrorResponse),
+		(status = 412, description = "Role was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn delete(
+	State(role_repository): State<DynRoleRepository>,
+	State(permission_cache): State<Arc<PermissionCache>>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let before = role_repository.find_by_id(id).await.ok();
+
+	role_repository.delete(id, version)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	permission_cache.clear();
+	audit.record("delete", "role", id, before.as_ref(), None).await;
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "message": "Data Role Berhasil Dihapus." }).to_string()
+	))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/permission",
+	tag = "Role",
+	responses(
+		(status = 200, description = "Every permission a role can be granted, ordered by code", body = DataResponse<Vec<PermissionData>>),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks role.manage", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn permissions(
+	State(role_repository): State<DynRoleRepository>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let query_find_many = role_repository.permissions()
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "data": query_find_many }).to_string()
+	))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/user/me/permissions",
+	tag = "User",
+	responses(
+		(status = 200, description = "Role of the caller and the permissions it grants, as checked by the server", body = DataResponse<PermissionSummary>),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn my_permissions(
+	Extension(permissions): Extension<Permissions>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let summary = PermissionSummary { role: permissions.role.clone(), permissions: permissions.codes() };
+
+	Ok((
+		StatusCode::OK,
+		json!({ "success": true, "data": summary }).to_string()
+	))
+}
@@ -1,60 +1,106 @@
+use std::sync::Arc;
+
 use axum:: {
-	extract::{ Path, State },
-	http::{ StatusCode },
-	Json
+	extract::{ Multipart, State },
+	http::{ header, HeaderName, StatusCode },
+	response::Response,
+	Extension
 };
 
-use sqlx::postgres::PgPool;
 use serde_json::json;
+use futures_util::StreamExt;
+use time::{ format_description::well_known::Rfc3339, OffsetDateTime };
+use validator::Validate;
 
-use bcrypt::{ DEFAULT_COST, hash, verify };
+use bcrypt::{ DEFAULT_COST, hash };
 
-use crate::model::user_model::{ UserCreateDto, UserUpdateDto, UserData, UserPaginate };
-use crate::model::utils_model::{ PaginationBody, PaginationResponse };
+use crate::controller::file_controller::user_image_exists;
+use crate::model::user_model::{ JwtClaims, DEFAULT_USER_PHOTO, UserCreateDto, UserData, UserImportRow, UserInviteData, UserInviteDto, UserInviteResponse, UserProfileDto, UserReplaceDto, UserUpdateDto, UserPaginate, UserView, INVITE_TTL, USER_EXPORT_COLUMNS, USER_SORT_FIELDS, USER_FILTER_FIELDS };
+use crate::model::utils_model::{ DataResponse, ErrorResponse, ImportReport, ImportResponse, MessageResponse, PaginateResponse, PaginationBody, PaginationResponse, ValidationErrorResponse };
+use crate::repository::RepositoryError;
+use crate::state::DynUserRepository;
+use crate::storage::DynStorage;
+use crate::utils::audit::Audit;
+use crate::utils::extractor::{ etag, field_errors, IfMatch, Json, Path, Query, ValidatedJson };
+use crate::utils::pagination::PageRequest;
+use crate::utils::permission::{ PermissionCache, Permissions };
+use crate::utils::phone;
+use crate::utils::tabular::{ self, push_row_error, ExportQuery, ImportQuery, EXPORT_BATCH_SIZE };
+use crate::utils::token::{ random_token, token_hash };
 
+#[utoipa::path(
+	post,
+	path = "/api/user/search-paginate",
+	tag = "User",
+	request_body = PaginationBody,
+	responses(
+		(status = 200, description = "Paginated users whose username, full name or phone number, written in any format, match the term", body = PaginateResponse<UserView>),
+		(status = 400, description = "Invalid page, per_page, sort_by, cursor or filter", body = ErrorResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "include_deleted requested without user.manage", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
 pub async fn search_paginate(
-	State(pg_pool): State<PgPool>,
+	State(user_repository): State<DynUserRepository>,
+	Extension(permissions): Extension<Permissions>,
 	Json(body): Json<PaginationBody>
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
+	paginate(user_repository, &permissions, body).await
+}
 
-	const PAGE_TAKE: i64 = 10;
+#[utoipa::path(
+	get,
+	path = "/api/user/search-paginate",
+	tag = "User",
+	params(PaginationBody),
+	responses(
+		(status = 200, description = "Paginated users whose username, full name or phone number, written in any format, match the term and filters", body = PaginateResponse<UserView>),
+		(status = 400, description = "Invalid page, per_page, sort_by, cursor or filter", body = ErrorResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "include_deleted requested without user.manage", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn filter_paginate(
+	State(user_repository): State<DynUserRepository>,
+	Extension(permissions): Extension<Permissions>,
+	Query(body): Query<PaginationBody>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	paginate(user_repository, &permissions, body).await
+}
 
-	let query_count: i64 = sqlx::query_scalar(
-		"SELECT COUNT(id) from user_system"
-	).fetch_one(&pg_pool)
-	.await
-	.map_err(|e| {
+async fn paginate(
+	user_repository: DynUserRepository,
+	permissions: &Permissions,
+	body: PaginationBody
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	if body.include_deleted {
+		permissions.require("user.manage")?;
+	}
+
+	let page_request = PageRequest::new(body, USER_SORT_FIELDS, USER_FILTER_FIELDS)
+	.map_err(|message| {
 		(
-			StatusCode::INTERNAL_SERVER_ERROR,
-			json!({ "success": false, "message": e.to_string() }).to_string()
+			StatusCode::BAD_REQUEST,
+			json!({ "success": false, "message": message }).to_string()
 		)
 	})?;
 
-	let query_search = sqlx::query_as!(
-		UserData,
-		"SELECT * FROM user_system WHERE username ILIKE $1 OR full_name ILIKE $2
-		LIMIT($3) OFFSET($4)",
-		format!("%{}%", body.term),
-		format!("%{}%", body.term),
-		PAGE_TAKE,
-		(&body.page - 1) * PAGE_TAKE
-	).fetch_all(&pg_pool)
+	let query_search = user_repository.search_paginate(&page_request)
 	.await
 	.map_err(|e| {
 		(
-			StatusCode::INTERNAL_SERVER_ERROR,
-			json!({ "success": false, "message": e.to_string()}).to_string()
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
 		)
 	})?;
 
 	let pagination_response = UserPaginate {
-		data: query_search,
-		paginate: PaginationResponse {
-			per_page: PAGE_TAKE,
-			total_page: ((query_count as f64 / PAGE_TAKE as f64) * 0.4).round() as i64,
-			count: query_count,
-			current_page: body.page
-		}
+		paginate: PaginationResponse::new(&page_request, &query_search),
+		data: query_search.data
 	};
 
 	Ok((
@@ -63,156 +109,941 @@ pub async fn search_paginate(
 	))
 }
 
+#[utoipa::path(
+	get,
+	path = "/api/user/me",
+	tag = "User",
+	responses(
+		(status = 200, description = "Profile of the caller, its version is also sent as ETag", body = DataResponse<UserView>, headers(("ETag" = String, description = "Version to send back in If-Match"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 404, description = "The user of the token was deleted", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn me(
+	State(user_repository): State<DynUserRepository>,
+	Extension(claims): Extension<JwtClaims>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	view(&user_repository, claims.id).await
+}
+
+#[utoipa::path(
+	patch,
+	path = "/api/user/me",
+	tag = "User",
+	params(("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")),
+	request_body = UserProfileDto,
+	responses(
+		(status = 200, description = "Fields present in the body updated, the rest left untouched", body = MessageResponse, headers(("ETag" = String, description = "New version of the profile"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 404, description = "The user of the token was deleted", body = ErrorResponse),
+		(status = 412, description = "Profile was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 422, description = "Body fails validation or sets a non-nullable field to null, or photo was not uploaded", body = ValidationErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn update_me(
+	State(user_repository): State<DynUserRepository>,
+	State(storage): State<DynStorage>,
+	Extension(claims): Extension<JwtClaims>,
+	audit: Audit,
+	IfMatch(version): IfMatch,
+	ValidatedJson(mut body): ValidatedJson<UserProfileDto>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	check_photo(&storage, body.photo.as_deref()).await?;
+	body.phone_number = body.phone_number.as_deref().map(phone::e164);
+	let before = user_repository.find_by_id(claims.id).await.ok().map(|user| user.audit_snapshot());
+
+	let version = user_repository.patch(claims.id, &body.into(), None, version)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let after = user_repository.find_by_id(claims.id).await.ok().map(|user| user.audit_snapshot());
+	audit.record("update", "user", claims.id, before.as_ref(), after.as_ref()).await;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": "Profil Anda Berhasil Diperbaharui." }).to_string()
+	))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/user/{id}",
+	tag = "User",
+	params(("id" = i32, Path, description = "User id")),
+	responses(
+		(status = 200, description = "The user, its version is also sent as ETag", body = DataResponse<UserView>, headers(("ETag" = String, description = "Version to send back in If-Match"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.manage", body = ErrorResponse),
+		(status = 404, description = "User not found", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn find_by_id(
+	State(user_repository): State<DynUserRepository>,
+	Path(id): Path<i32>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	view(&user_repository, id).await
+}
+
+async fn view(user_repository: &DynUserRepository, id: i32) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	let query_find_first = user_repository.find_view(id)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(query_find_first.version))],
+		json!({ "success": true, "data": query_find_first }).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/user",
+	tag = "User",
+	request_body = UserCreateDto,
+	params(("Idempotency-Key" = Option<String>, Header, description = "Retries with the same key and body replay the first response for 24 hours")),
+	responses(
+		(status = 201, description = "User created", body = MessageResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.create, or gives a role other than their own without user.manage", body = ErrorResponse),
+		(status = 409, description = "A live user has the username, or a request with the same Idempotency-Key is still running", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field, photo was not uploaded, or the Idempotency-Key was used for another body", body = ValidationErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
 pub async fn create(
-	State(pg_pool): State<PgPool>,
-	Json(body): Json<UserCreateDto>
+	State(user_repository): State<DynUserRepository>,
+	State(storage): State<DynStorage>,
+	Extension(permissions): Extension<Permissions>,
+	audit: Audit,
+	ValidatedJson(mut body): ValidatedJson<UserCreateDto>
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-	
-	let hashed_password = hash(body.password, DEFAULT_COST).unwrap();
-
-	sqlx::query!(
-		"INSERT INTO user_system (username, password, full_name, address, phone_number, role, photo) VALUES ($1, $2, $3, $4, $5, $6, $7)",
-		body.username,
-		hashed_password,
-		body.full_name,
-		body.address,
-		body.phone_number,
-		body.role,
-		body.photo
-	).execute(&pg_pool)
+	require_manage_for_role(&permissions, &body.role)?;
+	check_photo(&storage, Some(&body.photo)).await?;
+	body.phone_number = phone::e164(&body.phone_number);
+
+	let hashed_password = hash(&body.password, DEFAULT_COST).unwrap();
+
+	let query_insert = user_repository.create(&body, &hashed_password)
 	.await
 	.map_err(|e| {
 		(
-			StatusCode::INTERNAL_SERVER_ERROR,
+			e.status_code(),
 			json!({ "success": false, "message": e.to_string() }).to_string()
 		)
 	})?;
 
+	audit.record("create", "user", query_insert.id, None, Some(&query_insert.audit_snapshot())).await;
+
 	Ok((
 		StatusCode::CREATED,
 		json!({ "success": true, "message": format!("Data User {} berhasil ditambahkan.", body.full_name) }).to_string()
 	))
 }
 
+#[utoipa::path(
+	put,
+	path = "/api/user/{id}",
+	tag = "User",
+	params(
+		("id" = i32, Path, description = "User id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	request_body = UserReplaceDto,
+	responses(
+		(status = 200, description = "Every field of the user replaced", body = MessageResponse, headers(("ETag" = String, description = "New version of the user"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.update, or edited a user of another role, changed the username or role or sent a password without user.manage", body = ErrorResponse),
+		(status = 404, description = "User not found", body = ErrorResponse),
+		(status = 409, description = "A live user has the new username", body = ErrorResponse),
+		(status = 412, description = "User was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field, or photo was not uploaded", body = ValidationErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+#[allow(clippy::too_many_arguments)]
 pub async fn update(
-	State(pg_pool): State<PgPool>,
+	State(user_repository): State<DynUserRepository>,
+	State(storage): State<DynStorage>,
+	State(permission_cache): State<Arc<PermissionCache>>,
+	Extension(permissions): Extension<Permissions>,
+	audit: Audit,
 	Path(id): Path<i32>,
-	Json(body): Json<UserUpdateDto>
-) -> Result<(StatusCode, String), (StatusCode, String)> {
+	IfMatch(version): IfMatch,
+	ValidatedJson(mut body): ValidatedJson<UserReplaceDto>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	body.phone_number = phone::e164(&body.phone_number);
+	let current = user_repository.find_by_id(id).await.ok();
+	if let Some(current) = &current {
+		require_manage_for_role(&permissions, &current.role)?;
+	}
+	require_manage_for_identity(&permissions, current.as_ref(), Some(&body.username), Some(&body.role), body.password.is_some())?;
+	check_photo(&storage, Some(&body.photo)).await?;
 
-	if body.password.is_none() {
-		sqlx::query!(
-			"UPDATE user_system SET username = $1, full_name = $2, address = $3, phone_number = $4, role = $5, photo = $6 WHERE 
-			id = $7",
-			body.username,
-			body.full_name,
-			body.address,
-			body.phone_number,
-			body.role,
-			body.photo,
-			id
-		).execute(&pg_pool)
-		.await
-		.map_err(|e| {
-			(
-				StatusCode::INTERNAL_SERVER_ERROR,
-				json!({ "success": false, "message": e.to_string() }).to_string()
-			)
-		})?;
-
-		Ok((
-			StatusCode::OK,
-			json!({ "success": true, "message": "Data User berhasil diperbaharui."}).to_string()
-		))
-
-	} else {
-		let query_find_first = sqlx::query_as!(
-			UserData,
-			"SELECT * FROM user_system WHERE id = $1 LIMIT 1",
-			id
-		).fetch_one(&pg_pool)
-		.await
-		.map_err(|e| {
-			(
-				StatusCode::INTERNAL_SERVER_ERROR,
-				json!({ "success": false, "message": e.to_string() }).to_string()
-			)
-		})?;
-
-		let compare_password = verify(&body.password.as_ref().unwrap(), &query_find_first.password);
-
-		if compare_password.unwrap() == true {
-			sqlx::query!(
-				"UPDATE user_system SET username = $1, full_name = $2, address = $3, phone_number = $4, role = $5, photo = $6 WHERE 
-				id = $7",
-				body.username,
-				body.full_name,
-				body.address,
-				body.phone_number,
-				body.role,
-				body.photo,
-				id
-			).execute(&pg_pool)
-			.await
-			.map_err(|e| {
-				(
-					StatusCode::INTERNAL_SERVER_ERROR,
-					json!({ "success": false, "message": e.to_string() }).to_string()
-				)
-			})?;
+	let password_hash = body.password.as_ref().map(|password| hash(password, DEFAULT_COST).unwrap());
+	let before = current.as_ref().map(UserData::audit_snapshot);
 
-			Ok((
-				StatusCode::OK,
-				json!({ "success": true, "message": "Data User berhasil diperbaharui."}).to_string()
-			))
-		} else {
-			sqlx::query!(
-			"UPDATE user_system SET username = $1, password = $8, full_name = $2, address = $3, phone_number = $4, role = $5, photo = $6 WHERE 
-				id = $7",
-				body.username,
-				body.full_name,
-				body.address,
-				body.phone_number,
-				body.role,
-				body.photo,
-				id,
-				&body.password.unwrap()
-			).execute(&pg_pool)
-			.await
-			.map_err(|e| {
-				(
-					StatusCode::INTERNAL_SERVER_ERROR,
-					json!({ "success": false, "message": e.to_string() }).to_string()
-				)
-			})?;
+	let version = user_repository.update(id, &body, password_hash.as_deref(), version)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
 
-			Ok((
-				StatusCode::OK,
-				json!({ "success": true, "message": "Data User berhasil diperbaharui."}).to_string()
-			))
-		}
+	// Tokens of the user hold permissions resolved from the old role.
+	if current.as_ref().is_some_and(|current| body.role != current.role) {
+		permission_cache.clear();
+	}
+
+	let after = user_repository.find_by_id(id).await.ok().map(|user| user.audit_snapshot());
+	audit.record("update", "user", id, before.as_ref(), after.as_ref()).await;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": "Data User berhasil diperbaharui."}).to_string()
+	))
+}
+
+#[utoipa::path(
+	patch,
+	path = "/api/user/{id}",
+	tag = "User",
+	params(
+		("id" = i32, Path, description = "User id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	request_body = UserUpdateDto,
+	responses(
+		(status = 200, description = "Fields present in the body updated, the rest left untouched", body = MessageResponse, headers(("ETag" = String, description = "New version of the user"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.update, or edited a user of another role, changed the username or role or sent a password without user.manage", body = ErrorResponse),
+		(status = 404, description = "User not found", body = ErrorResponse),
+		(status = 409, description = "A live user has the new username", body = ErrorResponse),
+		(status = 412, description = "User was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 422, description = "Body fails validation or sets a non-nullable field to null, or photo was not uploaded", body = ValidationErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn patch(
+	State(user_repository): State<DynUserRepository>,
+	State(storage): State<DynStorage>,
+	State(permission_cache): State<Arc<PermissionCache>>,
+	Extension(permissions): Extension<Permissions>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch,
+	ValidatedJson(mut body): ValidatedJson<UserUpdateDto>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	body.phone_number = body.phone_number.as_deref().map(phone::e164);
+	let current = user_repository.find_by_id(id).await.ok();
+	if let Some(current) = &current {
+		require_manage_for_role(&permissions, &current.role)?;
+	}
+	require_manage_for_identity(&permissions, current.as_ref(), body.username.as_deref(), body.role.as_deref(), body.password.is_some())?;
+	check_photo(&storage, body.photo.as_deref()).await?;
+
+	let password_hash = body.password.as_ref().map(|password| hash(password, DEFAULT_COST).unwrap());
+	let before = current.as_ref().map(UserData::audit_snapshot);
+
+	let version = user_repository.patch(id, &body, password_hash.as_deref(), version)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	// Tokens of the user hold permissions resolved from the old role.
+	if current.as_ref().is_some_and(|current| body.role.as_ref().is_some_and(|role| *role != current.role)) {
+		permission_cache.clear();
+	}
+
+	let after = user_repository.find_by_id(id).await.ok().map(|user| user.audit_snapshot());
+	audit.record("update", "user", id, before.as_ref(), after.as_ref()).await;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": "Data User berhasil diperbaharui."}).to_string()
+	))
+}
+
+/// Fails with 422 unless the photo was stored through `/api/files/user` or is the default one.
+async fn check_photo(storage: &DynStorage, photo: Option<&str>) -> Result<(), (StatusCode, String)> {
+	match photo {
+		Some(photo) if !photo_exists(storage, photo).await => Err((
+			StatusCode::UNPROCESSABLE_ENTITY,
+			json!({ "success": false, "message": format!("Photo '{photo}' Belum Diupload.") }).to_string()
+		)),
+		_ => Ok(())
 	}
 }
 
+async fn photo_exists(storage: &DynStorage, photo: &str) -> bool {
+	photo == DEFAULT_USER_PHOTO || user_image_exists(storage, photo).await
+}
+
+/// Acting on a user of a role other than the caller's own needs `user.manage`, `user.create` alone would let a cashier
+/// create an admin and log in as it, `user.update` or `user.delete` alone would let it edit or delete an admin.
+fn require_manage_for_role(permissions: &Permissions, role: &str) -> Result<(), (StatusCode, String)> {
+	if role != permissions.role {
+		permissions.require("user.manage")?;
+	}
+
+	Ok(())
+}
+
+/// Only holders of `user.manage` may change a username or role, sending back the current values is allowed to anyone.
+/// Setting a password needs it whatever the value, comparing it with the current one would tell a guess apart. Users
+/// change their own password through `/api/auth/change-password`. An unknown user passes, the write answers 404.
+fn require_manage_for_identity(
+	permissions: &Permissions,
+	current: Option<&UserData>,
+	username: Option<&str>,
+	role: Option<&str>,
+	sets_password: bool
+) -> Result<(), (StatusCode, String)> {
+	let Some(current) = current else {
+		return Ok(());
+	};
+
+	let username_changes = username.is_some_and(|username| username != current.username);
+	let role_changes = role.is_some_and(|role| role != current.role);
+
+	if username_changes || role_changes || sets_password {
+		permissions.require("user.manage")?;
+	}
+
+	Ok(())
+}
+
+#[utoipa::path(
+	delete,
+	path = "/api/user/{id}",
+	tag = "User",
+	params(
+		("id" = i32, Path, description = "User id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	responses(
+		(status = 200, description = "User soft-deleted, it can be restored until the purge job removes it", body = MessageResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.delete, or deleted a user of another role without user.manage", body = ErrorResponse),
+		(status = 404, description = "User not found", body = ErrorResponse),
+		(status = 409, description = "Users cannot delete themselves", body = ErrorResponse),
+		(status = 412, description = "User was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
 pub async fn delete(
-	State(pg_pool): State<PgPool>,
-	Path(id): Path<i32>
+	State(user_repository): State<DynUserRepository>,
+	Extension(claims): Extension<JwtClaims>,
+	Extension(permissions): Extension<Permissions>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-	sqlx::query!(
-		"DELETE FROM user_system WHERE id = $1",
-		id
-	).execute(&pg_pool)
+	if id == claims.id {
+		return Err((
+			StatusCode::CONFLICT,
+			json!({ "success": false, "message": "Tidak Dapat Menghapus Akun Sendiri." }).to_string()
+		));
+	}
+
+	let current = user_repository.find_by_id(id).await.ok();
+	if let Some(current) = &current {
+		require_manage_for_role(&permissions, &current.role)?;
+	}
+	let before = current.as_ref().map(UserData::audit_snapshot);
+
+	user_repository.delete(id, version)
 	.await
 	.map_err(|e| {
 		(
-			StatusCode::INTERNAL_SERVER_ERROR,
+			e.status_code(),
 			json!({ "success": false, "message": e.to_string() }).to_string()
 		)
 	})?;
 
+	audit.record("delete", "user", id, before.as_ref(), None).await;
+
 	Ok((
 		StatusCode::OK,
 		json!({ "success": true, "message": "Data User berhasil dihapus." }).to_string()
 	))
 }
 
+#[utoipa::path(
+	post,
+	path = "/api/user/{id}/restore",
+	tag = "User",
+	params(("id" = i32, Path, description = "User id")),
+	responses(
+		(status = 200, description = "User restored", body = MessageResponse, headers(("ETag" = String, description = "New version of the user"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.manage", body = ErrorResponse),
+		(status = 404, description = "No soft-deleted user with this id", body = ErrorResponse),
+		(status = 409, description = "A live user took the username meanwhile", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn restore(
+	State(user_repository): State<DynUserRepository>,
+	audit: Audit,
+	Path(id): Path<i32>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	let version = user_repository.restore(id)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let after = user_repository.find_by_id(id).await.ok().map(|user| user.audit_snapshot());
+	audit.record("restore", "user", id, None, after.as_ref()).await;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": "Data User Berhasil Dipulihkan." }).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/user/invite",
+	tag = "User",
+	request_body = UserInviteDto,
+	params(("Idempotency-Key" = Option<String>, Header, description = "Retries with the same key and body replay the first response for 24 hours")),
+	responses(
+		(status = 201, description = "Inactive user created, the token of its activation link is only returned here", body = UserInviteResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.manage", body = ErrorResponse),
+		(status = 409, description = "A live user has the username, or a request with the same Idempotency-Key is still running", body = ErrorResponse),
+		(status = 422, description = "Body fails validation, lists every invalid field, photo was not uploaded, or the Idempotency-Key was used for another body", body = ValidationErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn invite(
+	State(user_repository): State<DynUserRepository>,
+	State(storage): State<DynStorage>,
+	audit: Audit,
+	ValidatedJson(mut body): ValidatedJson<UserInviteDto>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	check_photo(&storage, Some(&body.photo)).await?;
+	body.phone_number = phone::e164(&body.phone_number);
+
+	let token = random_token();
+	let expires_at = OffsetDateTime::now_utc() + INVITE_TTL;
+
+	let query_insert = user_repository.invite(&body, &token_hash(&token), expires_at)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	audit.record("invite", "user", query_insert.id, None, Some(&query_insert.audit_snapshot())).await;
+
+	Ok((
+		StatusCode::CREATED,
+		json!({
+			"success": true,
+			"message": format!("Undangan Untuk {} Berhasil Dibuat.", body.full_name),
+			"data": UserInviteData { user_id: query_insert.id, token, expires_at }
+		}).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/user/{id}/invite/resend",
+	tag = "User",
+	params(("id" = i32, Path, description = "User id")),
+	responses(
+		(status = 200, description = "New activation link issued, earlier links of the user stop working", body = UserInviteResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.manage", body = ErrorResponse),
+		(status = 404, description = "User not found", body = ErrorResponse),
+		(status = 409, description = "The user already activated the account", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn resend_invite(
+	State(user_repository): State<DynUserRepository>,
+	audit: Audit,
+	Path(id): Path<i32>
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let token = random_token();
+	let expires_at = OffsetDateTime::now_utc() + INVITE_TTL;
+
+	user_repository.resend_invite(id, &token_hash(&token), expires_at)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let expiry = |expires_at: OffsetDateTime| json!({ "invite_expires_at": expires_at.format(&Rfc3339).unwrap_or_default() });
+	audit.record("resend_invite", "user", id, None, Some(&expiry(expires_at))).await;
+
+	Ok((
+		StatusCode::OK,
+		json!({
+			"success": true,
+			"message": "Undangan Berhasil Dikirim Ulang.",
+			"data": UserInviteData { user_id: id, token, expires_at }
+		}).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/user/{id}/deactivate",
+	tag = "User",
+	params(
+		("id" = i32, Path, description = "User id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	responses(
+		(status = 200, description = "User can no longer log in and its tokens are rejected", body = MessageResponse, headers(("ETag" = String, description = "New version of the user"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.manage", body = ErrorResponse),
+		(status = 404, description = "User not found", body = ErrorResponse),
+		(status = 409, description = "Admins cannot deactivate themselves, or the invite of the user is pending", body = ErrorResponse),
+		(status = 412, description = "User was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn deactivate(
+	State(user_repository): State<DynUserRepository>,
+	Extension(claims): Extension<JwtClaims>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	if id == claims.id {
+		return Err((
+			StatusCode::CONFLICT,
+			json!({ "success": false, "message": "Tidak Dapat Menonaktifkan Akun Sendiri." }).to_string()
+		));
+	}
+
+	set_active(user_repository, audit, id, false, version).await
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/user/{id}/reactivate",
+	tag = "User",
+	params(
+		("id" = i32, Path, description = "User id"),
+		("If-Match" = String, Header, description = "ETag of the version being edited, `*` skips the check")
+	),
+	responses(
+		(status = 200, description = "Deactivated user can log in again", body = MessageResponse, headers(("ETag" = String, description = "New version of the user"))),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.manage", body = ErrorResponse),
+		(status = 404, description = "User not found", body = ErrorResponse),
+		(status = 409, description = "The invite of the user is pending, it activates the user once used", body = ErrorResponse),
+		(status = 412, description = "User was changed since the If-Match version was read", body = ErrorResponse),
+		(status = 428, description = "If-Match header is missing", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn reactivate(
+	State(user_repository): State<DynUserRepository>,
+	audit: Audit,
+	Path(id): Path<i32>,
+	IfMatch(version): IfMatch
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	set_active(user_repository, audit, id, true, version).await
+}
+
+async fn set_active(
+	user_repository: DynUserRepository,
+	audit: Audit,
+	id: i32,
+	is_active: bool,
+	version: Option<i32>
+) -> Result<(StatusCode, [(HeaderName, String); 1], String), (StatusCode, String)> {
+	let before = user_repository.find_by_id(id).await.ok().map(|user| user.audit_snapshot());
+
+	let version = user_repository.set_active(id, is_active, version)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+
+	let after = user_repository.find_by_id(id).await.ok().map(|user| user.audit_snapshot());
+	let (action, message) = match is_active {
+		true => ("reactivate", "User Berhasil Diaktifkan Kembali."),
+		false => ("deactivate", "User Berhasil Dinonaktifkan.")
+	};
+	audit.record(action, "user", id, before.as_ref(), after.as_ref()).await;
+
+	Ok((
+		StatusCode::OK,
+		[(header::ETAG, etag(version))],
+		json!({ "success": true, "message": message }).to_string()
+	))
+}
+
+#[utoipa::path(
+	post,
+	path = "/api/user/import",
+	tag = "User",
+	params(ImportQuery),
+	request_body(content_type = "multipart/form-data", description = "A csv or xlsx `file` with the columns username, password, full_name, address, phone_number, photo (defaults to default_user.png) and role (defaults to cashier), other columns are ignored"),
+	responses(
+		(status = 200, description = "Dry run, every row is valid and nothing was written", body = ImportResponse),
+		(status = 201, description = "Every row was created in one transaction", body = ImportResponse),
+		(status = 400, description = "The file field is missing or the body is not multipart", body = ErrorResponse),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.manage", body = ErrorResponse),
+		(status = 422, description = "The file cannot be read or rows are invalid, lists the errors of each row and nothing was written", body = ImportResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn import(
+	State(user_repository): State<DynUserRepository>,
+	State(storage): State<DynStorage>,
+	audit: Audit,
+	Query(query): Query<ImportQuery>,
+	multipart: Multipart
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+	let (format, bytes) = tabular::read_upload(multipart, query.format).await?;
+	let rows = tabular::read_rows(format, &bytes).map_err(|message| {
+		(
+			StatusCode::UNPROCESSABLE_ENTITY,
+			json!({ "success": false, "message": message }).to_string()
+		)
+	})?;
+
+	let mut errors = vec![];
+	let mut import_rows = vec![];
+
+	for row in &rows {
+		let text = |column: &str| row.get(column).unwrap_or_default().to_string();
+		let user = UserCreateDto {
+			username: text("username"),
+			password: text("password"),
+			full_name: text("full_name"),
+			address: row.get("address").map(str::to_string),
+			phone_number: phone::e164(&text("phone_number")),
+			photo: row.get("photo").unwrap_or(DEFAULT_USER_PHOTO).to_string(),
+			role: row.get("role").unwrap_or("cashier").to_string()
+		};
+
+		match user.validate() {
+			Ok(()) if !photo_exists(&storage, &user.photo).await => push_row_error(&mut errors, row.line, "photo", "Belum diupload."),
+			Ok(()) => import_rows.push(UserImportRow { line: row.line, user, password_hash: String::new() }),
+			Err(e) => for error in field_errors(&e) {
+				push_row_error(&mut errors, row.line, &error.field, error.message);
+			}
+		}
+	}
+
+	// Hashing is slow on purpose, it only runs once the whole file is known to be written.
+	let dry_run = query.dry_run || !errors.is_empty();
+	if !dry_run {
+		hash_passwords(&mut import_rows).await?;
+	}
+
+	let outcome = user_repository.import(&import_rows, dry_run)
+	.await
+	.map_err(|e| {
+		(
+			e.status_code(),
+			json!({ "success": false, "message": e.to_string() }).to_string()
+		)
+	})?;
+	errors.extend(outcome.errors);
+	errors.sort_by_key(|error| error.row);
+
+	for user in &outcome.created {
+		audit.record("import", "user", user.id, None, Some(&user.audit_snapshot())).await;
+	}
+
+	Ok(tabular::import_response(ImportReport { dry_run: query.dry_run, rows: rows.len(), created: outcome.created.len(), errors }))
+}
+
+/// Hashes every password on the blocking pool in parallel, bcrypt would otherwise stall the runtime for seconds.
+async fn hash_passwords(rows: &mut [UserImportRow]) -> Result<(), (StatusCode, String)> {
+	let hashes = rows.iter()
+		.map(|row| {
+			let password = row.user.password.clone();
+			tokio::task::spawn_blocking(move || hash(password, DEFAULT_COST))
+		})
+		.collect::<Vec<_>>();
+
+	for (row, password_hash) in rows.iter_mut().zip(futures_util::future::join_all(hashes).await) {
+		row.password_hash = password_hash
+			.map_err(|e| e.to_string())
+			.and_then(|password_hash| password_hash.map_err(|e| e.to_string()))
+			.map_err(|message| {
+				(
+					StatusCode::INTERNAL_SERVER_ERROR,
+					json!({ "success": false, "message": message }).to_string()
+				)
+			})?;
+	}
+
+	Ok(())
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/user/export",
+	tag = "User",
+	params(ExportQuery),
+	responses(
+		(status = 200, description = "Live users ordered by id as a csv or xlsx attachment, password hashes are never included", content_type = "text/csv"),
+		(status = 401, description = "Missing or invalid token", body = ErrorResponse),
+		(status = 403, description = "Caller lacks user.manage", body = ErrorResponse),
+		(status = 500, description = "Database error", body = ErrorResponse)
+	),
+	security(("bearer_auth" = []))
+)]
+pub async fn export(
+	State(user_repository): State<DynUserRepository>,
+	Query(query): Query<ExportQuery>
+) -> Result<Response, (StatusCode, String)> {
+	// Walks the table by id so a large user list is never held in memory for a csv, boxed because the compiler cannot
+	// prove the unfolded future Send through the trait object otherwise.
+	let batches = futures_util::stream::try_unfold((user_repository, Some(0)), next_export_batch).boxed();
+
+	tabular::export(query.format, "user", USER_EXPORT_COLUMNS, batches).await
+}
+
+/// Rows of the users following `after_id`, and the id to continue after, `None` once the last batch was read.
+async fn next_export_batch(
+	(user_repository, after_id): (DynUserRepository, Option<i32>)
+) -> Result<Option<(Vec<Vec<String>>, (DynUserRepository, Option<i32>))>, RepositoryError> {
+	let Some(after_id) = after_id else {
+		return Ok(None);
+	};
+
+	let users = user_repository.find_batch(after_id, EXPORT_BATCH_SIZE).await?;
+	let next = users.last().filter(|_| users.len() as i64 == EXPORT_BATCH_SIZE).map(|user| user.id);
+
+	Ok(Some((users.iter().map(UserView::export_row).collect(), (user_repository, next))))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use bcrypt::verify;
+
+	use super::*;
+	use crate::repository::audit_repository::InMemoryAuditRepository;
+	use crate::repository::user_repository::InMemoryUserRepository;
+	use crate::storage::memory::InMemoryStorage;
+
+	async fn repository_with_user(password: &str) -> (DynUserRepository, UserData) {
+		let user_repository: DynUserRepository = Arc::new(InMemoryUserRepository::default());
+		let body = UserCreateDto {
+			username: "budi".to_string(),
+			password: password.to_string(),
+			full_name: "Budi Santoso".to_string(),
+			address: Some("Jl. Sudirman 10".to_string()),
+			phone_number: "081298765432".to_string(),
+			photo: "default_user.png".to_string(),
+			role: "cashier".to_string()
+		};
+		let user = user_repository.create(&body, &hash(password, 4).unwrap()).await.unwrap();
+
+		(user_repository, user)
+	}
+
+	fn permissions(role: &str) -> Extension<Permissions> {
+		Extension(Permissions::new(role, vec!["user.update".to_string()]))
+	}
+
+	fn audit() -> Audit {
+		Audit::new(Arc::new(InMemoryAuditRepository::default()), None, None)
+	}
+
+	fn storage() -> DynStorage {
+		Arc::new(InMemoryStorage::default())
+	}
+
+	fn update_body(password: Option<&str>) -> UserUpdateDto {
+		UserUpdateDto {
+			username: None,
+			password: password.map(str::to_string),
+			full_name: Some("Budi S.".to_string()),
+			address: None,
+			phone_number: None,
+			photo: None,
+			role: None,
+			language: None
+		}
+	}
+
+	#[tokio::test]
+	async fn patch_without_password_keeps_the_hash() {
+		let (user_repository, user) = repository_with_user("rahasia123").await;
+
+		patch(State(user_repository.clone()), State(storage()), State(Arc::default()), permissions("admin"), audit(), Path(user.id), IfMatch(None), ValidatedJson(update_body(None))).await.unwrap();
+
+		let updated = user_repository.find_by_id(user.id).await.unwrap();
+		assert_eq!(updated.password, user.password);
+		assert_eq!(updated.full_name, "Budi S.");
+		assert_eq!(updated.address, user.address);
+	}
+
+	#[tokio::test]
+	async fn patch_with_current_password_hashes_it_again() {
+		let (user_repository, user) = repository_with_user("rahasia123").await;
+
+		patch(State(user_repository.clone()), State(storage()), State(Arc::default()), permissions("admin"), audit(), Path(user.id), IfMatch(None), ValidatedJson(update_body(Some("rahasia123")))).await.unwrap();
+
+		let updated = user_repository.find_by_id(user.id).await.unwrap();
+		assert_ne!(updated.password, user.password);
+		assert!(verify("rahasia123", &updated.password).unwrap());
+	}
+
+	#[tokio::test]
+	async fn patch_with_new_password_stores_its_hash() {
+		let (user_repository, user) = repository_with_user("rahasia123").await;
+
+		patch(State(user_repository.clone()), State(storage()), State(Arc::default()), permissions("admin"), audit(), Path(user.id), IfMatch(None), ValidatedJson(update_body(Some("baru12345")))).await.unwrap();
+
+		let updated = user_repository.find_by_id(user.id).await.unwrap();
+		assert_ne!(updated.password, "baru12345");
+		assert!(verify("baru12345", &updated.password).unwrap());
+	}
+
+	#[tokio::test]
+	async fn patch_unknown_user_is_not_found() {
+		let (user_repository, _) = repository_with_user("rahasia123").await;
+
+		let (status, _) = patch(State(user_repository), State(storage()), State(Arc::default()), permissions("admin"), audit(), Path(42), IfMatch(None), ValidatedJson(update_body(Some("baru12345")))).await.unwrap_err();
+
+		assert_eq!(status, StatusCode::NOT_FOUND);
+	}
+
+	#[tokio::test]
+	async fn patch_records_the_changed_fields() {
+		let (user_repository, user) = repository_with_user("rahasia123").await;
+		let audit_repository = Arc::new(InMemoryAuditRepository::default());
+		let audit = Audit::new(audit_repository.clone(), None, Some("10.0.0.1".to_string()));
+
+		let mut body = update_body(Some("baru12345"));
+		body.role = Some("admin".to_string());
+		patch(State(user_repository), State(storage()), State(Arc::default()), permissions("admin"), audit, Path(user.id), IfMatch(None), ValidatedJson(body)).await.unwrap();
+
+		let events = audit_repository.events();
+		assert_eq!(events.len(), 1);
+		assert_eq!((events[0].action.as_str(), events[0].entity_type.as_str()), ("update", "user"));
+		assert_eq!(events[0].changes["role"], json!({ "before": "cashier", "after": "admin" }));
+		assert_eq!(events[0].changes["password"]["after"], "[REDACTED]");
+		assert_eq!(events[0].ip_address.as_deref(), Some("10.0.0.1"));
+	}
+
+	#[tokio::test]
+	async fn usernames_of_live_users_are_taken() {
+		let (user_repository, user) = repository_with_user("rahasia123").await;
+		let siti = user_repository.create(&UserCreateDto {
+			username: "siti".to_string(),
+			password: "rahasia123".to_string(),
+			full_name: "Siti Aminah".to_string(),
+			address: None,
+			phone_number: "081298765433".to_string(),
+			photo: "default_user.png".to_string(),
+			role: "cashier".to_string()
+		}, &user.password).await.unwrap();
+
+		let mut body = update_body(None);
+		body.username = Some("siti".to_string());
+		let (status, _) = patch(State(user_repository.clone()), State(storage()), State(Arc::default()), permissions("admin"), audit(), Path(user.id), IfMatch(None), ValidatedJson(body)).await.unwrap_err();
+		assert_eq!(status, StatusCode::CONFLICT);
+
+		user_repository.delete(siti.id, None).await.unwrap();
+		let mut body = update_body(None);
+		body.username = Some("siti".to_string());
+		patch(State(user_repository.clone()), State(storage()), State(Arc::default()), permissions("admin"), audit(), Path(user.id), IfMatch(None), ValidatedJson(body)).await.unwrap();
+
+		assert!(matches!(user_repository.restore(siti.id).await, Err(RepositoryError::Conflict(_))));
+	}
+
+	#[tokio::test]
+	async fn only_admins_change_usernames_and_roles() {
+		let (user_repository, user) = repository_with_user("rahasia123").await;
+
+		let mut body = update_body(None);
+		body.role = Some("admin".to_string());
+		let (status, _) = patch(State(user_repository.clone()), State(storage()), State(Arc::default()), permissions("cashier"), audit(), Path(user.id), IfMatch(None), ValidatedJson(body)).await.unwrap_err();
+		assert_eq!(status, StatusCode::FORBIDDEN);
+
+		let mut body = update_body(None);
+		body.role = Some("cashier".to_string());
+		body.username = Some("budi".to_string());
+		patch(State(user_repository.clone()), State(storage()), State(Arc::default()), permissions("cashier"), audit(), Path(user.id), IfMatch(None), ValidatedJson(body)).await.unwrap();
+
+		let updated = user_repository.find_by_id(user.id).await.unwrap();
+		assert_eq!((updated.role.as_str(), updated.full_name.as_str()), ("cashier", "Budi S."));
+	}
+
+	#[tokio::test]
+	async fn only_admins_set_the_password_of_another_user() {
+		let (user_repository, user) = repository_with_user("rahasia123").await;
+
+		let (status, _) = patch(State(user_repository.clone()), State(storage()), State(Arc::default()), permissions("cashier"), audit(), Path(user.id), IfMatch(None), ValidatedJson(update_body(Some("diambilalih1")))).await.unwrap_err();
+		assert_eq!(status, StatusCode::FORBIDDEN);
+		let unchanged = user_repository.find_by_id(user.id).await.unwrap();
+		assert!(verify("rahasia123", &unchanged.password).unwrap());
+
+		// The current password is refused just the same, a different answer would confirm a guess.
+		let (status, _) = patch(State(user_repository.clone()), State(storage()), State(Arc::default()), permissions("cashier"), audit(), Path(user.id), IfMatch(None), ValidatedJson(update_body(Some("rahasia123")))).await.unwrap_err();
+		assert_eq!(status, StatusCode::FORBIDDEN);
+	}
+}
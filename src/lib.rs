@@ -0,0 +1,10 @@
+pub mod controller;
+pub mod model;
+pub mod repository;
+pub mod router;
+pub mod state;
+pub mod storage;
+pub mod utils;
+
+pub use router::build_app;
+pub use state::AppState;
@@ -1,14 +1,14 @@
-use axum::{middleware, routing::{ delete, get, post, put }, Router};
+use std::{ net::SocketAddr, sync::Arc, time::Duration };
+
 use sqlx::postgres::PgPoolOptions;
 use tokio::net::TcpListener;
-use tower_http::cors::{ Any, CorsLayer };
-
-mod controller;
-mod model;
-mod utils;
 
-use controller::{auth_controller, category_controller, file_controller, http_controller, user_controller};
-use utils::route_guard::auth_guard;
+use rust_axum_pos_backend::{ build_app, AppState };
+use rust_axum_pos_backend::storage;
+use rust_axum_pos_backend::utils::audit::TrustedProxies;
+use rust_axum_pos_backend::utils::phone::normalize_stored;
+use rust_axum_pos_backend::utils::purge::{ spawn_purge_job, DEFAULT_FILE_GRACE_HOURS, DEFAULT_RETENTION_DAYS };
+use rust_axum_pos_backend::utils::upload::UploadConfig;
 
 #[tokio::main]
 async fn main() {
@@ -17,6 +17,12 @@ async fn main() {
 
     let server_address = std::env::var("SERVER_ADDRESS").unwrap_or("localhost:3000".to_owned());
     let database_url = std::env::var("DATABASE_URL").expect("Database Url from .env file not found.");
+    let retention_days = std::env::var("SOFT_DELETE_RETENTION_DAYS")
+    .map(|days| days.parse().expect("SOFT_DELETE_RETENTION_DAYS must be a number of days."))
+    .unwrap_or(DEFAULT_RETENTION_DAYS);
+    let file_grace_hours = std::env::var("UNREFERENCED_FILE_GRACE_HOURS")
+    .map(|hours| hours.parse().expect("UNREFERENCED_FILE_GRACE_HOURS must be a number of hours."))
+    .unwrap_or(DEFAULT_FILE_GRACE_HOURS);
 
     let db_pool = PgPoolOptions::new()
     .max_connections(16)
@@ -24,53 +30,47 @@ async fn main() {
     .await
     .expect("Failed to connect to the Database.");
 
+    sqlx::migrate!()
+    .run(&db_pool)
+    .await
+    .expect("Failed to run the Database migrations.");
+
+    // One-off maintenance: `cargo run -- normalize-phone-numbers [--dry-run]` rewrites stored numbers to E.164 and exits.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("normalize-phone-numbers") {
+        let dry_run = args.iter().any(|arg| arg == "--dry-run");
+        let report = normalize_stored(&AppState::postgres(db_pool).user_repository, dry_run)
+        .await
+        .expect("Failed to normalize phone numbers.");
+
+        println!("{} normalized{}, {} already normalized, {} invalid.", report.normalized, if dry_run { " (dry run)" } else { "" }, report.unchanged, report.invalid.len());
+        for (id, phone_number) in report.invalid {
+            println!("  user {id}: {phone_number:?}");
+        }
+        return;
+    }
+
     let listener = TcpListener::bind(server_address)
     .await.expect("Couldn't create TCP Listener.");
 
     print!("Listening on {} ", listener.local_addr().unwrap());
 
-    let cors = CorsLayer::new().allow_origin(Any);
+    let app_state = AppState {
+        storage: storage::from_env(),
+        upload_config: Arc::new(UploadConfig::from_env()),
+        trusted_proxies: Arc::new(TrustedProxies::from_env()),
+        ..AppState::postgres(db_pool)
+    };
 
-    let app_router = Router::new()
-    .route("/", get(|| async { "Hello World" }))
-    /* Category Route */
-    .route("/api/category/search-paginate", post(category_controller::search_paginate))
-    .route_layer(middleware::from_fn(auth_guard))
-    .route("/api/category", get(category_controller::find_many))
-    .route_layer(middleware::from_fn(auth_guard))
-    .route("/api/category", post(category_controller::create))
-    .route_layer(middleware::from_fn(auth_guard))
-    .route("/api/category/{id}", put(category_controller::update))
-    .route_layer(middleware::from_fn(auth_guard))
-    .route("/api/category/{id}", delete(category_controller::delete))
-    .route_layer(middleware::from_fn(auth_guard))
+    spawn_purge_job(
+        app_state.clone(),
+        Duration::from_secs(retention_days * 24 * 60 * 60),
+        Duration::from_secs(file_grace_hours * 60 * 60),
+        Duration::from_secs(60 * 60)
+    );
 
-    /* User Route */
-    .route("/api/user/search-paginate", post(user_controller::search_paginate))
-    .route_layer(middleware::from_fn(auth_guard))
-    .route("/api/user", post(user_controller::create))
-    .route_layer(middleware::from_fn(auth_guard))
-    .route("/api/user/{id}", put(user_controller::update))
-    .route_layer(middleware::from_fn(auth_guard))
-    .route("/api/user/{id}", delete(user_controller::delete))
-    .route_layer(middleware::from_fn(auth_guard))
-
-    /* Auth Route */
-    .route("/api/auth/login", post(auth_controller::login))
-    .route("/api/auth/authenticated", post(auth_controller::authenticated))
-    .route_layer(middleware::from_fn(auth_guard))
-    .route("/api/auth/change-password", post(auth_controller::change_password))
-    .route_layer(middleware::from_fn(auth_guard))
-    
-    /* Http Example Route */
-    .route("/api/http", get(http_controller::get_http_example))
-    .route("/api/http", post(http_controller::post_http_example))
-    /* Upload User File Route */
-    .route("/api/files/user", post(file_controller::upload_user_image))
-    .route("/api/files/user/image/{filename}", get(file_controller::get_user_image))
-    .route("/api/files/user/delete/{filename}", delete(file_controller::delete_user_image))
-    .layer(cors)
-    .with_state(db_pool);
+    let app_router = build_app(app_state);
 
-    axum::serve(listener, app_router).await.expect("Error while serving the server.");
+    // Connect info gives the audit trail the client address, or the proxy whose forwarded headers are then believed.
+    axum::serve(listener, app_router.into_make_service_with_connect_info::<SocketAddr>()).await.expect("Error while serving the server.");
 }
@@ -0,0 +1,87 @@
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+use sqlx::types::time::OffsetDateTime;
+use utoipa::ToSchema;
+
+use crate::model::utils_model::PaginationResponse;
+use crate::utils::filter::FilterField;
+use crate::utils::pagination::{ FieldKind, FieldValue, SortField, Sortable };
+
+#[derive(Serialize, Deserialize, Clone, Debug, sqlx::FromRow, ToSchema)]
+pub struct AuditData {
+	pub id: i32,
+	/// `null` when the request carried no token, and for files removed by the purge job.
+	pub actor_id: Option<i32>,
+	pub actor_username: Option<String>,
+	/// One of `create`, `update`, `move`, `reorder`, `merge`, `import`, `delete`, `restore`, `change_password`,
+	/// `invite`, `resend_invite`, `activate`, `deactivate` or `reactivate`.
+	#[schema(example = "update")]
+	pub action: String,
+	/// One of `category`, `user`, `role` or `file`.
+	#[schema(example = "user")]
+	pub entity_type: String,
+	/// Id of the row, or `{prefix}/{file_name}` for files, which are only `delete`d once nothing refers to them.
+	pub entity_id: Option<String>,
+	/// Changed fields as `{ "field": { "before": .., "after": .. } }`, passwords are redacted.
+	#[schema(value_type = Object, example = json!({ "role": { "before": "cashier", "after": "admin" } }))]
+	pub changes: Value,
+	pub ip_address: Option<String>,
+	#[serde(with = "time::serde::rfc3339")]
+	pub created_at: OffsetDateTime
+}
+
+/// An audit event before it is stored, `id` and `created_at` are assigned by the repository.
+#[derive(Clone, Debug)]
+pub struct AuditEvent {
+	pub actor_id: Option<i32>,
+	pub actor_username: Option<String>,
+	pub action: &'static str,
+	pub entity_type: &'static str,
+	pub entity_id: Option<String>,
+	pub changes: Value,
+	pub ip_address: Option<String>
+}
+
+/// Fields `/api/audit` may sort on, the first one is the default.
+pub const AUDIT_SORT_FIELDS: &[SortField] = &[
+	SortField { name: "created_at", kind: FieldKind::Timestamp },
+	SortField { name: "id", kind: FieldKind::Integer },
+	SortField { name: "action", kind: FieldKind::Text },
+	SortField { name: "entity_type", kind: FieldKind::Text }
+];
+
+/// Fields the `filter` parameter of `/api/audit` may reference.
+pub const AUDIT_FILTER_FIELDS: &[FilterField] = &[
+	FilterField { name: "actor_id", kind: FieldKind::Integer },
+	FilterField { name: "actor_username", kind: FieldKind::Text },
+	FilterField { name: "action", kind: FieldKind::Text },
+	FilterField { name: "entity_type", kind: FieldKind::Text },
+	FilterField { name: "entity_id", kind: FieldKind::Text },
+	FilterField { name: "ip_address", kind: FieldKind::Text },
+	FilterField { name: "created_at", kind: FieldKind::Timestamp }
+];
+
+impl Sortable for AuditData {
+	fn id(&self) -> i32 {
+		self.id
+	}
+
+	fn field_value(&self, field: &str) -> FieldValue {
+		match field {
+			"actor_id" => FieldValue::Integer(self.actor_id.unwrap_or_default() as i64),
+			"actor_username" => FieldValue::Text(self.actor_username.clone().unwrap_or_default()),
+			"action" => FieldValue::Text(self.action.clone()),
+			"entity_type" => FieldValue::Text(self.entity_type.clone()),
+			"entity_id" => FieldValue::Text(self.entity_id.clone().unwrap_or_default()),
+			"ip_address" => FieldValue::Text(self.ip_address.clone().unwrap_or_default()),
+			"created_at" => FieldValue::Timestamp(self.created_at),
+			_ => FieldValue::Integer(self.id as i64)
+		}
+	}
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AuditPaginate {
+	pub data: Vec<AuditData>,
+	pub paginate: PaginationResponse
+}
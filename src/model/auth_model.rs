@@ -1,14 +1,41 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Deserialize)]
+use crate::model::user_model::UserView;
+
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct LoginBody {
+	#[validate(custom(function = "crate::utils::validation::not_blank"))]
 	pub username: String,
+	#[validate(custom(function = "crate::utils::validation::not_blank"))]
 	pub password: String
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct ChangePasswordBody {
+	#[validate(custom(function = "crate::utils::validation::not_blank"))]
 	pub username: String,
+	#[validate(custom(function = "crate::utils::validation::not_blank"))]
 	pub old_password: String,
+	#[validate(length(min = 8, message = "Minimal 8 karakter."))]
 	pub new_password: String
-}
\ No newline at end of file
+}
+
+/// Body of the activation route, the password becomes the first password of the invited user.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ActivateBody {
+	#[validate(custom(function = "crate::utils::validation::not_blank"))]
+	pub token: String,
+	#[validate(length(min = 8, message = "Minimal 8 karakter."))]
+	pub password: String
+}
+
+/// Response of the login and authenticated handlers.
+#[derive(Serialize, ToSchema)]
+pub struct AuthResponse {
+	#[schema(example = true)]
+	pub success: bool,
+	pub data: UserView,
+	pub token: String
+}
@@ -1,34 +1,228 @@
+use std::collections::{ HashMap, HashSet };
+
 use serde::{Deserialize, Serialize};
 use sqlx::types::time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use utoipa::{ IntoParams, ToSchema };
+use validator::Validate;
 use crate::model::utils_model::PaginationResponse;
+use crate::utils::filter::FilterField;
+use crate::utils::pagination::{ FieldKind, FieldValue, SortField, Sortable };
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, sqlx::FromRow, ToSchema)]
 pub struct CategoryData {
 	pub id: i32,
 	pub name: String,
+	/// Unique, generated from the name and regenerated when it changes.
+	#[schema(example = "kopi-dan-teh")]
+	pub slug: String,
+	/// `null` for a top-level category.
+	pub parent_id: Option<i32>,
+	/// Position among its siblings, set through the reorder route, ties are ordered by name.
+	pub sort_order: i32,
+	/// File name returned by `/api/files/category`.
+	pub icon: Option<String>,
 	#[serde(with = "time::serde::rfc3339")]
 	pub created_at: OffsetDateTime,
 	#[serde(with = "time::serde::rfc3339")]
-	pub updated_at: OffsetDateTime
+	pub updated_at: OffsetDateTime,
+	/// Set when the row was soft-deleted, only listed with `include_deleted`.
+	#[serde(with = "time::serde::rfc3339::option")]
+	pub deleted_at: Option<OffsetDateTime>,
+	/// Bumped on every write, send it back in `If-Match` to update or delete.
+	pub version: i32
 }
 
-#[derive(Serialize)]
+/// Fields `search_paginate` may sort on, the first one is the default.
+pub const CATEGORY_SORT_FIELDS: &[SortField] = &[
+	SortField { name: "id", kind: FieldKind::Integer },
+	SortField { name: "name", kind: FieldKind::Text },
+	SortField { name: "sort_order", kind: FieldKind::Integer },
+	SortField { name: "created_at", kind: FieldKind::Timestamp },
+	SortField { name: "updated_at", kind: FieldKind::Timestamp }
+];
+
+/// Fields the `filter` parameter of `search_paginate` may reference.
+pub const CATEGORY_FILTER_FIELDS: &[FilterField] = &[
+	FilterField { name: "id", kind: FieldKind::Integer },
+	FilterField { name: "name", kind: FieldKind::Text },
+	FilterField { name: "slug", kind: FieldKind::Text },
+	FilterField { name: "parent_id", kind: FieldKind::Integer },
+	FilterField { name: "created_at", kind: FieldKind::Timestamp },
+	FilterField { name: "updated_at", kind: FieldKind::Timestamp }
+];
+
+impl Sortable for CategoryData {
+	fn id(&self) -> i32 {
+		self.id
+	}
+
+	fn field_value(&self, field: &str) -> FieldValue {
+		match field {
+			"name" => FieldValue::Text(self.name.clone()),
+			"slug" => FieldValue::Text(self.slug.clone()),
+			"parent_id" => FieldValue::Integer(self.parent_id.unwrap_or_default() as i64),
+			"sort_order" => FieldValue::Integer(self.sort_order as i64),
+			"created_at" => FieldValue::Timestamp(self.created_at),
+			"updated_at" => FieldValue::Timestamp(self.updated_at),
+			_ => FieldValue::Integer(self.id as i64)
+		}
+	}
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct CategoryPaginate {
 	pub data: Vec<CategoryData>,
 	pub paginate: PaginationResponse
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct CategoryCreateBody {
-	pub name: String
-} 
+	#[validate(custom(function = "crate::utils::validation::not_blank"), length(max = 100, message = "Maksimal 100 karakter."))]
+	#[schema(max_length = 100)]
+	pub name: String,
+	/// Category to nest the new one under, absent or `null` for a top-level category.
+	#[serde(default)]
+	pub parent_id: Option<i32>,
+	/// File name returned by `/api/files/category`.
+	#[serde(default)]
+	#[validate(custom(function = "crate::utils::validation::file_name"))]
+	pub icon: Option<String>
+}
+
+/// Body of PUT, every field is written, an absent `icon` clears it.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CategoryReplaceBody {
+	#[validate(custom(function = "crate::utils::validation::not_blank"), length(max = 100, message = "Maksimal 100 karakter."))]
+	#[schema(max_length = 100)]
+	pub name: String,
+	/// File name returned by `/api/files/category`.
+	#[serde(default)]
+	#[validate(custom(function = "crate::utils::validation::file_name"))]
+	pub icon: Option<String>
+}
 
-#[derive(Deserialize)]
+/// Body of PATCH, absent fields are left untouched.
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct CategoryUpdateBody {
-	pub name: Option<String>
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::not_blank"), length(max = 100, message = "Maksimal 100 karakter."))]
+	#[schema(max_length = 100)]
+	pub name: Option<String>,
+	/// File name returned by `/api/files/category`, `null` removes the icon.
+	#[serde(default, with = "::serde_with::rust::double_option")]
+	#[schema(value_type = Option<String>)]
+	#[validate(custom(function = "crate::utils::validation::file_name"))]
+	pub icon: Option<Option<String>>
+}
+
+/// Body of the reorder route, the categories are shown in the order of `ids`.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CategoryReorderBody {
+	#[validate(length(min = 1, max = 1000, message = "Harus berisi 1 sampai 1000 id."))]
+	pub ids: Vec<i32>
+}
+
+/// Body of the move route, `null` turns the category into a top-level one.
+#[derive(Deserialize, ToSchema)]
+pub struct CategoryMoveBody {
+	pub parent_id: Option<i32>
+}
+
+/// Body of the merge route, the listed categories are folded into the one in the path.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CategoryMergeBody {
+	#[validate(length(min = 1, max = 100, message = "Harus berisi 1 sampai 100 id."))]
+	pub source_ids: Vec<i32>
+}
+
+/// A data row of a category import, `parent` is the name of a live category or of a category on an earlier row.
+pub struct CategoryImportRow {
+	pub line: usize,
+	pub name: String,
+	pub parent: Option<String>,
+	pub sort_order: i32
+}
+
+/// Header of a category export, `parent` holds the parent name so the file can be imported elsewhere as is.
+pub const CATEGORY_EXPORT_COLUMNS: &[&str] = &["id", "name", "slug", "parent", "sort_order", "icon", "created_at", "updated_at"];
+
+impl CategoryData {
+	/// Values in the order of `CATEGORY_EXPORT_COLUMNS`.
+	pub fn export_row(&self, parent: Option<&str>) -> Vec<String> {
+		vec![
+			self.id.to_string(),
+			self.name.clone(),
+			self.slug.clone(),
+			parent.unwrap_or_default().to_string(),
+			self.sort_order.to_string(),
+			self.icon.clone().unwrap_or_default(),
+			self.created_at.format(&Rfc3339).unwrap_or_default(),
+			self.updated_at.format(&Rfc3339).unwrap_or_default()
+		]
+	}
+}
+
+/// The name as it is stored, surrounding whitespace removed and inner runs of whitespace collapsed to one space.
+pub fn clean_name(name: &str) -> String {
+	name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Two names naming the same category compare equal on this key, it matches the unique index on `category`.
+pub fn name_key(name: &str) -> String {
+	clean_name(name).to_lowercase()
 }
 
-#[derive(Serialize)]
-pub struct ReturningId {
-    pub id: i32
+/// Query string of the search-paginate routes.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CategorySubtreeQuery {
+	/// Only return this category and its descendants.
+	pub subtree_of: Option<i32>
+}
+
+/// A category with its children, nested as deep as the hierarchy goes.
+#[derive(Serialize, ToSchema)]
+pub struct CategoryNode {
+	pub id: i32,
+	pub name: String,
+	pub slug: String,
+	pub parent_id: Option<i32>,
+	pub sort_order: i32,
+	pub icon: Option<String>,
+	pub version: i32,
+	#[schema(no_recursion)]
+	pub children: Vec<CategoryNode>
+}
+
+impl CategoryNode {
+	/// Nests categories under their parents keeping the input order among siblings, a category whose parent
+	/// is missing from `categories`, e.g. soft-deleted, is listed at the top level.
+	pub fn tree(categories: Vec<CategoryData>) -> Vec<CategoryNode> {
+		let ids: HashSet<i32> = categories.iter().map(|category| category.id).collect();
+		let mut children: HashMap<Option<i32>, Vec<CategoryData>> = HashMap::new();
+
+		for category in categories {
+			let parent_id = category.parent_id.filter(|parent_id| ids.contains(parent_id));
+			children.entry(parent_id).or_default().push(category);
+		}
+
+		Self::children_of(None, &mut children)
+	}
+
+	fn children_of(parent_id: Option<i32>, children: &mut HashMap<Option<i32>, Vec<CategoryData>>) -> Vec<CategoryNode> {
+		children.remove(&parent_id).unwrap_or_default()
+			.into_iter()
+			.map(|category| CategoryNode {
+				children: Self::children_of(Some(category.id), children),
+				id: category.id,
+				name: category.name,
+				slug: category.slug,
+				parent_id: category.parent_id,
+				sort_order: category.sort_order,
+				icon: category.icon,
+				version: category.version
+			})
+			.collect()
+	}
 }
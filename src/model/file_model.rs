@@ -0,0 +1,54 @@
+use serde::{ Deserialize, Serialize };
+use sqlx::types::time::OffsetDateTime;
+use utoipa::{ IntoParams, ToSchema };
+
+/// Multipart form accepted by the upload handlers.
+// Only describes the request body in the OpenAPI document, handlers read the raw `Multipart`.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct UploadFileForm {
+	#[schema(format = Binary, content_media_type = "application/octet-stream")]
+	pub file: String
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UploadFileResponse {
+	pub file_name: String,
+	pub file_extension: String
+}
+
+/// Query string of the image routes.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImageQuery {
+	/// Longest side wanted in pixels, answered with the smallest configured variant at least that large, or the
+	/// original when none is. Uploads stored before variants existed are always sent as they are.
+	pub size: Option<u32>
+}
+
+/// Row of `file_object`, one per stored content and prefix.
+#[derive(Serialize, Clone, Debug, sqlx::FromRow)]
+pub struct FileObject {
+	pub prefix: String,
+	/// `{sha256}.{extension}`, what referencing columns such as `user_system.photo` store.
+	pub file_name: String,
+	pub sha256: String,
+	pub content_type: String,
+	pub size: i64,
+	/// Rows naming the file, kept up to date by database triggers.
+	pub ref_count: i32,
+	#[serde(with = "time::serde::rfc3339")]
+	pub created_at: OffsetDateTime,
+	/// `None` while referenced.
+	#[serde(with = "time::serde::rfc3339::option")]
+	pub unreferenced_since: Option<OffsetDateTime>
+}
+
+/// What an upload registers before its blobs are written.
+pub struct NewFileObject {
+	pub prefix: String,
+	pub file_name: String,
+	pub sha256: String,
+	pub content_type: String,
+	pub size: i64
+}
@@ -0,0 +1,20 @@
+/// Response stored for an idempotency key, enough to send it again byte for byte.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredResponse {
+	pub status: u16,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>
+}
+
+/// Outcome of claiming an idempotency key before running the request.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IdempotencyClaim {
+	/// First use of the key, the request runs and its response is stored afterwards.
+	Started,
+	/// The key was already answered for the same request.
+	Replay(StoredResponse),
+	/// The key was already used for a different request.
+	Mismatch,
+	/// The first request with this key has not been answered yet.
+	InProgress
+}
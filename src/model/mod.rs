@@ -1,4 +1,8 @@
+pub mod audit_model;
 pub mod auth_model;
 pub mod category_model;
+pub mod file_model;
+pub mod idempotency_model;
+pub mod role_model;
 pub mod user_model;
 pub mod utils_model;
\ No newline at end of file
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use sqlx::types::time::OffsetDateTime;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Role granted every permission, including ones added later, so an admin can never lock themselves out.
+pub const ADMIN_ROLE: &str = "admin";
+
+#[derive(Serialize, Clone, Debug, sqlx::FromRow, ToSchema)]
+pub struct RoleData {
+	pub id: i32,
+	#[schema(example = "supervisor")]
+	pub name: String,
+	pub description: Option<String>,
+	/// Built-in roles cannot be renamed or deleted.
+	pub is_system: bool,
+	/// Codes of the granted permissions, sorted.
+	#[schema(example = json!(["category.delete", "report.view"]))]
+	pub permissions: Vec<String>,
+	#[serde(with = "time::serde::rfc3339")]
+	pub created_at: OffsetDateTime,
+	#[serde(with = "time::serde::rfc3339")]
+	pub updated_at: OffsetDateTime,
+	/// Bumped on every write, send it back in `If-Match` to update or delete.
+	pub version: i32
+}
+
+#[derive(Serialize, Clone, Debug, sqlx::FromRow, ToSchema)]
+pub struct PermissionData {
+	#[schema(example = "category.delete")]
+	pub code: String,
+	pub description: String
+}
+
+/// Body of both POST and PUT, PUT replaces the name, description and every permission of the role.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct RoleBody {
+	/// Lowercase letters, digits and `_`, what `role` of a user is set to.
+	#[validate(custom(function = "crate::utils::validation::role"))]
+	#[schema(example = "supervisor")]
+	pub name: String,
+	#[serde(default)]
+	#[validate(length(max = 255, message = "Maksimal 255 karakter."))]
+	pub description: Option<String>,
+	/// Codes listed by `/api/permission`.
+	#[serde(default)]
+	#[validate(length(max = 100, message = "Maksimal 100 permission."))]
+	#[schema(example = json!(["category.delete", "report.view"]))]
+	pub permissions: Vec<String>
+}
+
+/// What the caller may do, as returned by `/api/user/me/permissions`.
+#[derive(Serialize, ToSchema)]
+pub struct PermissionSummary {
+	#[schema(example = "cashier")]
+	pub role: String,
+	#[schema(example = json!(["category.create", "category.update"]))]
+	pub permissions: Vec<String>
+}
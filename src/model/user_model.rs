@@ -1,54 +1,402 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{ json, Value };
 use sqlx::types::time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use utoipa::ToSchema;
+use validator::Validate;
 
 use crate::model::utils_model::{ PaginationResponse };
+use crate::utils::filter::FilterField;
+use crate::utils::pagination::{ FieldKind, FieldValue, SortField, Sortable };
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// A row of `user_system` as stored, including the password hash. It deliberately does not implement `Serialize`,
+/// responses convert it to a `UserView` first.
+#[derive(Clone, Debug, sqlx::FromRow)]
 pub struct UserData {
 	pub id: i32,
 	pub username: String,
 	pub password: String,
 	pub full_name: String,
-	pub address: String,
+	pub address: Option<String>,
+	pub phone_number: String,
+	pub photo: String,
+	pub role: String,
+	pub created_at: OffsetDateTime,
+	pub updated_at: OffsetDateTime,
+	pub deleted_at: Option<OffsetDateTime>,
+	pub version: i32,
+	pub language: String,
+	pub is_active: bool,
+	pub activated_at: Option<OffsetDateTime>
+}
+
+// Fails to compile once `UserData` implements `Serialize`, the same check `static_assertions::assert_not_impl_any!` does:
+// with an implementation both blanket impls apply and the inferred parameter becomes ambiguous.
+const _: fn() = || {
+	trait AmbiguousIfSerialize<A> {
+		fn some_item() {}
+	}
+
+	impl<T: ?Sized> AmbiguousIfSerialize<()> for T {}
+
+	struct Invalid;
+	impl<T: ?Sized + Serialize> AmbiguousIfSerialize<Invalid> for T {}
+
+	let _ = <UserData as AmbiguousIfSerialize<_>>::some_item;
+};
+
+/// A user as returned to clients, queries select `USER_VIEW_COLUMNS` so the password hash is never read.
+#[derive(Serialize, Clone, Debug, sqlx::FromRow, ToSchema)]
+pub struct UserView {
+	pub id: i32,
+	pub username: String,
+	pub full_name: String,
+	pub address: Option<String>,
 	pub phone_number: String,
 	pub photo: String,
 	pub role: String,
 	#[serde(with = "time::serde::rfc3339")]
 	pub created_at: OffsetDateTime,
 	#[serde(with = "time::serde::rfc3339")]
-	pub updated_at: OffsetDateTime
+	pub updated_at: OffsetDateTime,
+	/// Set when the row was soft-deleted, only listed with `include_deleted`.
+	#[serde(with = "time::serde::rfc3339::option")]
+	pub deleted_at: Option<OffsetDateTime>,
+	/// Bumped on every write, send it back in `If-Match` to update or delete.
+	pub version: i32,
+	/// One of `id` or `en`, chosen by the user through `/api/user/me`.
+	#[schema(example = "id")]
+	pub language: String,
+	/// Inactive users cannot log in and their tokens are rejected.
+	pub is_active: bool,
+	/// `null` while the invite of the user is pending.
+	#[serde(with = "time::serde::rfc3339::option")]
+	pub activated_at: Option<OffsetDateTime>
+}
+
+/// Columns of `user_system` read into a `UserView`.
+pub const USER_VIEW_COLUMNS: &str = "id, username, full_name, address, phone_number, photo, role, created_at, updated_at, deleted_at, version, language, is_active, activated_at";
+
+impl From<&UserData> for UserView {
+	fn from(user: &UserData) -> Self {
+		Self {
+			id: user.id,
+			username: user.username.clone(),
+			full_name: user.full_name.clone(),
+			address: user.address.clone(),
+			phone_number: user.phone_number.clone(),
+			photo: user.photo.clone(),
+			role: user.role.clone(),
+			created_at: user.created_at,
+			updated_at: user.updated_at,
+			deleted_at: user.deleted_at,
+			version: user.version,
+			language: user.language.clone(),
+			is_active: user.is_active,
+			activated_at: user.activated_at
+		}
+	}
 }
 
-#[derive(Serialize)]
+impl UserData {
+	/// What the audit log compares before and after a write, the hash is only there so a password change shows up,
+	/// `Audit::record` redacts its value.
+	pub fn audit_snapshot(&self) -> Value {
+		let mut snapshot = json!(UserView::from(self));
+		snapshot["password"] = json!(self.password);
+
+		snapshot
+	}
+}
+
+/// Photo of users who did not upload one, the clients ship it so it has no blob in the storage.
+pub const DEFAULT_USER_PHOTO: &str = "default_user.png";
+
+/// Values accepted for `language`, the first one is the default of new users.
+pub const USER_LANGUAGES: &[&str] = &["id", "en"];
+
+/// Fields `search_paginate` may sort on, the first one is the default.
+pub const USER_SORT_FIELDS: &[SortField] = &[
+	SortField { name: "id", kind: FieldKind::Integer },
+	SortField { name: "username", kind: FieldKind::Text },
+	SortField { name: "full_name", kind: FieldKind::Text },
+	SortField { name: "role", kind: FieldKind::Text },
+	SortField { name: "created_at", kind: FieldKind::Timestamp },
+	SortField { name: "updated_at", kind: FieldKind::Timestamp }
+];
+
+/// Fields the `filter` parameter of `search_paginate` may reference, the password hash is deliberately absent.
+pub const USER_FILTER_FIELDS: &[FilterField] = &[
+	FilterField { name: "id", kind: FieldKind::Integer },
+	FilterField { name: "username", kind: FieldKind::Text },
+	FilterField { name: "full_name", kind: FieldKind::Text },
+	FilterField { name: "phone_number", kind: FieldKind::Text },
+	FilterField { name: "role", kind: FieldKind::Text },
+	FilterField { name: "language", kind: FieldKind::Text },
+	FilterField { name: "created_at", kind: FieldKind::Timestamp },
+	FilterField { name: "updated_at", kind: FieldKind::Timestamp }
+];
+
+impl Sortable for UserView {
+	fn id(&self) -> i32 {
+		self.id
+	}
+
+	fn field_value(&self, field: &str) -> FieldValue {
+		match field {
+			"username" => FieldValue::Text(self.username.clone()),
+			"full_name" => FieldValue::Text(self.full_name.clone()),
+			"phone_number" => FieldValue::Text(self.phone_number.clone()),
+			"role" => FieldValue::Text(self.role.clone()),
+			"language" => FieldValue::Text(self.language.clone()),
+			"created_at" => FieldValue::Timestamp(self.created_at),
+			"updated_at" => FieldValue::Timestamp(self.updated_at),
+			_ => FieldValue::Integer(self.id as i64)
+		}
+	}
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct UserPaginate {
-	pub data: Vec<UserData>,
+	pub data: Vec<UserView>,
 	pub paginate: PaginationResponse
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct UserCreateDto {
+	#[validate(length(min = 3, max = 50, message = "Harus 3 sampai 50 karakter."), custom(function = "crate::utils::validation::username"))]
 	pub username: String,
+	#[validate(length(min = 8, message = "Minimal 8 karakter."))]
 	pub password: String,
+	#[validate(custom(function = "crate::utils::validation::not_blank"), length(max = 100, message = "Maksimal 100 karakter."))]
 	pub full_name: String,
-	pub address: String,
+	#[serde(default)]
+	#[validate(length(max = 255, message = "Maksimal 255 karakter."))]
+	pub address: Option<String>,
+	/// Indonesian mobile number as `08..`, `62..` or `+62..`, separators allowed, stored in E.164 (`+6281234567890`).
+	#[validate(custom(function = "crate::utils::validation::phone_number"))]
+	#[schema(example = "081234567890")]
 	pub phone_number: String,
+	/// File name returned by `/api/files/user`, or `default_user.png`.
+	#[validate(custom(function = "crate::utils::validation::file_name"))]
 	pub photo: String,
+	/// Name of an existing role.
+	#[validate(custom(function = "crate::utils::validation::role"))]
+	#[schema(example = "cashier")]
 	pub role: String
 }
 
-#[derive(Deserialize)]
+/// Body of PUT, every field is written, an absent `address` clears it and an absent `password` keeps the current one.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UserReplaceDto {
+	#[validate(length(min = 3, max = 50, message = "Harus 3 sampai 50 karakter."), custom(function = "crate::utils::validation::username"))]
+	pub username: String,
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(length(min = 8, message = "Minimal 8 karakter."))]
+	pub password: Option<String>,
+	#[validate(custom(function = "crate::utils::validation::not_blank"), length(max = 100, message = "Maksimal 100 karakter."))]
+	pub full_name: String,
+	#[serde(default)]
+	#[validate(length(max = 255, message = "Maksimal 255 karakter."))]
+	pub address: Option<String>,
+	/// Indonesian mobile number as `08..`, `62..` or `+62..`, separators allowed, stored in E.164 (`+6281234567890`).
+	#[validate(custom(function = "crate::utils::validation::phone_number"))]
+	#[schema(example = "081234567890")]
+	pub phone_number: String,
+	/// File name returned by `/api/files/user`, or `default_user.png`.
+	#[validate(custom(function = "crate::utils::validation::file_name"))]
+	pub photo: String,
+	/// Name of an existing role.
+	#[validate(custom(function = "crate::utils::validation::role"))]
+	#[schema(example = "cashier")]
+	pub role: String,
+	/// One of `id` or `en`.
+	#[validate(custom(function = "crate::utils::validation::language"))]
+	#[schema(example = "id")]
+	pub language: String
+}
+
+/// Body of PATCH, only the fields present are written.
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct UserUpdateDto {
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(length(min = 3, max = 50, message = "Harus 3 sampai 50 karakter."), custom(function = "crate::utils::validation::username"))]
 	pub username: Option<String>,
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(length(min = 8, message = "Minimal 8 karakter."))]
 	pub password: Option<String>,
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::not_blank"), length(max = 100, message = "Maksimal 100 karakter."))]
 	pub full_name: Option<String>,
-	pub address: Option<String>,
+	/// Absent keeps the address, `null` clears it.
+	#[serde(default, with = "::serde_with::rust::double_option")]
+	#[schema(value_type = Option<String>)]
+	#[validate(length(max = 255, message = "Maksimal 255 karakter."))]
+	pub address: Option<Option<String>>,
+	/// Indonesian mobile number as `08..`, `62..` or `+62..`, separators allowed, stored in E.164 (`+6281234567890`).
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::phone_number"))]
+	#[schema(example = "081234567890")]
+	pub phone_number: Option<String>,
+	/// File name returned by `/api/files/user`, or `default_user.png`.
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::file_name"))]
+	pub photo: Option<String>,
+	/// Name of an existing role.
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::role"))]
+	#[schema(example = "cashier")]
+	pub role: Option<String>,
+	/// One of `id` or `en`.
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::language"))]
+	#[schema(example = "id")]
+	pub language: Option<String>
+}
+
+/// Body of PATCH `/api/user/me`, the fields a user may change on their own profile, absent ones are left untouched.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UserProfileDto {
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::not_blank"), length(max = 100, message = "Maksimal 100 karakter."))]
+	pub full_name: Option<String>,
+	/// Absent keeps the address, `null` clears it.
+	#[serde(default, with = "::serde_with::rust::double_option")]
+	#[schema(value_type = Option<String>)]
+	#[validate(length(max = 255, message = "Maksimal 255 karakter."))]
+	pub address: Option<Option<String>>,
+	/// Indonesian mobile number as `08..`, `62..` or `+62..`, separators allowed, stored in E.164 (`+6281234567890`).
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::phone_number"))]
+	#[schema(example = "081234567890")]
 	pub phone_number: Option<String>,
+	/// File name returned by `/api/files/user`, or `default_user.png`.
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::file_name"))]
 	pub photo: Option<String>,
-	pub role: Option<String>
+	/// One of `id` or `en`.
+	#[serde(default, deserialize_with = "crate::utils::patch::non_null")]
+	#[validate(custom(function = "crate::utils::validation::language"))]
+	#[schema(example = "en")]
+	pub language: Option<String>
+}
+
+impl From<UserProfileDto> for UserUpdateDto {
+	fn from(profile: UserProfileDto) -> Self {
+		Self {
+			username: None,
+			password: None,
+			full_name: profile.full_name,
+			address: profile.address,
+			phone_number: profile.phone_number,
+			photo: profile.photo,
+			role: None,
+			language: profile.language
+		}
+	}
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Body of the invite route, the user gets no password and stays inactive until the activation link is used.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UserInviteDto {
+	#[validate(length(min = 3, max = 50, message = "Harus 3 sampai 50 karakter."), custom(function = "crate::utils::validation::username"))]
+	pub username: String,
+	#[validate(custom(function = "crate::utils::validation::not_blank"), length(max = 100, message = "Maksimal 100 karakter."))]
+	pub full_name: String,
+	#[serde(default)]
+	#[validate(length(max = 255, message = "Maksimal 255 karakter."))]
+	pub address: Option<String>,
+	/// Indonesian mobile number as `08..`, `62..` or `+62..`, separators allowed, stored in E.164 (`+6281234567890`).
+	#[validate(custom(function = "crate::utils::validation::phone_number"))]
+	#[schema(example = "081234567890")]
+	pub phone_number: String,
+	/// File name returned by `/api/files/user`, or `default_user.png`.
+	#[validate(custom(function = "crate::utils::validation::file_name"))]
+	pub photo: String,
+	/// Name of an existing role.
+	#[validate(custom(function = "crate::utils::validation::role"))]
+	#[schema(example = "cashier")]
+	pub role: String
+}
+
+/// How long an activation link stays valid, resending it issues a new one with a fresh expiry.
+pub const INVITE_TTL: time::Duration = time::Duration::hours(72);
+
+/// A row of `user_invite`, the token itself is only known to whoever received the link.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct UserInvite {
+	pub id: i32,
+	pub user_id: i32,
+	pub token_hash: String,
+	pub expires_at: OffsetDateTime,
+	pub used_at: Option<OffsetDateTime>,
+	pub created_at: OffsetDateTime
+}
+
+/// What an admin passes on to the invited user, the token is shown once and cannot be read again, resending issues a new one.
+#[derive(Serialize, ToSchema)]
+pub struct UserInviteData {
+	pub user_id: i32,
+	/// Goes into the activation link, e.g. `https://pos.example.com/activate?token=...`.
+	pub token: String,
+	#[serde(with = "time::serde::rfc3339")]
+	pub expires_at: OffsetDateTime
+}
+
+/// Response of the invite and resend routes.
+#[derive(Serialize, ToSchema)]
+pub struct UserInviteResponse {
+	#[schema(example = true)]
+	pub success: bool,
+	pub message: String,
+	pub data: UserInviteData
+}
+
+/// A data row of a user import that passed validation, `password_hash` is empty on a dry run.
+pub struct UserImportRow {
+	pub line: usize,
+	pub user: UserCreateDto,
+	pub password_hash: String
+}
+
+/// Header of a user export, the password hash is never exported.
+pub const USER_EXPORT_COLUMNS: &[&str] = &["id", "username", "full_name", "address", "phone_number", "photo", "role", "created_at", "updated_at"];
+
+impl UserView {
+	/// Values in the order of `USER_EXPORT_COLUMNS`.
+	pub fn export_row(&self) -> Vec<String> {
+		vec![
+			self.id.to_string(),
+			self.username.clone(),
+			self.full_name.clone(),
+			self.address.clone().unwrap_or_default(),
+			self.phone_number.clone(),
+			self.photo.clone(),
+			self.role.clone(),
+			self.created_at.format(&Rfc3339).unwrap_or_default(),
+			self.updated_at.format(&Rfc3339).unwrap_or_default()
+		]
+	}
+}
+
+/// Payload of a bearer token, only what the guards and the audit log need so the token stays small and never
+/// carries anything private.
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct JwtClaims {
-	pub user_data: UserData,
-	pub exp: usize,
+	pub id: i32,
+	pub role: String,
+	/// Username of the caller.
+	pub name: String,
+	pub exp: usize
+}
+
+impl JwtClaims {
+	pub fn new(user: &UserData, exp: usize) -> Self {
+		Self {
+			id: user.id,
+			role: user.role.clone(),
+			name: user.username.clone(),
+			exp
+		}
+	}
 }
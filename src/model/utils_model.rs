@@ -1,15 +1,139 @@
 use serde::{Deserialize, Serialize };
+use utoipa::{ IntoParams, ToSchema };
 
-#[derive(Serialize)]
+use crate::utils::pagination::SortDirection;
+
+#[derive(Serialize, ToSchema)]
 pub struct PaginationResponse {
 	pub per_page: i64,
 	pub total_page: i64,
+	/// Rows matching the search term.
 	pub count: i64,
-	pub current_page: i64
+	/// `null` when the page was requested with a cursor.
+	pub current_page: Option<i64>,
+	/// Pass back as `cursor` to fetch the following page, `null` on the last page.
+	pub next_cursor: Option<String>
 }
 
-#[derive(Deserialize)]
+/// Read from the JSON body by the POST search-paginate routes and from the query string by the GET ones.
+#[derive(Deserialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PaginationBody {
+	#[serde(default)]
 	pub term: String,
-	pub page: i64
+	#[serde(default = "first_page")]
+	pub page: i64,
+	/// Defaults to 10, at most 100.
+	pub per_page: Option<i64>,
+	/// One of the sortable fields of the resource, defaults to `id`.
+	pub sort_by: Option<String>,
+	pub sort_dir: Option<SortDirection>,
+	/// `next_cursor` of the previous page, switches to keyset pagination and ignores `page`.
+	pub cursor: Option<String>,
+	/// `field:operator:value` conditions combined with AND, operators are eq, ne, gt, gte, lt, lte, like and in.
+	#[serde(default)]
+	#[param(example = json!(["created_at:gte:2026-01-01"]))]
+	#[schema(example = json!(["created_at:gte:2026-01-01"]))]
+	pub filter: Vec<String>,
+	/// Admins only, also returns soft-deleted rows.
+	#[serde(default)]
+	pub include_deleted: bool
+}
+
+/// Query string of the list routes.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+	/// Admins only, also returns soft-deleted rows.
+	#[serde(default)]
+	pub include_deleted: bool
+}
+
+fn first_page() -> i64 {
+	1
+}
+
+
+/// Envelope returned by every handler when the request fails.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+	#[schema(example = false)]
+	pub success: bool,
+	pub message: String
+}
+
+/// Envelope returned by mutations that only report a message.
+#[derive(Serialize, ToSchema)]
+pub struct MessageResponse {
+	#[schema(example = true)]
+	pub success: bool,
+	pub message: String
+}
+
+/// Envelope returned by handlers that respond with a single payload.
+#[derive(Serialize, ToSchema)]
+pub struct DataResponse<T> {
+	#[schema(example = true)]
+	pub success: bool,
+	pub data: T
+}
+
+/// Envelope returned by the search-paginate handlers.
+#[derive(Serialize, ToSchema)]
+pub struct PaginateResponse<T> {
+	#[schema(example = true)]
+	pub success: bool,
+	pub data: Vec<T>,
+	pub paginate: PaginationResponse
+}
+
+/// One rule a request field failed.
+#[derive(Serialize, ToSchema)]
+pub struct FieldError {
+	#[schema(example = "name")]
+	pub field: String,
+	pub message: String
+}
+
+/// Envelope returned with 422 when the body deserialized but broke validation rules.
+#[derive(Serialize, ToSchema)]
+pub struct ValidationErrorResponse {
+	#[schema(example = false)]
+	pub success: bool,
+	pub message: String,
+	pub errors: Vec<FieldError>
+}
+
+/// Rules one row of an imported file failed.
+#[derive(Serialize, ToSchema)]
+pub struct ImportRowError {
+	/// Row number as shown by a spreadsheet, the header is row 1.
+	#[schema(example = 2)]
+	pub row: usize,
+	pub errors: Vec<FieldError>
+}
+
+/// What a repository `import` wrote, `created` stays empty when `errors` is not or on a dry run.
+pub struct ImportOutcome<T> {
+	pub created: Vec<T>,
+	pub errors: Vec<ImportRowError>
+}
+
+/// Outcome of an import, nothing is written when `errors` is not empty or on a dry run.
+#[derive(Serialize, ToSchema)]
+pub struct ImportReport {
+	pub dry_run: bool,
+	/// Data rows read from the file.
+	pub rows: usize,
+	/// Rows written, 0 on a dry run or when a row failed.
+	pub created: usize,
+	pub errors: Vec<ImportRowError>
+}
+
+/// Envelope returned by the import routes.
+#[derive(Serialize, ToSchema)]
+pub struct ImportResponse {
+	pub success: bool,
+	pub message: String,
+	pub data: ImportReport
 }
@@ -0,0 +1,121 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use sqlx::{ postgres::PgPool, QueryBuilder };
+use sqlx::types::time::OffsetDateTime;
+
+use crate::model::audit_model::{ AuditData, AuditEvent };
+use crate::repository::RepositoryError;
+use crate::utils::filter::contains_pattern;
+use crate::utils::pagination::{ Page, PageRequest };
+
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+	/// Events whose actor username or entity id contains the term, case-insensitively, and that match every filter.
+	async fn search_paginate(&self, request: &PageRequest) -> Result<Page<AuditData>, RepositoryError>;
+
+	async fn record(&self, event: &AuditEvent) -> Result<AuditData, RepositoryError>;
+}
+
+pub struct PgAuditRepository {
+	pg_pool: PgPool
+}
+
+impl PgAuditRepository {
+	pub fn new(pg_pool: PgPool) -> Self {
+		Self { pg_pool }
+	}
+}
+
+#[async_trait]
+impl AuditRepository for PgAuditRepository {
+	async fn search_paginate(&self, request: &PageRequest) -> Result<Page<AuditData>, RepositoryError> {
+		let term = contains_pattern(&request.term);
+
+		let mut query_count = QueryBuilder::new("SELECT COUNT(id) FROM audit_log WHERE (COALESCE(actor_username, '') ILIKE ");
+		query_count.push_bind(&term).push(" ESCAPE '\\' OR COALESCE(entity_id, '') ILIKE ").push_bind(&term).push(" ESCAPE '\\')");
+		request.push_filters(&mut query_count);
+
+		let query_count: i64 = query_count.build_query_scalar()
+		.fetch_one(&self.pg_pool)
+		.await?;
+
+		let mut query = QueryBuilder::new("SELECT * FROM audit_log WHERE (COALESCE(actor_username, '') ILIKE ");
+		query.push_bind(&term).push(" ESCAPE '\\' OR COALESCE(entity_id, '') ILIKE ").push_bind(&term).push(" ESCAPE '\\')");
+		request.push_filters(&mut query);
+		request.push_keyset(&mut query);
+		request.push_order_and_limit(&mut query);
+
+		let query_search = query.build_query_as::<AuditData>()
+		.fetch_all(&self.pg_pool)
+		.await?;
+
+		Ok(request.into_page(query_search, query_count))
+	}
+
+	async fn record(&self, event: &AuditEvent) -> Result<AuditData, RepositoryError> {
+		let query_insert = sqlx::query_as!(
+			AuditData,
+			"INSERT INTO audit_log (actor_id, actor_username, action, entity_type, entity_id, changes, ip_address)
+			VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *",
+			event.actor_id,
+			event.actor_username,
+			event.action,
+			event.entity_type,
+			event.entity_id,
+			event.changes,
+			event.ip_address
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		Ok(query_insert)
+	}
+}
+
+/// Keeps audit events in memory, used to exercise controllers without a database.
+#[derive(Default)]
+pub struct InMemoryAuditRepository {
+	events: Mutex<Vec<AuditData>>
+}
+
+impl InMemoryAuditRepository {
+	/// Every recorded event in insertion order.
+	pub fn events(&self) -> Vec<AuditData> {
+		self.events.lock().unwrap().clone()
+	}
+}
+
+#[async_trait]
+impl AuditRepository for InMemoryAuditRepository {
+	async fn search_paginate(&self, request: &PageRequest) -> Result<Page<AuditData>, RepositoryError> {
+		let term = request.term.to_lowercase();
+		let contains = |value: &Option<String>| value.as_deref().unwrap_or_default().to_lowercase().contains(&term);
+
+		let events = self.events.lock().unwrap()
+			.iter()
+			.filter(|event| contains(&event.actor_username) || contains(&event.entity_id))
+			.cloned()
+			.collect();
+
+		Ok(request.paginate_in_memory(events))
+	}
+
+	async fn record(&self, event: &AuditEvent) -> Result<AuditData, RepositoryError> {
+		let mut events = self.events.lock().unwrap();
+
+		let event = AuditData {
+			id: events.len() as i32 + 1,
+			actor_id: event.actor_id,
+			actor_username: event.actor_username.clone(),
+			action: event.action.to_string(),
+			entity_type: event.entity_type.to_string(),
+			entity_id: event.entity_id.clone(),
+			changes: event.changes.clone(),
+			ip_address: event.ip_address.clone(),
+			created_at: OffsetDateTime::now_utc()
+		};
+		events.push(event.clone());
+
+		Ok(event)
+	}
+}
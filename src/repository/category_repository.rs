@@ -0,0 +1,1132 @@
+use std::{ collections::HashSet, sync::Mutex };
+
+use async_trait::async_trait;
+use sqlx::{ postgres::{ PgPool, Postgres }, PgExecutor, QueryBuilder };
+use sqlx::types::time::OffsetDateTime;
+
+use crate::model::category_model::{ clean_name, name_key, CategoryCreateBody, CategoryData, CategoryImportRow, CategoryUpdateBody };
+use crate::model::utils_model::ImportOutcome;
+use crate::repository::{ not_found_or_stale, RepositoryError };
+use crate::utils::filter::contains_pattern;
+use crate::utils::pagination::{ Page, PageRequest };
+use crate::utils::patch::PatchQuery;
+use crate::utils::slug::{ pick_slug, slugify };
+use crate::utils::tabular::push_row_error;
+
+#[async_trait]
+pub trait CategoryRepository: Send + Sync {
+	/// Categories whose name contains the term, case-insensitively, and that match every filter, soft-deleted ones
+	/// only with `request.include_deleted`. `subtree_of` narrows the search to a category and its descendants.
+	async fn search_paginate(&self, request: &PageRequest, subtree_of: Option<i32>) -> Result<Page<CategoryData>, RepositoryError>;
+
+	/// Every category ordered by `sort_order` then name, soft-deleted ones only when `include_deleted`.
+	async fn find_many(&self, include_deleted: bool) -> Result<Vec<CategoryData>, RepositoryError>;
+
+	/// Soft-deleted categories are not found.
+	async fn find_by_id(&self, id: i32) -> Result<CategoryData, RepositoryError>;
+
+	/// The category followed by its ancestors up to the top level, root first, fails with `RepositoryError::NotFound`
+	/// when no live category has the given id. A soft-deleted ancestor ends the path.
+	async fn ancestors(&self, id: i32) -> Result<Vec<CategoryData>, RepositoryError>;
+
+	/// Stores the name with its whitespace cleaned up and generates a unique slug from it. Fails with
+	/// `RepositoryError::Conflict` naming the live category that already has the name, ignoring case and whitespace, or
+	/// when the category would be nested deeper than `MAX_DEPTH` levels, and with `RepositoryError::Invalid` when
+	/// `parent_id` is not a live category.
+	async fn create(&self, body: &CategoryCreateBody) -> Result<CategoryData, RepositoryError>;
+
+	/// Returns the new version. Writes only while the row is still at `version` and fails with
+	/// `RepositoryError::VersionMismatch` otherwise, `None` skips the check. The slug follows the new name, which must
+	/// be free like in `create`.
+	async fn update(&self, id: i32, name: &str, icon: Option<&str>, version: Option<i32>) -> Result<i32, RepositoryError>;
+
+	/// Writes only the fields present in `body`, the slug follows a new name and `version` is checked like in `update`.
+	async fn patch(&self, id: i32, body: &CategoryUpdateBody, version: Option<i32>) -> Result<i32, RepositoryError>;
+
+	/// Nests the category under `parent_id`, `None` makes it top-level. Fails with `RepositoryError::Invalid` when the
+	/// parent is not a live category and with `RepositoryError::Conflict` when it is the category itself or one of its
+	/// descendants or when the subtree would end up deeper than `MAX_DEPTH` levels, `version` is checked like in `update`.
+	async fn move_to(&self, id: i32, parent_id: Option<i32>, version: Option<i32>) -> Result<i32, RepositoryError>;
+
+	/// Sets `sort_order` to the position of each id in `ids`, in one transaction. Fails with `RepositoryError::Invalid`
+	/// listing the ids that are not live categories, categories left out keep their order.
+	async fn reorder(&self, ids: &[i32]) -> Result<(), RepositoryError>;
+
+	/// Folds the `source_ids` categories into `id` in one transaction and returns the new version of `id`. Sub
+	/// categories of the sources move under `id` and the sources are soft-deleted. Fails with `RepositoryError::Invalid`
+	/// listing the sources that are not live categories or when `id` is among them, and with `RepositoryError::Conflict`
+	/// when `id` is below one of the sources or the moved sub categories would end up deeper than `MAX_DEPTH` levels,
+	/// `version` of `id` is checked like in `update`.
+	async fn merge(&self, id: i32, source_ids: &[i32], version: Option<i32>) -> Result<i32, RepositoryError>;
+
+	/// Creates a category per row in one transaction, in file order so a row may name an earlier one as its parent.
+	/// Returns them with the rows that break a rule checked against the stored data, such as a taken name or an unknown
+	/// parent, nothing is written when there is any or on a dry run.
+	async fn import(&self, rows: &[CategoryImportRow], dry_run: bool) -> Result<ImportOutcome<CategoryData>, RepositoryError>;
+
+	/// Soft-deletes the category, fails with `RepositoryError::NotFound` when no live category has the given id and with
+	/// `RepositoryError::Conflict` while it still has live children, `version` is checked like in `update`.
+	async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), RepositoryError>;
+
+	/// Undoes `delete` and returns the new version, fails with `RepositoryError::NotFound` unless the category is
+	/// soft-deleted and with `RepositoryError::Conflict` when its parent is still soft-deleted or a live category took its
+	/// name meanwhile.
+	async fn restore(&self, id: i32) -> Result<i32, RepositoryError>;
+
+	/// Removes categories soft-deleted before `deleted_before` for good, returns how many were removed.
+	async fn purge_deleted(&self, deleted_before: OffsetDateTime) -> Result<u64, RepositoryError>;
+}
+
+pub struct PgCategoryRepository {
+	pg_pool: PgPool
+}
+
+impl PgCategoryRepository {
+	pub fn new(pg_pool: PgPool) -> Self {
+		Self { pg_pool }
+	}
+
+	/// Slug of `name` with the first free numeric suffix, the category being renamed keeps its own slug available.
+	async fn unique_slug<'e>(executor: impl PgExecutor<'e>, name: &str, id: Option<i32>) -> Result<String, RepositoryError> {
+		let base = slugify(name);
+
+		let taken = sqlx::query_scalar!(
+			"SELECT slug FROM category WHERE (slug = $1 OR slug LIKE $1 || '-%') AND id IS DISTINCT FROM $2",
+			base,
+			id
+		).fetch_all(executor)
+		.await?;
+
+		Ok(pick_slug(&base, &taken))
+	}
+
+	/// Id and name of the live category other than `id` whose name matches, ignoring case and whitespace.
+	async fn find_by_name<'e>(executor: impl PgExecutor<'e>, name: &str, id: Option<i32>) -> Result<Option<(i32, String)>, RepositoryError> {
+		let query_find_first = sqlx::query!(
+			r#"SELECT id, name FROM category
+			WHERE LOWER(BTRIM(REGEXP_REPLACE(name, '\s+', ' ', 'g'))) = LOWER($1) AND deleted_at IS NULL AND id IS DISTINCT FROM $2
+			LIMIT 1"#,
+			clean_name(name),
+			id
+		).fetch_optional(executor)
+		.await?;
+
+		Ok(query_find_first.map(|existing| (existing.id, existing.name)))
+	}
+
+	/// Writes that nest categories take turns: two concurrent moves could each pass the cycle and depth checks and
+	/// still break them together. Held until the transaction ends.
+	async fn lock_hierarchy<'e>(executor: impl PgExecutor<'e>) -> Result<(), RepositoryError> {
+		sqlx::query("SELECT pg_advisory_xact_lock(hashtext('category_hierarchy'))")
+		.execute(executor)
+		.await?;
+
+		Ok(())
+	}
+
+	/// Locks the live parent until the transaction ends, a concurrent `delete` of it then waits for the child or the
+	/// child finds it deleted. Fails with `RepositoryError::Invalid` when it is not a live category.
+	async fn lock_parent<'e>(executor: impl PgExecutor<'e>, parent_id: i32) -> Result<(), RepositoryError> {
+		let query_lock = sqlx::query_scalar!(
+			"SELECT id FROM category WHERE id = $1 AND deleted_at IS NULL FOR SHARE",
+			parent_id
+		).fetch_optional(executor)
+		.await?;
+
+		query_lock.map(|_| ()).ok_or_else(parent_not_found)
+	}
+
+	/// Level of the category, 1 at the top, counted up to `MAX_DEPTH + 1`.
+	async fn level<'e>(executor: impl PgExecutor<'e>, id: i32) -> Result<i32, RepositoryError> {
+		let query_level = sqlx::query_scalar!(
+			r#"WITH RECURSIVE ancestors AS (
+				SELECT parent_id, 1 AS level FROM category WHERE id = $1
+				UNION ALL
+				SELECT parent.parent_id, ancestors.level + 1 FROM category parent
+				JOIN ancestors ON parent.id = ancestors.parent_id
+				WHERE ancestors.level <= $2
+			)
+			SELECT COALESCE(MAX(level), 0) AS "level!" FROM ancestors"#,
+			id,
+			MAX_DEPTH
+		).fetch_one(executor)
+		.await?;
+
+		Ok(query_level)
+	}
+
+	/// Levels from the category down to its deepest descendant, 1 without children, counted up to `MAX_DEPTH + 1`.
+	/// Soft-deleted descendants count, they may be restored where they are.
+	async fn height<'e>(executor: impl PgExecutor<'e>, id: i32) -> Result<i32, RepositoryError> {
+		let query_height = sqlx::query_scalar!(
+			r#"WITH RECURSIVE subtree AS (
+				SELECT id, 1 AS height FROM category WHERE id = $1
+				UNION ALL
+				SELECT category.id, subtree.height + 1 FROM category
+				JOIN subtree ON category.parent_id = subtree.id
+				WHERE subtree.height <= $2
+			)
+			SELECT COALESCE(MAX(height), 0) AS "height!" FROM subtree"#,
+			id,
+			MAX_DEPTH
+		).fetch_one(executor)
+		.await?;
+
+		Ok(query_height)
+	}
+
+	/// Fails with `RepositoryError::Conflict` when a live category other than `id` already has the name.
+	async fn ensure_name_free<'e>(executor: impl PgExecutor<'e>, name: &str, id: Option<i32>) -> Result<(), RepositoryError> {
+		match Self::find_by_name(executor, name, id).await? {
+			Some((existing_id, existing_name)) => Err(name_taken(existing_id, &existing_name)),
+			None => Ok(())
+		}
+	}
+}
+
+#[async_trait]
+impl CategoryRepository for PgCategoryRepository {
+	async fn search_paginate(&self, request: &PageRequest, subtree_of: Option<i32>) -> Result<Page<CategoryData>, RepositoryError> {
+		let term = contains_pattern(&request.term);
+
+		let mut query_count = QueryBuilder::new("SELECT COUNT(id) from category WHERE name ILIKE ");
+		query_count.push_bind(&term).push(" ESCAPE '\\'");
+		request.push_soft_delete(&mut query_count);
+		request.push_filters(&mut query_count);
+		push_subtree(&mut query_count, subtree_of);
+
+		let query_count: i64 = query_count.build_query_scalar()
+		.fetch_one(&self.pg_pool)
+		.await?;
+
+		let mut query = QueryBuilder::new("SELECT * FROM category WHERE name ILIKE ");
+		query.push_bind(&term).push(" ESCAPE '\\'");
+		request.push_soft_delete(&mut query);
+		request.push_filters(&mut query);
+		push_subtree(&mut query, subtree_of);
+		request.push_keyset(&mut query);
+		request.push_order_and_limit(&mut query);
+
+		let query_search = query.build_query_as::<CategoryData>()
+		.fetch_all(&self.pg_pool)
+		.await?;
+
+		Ok(request.into_page(query_search, query_count))
+	}
+
+	async fn find_many(&self, include_deleted: bool) -> Result<Vec<CategoryData>, RepositoryError> {
+		let query_find_many = sqlx::query_as!(
+			CategoryData,
+			"SELECT * FROM category WHERE $1 OR deleted_at IS NULL ORDER BY sort_order ASC, name ASC",
+			include_deleted
+		).fetch_all(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_many)
+	}
+
+	async fn find_by_id(&self, id: i32) -> Result<CategoryData, RepositoryError> {
+		let query_find_first = sqlx::query_as!(
+			CategoryData,
+			"SELECT * FROM category WHERE id = $1 AND deleted_at IS NULL",
+			id
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_first)
+	}
+
+	async fn ancestors(&self, id: i32) -> Result<Vec<CategoryData>, RepositoryError> {
+		let query_ancestors = sqlx::query_as!(
+			CategoryData,
+			r#"WITH RECURSIVE ancestors AS (
+				SELECT category.*, 0 AS depth FROM category WHERE id = $1 AND deleted_at IS NULL
+				UNION ALL
+				SELECT parent.*, ancestors.depth + 1 FROM category parent
+				JOIN ancestors ON parent.id = ancestors.parent_id
+				WHERE parent.deleted_at IS NULL AND ancestors.depth < $2
+			)
+			SELECT id AS "id!", name AS "name!", slug AS "slug!", parent_id, sort_order AS "sort_order!", icon,
+			created_at AS "created_at!", updated_at AS "updated_at!", deleted_at, version AS "version!"
+			FROM ancestors ORDER BY depth DESC"#,
+			id,
+			MAX_DEPTH
+		).fetch_all(&self.pg_pool)
+		.await?;
+
+		if query_ancestors.is_empty() {
+			return Err(RepositoryError::NotFound);
+		}
+
+		Ok(query_ancestors)
+	}
+
+	async fn create(&self, body: &CategoryCreateBody) -> Result<CategoryData, RepositoryError> {
+		let name = clean_name(&body.name);
+		Self::ensure_name_free(&self.pg_pool, &name, None).await?;
+
+		let mut tx = self.pg_pool.begin().await?;
+
+		if let Some(parent_id) = body.parent_id {
+			Self::lock_hierarchy(&mut *tx).await?;
+			Self::lock_parent(&mut *tx, parent_id).await?;
+
+			if Self::level(&mut *tx, parent_id).await? + 1 > MAX_DEPTH {
+				return Err(too_deep());
+			}
+		}
+
+		let slug = Self::unique_slug(&mut *tx, &name, None).await?;
+
+		let query_insert = sqlx::query_as!(
+			CategoryData,
+			"INSERT INTO category (name, slug, parent_id, icon) VALUES ($1, $2, $3, $4) RETURNING *",
+			name,
+			slug,
+			body.parent_id,
+			body.icon
+		).fetch_one(&mut *tx)
+		.await
+		.map_err(|e| duplicate_name(e, &name))?;
+
+		tx.commit().await?;
+
+		Ok(query_insert)
+	}
+
+	async fn update(&self, id: i32, name: &str, icon: Option<&str>, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let name = clean_name(name);
+		Self::ensure_name_free(&self.pg_pool, &name, Some(id)).await?;
+		let slug = Self::unique_slug(&self.pg_pool, &name, Some(id)).await?;
+
+		let query_update = sqlx::query_scalar!(
+			"UPDATE category set name = $1, slug = $2, icon = $3, updated_at = NOW(), version = version + 1
+			WHERE id = $4 AND deleted_at IS NULL AND ($5::INTEGER IS NULL OR version = $5) RETURNING version",
+			name,
+			slug,
+			icon,
+			id,
+			version
+		).fetch_optional(&self.pg_pool)
+		.await
+		.map_err(|e| duplicate_name(e, &name))?;
+
+		match query_update {
+			Some(version) => Ok(version),
+			None => Err(not_found_or_stale(&self.pg_pool, "category", id).await)
+		}
+	}
+
+	async fn patch(&self, id: i32, body: &CategoryUpdateBody, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let name = body.name.as_deref().map(clean_name);
+		let slug = match &name {
+			Some(name) => {
+				Self::ensure_name_free(&self.pg_pool, name, Some(id)).await?;
+				Some(Self::unique_slug(&self.pg_pool, name, Some(id)).await?)
+			},
+			None => None
+		};
+
+		let mut patch = PatchQuery::new("category");
+		patch.set("name", name.as_deref())
+		.set("slug", slug)
+		.set("icon", body.icon.as_ref().map(|icon| icon.as_deref()));
+
+		let query_patch: Option<i32> = patch.build(id, version)
+		.build_query_scalar()
+		.fetch_optional(&self.pg_pool)
+		.await
+		.map_err(|e| duplicate_name(e, name.as_deref().unwrap_or_default()))?;
+
+		match query_patch {
+			Some(version) => Ok(version),
+			None => Err(not_found_or_stale(&self.pg_pool, "category", id).await)
+		}
+	}
+
+	async fn move_to(&self, id: i32, parent_id: Option<i32>, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+		Self::lock_hierarchy(&mut *tx).await?;
+
+		if let Some(parent_id) = parent_id {
+			Self::lock_parent(&mut *tx, parent_id).await?;
+
+			let creates_cycle = sqlx::query_scalar!(
+				r#"WITH RECURSIVE ancestors AS (
+					SELECT id, parent_id, 0 AS depth FROM category WHERE id = $1
+					UNION ALL
+					SELECT parent.id, parent.parent_id, ancestors.depth + 1 FROM category parent
+					JOIN ancestors ON parent.id = ancestors.parent_id
+					WHERE ancestors.depth < $3
+				)
+				SELECT EXISTS(SELECT 1 FROM ancestors WHERE id = $2) AS "creates_cycle!""#,
+				parent_id,
+				id,
+				MAX_DEPTH
+			).fetch_one(&mut *tx)
+			.await?;
+
+			if creates_cycle {
+				return Err(cycle());
+			}
+
+			if Self::level(&mut *tx, parent_id).await? + Self::height(&mut *tx, id).await? > MAX_DEPTH {
+				return Err(too_deep());
+			}
+		}
+
+		let query_move = sqlx::query_scalar!(
+			"UPDATE category SET parent_id = $1, updated_at = NOW(), version = version + 1
+			WHERE id = $2 AND deleted_at IS NULL AND ($3::INTEGER IS NULL OR version = $3) RETURNING version",
+			parent_id,
+			id,
+			version
+		).fetch_optional(&mut *tx)
+		.await?;
+
+		let Some(version) = query_move else {
+			return Err(not_found_or_stale(&self.pg_pool, "category", id).await);
+		};
+
+		tx.commit().await?;
+
+		Ok(version)
+	}
+
+	async fn reorder(&self, ids: &[i32]) -> Result<(), RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+
+		let unknown_ids = sqlx::query_scalar!(
+			r#"SELECT ordered.id AS "id!" FROM UNNEST($1::INTEGER[]) AS ordered(id)
+			WHERE NOT EXISTS(SELECT 1 FROM category WHERE category.id = ordered.id AND deleted_at IS NULL)"#,
+			ids
+		).fetch_all(&mut *tx)
+		.await?;
+
+		if !unknown_ids.is_empty() {
+			return Err(unknown_categories(&unknown_ids));
+		}
+
+		sqlx::query!(
+			"UPDATE category SET sort_order = ordered.position::INTEGER, updated_at = NOW(), version = version + 1
+			FROM UNNEST($1::INTEGER[]) WITH ORDINALITY AS ordered(id, position)
+			WHERE category.id = ordered.id AND category.sort_order <> ordered.position",
+			ids
+		).execute(&mut *tx)
+		.await?;
+
+		tx.commit().await?;
+
+		Ok(())
+	}
+
+	async fn merge(&self, id: i32, source_ids: &[i32], version: Option<i32>) -> Result<i32, RepositoryError> {
+		if source_ids.contains(&id) {
+			return Err(merge_into_itself());
+		}
+
+		let mut tx = self.pg_pool.begin().await?;
+
+		// Sub categories change parent, a concurrent move must not slip a cycle in between the checks and the writes.
+		Self::lock_hierarchy(&mut *tx).await?;
+
+		let unknown_ids = sqlx::query_scalar!(
+			r#"SELECT source.id AS "id!" FROM UNNEST($1::INTEGER[]) AS source(id)
+			WHERE NOT EXISTS(SELECT 1 FROM category WHERE category.id = source.id AND deleted_at IS NULL)"#,
+			source_ids
+		).fetch_all(&mut *tx)
+		.await?;
+
+		if !unknown_ids.is_empty() {
+			return Err(unknown_categories(&unknown_ids));
+		}
+
+		let below_source = sqlx::query_scalar!(
+			r#"WITH RECURSIVE ancestors AS (
+				SELECT id, parent_id, 0 AS depth FROM category WHERE id = $1
+				UNION ALL
+				SELECT parent.id, parent.parent_id, ancestors.depth + 1 FROM category parent
+				JOIN ancestors ON parent.id = ancestors.parent_id
+				WHERE ancestors.depth < $3
+			)
+			SELECT EXISTS(SELECT 1 FROM ancestors WHERE id = ANY($2)) AS "below_source!""#,
+			id,
+			source_ids,
+			MAX_DEPTH
+		).fetch_one(&mut *tx)
+		.await?;
+
+		if below_source {
+			return Err(merge_into_descendant());
+		}
+
+		// Sub categories of the sources, with their own subtrees, end up one level below the target.
+		let moved_height = sqlx::query_scalar!(
+			r#"WITH RECURSIVE subtree AS (
+				SELECT id, 1 AS height FROM category WHERE parent_id = ANY($1) AND NOT id = ANY($1)
+				UNION ALL
+				SELECT category.id, subtree.height + 1 FROM category
+				JOIN subtree ON category.parent_id = subtree.id
+				WHERE subtree.height <= $2
+			)
+			SELECT COALESCE(MAX(height), 0) AS "height!" FROM subtree"#,
+			source_ids,
+			MAX_DEPTH
+		).fetch_one(&mut *tx)
+		.await?;
+
+		if Self::level(&mut *tx, id).await? + moved_height > MAX_DEPTH {
+			return Err(too_deep());
+		}
+
+		let query_merge = sqlx::query_scalar!(
+			"UPDATE category SET updated_at = NOW(), version = version + 1
+			WHERE id = $1 AND deleted_at IS NULL AND ($2::INTEGER IS NULL OR version = $2) RETURNING version",
+			id,
+			version
+		).fetch_optional(&mut *tx)
+		.await?;
+
+		let Some(version) = query_merge else {
+			return Err(not_found_or_stale(&self.pg_pool, "category", id).await);
+		};
+
+		sqlx::query!(
+			"UPDATE category SET parent_id = $1, updated_at = NOW(), version = version + 1
+			WHERE parent_id = ANY($2) AND NOT id = ANY($2)",
+			id,
+			source_ids
+		).execute(&mut *tx)
+		.await?;
+
+		sqlx::query!(
+			"UPDATE category SET deleted_at = NOW(), updated_at = NOW(), version = version + 1 WHERE id = ANY($1)",
+			source_ids
+		).execute(&mut *tx)
+		.await?;
+
+		tx.commit().await?;
+
+		Ok(version)
+	}
+
+	async fn import(&self, rows: &[CategoryImportRow], dry_run: bool) -> Result<ImportOutcome<CategoryData>, RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+
+		// Rows nest under each other, a concurrent move must not see half of the import.
+		Self::lock_hierarchy(&mut *tx).await?;
+
+		let mut created = vec![];
+		let mut errors = vec![];
+
+		for row in rows {
+			let name = clean_name(&row.name);
+
+			if let Some((existing_id, existing_name)) = Self::find_by_name(&mut *tx, &name, None).await? {
+				push_row_error(&mut errors, row.line, "name", name_taken(existing_id, &existing_name).to_string());
+				continue;
+			}
+
+			let parent_id = match &row.parent {
+				Some(parent) => match Self::find_by_name(&mut *tx, parent, None).await? {
+					Some((parent_id, _)) => Some(parent_id),
+					None => {
+						push_row_error(&mut errors, row.line, "parent", format!("Parent Category \"{parent}\" Tidak Ditemukan."));
+						continue;
+					}
+				},
+				None => None
+			};
+
+			if let Some(parent_id) = parent_id {
+				if Self::level(&mut *tx, parent_id).await? + 1 > MAX_DEPTH {
+					push_row_error(&mut errors, row.line, "parent", too_deep().to_string());
+					continue;
+				}
+			}
+
+			let slug = Self::unique_slug(&mut *tx, &name, None).await?;
+
+			let query_insert = sqlx::query_as!(
+				CategoryData,
+				"INSERT INTO category (name, slug, parent_id, sort_order) VALUES ($1, $2, $3, $4) RETURNING *",
+				name,
+				slug,
+				parent_id,
+				row.sort_order
+			).fetch_one(&mut *tx)
+			.await
+			.map_err(|e| duplicate_name(e, &name))?;
+			created.push(query_insert);
+		}
+
+		// Dropping the transaction rolls every row back.
+		if !errors.is_empty() || dry_run {
+			return Ok(ImportOutcome { created: vec![], errors });
+		}
+
+		tx.commit().await?;
+
+		Ok(ImportOutcome { created, errors })
+	}
+
+	async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+
+		// Creates and moves lock the parent they nest under, one landing under this category either finished and is
+		// counted below or waits and then finds it deleted.
+		let current_version = sqlx::query_scalar!(
+			"SELECT version FROM category WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+			id
+		).fetch_optional(&mut *tx)
+		.await?
+		.ok_or(RepositoryError::NotFound)?;
+
+		let has_children = sqlx::query_scalar!(
+			r#"SELECT EXISTS(SELECT 1 FROM category WHERE parent_id = $1 AND deleted_at IS NULL) AS "has_children!""#,
+			id
+		).fetch_one(&mut *tx)
+		.await?;
+
+		if has_children {
+			return Err(has_live_children());
+		}
+
+		if version.is_some_and(|version| version != current_version) {
+			return Err(RepositoryError::VersionMismatch);
+		}
+
+		sqlx::query!(
+			"UPDATE category SET deleted_at = NOW(), updated_at = NOW(), version = version + 1 WHERE id = $1",
+			id
+		).execute(&mut *tx)
+		.await?;
+
+		tx.commit().await?;
+
+		Ok(())
+	}
+
+	async fn restore(&self, id: i32) -> Result<i32, RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+
+		// The category comes back under its parent, a concurrent delete or move must not leave it under a deleted one.
+		Self::lock_hierarchy(&mut *tx).await?;
+
+		let query_find_first = sqlx::query!(
+			"SELECT name, parent_id FROM category WHERE id = $1 AND deleted_at IS NOT NULL FOR UPDATE",
+			id
+		).fetch_one(&mut *tx)
+		.await?;
+
+		if let Some(parent_id) = query_find_first.parent_id {
+			Self::lock_parent(&mut *tx, parent_id)
+			.await
+			.map_err(|e| match e {
+				RepositoryError::Invalid(_) => parent_deleted(),
+				e => e
+			})?;
+		}
+
+		Self::ensure_name_free(&mut *tx, &query_find_first.name, Some(id)).await?;
+
+		let query_restore = sqlx::query_scalar!(
+			"UPDATE category SET deleted_at = NULL, updated_at = NOW(), version = version + 1
+			WHERE id = $1 AND deleted_at IS NOT NULL RETURNING version",
+			id
+		).fetch_one(&mut *tx)
+		.await
+		.map_err(|e| duplicate_name(e, &query_find_first.name))?;
+
+		tx.commit().await?;
+
+		Ok(query_restore)
+	}
+
+	async fn purge_deleted(&self, deleted_before: OffsetDateTime) -> Result<u64, RepositoryError> {
+		let query_purge = sqlx::query!(
+			"DELETE FROM category WHERE deleted_at < $1",
+			deleted_before
+		).execute(&self.pg_pool)
+		.await?;
+
+		Ok(query_purge.rows_affected())
+	}
+}
+
+/// Keeps categories in memory, used to exercise controllers without a database.
+#[derive(Default)]
+pub struct InMemoryCategoryRepository {
+	categories: Mutex<Vec<CategoryData>>
+}
+
+#[async_trait]
+impl CategoryRepository for InMemoryCategoryRepository {
+	async fn search_paginate(&self, request: &PageRequest, subtree_of: Option<i32>) -> Result<Page<CategoryData>, RepositoryError> {
+		let term = request.term.to_lowercase();
+		let categories = self.categories.lock().unwrap();
+		let subtree = subtree_of.map(|root| subtree_ids(&categories, root));
+
+		let categories = categories.iter()
+			.filter(|category| request.include_deleted || category.deleted_at.is_none())
+			.filter(|category| category.name.to_lowercase().contains(&term))
+			.filter(|category| subtree.as_ref().is_none_or(|subtree| subtree.contains(&category.id)))
+			.cloned()
+			.collect();
+
+		Ok(request.paginate_in_memory(categories))
+	}
+
+	async fn find_many(&self, include_deleted: bool) -> Result<Vec<CategoryData>, RepositoryError> {
+		let mut categories = self.categories.lock().unwrap().clone();
+		categories.retain(|category| include_deleted || category.deleted_at.is_none());
+		categories.sort_by(|a, b| (a.sort_order, &a.name).cmp(&(b.sort_order, &b.name)));
+
+		Ok(categories)
+	}
+
+	async fn find_by_id(&self, id: i32) -> Result<CategoryData, RepositoryError> {
+		self.categories.lock().unwrap()
+			.iter()
+			.find(|category| category.id == id && category.deleted_at.is_none())
+			.cloned()
+			.ok_or(RepositoryError::NotFound)
+	}
+
+	async fn ancestors(&self, id: i32) -> Result<Vec<CategoryData>, RepositoryError> {
+		let categories = self.categories.lock().unwrap();
+		let find_live = |id: i32| categories.iter().find(|category| category.id == id && category.deleted_at.is_none());
+
+		let mut ancestors = vec![find_live(id).cloned().ok_or(RepositoryError::NotFound)?];
+
+		while let Some(parent) = ancestors.last().and_then(|category| category.parent_id).and_then(find_live) {
+			if ancestors.len() > MAX_DEPTH as usize {
+				break;
+			}
+			ancestors.push(parent.clone());
+		}
+		ancestors.reverse();
+
+		Ok(ancestors)
+	}
+
+	async fn create(&self, body: &CategoryCreateBody) -> Result<CategoryData, RepositoryError> {
+		let mut categories = self.categories.lock().unwrap();
+		let now = OffsetDateTime::now_utc();
+
+		let name = clean_name(&body.name);
+		ensure_name_free(&categories, &name, None)?;
+
+		if let Some(parent_id) = body.parent_id {
+			if !categories.iter().any(|category| category.id == parent_id && category.deleted_at.is_none()) {
+				return Err(parent_not_found());
+			}
+
+			if level(&categories, parent_id) + 1 > MAX_DEPTH {
+				return Err(too_deep());
+			}
+		}
+
+		let category = CategoryData {
+			id: categories.iter().map(|category| category.id).max().unwrap_or(0) + 1,
+			slug: unique_slug(&categories, &name, None),
+			name,
+			parent_id: body.parent_id,
+			sort_order: 0,
+			icon: body.icon.clone(),
+			created_at: now,
+			updated_at: now,
+			deleted_at: None,
+			version: 1
+		};
+		categories.push(category.clone());
+
+		Ok(category)
+	}
+
+	async fn update(&self, id: i32, name: &str, icon: Option<&str>, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let mut categories = self.categories.lock().unwrap();
+		let name = clean_name(name);
+		ensure_name_free(&categories, &name, Some(id))?;
+		let slug = unique_slug(&categories, &name, Some(id));
+		let category = find_current(&mut categories, id, version)?;
+
+		category.name = name;
+		category.slug = slug;
+		category.icon = icon.map(str::to_string);
+		category.updated_at = OffsetDateTime::now_utc();
+		category.version += 1;
+
+		Ok(category.version)
+	}
+
+	async fn patch(&self, id: i32, body: &CategoryUpdateBody, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let mut categories = self.categories.lock().unwrap();
+		let name = body.name.as_deref().map(clean_name);
+		if let Some(name) = &name {
+			ensure_name_free(&categories, name, Some(id))?;
+		}
+		let slug = name.as_ref().map(|name| unique_slug(&categories, name, Some(id)));
+		let category = find_current(&mut categories, id, version)?;
+
+		if let Some(name) = name { category.name = name; }
+		if let Some(slug) = slug { category.slug = slug; }
+		if let Some(icon) = &body.icon { category.icon = icon.clone(); }
+		category.updated_at = OffsetDateTime::now_utc();
+		category.version += 1;
+
+		Ok(category.version)
+	}
+
+	async fn move_to(&self, id: i32, parent_id: Option<i32>, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let mut categories = self.categories.lock().unwrap();
+
+		if let Some(parent_id) = parent_id {
+			if !categories.iter().any(|category| category.id == parent_id && category.deleted_at.is_none()) {
+				return Err(parent_not_found());
+			}
+
+			if subtree_ids(&categories, id).contains(&parent_id) {
+				return Err(cycle());
+			}
+
+			if level(&categories, parent_id) + height(&categories, id) > MAX_DEPTH {
+				return Err(too_deep());
+			}
+		}
+
+		let category = find_current(&mut categories, id, version)?;
+
+		category.parent_id = parent_id;
+		category.updated_at = OffsetDateTime::now_utc();
+		category.version += 1;
+
+		Ok(category.version)
+	}
+
+	async fn reorder(&self, ids: &[i32]) -> Result<(), RepositoryError> {
+		let mut categories = self.categories.lock().unwrap();
+
+		let unknown_ids: Vec<i32> = ids.iter()
+			.copied()
+			.filter(|id| !categories.iter().any(|category| category.id == *id && category.deleted_at.is_none()))
+			.collect();
+
+		if !unknown_ids.is_empty() {
+			return Err(unknown_categories(&unknown_ids));
+		}
+
+		for (position, id) in ids.iter().enumerate() {
+			let category = categories.iter_mut().find(|category| category.id == *id).unwrap();
+			let sort_order = position as i32 + 1;
+
+			if category.sort_order != sort_order {
+				category.sort_order = sort_order;
+				category.updated_at = OffsetDateTime::now_utc();
+				category.version += 1;
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn merge(&self, id: i32, source_ids: &[i32], version: Option<i32>) -> Result<i32, RepositoryError> {
+		if source_ids.contains(&id) {
+			return Err(merge_into_itself());
+		}
+
+		let mut categories = self.categories.lock().unwrap();
+
+		let unknown_ids: Vec<i32> = source_ids.iter()
+			.copied()
+			.filter(|source_id| !categories.iter().any(|category| category.id == *source_id && category.deleted_at.is_none()))
+			.collect();
+
+		if !unknown_ids.is_empty() {
+			return Err(unknown_categories(&unknown_ids));
+		}
+
+		if source_ids.iter().any(|source_id| subtree_ids(&categories, *source_id).contains(&id)) {
+			return Err(merge_into_descendant());
+		}
+
+		let moved_height = categories.iter()
+			.filter(|category| !source_ids.contains(&category.id))
+			.filter(|category| category.parent_id.is_some_and(|parent_id| source_ids.contains(&parent_id)))
+			.map(|category| height(&categories, category.id))
+			.max()
+			.unwrap_or(0);
+
+		if level(&categories, id) + moved_height > MAX_DEPTH {
+			return Err(too_deep());
+		}
+
+		let target = find_current(&mut categories, id, version)?;
+		let now = OffsetDateTime::now_utc();
+		target.updated_at = now;
+		target.version += 1;
+		let version = target.version;
+
+		for category in categories.iter_mut() {
+			if source_ids.contains(&category.id) {
+				category.deleted_at = Some(now);
+			} else if category.parent_id.is_some_and(|parent_id| source_ids.contains(&parent_id)) {
+				category.parent_id = Some(id);
+			} else {
+				continue;
+			}
+			category.updated_at = now;
+			category.version += 1;
+		}
+
+		Ok(version)
+	}
+
+	async fn import(&self, rows: &[CategoryImportRow], dry_run: bool) -> Result<ImportOutcome<CategoryData>, RepositoryError> {
+		let mut categories = self.categories.lock().unwrap();
+		let mut imported = categories.clone();
+		let mut errors = vec![];
+		let now = OffsetDateTime::now_utc();
+		let find_live = |categories: &[CategoryData], name: &str| categories.iter()
+			.find(|category| category.deleted_at.is_none() && name_key(&category.name) == name_key(name))
+			.map(|category| category.id);
+
+		for row in rows {
+			let name = clean_name(&row.name);
+
+			if let Err(e) = ensure_name_free(&imported, &name, None) {
+				push_row_error(&mut errors, row.line, "name", e.to_string());
+				continue;
+			}
+
+			let parent_id = match &row.parent {
+				Some(parent) => match find_live(&imported, parent) {
+					Some(parent_id) => Some(parent_id),
+					None => {
+						push_row_error(&mut errors, row.line, "parent", format!("Parent Category \"{parent}\" Tidak Ditemukan."));
+						continue;
+					}
+				},
+				None => None
+			};
+
+			if parent_id.is_some_and(|parent_id| level(&imported, parent_id) + 1 > MAX_DEPTH) {
+				push_row_error(&mut errors, row.line, "parent", too_deep().to_string());
+				continue;
+			}
+
+			imported.push(CategoryData {
+				id: imported.iter().map(|category| category.id).max().unwrap_or(0) + 1,
+				slug: unique_slug(&imported, &name, None),
+				name,
+				parent_id,
+				sort_order: row.sort_order,
+				icon: None,
+				created_at: now,
+				updated_at: now,
+				deleted_at: None,
+				version: 1
+			});
+		}
+
+		if !errors.is_empty() || dry_run {
+			return Ok(ImportOutcome { created: vec![], errors });
+		}
+
+		let created = imported[categories.len()..].to_vec();
+		*categories = imported;
+
+		Ok(ImportOutcome { created, errors })
+	}
+
+	async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), RepositoryError> {
+		let mut categories = self.categories.lock().unwrap();
+
+		if categories.iter().any(|category| category.parent_id == Some(id) && category.deleted_at.is_none()) {
+			return Err(has_live_children());
+		}
+
+		let category = find_current(&mut categories, id, version)?;
+		let now = OffsetDateTime::now_utc();
+
+		category.deleted_at = Some(now);
+		category.updated_at = now;
+		category.version += 1;
+
+		Ok(())
+	}
+
+	async fn restore(&self, id: i32) -> Result<i32, RepositoryError> {
+		let mut categories = self.categories.lock().unwrap();
+		let (name, parent_id) = categories.iter()
+			.find(|category| category.id == id && category.deleted_at.is_some())
+			.map(|category| (category.name.clone(), category.parent_id))
+			.ok_or(RepositoryError::NotFound)?;
+
+		if parent_id.is_some_and(|parent_id| !categories.iter().any(|category| category.id == parent_id && category.deleted_at.is_none())) {
+			return Err(parent_deleted());
+		}
+		ensure_name_free(&categories, &name, Some(id))?;
+
+		let category = categories.iter_mut().find(|category| category.id == id).unwrap();
+		category.deleted_at = None;
+		category.updated_at = OffsetDateTime::now_utc();
+		category.version += 1;
+
+		Ok(category.version)
+	}
+
+	async fn purge_deleted(&self, deleted_before: OffsetDateTime) -> Result<u64, RepositoryError> {
+		let mut categories = self.categories.lock().unwrap();
+		let before = categories.len();
+		categories.retain(|category| category.deleted_at.is_none_or(|deleted_at| deleted_at >= deleted_before));
+
+		Ok((before - categories.len()) as u64)
+	}
+}
+
+fn find_current(categories: &mut [CategoryData], id: i32, version: Option<i32>) -> Result<&mut CategoryData, RepositoryError> {
+	let category = categories.iter_mut()
+		.find(|category| category.id == id && category.deleted_at.is_none())
+		.ok_or(RepositoryError::NotFound)?;
+
+	if version.is_some_and(|version| version != category.version) {
+		return Err(RepositoryError::VersionMismatch);
+	}
+
+	Ok(category)
+}
+
+/// Deepest nesting allowed, a top-level category is at level 1. Also bounds the recursive queries, the hierarchy
+/// cannot contain cycles but a corrupted row must not hang a request.
+const MAX_DEPTH: i32 = 100;
+
+/// Restricts a query that already has a `WHERE` to `root` and its descendants.
+fn push_subtree(query: &mut QueryBuilder<'_, Postgres>, root: Option<i32>) {
+	if let Some(root) = root {
+		query.push(" AND id IN (WITH RECURSIVE subtree AS (SELECT id, 0 AS depth FROM category WHERE id = ")
+		.push_bind(root)
+		.push(" UNION ALL SELECT category.id, subtree.depth + 1 FROM category JOIN subtree ON category.parent_id = subtree.id WHERE subtree.depth < ")
+		.push_bind(MAX_DEPTH)
+		.push(") SELECT id FROM subtree)");
+	}
+}
+
+/// `root` and the ids of every category below it, soft-deleted ones included.
+fn subtree_ids(categories: &[CategoryData], root: i32) -> HashSet<i32> {
+	let mut subtree = HashSet::from([root]);
+	let mut frontier = vec![root];
+
+	while let Some(parent_id) = frontier.pop() {
+		for category in categories.iter().filter(|category| category.parent_id == Some(parent_id)) {
+			if subtree.insert(category.id) {
+				frontier.push(category.id);
+			}
+		}
+	}
+
+	subtree
+}
+
+/// Same as `PgCategoryRepository::level`.
+fn level(categories: &[CategoryData], id: i32) -> i32 {
+	let mut level = 0;
+	let mut current = Some(id);
+
+	while let Some(category) = current.and_then(|id| categories.iter().find(|category| category.id == id)) {
+		level += 1;
+		if level > MAX_DEPTH {
+			break;
+		}
+		current = category.parent_id;
+	}
+
+	level
+}
+
+/// Same as `PgCategoryRepository::height`.
+fn height(categories: &[CategoryData], root: i32) -> i32 {
+	let mut height = 0;
+	let mut frontier = vec![root];
+
+	while !frontier.is_empty() && height <= MAX_DEPTH {
+		height += 1;
+		frontier = categories.iter()
+			.filter(|category| category.parent_id.is_some_and(|parent_id| frontier.contains(&parent_id)))
+			.map(|category| category.id)
+			.collect();
+	}
+
+	height
+}
+
+fn unique_slug(categories: &[CategoryData], name: &str, id: Option<i32>) -> String {
+	let base = slugify(name);
+	let taken: Vec<String> = categories.iter()
+		.filter(|category| Some(category.id) != id)
+		.map(|category| category.slug.clone())
+		.collect();
+
+	pick_slug(&base, &taken)
+}
+
+fn ensure_name_free(categories: &[CategoryData], name: &str, id: Option<i32>) -> Result<(), RepositoryError> {
+	let key = name_key(name);
+
+	match categories.iter().find(|category| category.deleted_at.is_none() && Some(category.id) != id && name_key(&category.name) == key) {
+		Some(existing) => Err(name_taken(existing.id, &existing.name)),
+		None => Ok(())
+	}
+}
+
+fn name_taken(id: i32, name: &str) -> RepositoryError {
+	RepositoryError::Conflict(format!("Nama Category Sudah Dipakai Oleh \"{name}\" (Id {id})."))
+}
+
+/// A concurrent write with the same name got past `ensure_name_free` first, or one with the same slug got past
+/// `unique_slug`, the unique indexes reject this one. A retry picks the next free slug.
+fn duplicate_name(e: sqlx::Error, name: &str) -> RepositoryError {
+	match &e {
+		sqlx::Error::Database(db) if db.constraint() == Some("category_name_unique_idx") => {
+			RepositoryError::Conflict(format!("Nama Category \"{name}\" Sudah Dipakai."))
+		},
+		sqlx::Error::Database(db) if db.constraint() == Some("category_slug_idx") => {
+			RepositoryError::Conflict(format!("Slug Category \"{name}\" Baru Saja Dipakai Category Lain, Coba Lagi."))
+		},
+		_ => e.into()
+	}
+}
+
+fn unknown_categories(ids: &[i32]) -> RepositoryError {
+	let ids: Vec<String> = ids.iter().map(i32::to_string).collect();
+
+	RepositoryError::Invalid(format!("Category Dengan Id {} Tidak Ditemukan.", ids.join(", ")))
+}
+
+fn parent_not_found() -> RepositoryError {
+	RepositoryError::Invalid("Parent Category Tidak Ditemukan.".to_string())
+}
+
+fn parent_deleted() -> RepositoryError {
+	RepositoryError::Conflict("Parent Category Masih Terhapus, Pulihkan Parent Category Terlebih Dahulu.".to_string())
+}
+
+fn too_deep() -> RepositoryError {
+	RepositoryError::Conflict(format!("Category Tidak Dapat Lebih Dalam Dari {MAX_DEPTH} Tingkat."))
+}
+
+fn cycle() -> RepositoryError {
+	RepositoryError::Conflict("Category Tidak Dapat Dipindahkan Ke Dalam Dirinya Sendiri Atau Sub Category-nya.".to_string())
+}
+
+fn merge_into_itself() -> RepositoryError {
+	RepositoryError::Invalid("Category Tidak Dapat Digabungkan Dengan Dirinya Sendiri.".to_string())
+}
+
+fn merge_into_descendant() -> RepositoryError {
+	RepositoryError::Conflict("Category Tidak Dapat Digabungkan Ke Dalam Sub Category Dari Category Yang Digabungkan.".to_string())
+}
+
+fn has_live_children() -> RepositoryError {
+	RepositoryError::Conflict("Category Masih Memiliki Sub Category, Pindahkan Atau Hapus Sub Category Terlebih Dahulu.".to_string())
+}
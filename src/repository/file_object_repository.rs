@@ -0,0 +1,170 @@
+use std::{ collections::HashMap, sync::Mutex };
+
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use sqlx::postgres::PgPool;
+use sqlx::types::time::OffsetDateTime;
+
+use crate::model::file_model::{ FileObject, NewFileObject };
+use crate::repository::RepositoryError;
+use crate::storage::StorageError;
+
+/// Objects removed per transaction by `collect_unreferenced`, their rows stay locked until every blob is gone.
+pub const COLLECT_BATCH: i64 = 100;
+
+/// Removes the blobs of an object, the original and its variants, before its row goes.
+pub type RemoveBlobs<'a> = &'a (dyn Fn(&FileObject) -> BoxFuture<'static, Result<(), StorageError>> + Send + Sync);
+
+/// Uploads stored by content, `ref_count` is maintained by the database from the columns holding file names.
+#[async_trait]
+pub trait FileObjectRepository: Send + Sync {
+	/// Records an upload before its blobs are written. The same content uploaded again keeps its row and restarts
+	/// its grace period, so it is not collected before the client gets to reference it.
+	async fn register(&self, object: &NewFileObject) -> Result<FileObject, RepositoryError>;
+
+	async fn find(&self, prefix: &str, file_name: &str) -> Result<FileObject, RepositoryError>;
+
+	/// Removes up to `COLLECT_BATCH` objects unreferenced since before `unreferenced_before`, blobs first, and returns
+	/// them. An upload of the same content meanwhile waits for the rows and writes the blobs again. A failing removal
+	/// keeps every row of the batch, blobs already gone are removed again on the next run.
+	async fn collect_unreferenced(&self, unreferenced_before: OffsetDateTime, remove_blobs: RemoveBlobs<'_>) -> Result<Vec<FileObject>, RepositoryError>;
+}
+
+pub struct PgFileObjectRepository {
+	pg_pool: PgPool
+}
+
+impl PgFileObjectRepository {
+	pub fn new(pg_pool: PgPool) -> Self {
+		Self { pg_pool }
+	}
+}
+
+#[async_trait]
+impl FileObjectRepository for PgFileObjectRepository {
+	async fn register(&self, object: &NewFileObject) -> Result<FileObject, RepositoryError> {
+		let query_upsert = sqlx::query_as!(
+			FileObject,
+			"INSERT INTO file_object (prefix, file_name, sha256, content_type, size) VALUES ($1, $2, $3, $4, $5)
+			ON CONFLICT (prefix, file_name) DO UPDATE SET unreferenced_since = CASE WHEN file_object.ref_count = 0 THEN NOW() END
+			RETURNING prefix, file_name, sha256, content_type, size, ref_count, created_at, unreferenced_since",
+			object.prefix,
+			object.file_name,
+			object.sha256,
+			object.content_type,
+			object.size
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		Ok(query_upsert)
+	}
+
+	async fn find(&self, prefix: &str, file_name: &str) -> Result<FileObject, RepositoryError> {
+		let query_find_first = sqlx::query_as!(
+			FileObject,
+			"SELECT prefix, file_name, sha256, content_type, size, ref_count, created_at, unreferenced_since
+			FROM file_object WHERE prefix = $1 AND file_name = $2",
+			prefix,
+			file_name
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_first)
+	}
+
+	async fn collect_unreferenced(&self, unreferenced_before: OffsetDateTime, remove_blobs: RemoveBlobs<'_>) -> Result<Vec<FileObject>, RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+
+		// Rows locked by a concurrent run are left to it.
+		let objects = sqlx::query_as!(
+			FileObject,
+			"SELECT prefix, file_name, sha256, content_type, size, ref_count, created_at, unreferenced_since
+			FROM file_object WHERE ref_count = 0 AND unreferenced_since < $1
+			ORDER BY unreferenced_since LIMIT $2 FOR UPDATE SKIP LOCKED",
+			unreferenced_before,
+			COLLECT_BATCH
+		).fetch_all(&mut *tx)
+		.await?;
+
+		for object in &objects {
+			remove_blobs(object).await?;
+
+			sqlx::query!(
+				"DELETE FROM file_object WHERE prefix = $1 AND file_name = $2",
+				object.prefix,
+				object.file_name
+			).execute(&mut *tx)
+			.await?;
+		}
+
+		tx.commit().await?;
+
+		Ok(objects)
+	}
+}
+
+/// Keeps file objects in memory for `AppState::in_memory`. Nothing maintains `ref_count` without the database
+/// triggers, every object stays unreferenced.
+#[derive(Default)]
+pub struct InMemoryFileObjectRepository {
+	objects: Mutex<HashMap<(String, String), FileObject>>
+}
+
+impl InMemoryFileObjectRepository {
+	/// Drops the object unless it was referenced or uploaded again while its blobs were being removed.
+	fn remove_if_unchanged(&self, object: &FileObject) {
+		let mut objects = self.objects.lock().unwrap();
+		let id = (object.prefix.clone(), object.file_name.clone());
+
+		if objects.get(&id).is_some_and(|current| current.ref_count == 0 && current.unreferenced_since == object.unreferenced_since) {
+			objects.remove(&id);
+		}
+	}
+}
+
+#[async_trait]
+impl FileObjectRepository for InMemoryFileObjectRepository {
+	async fn register(&self, object: &NewFileObject) -> Result<FileObject, RepositoryError> {
+		let now = OffsetDateTime::now_utc();
+		let mut objects = self.objects.lock().unwrap();
+
+		let stored = objects.entry((object.prefix.clone(), object.file_name.clone()))
+		.and_modify(|stored| if stored.ref_count == 0 { stored.unreferenced_since = Some(now) })
+		.or_insert_with(|| FileObject {
+			prefix: object.prefix.clone(),
+			file_name: object.file_name.clone(),
+			sha256: object.sha256.clone(),
+			content_type: object.content_type.clone(),
+			size: object.size,
+			ref_count: 0,
+			created_at: now,
+			unreferenced_since: Some(now)
+		});
+
+		Ok(stored.clone())
+	}
+
+	async fn find(&self, prefix: &str, file_name: &str) -> Result<FileObject, RepositoryError> {
+		self.objects.lock().unwrap().get(&(prefix.to_string(), file_name.to_string())).cloned().ok_or(RepositoryError::NotFound)
+	}
+
+	async fn collect_unreferenced(&self, unreferenced_before: OffsetDateTime, remove_blobs: RemoveBlobs<'_>) -> Result<Vec<FileObject>, RepositoryError> {
+		let mut objects: Vec<FileObject> = self.objects.lock().unwrap()
+		.values()
+		.filter(|object| object.ref_count == 0 && object.unreferenced_since.is_some_and(|since| since < unreferenced_before))
+		.cloned()
+		.collect();
+		objects.sort_by_key(|object| object.unreferenced_since);
+		objects.truncate(COLLECT_BATCH as usize);
+
+		for object in &objects {
+			remove_blobs(object).await?;
+		}
+
+		for object in &objects {
+			self.remove_if_unchanged(object);
+		}
+
+		Ok(objects)
+	}
+}
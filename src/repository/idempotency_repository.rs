@@ -0,0 +1,177 @@
+use std::{ collections::HashMap, sync::Mutex };
+
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::postgres::PgPool;
+use sqlx::types::time::OffsetDateTime;
+
+use crate::model::idempotency_model::{ IdempotencyClaim, StoredResponse };
+use crate::repository::RepositoryError;
+
+/// Keys are scoped to the user who sent them, two users may pick the same key without clashing.
+#[async_trait]
+pub trait IdempotencyRepository: Send + Sync {
+	/// Claims `key` for a request with the given hash, a key created before `expired_before` is claimed afresh.
+	async fn claim(&self, user_id: i32, key: &str, request_hash: &str, expired_before: OffsetDateTime) -> Result<IdempotencyClaim, RepositoryError>;
+
+	/// Stores the response of a claimed key so retries replay it.
+	async fn complete(&self, user_id: i32, key: &str, response: &StoredResponse) -> Result<(), RepositoryError>;
+
+	/// Forgets a claimed key whose request failed, a retry then runs the request again.
+	async fn release(&self, user_id: i32, key: &str) -> Result<(), RepositoryError>;
+
+	/// Removes keys created before `expired_before`, returns how many were removed.
+	async fn purge_expired(&self, expired_before: OffsetDateTime) -> Result<u64, RepositoryError>;
+}
+
+pub struct PgIdempotencyRepository {
+	pg_pool: PgPool
+}
+
+impl PgIdempotencyRepository {
+	pub fn new(pg_pool: PgPool) -> Self {
+		Self { pg_pool }
+	}
+}
+
+#[async_trait]
+impl IdempotencyRepository for PgIdempotencyRepository {
+	async fn claim(&self, user_id: i32, key: &str, request_hash: &str, expired_before: OffsetDateTime) -> Result<IdempotencyClaim, RepositoryError> {
+		sqlx::query!(
+			"DELETE FROM idempotency_key WHERE user_id = $1 AND key = $2 AND created_at < $3",
+			user_id,
+			key,
+			expired_before
+		).execute(&self.pg_pool)
+		.await?;
+
+		let query_insert = sqlx::query!(
+			"INSERT INTO idempotency_key (user_id, key, request_hash) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+			user_id,
+			key,
+			request_hash
+		).execute(&self.pg_pool)
+		.await?;
+
+		if query_insert.rows_affected() == 1 {
+			return Ok(IdempotencyClaim::Started);
+		}
+
+		let query_find_first = sqlx::query!(
+			"SELECT request_hash, response_status, response_headers, response_body FROM idempotency_key WHERE user_id = $1 AND key = $2",
+			user_id,
+			key
+		).fetch_optional(&self.pg_pool)
+		.await?;
+
+		// Released by the first request between the insert and this select, it is still running or just failed.
+		let Some(stored) = query_find_first else {
+			return Ok(IdempotencyClaim::InProgress);
+		};
+
+		if stored.request_hash != request_hash {
+			return Ok(IdempotencyClaim::Mismatch);
+		}
+
+		match (stored.response_status, stored.response_body) {
+			(Some(status), Some(body)) => Ok(IdempotencyClaim::Replay(StoredResponse {
+				status: status as u16,
+				headers: serde_json::from_value(stored.response_headers).unwrap_or_default(),
+				body
+			})),
+			_ => Ok(IdempotencyClaim::InProgress)
+		}
+	}
+
+	async fn complete(&self, user_id: i32, key: &str, response: &StoredResponse) -> Result<(), RepositoryError> {
+		sqlx::query!(
+			"UPDATE idempotency_key SET response_status = $1, response_headers = $2, response_body = $3 WHERE user_id = $4 AND key = $5",
+			response.status as i32,
+			serde_json::to_value(&response.headers).unwrap_or(Value::Array(vec![])),
+			response.body,
+			user_id,
+			key
+		).execute(&self.pg_pool)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn release(&self, user_id: i32, key: &str) -> Result<(), RepositoryError> {
+		sqlx::query!(
+			"DELETE FROM idempotency_key WHERE user_id = $1 AND key = $2",
+			user_id,
+			key
+		).execute(&self.pg_pool)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn purge_expired(&self, expired_before: OffsetDateTime) -> Result<u64, RepositoryError> {
+		let query_purge = sqlx::query!(
+			"DELETE FROM idempotency_key WHERE created_at < $1",
+			expired_before
+		).execute(&self.pg_pool)
+		.await?;
+
+		Ok(query_purge.rows_affected())
+	}
+}
+
+struct Entry {
+	request_hash: String,
+	response: Option<StoredResponse>,
+	created_at: OffsetDateTime
+}
+
+/// Keeps idempotency keys in memory, used to exercise the middleware without a database.
+#[derive(Default)]
+pub struct InMemoryIdempotencyRepository {
+	keys: Mutex<HashMap<(i32, String), Entry>>
+}
+
+#[async_trait]
+impl IdempotencyRepository for InMemoryIdempotencyRepository {
+	async fn claim(&self, user_id: i32, key: &str, request_hash: &str, expired_before: OffsetDateTime) -> Result<IdempotencyClaim, RepositoryError> {
+		let mut keys = self.keys.lock().unwrap();
+		let id = (user_id, key.to_string());
+
+		match keys.get(&id) {
+			Some(entry) if entry.created_at >= expired_before => {
+				if entry.request_hash != request_hash {
+					return Ok(IdempotencyClaim::Mismatch);
+				}
+
+				Ok(entry.response.clone().map(IdempotencyClaim::Replay).unwrap_or(IdempotencyClaim::InProgress))
+			},
+			_ => {
+				keys.insert(id, Entry { request_hash: request_hash.to_string(), response: None, created_at: OffsetDateTime::now_utc() });
+
+				Ok(IdempotencyClaim::Started)
+			}
+		}
+	}
+
+	async fn complete(&self, user_id: i32, key: &str, response: &StoredResponse) -> Result<(), RepositoryError> {
+		if let Some(entry) = self.keys.lock().unwrap().get_mut(&(user_id, key.to_string())) {
+			entry.response = Some(response.clone());
+		}
+
+		Ok(())
+	}
+
+	async fn release(&self, user_id: i32, key: &str) -> Result<(), RepositoryError> {
+		self.keys.lock().unwrap().remove(&(user_id, key.to_string()));
+
+		Ok(())
+	}
+
+	async fn purge_expired(&self, expired_before: OffsetDateTime) -> Result<u64, RepositoryError> {
+		let mut keys = self.keys.lock().unwrap();
+		let before = keys.len();
+		keys.retain(|_, entry| entry.created_at >= expired_before);
+
+		Ok((before - keys.len()) as u64)
+	}
+}
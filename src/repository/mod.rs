@@ -0,0 +1,86 @@
+pub mod audit_repository;
+pub mod category_repository;
+pub mod file_object_repository;
+pub mod idempotency_repository;
+pub mod role_repository;
+pub mod user_repository;
+
+use std::fmt;
+
+use axum::http::StatusCode;
+use sqlx::postgres::PgPool;
+
+use crate::storage::StorageError;
+
+/// Error returned by every repository implementation so controllers stay storage agnostic.
+#[derive(Debug)]
+pub enum RepositoryError {
+	NotFound,
+	/// The row exists but its version differs from the one sent in `If-Match`.
+	VersionMismatch,
+	/// The write would break a rule involving other rows, e.g. a category moved under its own descendant.
+	Conflict(String),
+	/// The request refers to a row that cannot be used, e.g. a parent category that does not exist.
+	Invalid(String),
+	Database(sqlx::Error),
+	/// Removing the blobs of a row failed, the row is kept.
+	Storage(StorageError)
+}
+
+impl RepositoryError {
+	pub fn status_code(&self) -> StatusCode {
+		match self {
+			RepositoryError::NotFound => StatusCode::NOT_FOUND,
+			RepositoryError::VersionMismatch => StatusCode::PRECONDITION_FAILED,
+			RepositoryError::Conflict(_) => StatusCode::CONFLICT,
+			RepositoryError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+			RepositoryError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+			RepositoryError::Storage(e) => e.status_code()
+		}
+	}
+}
+
+impl fmt::Display for RepositoryError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RepositoryError::NotFound => write!(f, "Data Tidak Ditemukan."),
+			RepositoryError::VersionMismatch => write!(f, "Data Telah Diubah Oleh Pengguna Lain, Muat Ulang Lalu Coba Lagi."),
+			RepositoryError::Conflict(message) | RepositoryError::Invalid(message) => write!(f, "{message}"),
+			RepositoryError::Database(e) => write!(f, "{e}"),
+			RepositoryError::Storage(e) => write!(f, "{e}")
+		}
+	}
+}
+
+impl std::error::Error for RepositoryError {}
+
+impl From<StorageError> for RepositoryError {
+	fn from(e: StorageError) -> Self {
+		RepositoryError::Storage(e)
+	}
+}
+
+impl From<sqlx::Error> for RepositoryError {
+	fn from(e: sqlx::Error) -> Self {
+		match e {
+			sqlx::Error::RowNotFound => RepositoryError::NotFound,
+			// Raised by `file_object_move_reference` for a file name whose object is gone, the message names it.
+			sqlx::Error::Database(db) if db.constraint() == Some("file_object_reference") => RepositoryError::Invalid(db.message().to_string()),
+			e => RepositoryError::Database(e)
+		}
+	}
+}
+
+/// Tells apart the two reasons a versioned write touched no row, soft-deleted rows count as missing.
+pub(crate) async fn not_found_or_stale(pg_pool: &PgPool, table: &str, id: i32) -> RepositoryError {
+	let exists = sqlx::query_scalar::<_, bool>(&format!("SELECT EXISTS(SELECT 1 FROM {table} WHERE id = $1 AND deleted_at IS NULL)"))
+	.bind(id)
+	.fetch_one(pg_pool)
+	.await;
+
+	match exists {
+		Ok(true) => RepositoryError::VersionMismatch,
+		Ok(false) => RepositoryError::NotFound,
+		Err(e) => RepositoryError::Database(e)
+	}
+}
@@ -0,0 +1,387 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use sqlx::postgres::PgPool;
+use sqlx::types::time::OffsetDateTime;
+
+use crate::model::role_model::{ PermissionData, RoleBody, RoleData, ADMIN_ROLE };
+use crate::repository::RepositoryError;
+
+#[async_trait]
+pub trait RoleRepository: Send + Sync {
+	/// Every role with its permissions, ordered by name.
+	async fn find_all(&self) -> Result<Vec<RoleData>, RepositoryError>;
+
+	async fn find_by_id(&self, id: i32) -> Result<RoleData, RepositoryError>;
+
+	/// The permission catalog ordered by code.
+	async fn permissions(&self) -> Result<Vec<PermissionData>, RepositoryError>;
+
+	/// Codes granted to the role, every code of the catalog for `ADMIN_ROLE` and none for an unknown role.
+	async fn permissions_of(&self, role: &str) -> Result<Vec<String>, RepositoryError>;
+
+	/// Fails with `RepositoryError::Conflict` when the name is taken and `RepositoryError::Invalid` for unknown permission codes.
+	async fn create(&self, body: &RoleBody) -> Result<RoleData, RepositoryError>;
+
+	/// Replaces the name, description and permissions and returns the new version, users of the role follow a rename.
+	/// Fails like `create`, with `RepositoryError::Conflict` when renaming a built-in role, and `version` is checked like
+	/// in the other repositories.
+	async fn update(&self, id: i32, body: &RoleBody, version: Option<i32>) -> Result<i32, RepositoryError>;
+
+	/// Fails with `RepositoryError::Conflict` for built-in roles and roles still assigned to a user, deleted ones included.
+	async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), RepositoryError>;
+}
+
+pub struct PgRoleRepository {
+	pg_pool: PgPool
+}
+
+impl PgRoleRepository {
+	pub fn new(pg_pool: PgPool) -> Self {
+		Self { pg_pool }
+	}
+
+	/// Fails with `RepositoryError::Invalid` listing the codes missing from the catalog.
+	async fn ensure_permissions_exist(&self, codes: &[String]) -> Result<(), RepositoryError> {
+		let known = sqlx::query_scalar!("SELECT code FROM permission WHERE code = ANY($1)", codes)
+		.fetch_all(&self.pg_pool)
+		.await?;
+
+		unknown_permissions(codes, &known)
+	}
+}
+
+#[async_trait]
+impl RoleRepository for PgRoleRepository {
+	async fn find_all(&self) -> Result<Vec<RoleData>, RepositoryError> {
+		let query_find_many = sqlx::query_as!(
+			RoleData,
+			r#"SELECT role.id, role.name, role.description, role.is_system,
+			COALESCE(ARRAY_AGG(role_permission.permission_code ORDER BY role_permission.permission_code)
+				FILTER (WHERE role_permission.permission_code IS NOT NULL), '{}') AS "permissions!",
+			role.created_at, role.updated_at, role.version
+			FROM role LEFT JOIN role_permission ON role_permission.role_id = role.id
+			GROUP BY role.id ORDER BY role.name ASC"#
+		).fetch_all(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_many)
+	}
+
+	async fn find_by_id(&self, id: i32) -> Result<RoleData, RepositoryError> {
+		let query_find_first = sqlx::query_as!(
+			RoleData,
+			r#"SELECT role.id, role.name, role.description, role.is_system,
+			COALESCE(ARRAY_AGG(role_permission.permission_code ORDER BY role_permission.permission_code)
+				FILTER (WHERE role_permission.permission_code IS NOT NULL), '{}') AS "permissions!",
+			role.created_at, role.updated_at, role.version
+			FROM role LEFT JOIN role_permission ON role_permission.role_id = role.id
+			WHERE role.id = $1 GROUP BY role.id"#,
+			id
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_first)
+	}
+
+	async fn permissions(&self) -> Result<Vec<PermissionData>, RepositoryError> {
+		let query_find_many = sqlx::query_as!(PermissionData, "SELECT code, description FROM permission ORDER BY code ASC")
+		.fetch_all(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_many)
+	}
+
+	async fn permissions_of(&self, role: &str) -> Result<Vec<String>, RepositoryError> {
+		let query_find_many = sqlx::query_scalar!(
+			"SELECT code FROM permission WHERE $1 = $2
+			OR code IN (SELECT permission_code FROM role_permission JOIN role ON role.id = role_permission.role_id WHERE role.name = $1)
+			ORDER BY code ASC",
+			role,
+			ADMIN_ROLE
+		).fetch_all(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_many)
+	}
+
+	async fn create(&self, body: &RoleBody) -> Result<RoleData, RepositoryError> {
+		self.ensure_permissions_exist(&body.permissions).await?;
+
+		let mut tx = self.pg_pool.begin().await?;
+
+		let id = sqlx::query_scalar!(
+			"INSERT INTO role (name, description) VALUES ($1, $2) RETURNING id",
+			body.name,
+			body.description
+		).fetch_one(&mut *tx)
+		.await
+		.map_err(|e| duplicate_name(e, &body.name))?;
+
+		sqlx::query!(
+			"INSERT INTO role_permission (role_id, permission_code) SELECT $1, UNNEST($2::VARCHAR[]) ON CONFLICT DO NOTHING",
+			id,
+			&body.permissions
+		).execute(&mut *tx)
+		.await?;
+
+		tx.commit().await?;
+
+		self.find_by_id(id).await
+	}
+
+	async fn update(&self, id: i32, body: &RoleBody, version: Option<i32>) -> Result<i32, RepositoryError> {
+		self.ensure_permissions_exist(&body.permissions).await?;
+
+		let mut tx = self.pg_pool.begin().await?;
+
+		let current = sqlx::query!("SELECT name, is_system, version FROM role WHERE id = $1 FOR UPDATE", id)
+		.fetch_optional(&mut *tx)
+		.await?
+		.ok_or(RepositoryError::NotFound)?;
+
+		if version.is_some_and(|version| version != current.version) {
+			return Err(RepositoryError::VersionMismatch);
+		}
+		if current.is_system && current.name != body.name {
+			return Err(built_in(&current.name));
+		}
+
+		let version = sqlx::query_scalar!(
+			"UPDATE role SET name = $2, description = $3, updated_at = NOW(), version = version + 1 WHERE id = $1 RETURNING version",
+			id,
+			body.name,
+			body.description
+		).fetch_one(&mut *tx)
+		.await
+		.map_err(|e| duplicate_name(e, &body.name))?;
+
+		sqlx::query!("DELETE FROM role_permission WHERE role_id = $1", id)
+		.execute(&mut *tx)
+		.await?;
+
+		sqlx::query!(
+			"INSERT INTO role_permission (role_id, permission_code) SELECT $1, UNNEST($2::VARCHAR[]) ON CONFLICT DO NOTHING",
+			id,
+			&body.permissions
+		).execute(&mut *tx)
+		.await?;
+
+		tx.commit().await?;
+
+		Ok(version)
+	}
+
+	async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+
+		let current = sqlx::query!("SELECT name, is_system, version FROM role WHERE id = $1 FOR UPDATE", id)
+		.fetch_optional(&mut *tx)
+		.await?
+		.ok_or(RepositoryError::NotFound)?;
+
+		if version.is_some_and(|version| version != current.version) {
+			return Err(RepositoryError::VersionMismatch);
+		}
+		if current.is_system {
+			return Err(built_in(&current.name));
+		}
+
+		let users = sqlx::query_scalar!(r#"SELECT COUNT(id) AS "count!" FROM user_system WHERE role = $1"#, current.name)
+		.fetch_one(&mut *tx)
+		.await?;
+
+		if users > 0 {
+			return Err(still_assigned(&current.name, users));
+		}
+
+		sqlx::query!("DELETE FROM role WHERE id = $1", id)
+		.execute(&mut *tx)
+		.await?;
+
+		tx.commit().await?;
+
+		Ok(())
+	}
+}
+
+/// Keeps roles in memory, seeded like the migration, used to exercise controllers without a database.
+/// It knows no users, so deleting a role never fails for being assigned.
+pub struct InMemoryRoleRepository {
+	roles: Mutex<Vec<RoleData>>,
+	permissions: Vec<PermissionData>
+}
+
+impl Default for InMemoryRoleRepository {
+	fn default() -> Self {
+		let now = OffsetDateTime::now_utc();
+		let role = |id: i32, name: &str, permissions: &[&str]| RoleData {
+			id,
+			name: name.to_string(),
+			description: None,
+			is_system: true,
+			permissions: permissions.iter().map(|code| code.to_string()).collect(),
+			created_at: now,
+			updated_at: now,
+			version: 1
+		};
+
+		let permissions = [
+			"audit.view", "category.create", "category.delete", "category.manage", "category.update", "report.view",
+			"role.manage", "sale.void", "user.create", "user.delete", "user.manage", "user.update"
+		];
+
+		Self {
+			roles: Mutex::new(vec![
+				role(1, ADMIN_ROLE, &[]),
+				role(2, "cashier", &["category.create", "category.delete", "category.update"])
+			]),
+			permissions: permissions.iter().map(|code| PermissionData { code: code.to_string(), description: code.to_string() }).collect()
+		}
+	}
+}
+
+impl InMemoryRoleRepository {
+	fn ensure_permissions_exist(&self, codes: &[String]) -> Result<(), RepositoryError> {
+		let known: Vec<String> = self.permissions.iter().map(|permission| permission.code.clone()).collect();
+
+		unknown_permissions(codes, &known)
+	}
+}
+
+#[async_trait]
+impl RoleRepository for InMemoryRoleRepository {
+	async fn find_all(&self) -> Result<Vec<RoleData>, RepositoryError> {
+		let mut roles = self.roles.lock().unwrap().clone();
+		roles.sort_by(|a, b| a.name.cmp(&b.name));
+
+		Ok(roles)
+	}
+
+	async fn find_by_id(&self, id: i32) -> Result<RoleData, RepositoryError> {
+		self.roles.lock().unwrap()
+			.iter()
+			.find(|role| role.id == id)
+			.cloned()
+			.ok_or(RepositoryError::NotFound)
+	}
+
+	async fn permissions(&self) -> Result<Vec<PermissionData>, RepositoryError> {
+		Ok(self.permissions.clone())
+	}
+
+	async fn permissions_of(&self, role: &str) -> Result<Vec<String>, RepositoryError> {
+		if role == ADMIN_ROLE {
+			return Ok(self.permissions.iter().map(|permission| permission.code.clone()).collect());
+		}
+
+		Ok(self.roles.lock().unwrap()
+			.iter()
+			.find(|candidate| candidate.name == role)
+			.map(|role| role.permissions.clone())
+			.unwrap_or_default())
+	}
+
+	async fn create(&self, body: &RoleBody) -> Result<RoleData, RepositoryError> {
+		self.ensure_permissions_exist(&body.permissions)?;
+
+		let mut roles = self.roles.lock().unwrap();
+		if roles.iter().any(|role| role.name == body.name) {
+			return Err(name_taken(&body.name));
+		}
+
+		let now = OffsetDateTime::now_utc();
+		let role = RoleData {
+			id: roles.iter().map(|role| role.id).max().unwrap_or(0) + 1,
+			name: body.name.clone(),
+			description: body.description.clone(),
+			is_system: false,
+			permissions: sorted(&body.permissions),
+			created_at: now,
+			updated_at: now,
+			version: 1
+		};
+		roles.push(role.clone());
+
+		Ok(role)
+	}
+
+	async fn update(&self, id: i32, body: &RoleBody, version: Option<i32>) -> Result<i32, RepositoryError> {
+		self.ensure_permissions_exist(&body.permissions)?;
+
+		let mut roles = self.roles.lock().unwrap();
+		if roles.iter().any(|role| role.name == body.name && role.id != id) {
+			return Err(name_taken(&body.name));
+		}
+
+		let role = roles.iter_mut().find(|role| role.id == id).ok_or(RepositoryError::NotFound)?;
+
+		if version.is_some_and(|version| version != role.version) {
+			return Err(RepositoryError::VersionMismatch);
+		}
+		if role.is_system && role.name != body.name {
+			return Err(built_in(&role.name));
+		}
+
+		role.name = body.name.clone();
+		role.description = body.description.clone();
+		role.permissions = sorted(&body.permissions);
+		role.updated_at = OffsetDateTime::now_utc();
+		role.version += 1;
+
+		Ok(role.version)
+	}
+
+	async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), RepositoryError> {
+		let mut roles = self.roles.lock().unwrap();
+		let role = roles.iter().find(|role| role.id == id).ok_or(RepositoryError::NotFound)?;
+
+		if version.is_some_and(|version| version != role.version) {
+			return Err(RepositoryError::VersionMismatch);
+		}
+		if role.is_system {
+			return Err(built_in(&role.name));
+		}
+
+		roles.retain(|role| role.id != id);
+
+		Ok(())
+	}
+}
+
+fn unknown_permissions(codes: &[String], known: &[String]) -> Result<(), RepositoryError> {
+	let unknown: Vec<&str> = codes.iter().filter(|code| !known.contains(code)).map(String::as_str).collect();
+
+	if !unknown.is_empty() {
+		return Err(RepositoryError::Invalid(format!("Permission Tidak Dikenal: {}.", unknown.join(", "))));
+	}
+
+	Ok(())
+}
+
+fn sorted(codes: &[String]) -> Vec<String> {
+	let mut codes = codes.to_vec();
+	codes.sort();
+	codes.dedup();
+
+	codes
+}
+
+fn duplicate_name(e: sqlx::Error, name: &str) -> RepositoryError {
+	match &e {
+		sqlx::Error::Database(db) if db.constraint() == Some("role_name_key") => name_taken(name),
+		_ => e.into()
+	}
+}
+
+fn name_taken(name: &str) -> RepositoryError {
+	RepositoryError::Conflict(format!("Nama Role \"{name}\" Sudah Dipakai."))
+}
+
+fn built_in(name: &str) -> RepositoryError {
+	RepositoryError::Conflict(format!("Role Bawaan \"{name}\" Tidak Dapat Diganti Namanya Atau Dihapus."))
+}
+
+fn still_assigned(name: &str, users: i64) -> RepositoryError {
+	RepositoryError::Conflict(format!("Role \"{name}\" Masih Dipakai Oleh {users} User."))
+}
@@ -0,0 +1,916 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use sqlx::{ postgres::PgPool, QueryBuilder };
+use sqlx::types::time::OffsetDateTime;
+
+use crate::model::user_model::{ UserCreateDto, UserData, UserImportRow, UserInvite, UserInviteDto, UserReplaceDto, UserUpdateDto, UserView, USER_LANGUAGES, USER_VIEW_COLUMNS };
+use crate::model::utils_model::ImportOutcome;
+use crate::repository::{ not_found_or_stale, RepositoryError };
+use crate::utils::filter::contains_pattern;
+use crate::utils::pagination::{ Page, PageRequest };
+use crate::utils::patch::PatchQuery;
+use crate::utils::phone;
+use crate::utils::tabular::push_row_error;
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+	/// Users whose username or full name contains the term, case-insensitively, or whose phone number contains it
+	/// when it reads as one in any format (see `phone::search_digits`), and that match every filter,
+	/// soft-deleted ones only with `request.include_deleted`.
+	async fn search_paginate(&self, request: &PageRequest) -> Result<Page<UserView>, RepositoryError>;
+
+	/// Soft-deleted users are not found, neither here nor in `find_by_username`, so they cannot log in.
+	/// Both return the password hash, convert the user to a `UserView` before it leaves the server.
+	async fn find_by_id(&self, id: i32) -> Result<UserData, RepositoryError>;
+
+	async fn find_by_username(&self, username: &str) -> Result<UserData, RepositoryError>;
+
+	/// Like `find_by_id` without reading the password hash.
+	async fn find_view(&self, id: i32) -> Result<UserView, RepositoryError>;
+
+	/// Up to `limit` live users with an id above `after_id`, ordered by id, exports walk the table with it.
+	async fn find_batch(&self, after_id: i32, limit: i64) -> Result<Vec<UserView>, RepositoryError>;
+
+	/// Stores `password_hash` in place of the plain `body.password`. Like `update`, `patch` and `restore`, fails with
+	/// `RepositoryError::Conflict` when another live user has the username.
+	async fn create(&self, body: &UserCreateDto, password_hash: &str) -> Result<UserData, RepositoryError>;
+
+	/// Writes every field of `body`, the password only when `password_hash` is given. Returns the new version,
+	/// writes only while the row is still at `version` and fails with `RepositoryError::VersionMismatch` otherwise.
+	async fn update(&self, id: i32, body: &UserReplaceDto, password_hash: Option<&str>, version: Option<i32>) -> Result<i32, RepositoryError>;
+
+	/// Writes only the fields present in `body`, the password only when `password_hash` is given, `version` is checked like in `update`.
+	async fn patch(&self, id: i32, body: &UserUpdateDto, password_hash: Option<&str>, version: Option<i32>) -> Result<i32, RepositoryError>;
+
+	async fn update_password(&self, username: &str, password_hash: &str) -> Result<(), RepositoryError>;
+
+	/// Whether the user may use the API, `false` for inactive, pending and soft-deleted users. `auth_guard` asks on every request
+	/// so a deactivation takes effect on tokens issued before it.
+	async fn is_active(&self, id: i32) -> Result<bool, RepositoryError>;
+
+	/// Activates or deactivates a user, `version` is checked like in `update`. Fails with `RepositoryError::Conflict`
+	/// while the invite of the user is pending, the user activates themselves through it.
+	async fn set_active(&self, id: i32, is_active: bool, version: Option<i32>) -> Result<i32, RepositoryError>;
+
+	/// Creates an inactive user without a password together with an invite expiring at `expires_at`,
+	/// fails with `RepositoryError::Conflict` when a live user has the username.
+	async fn invite(&self, body: &UserInviteDto, token_hash: &str, expires_at: OffsetDateTime) -> Result<UserData, RepositoryError>;
+
+	/// Replaces the unused invites of a pending user with a new one so only the latest link works. Fails with
+	/// `RepositoryError::NotFound` for an unknown user and `RepositoryError::Conflict` once the user is activated.
+	async fn resend_invite(&self, id: i32, token_hash: &str, expires_at: OffsetDateTime) -> Result<(), RepositoryError>;
+
+	/// The invite with the token hash, used and expired ones included so callers can tell why a link is refused.
+	async fn find_invite(&self, token_hash: &str) -> Result<UserInvite, RepositoryError>;
+
+	/// Marks the invite used and activates its user with `password_hash`. Fails with `RepositoryError::NotFound` unless the
+	/// invite is unused and unexpired, so a link works once even when it is opened twice at the same time.
+	async fn accept_invite(&self, token_hash: &str, password_hash: &str) -> Result<UserData, RepositoryError>;
+
+	/// Creates a user per row in one transaction and returns them with the rows whose username is taken by a live user
+	/// or by an earlier row or whose role does not exist, nothing is written when there is any or on a dry run.
+	async fn import(&self, rows: &[UserImportRow], dry_run: bool) -> Result<ImportOutcome<UserData>, RepositoryError>;
+
+	/// Soft-deletes the user, fails with `RepositoryError::NotFound` when no live user has the given id,
+	/// `version` is checked like in `update`.
+	async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), RepositoryError>;
+
+	/// Undoes `delete` and returns the new version, fails with `RepositoryError::NotFound` unless the user is soft-deleted.
+	async fn restore(&self, id: i32) -> Result<i32, RepositoryError>;
+
+	/// Removes users soft-deleted before `deleted_before` for good, returns how many were removed.
+	async fn purge_deleted(&self, deleted_before: OffsetDateTime) -> Result<u64, RepositoryError>;
+
+	/// Id and phone number of every user, soft-deleted ones included, ordered by id.
+	async fn phone_numbers(&self) -> Result<Vec<(i32, String)>, RepositoryError>;
+
+	/// Rewrites the phone number of each listed user in one transaction and bumps their versions, `updated_at` is kept
+	/// since only the format changes.
+	async fn set_phone_numbers(&self, numbers: &[(i32, String)]) -> Result<(), RepositoryError>;
+}
+
+pub struct PgUserRepository {
+	pg_pool: PgPool
+}
+
+impl PgUserRepository {
+	pub fn new(pg_pool: PgPool) -> Self {
+		Self { pg_pool }
+	}
+}
+
+#[async_trait]
+impl UserRepository for PgUserRepository {
+	async fn search_paginate(&self, request: &PageRequest) -> Result<Page<UserView>, RepositoryError> {
+		let term = contains_pattern(&request.term);
+		let phone_term = phone::search_digits(&request.term).map(|digits| format!("%{digits}%"));
+
+		let mut query_count = QueryBuilder::new("SELECT COUNT(id) from user_system WHERE (username ILIKE ");
+		query_count.push_bind(&term).push(" ESCAPE '\\' OR full_name ILIKE ").push_bind(&term).push(" ESCAPE '\\' OR phone_number LIKE ").push_bind(&phone_term).push(")");
+		request.push_soft_delete(&mut query_count);
+		request.push_filters(&mut query_count);
+
+		let query_count: i64 = query_count.build_query_scalar()
+		.fetch_one(&self.pg_pool)
+		.await?;
+
+		let mut query = QueryBuilder::new(format!("SELECT {USER_VIEW_COLUMNS} FROM user_system WHERE (username ILIKE "));
+		query.push_bind(&term).push(" ESCAPE '\\' OR full_name ILIKE ").push_bind(&term).push(" ESCAPE '\\' OR phone_number LIKE ").push_bind(&phone_term).push(")");
+		request.push_soft_delete(&mut query);
+		request.push_filters(&mut query);
+		request.push_keyset(&mut query);
+		request.push_order_and_limit(&mut query);
+
+		let query_search = query.build_query_as::<UserView>()
+		.fetch_all(&self.pg_pool)
+		.await?;
+
+		Ok(request.into_page(query_search, query_count))
+	}
+
+	async fn find_by_id(&self, id: i32) -> Result<UserData, RepositoryError> {
+		let query_find_first = sqlx::query_as!(
+			UserData,
+			"SELECT * FROM user_system WHERE id = $1 AND deleted_at IS NULL LIMIT 1",
+			id
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_first)
+	}
+
+	async fn find_by_username(&self, username: &str) -> Result<UserData, RepositoryError> {
+		let query_find_first = sqlx::query_as!(
+			UserData,
+			"SELECT * FROM user_system WHERE username = $1 AND deleted_at IS NULL LIMIT 1",
+			username
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_first)
+	}
+
+	async fn find_view(&self, id: i32) -> Result<UserView, RepositoryError> {
+		let query_find_first = sqlx::query_as!(
+			UserView,
+			"SELECT id, username, full_name, address, phone_number, photo, role, created_at, updated_at, deleted_at, version, language, is_active, activated_at
+			FROM user_system WHERE id = $1 AND deleted_at IS NULL LIMIT 1",
+			id
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_first)
+	}
+
+	async fn find_batch(&self, after_id: i32, limit: i64) -> Result<Vec<UserView>, RepositoryError> {
+		let query_find_many = sqlx::query_as!(
+			UserView,
+			"SELECT id, username, full_name, address, phone_number, photo, role, created_at, updated_at, deleted_at, version, language, is_active, activated_at
+			FROM user_system WHERE id > $1 AND deleted_at IS NULL ORDER BY id ASC LIMIT $2",
+			after_id,
+			limit
+		).fetch_all(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_many)
+	}
+
+	async fn create(&self, body: &UserCreateDto, password_hash: &str) -> Result<UserData, RepositoryError> {
+		let query_insert = sqlx::query_as!(
+			UserData,
+			"INSERT INTO user_system (username, password, full_name, address, phone_number, role, photo) VALUES ($1, $2, $3, $4, $5, $6, $7)
+			RETURNING *",
+			body.username,
+			password_hash,
+			body.full_name,
+			body.address,
+			body.phone_number,
+			body.role,
+			body.photo
+		).fetch_one(&self.pg_pool)
+		.await
+		.map_err(|e| write_error(e, &body.username))?;
+
+		Ok(query_insert)
+	}
+
+	async fn update(&self, id: i32, body: &UserReplaceDto, password_hash: Option<&str>, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let query_update = sqlx::query_scalar!(
+			"UPDATE user_system SET username = $1, password = COALESCE($8, password), full_name = $2, address = $3,
+			phone_number = $4, role = $5, photo = $6, language = $10, updated_at = NOW(), version = version + 1
+			WHERE id = $7 AND deleted_at IS NULL AND ($9::INTEGER IS NULL OR version = $9) RETURNING version",
+			body.username,
+			body.full_name,
+			body.address,
+			body.phone_number,
+			body.role,
+			body.photo,
+			id,
+			password_hash,
+			version,
+			body.language
+		).fetch_optional(&self.pg_pool)
+		.await
+		.map_err(|e| write_error(e, &body.username))?;
+
+		match query_update {
+			Some(version) => Ok(version),
+			None => Err(not_found_or_stale(&self.pg_pool, "user_system", id).await)
+		}
+	}
+
+	async fn patch(&self, id: i32, body: &UserUpdateDto, password_hash: Option<&str>, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let mut patch = PatchQuery::new("user_system");
+		patch
+		.set("username", body.username.as_deref())
+		.set("password", password_hash)
+		.set("full_name", body.full_name.as_deref())
+		.set("address", body.address.as_ref().map(Option::as_deref))
+		.set("phone_number", body.phone_number.as_deref())
+		.set("role", body.role.as_deref())
+		.set("photo", body.photo.as_deref())
+		.set("language", body.language.as_deref());
+
+		let query_patch: Option<i32> = patch.build(id, version)
+		.build_query_scalar()
+		.fetch_optional(&self.pg_pool)
+		.await
+		// The unique index only fails when the username changes.
+		.map_err(|e| write_error(e, body.username.as_deref().unwrap_or_default()))?;
+
+		match query_patch {
+			Some(version) => Ok(version),
+			None => Err(not_found_or_stale(&self.pg_pool, "user_system", id).await)
+		}
+	}
+
+	async fn update_password(&self, username: &str, password_hash: &str) -> Result<(), RepositoryError> {
+		sqlx::query!(
+			"UPDATE user_system SET password = $1, updated_at = NOW(), version = version + 1 WHERE username = $2 AND deleted_at IS NULL",
+			password_hash,
+			username
+		).execute(&self.pg_pool)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn is_active(&self, id: i32) -> Result<bool, RepositoryError> {
+		let query_find_first = sqlx::query_scalar!(
+			"SELECT is_active FROM user_system WHERE id = $1 AND deleted_at IS NULL",
+			id
+		).fetch_optional(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_first.unwrap_or(false))
+	}
+
+	async fn set_active(&self, id: i32, is_active: bool, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let query_update = sqlx::query_scalar!(
+			"UPDATE user_system SET is_active = $2, updated_at = NOW(), version = version + 1
+			WHERE id = $1 AND deleted_at IS NULL AND activated_at IS NOT NULL AND ($3::INTEGER IS NULL OR version = $3) RETURNING version",
+			id,
+			is_active,
+			version
+		).fetch_optional(&self.pg_pool)
+		.await?;
+
+		if let Some(version) = query_update {
+			return Ok(version);
+		}
+
+		let pending = sqlx::query_scalar!(
+			r#"SELECT EXISTS(SELECT 1 FROM user_system WHERE id = $1 AND deleted_at IS NULL AND activated_at IS NULL) AS "pending!""#,
+			id
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		match pending {
+			true => Err(invite_pending()),
+			false => Err(not_found_or_stale(&self.pg_pool, "user_system", id).await)
+		}
+	}
+
+	async fn invite(&self, body: &UserInviteDto, token_hash: &str, expires_at: OffsetDateTime) -> Result<UserData, RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+
+		let username_taken = sqlx::query_scalar!(
+			r#"SELECT EXISTS(SELECT 1 FROM user_system WHERE username = $1 AND deleted_at IS NULL) AS "taken!""#,
+			body.username
+		).fetch_one(&mut *tx)
+		.await?;
+
+		if username_taken {
+			return Err(RepositoryError::Conflict(username_taken_message(&body.username)));
+		}
+
+		// An empty hash never verifies, the user cannot log in before choosing a password.
+		let query_insert = sqlx::query_as!(
+			UserData,
+			"INSERT INTO user_system (username, password, full_name, address, phone_number, role, photo, is_active, activated_at)
+			VALUES ($1, '', $2, $3, $4, $5, $6, FALSE, NULL) RETURNING *",
+			body.username,
+			body.full_name,
+			body.address,
+			body.phone_number,
+			body.role,
+			body.photo
+		).fetch_one(&mut *tx)
+		.await
+		.map_err(|e| write_error(e, &body.username))?;
+
+		sqlx::query!(
+			"INSERT INTO user_invite (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+			query_insert.id,
+			token_hash,
+			expires_at
+		).execute(&mut *tx)
+		.await?;
+
+		tx.commit().await?;
+
+		Ok(query_insert)
+	}
+
+	async fn resend_invite(&self, id: i32, token_hash: &str, expires_at: OffsetDateTime) -> Result<(), RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+
+		let activated = sqlx::query_scalar!(
+			r#"SELECT activated_at IS NOT NULL AS "activated!" FROM user_system WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"#,
+			id
+		).fetch_optional(&mut *tx)
+		.await?
+		.ok_or(RepositoryError::NotFound)?;
+
+		if activated {
+			return Err(already_activated());
+		}
+
+		sqlx::query!("DELETE FROM user_invite WHERE user_id = $1 AND used_at IS NULL", id)
+		.execute(&mut *tx)
+		.await?;
+
+		sqlx::query!(
+			"INSERT INTO user_invite (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+			id,
+			token_hash,
+			expires_at
+		).execute(&mut *tx)
+		.await?;
+
+		tx.commit().await?;
+
+		Ok(())
+	}
+
+	async fn find_invite(&self, token_hash: &str) -> Result<UserInvite, RepositoryError> {
+		let query_find_first = sqlx::query_as!(
+			UserInvite,
+			"SELECT id, user_id, token_hash, expires_at, used_at, created_at FROM user_invite WHERE token_hash = $1",
+			token_hash
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_first)
+	}
+
+	async fn accept_invite(&self, token_hash: &str, password_hash: &str) -> Result<UserData, RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+
+		let user_id = sqlx::query_scalar!(
+			"UPDATE user_invite SET used_at = NOW() WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW() RETURNING user_id",
+			token_hash
+		).fetch_optional(&mut *tx)
+		.await?
+		.ok_or(RepositoryError::NotFound)?;
+
+		let query_update = sqlx::query_as!(
+			UserData,
+			"UPDATE user_system SET password = $2, is_active = TRUE, activated_at = NOW(), updated_at = NOW(), version = version + 1
+			WHERE id = $1 AND deleted_at IS NULL AND activated_at IS NULL RETURNING *",
+			user_id,
+			password_hash
+		).fetch_optional(&mut *tx)
+		.await?
+		.ok_or(RepositoryError::NotFound)?;
+
+		tx.commit().await?;
+
+		Ok(query_update)
+	}
+
+	async fn import(&self, rows: &[UserImportRow], dry_run: bool) -> Result<ImportOutcome<UserData>, RepositoryError> {
+		let mut tx = self.pg_pool.begin().await?;
+		let mut created = vec![];
+		let mut errors = vec![];
+
+		for row in rows {
+			let username_taken = sqlx::query_scalar!(
+				r#"SELECT EXISTS(SELECT 1 FROM user_system WHERE username = $1 AND deleted_at IS NULL) AS "taken!""#,
+				row.user.username
+			).fetch_one(&mut *tx)
+			.await?;
+
+			if username_taken {
+				push_row_error(&mut errors, row.line, "username", username_taken_message(&row.user.username));
+				continue;
+			}
+
+			// A failed insert would abort the whole transaction, so the role is checked first.
+			let role_exists = sqlx::query_scalar!(
+				r#"SELECT EXISTS(SELECT 1 FROM role WHERE name = $1) AS "exists!""#,
+				row.user.role
+			).fetch_one(&mut *tx)
+			.await?;
+
+			if !role_exists {
+				push_row_error(&mut errors, row.line, "role", unknown_role_message());
+				continue;
+			}
+
+			let query_insert = sqlx::query_as!(
+				UserData,
+				"INSERT INTO user_system (username, password, full_name, address, phone_number, role, photo) VALUES ($1, $2, $3, $4, $5, $6, $7)
+				RETURNING *",
+				row.user.username,
+				row.password_hash,
+				row.user.full_name,
+				row.user.address,
+				row.user.phone_number,
+				row.user.role,
+				row.user.photo
+			).fetch_one(&mut *tx)
+			.await
+			.map_err(|e| write_error(e, &row.user.username))?;
+			created.push(query_insert);
+		}
+
+		// Dropping the transaction rolls every row back.
+		if !errors.is_empty() || dry_run {
+			return Ok(ImportOutcome { created: vec![], errors });
+		}
+
+		tx.commit().await?;
+
+		Ok(ImportOutcome { created, errors })
+	}
+
+	async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), RepositoryError> {
+		let query_delete = sqlx::query!(
+			"UPDATE user_system SET deleted_at = NOW(), updated_at = NOW(), version = version + 1
+			WHERE id = $1 AND deleted_at IS NULL AND ($2::INTEGER IS NULL OR version = $2)",
+			id,
+			version
+		).execute(&self.pg_pool)
+		.await?;
+
+		if query_delete.rows_affected() == 0 {
+			return Err(not_found_or_stale(&self.pg_pool, "user_system", id).await);
+		}
+
+		Ok(())
+	}
+
+	async fn restore(&self, id: i32) -> Result<i32, RepositoryError> {
+		let username = sqlx::query_scalar!(
+			"SELECT username FROM user_system WHERE id = $1 AND deleted_at IS NOT NULL",
+			id
+		).fetch_one(&self.pg_pool)
+		.await?;
+
+		let query_restore = sqlx::query_scalar!(
+			"UPDATE user_system SET deleted_at = NULL, updated_at = NOW(), version = version + 1
+			WHERE id = $1 AND deleted_at IS NOT NULL RETURNING version",
+			id
+		).fetch_one(&self.pg_pool)
+		.await
+		.map_err(|e| write_error(e, &username))?;
+
+		Ok(query_restore)
+	}
+
+	async fn purge_deleted(&self, deleted_before: OffsetDateTime) -> Result<u64, RepositoryError> {
+		let query_purge = sqlx::query!(
+			"DELETE FROM user_system WHERE deleted_at < $1",
+			deleted_before
+		).execute(&self.pg_pool)
+		.await?;
+
+		Ok(query_purge.rows_affected())
+	}
+
+	async fn phone_numbers(&self) -> Result<Vec<(i32, String)>, RepositoryError> {
+		let query_find_many = sqlx::query!("SELECT id, phone_number FROM user_system ORDER BY id ASC")
+		.fetch_all(&self.pg_pool)
+		.await?;
+
+		Ok(query_find_many.into_iter().map(|row| (row.id, row.phone_number)).collect())
+	}
+
+	async fn set_phone_numbers(&self, numbers: &[(i32, String)]) -> Result<(), RepositoryError> {
+		let (ids, phone_numbers): (Vec<i32>, Vec<String>) = numbers.iter().cloned().unzip();
+
+		sqlx::query!(
+			"UPDATE user_system SET phone_number = numbers.phone_number, version = version + 1
+			FROM UNNEST($1::INTEGER[], $2::VARCHAR[]) AS numbers (id, phone_number) WHERE user_system.id = numbers.id",
+			&ids,
+			&phone_numbers
+		).execute(&self.pg_pool)
+		.await?;
+
+		Ok(())
+	}
+}
+
+/// Keeps users in memory, used to exercise controllers without a database.
+#[derive(Default)]
+pub struct InMemoryUserRepository {
+	users: Mutex<Vec<UserData>>,
+	invites: Mutex<Vec<UserInvite>>
+}
+
+impl InMemoryUserRepository {
+	fn push_invite(&self, user_id: i32, token_hash: &str, expires_at: OffsetDateTime) {
+		let mut invites = self.invites.lock().unwrap();
+
+		let invite = UserInvite {
+			id: invites.iter().map(|invite| invite.id).max().unwrap_or(0) + 1,
+			user_id,
+			token_hash: token_hash.to_string(),
+			expires_at,
+			used_at: None,
+			created_at: OffsetDateTime::now_utc()
+		};
+		invites.push(invite);
+	}
+}
+
+#[async_trait]
+impl UserRepository for InMemoryUserRepository {
+	async fn search_paginate(&self, request: &PageRequest) -> Result<Page<UserView>, RepositoryError> {
+		let term = request.term.to_lowercase();
+		let phone_term = phone::search_digits(&request.term);
+
+		let users = self.users.lock().unwrap()
+			.iter()
+			.filter(|user| request.include_deleted || user.deleted_at.is_none())
+			.filter(|user| {
+				user.username.to_lowercase().contains(&term)
+					|| user.full_name.to_lowercase().contains(&term)
+					|| phone_term.as_ref().is_some_and(|digits| user.phone_number.contains(digits.as_str()))
+			})
+			.map(UserView::from)
+			.collect();
+
+		Ok(request.paginate_in_memory(users))
+	}
+
+	async fn find_by_id(&self, id: i32) -> Result<UserData, RepositoryError> {
+		self.users.lock().unwrap()
+			.iter()
+			.find(|user| user.id == id && user.deleted_at.is_none())
+			.cloned()
+			.ok_or(RepositoryError::NotFound)
+	}
+
+	async fn find_by_username(&self, username: &str) -> Result<UserData, RepositoryError> {
+		self.users.lock().unwrap()
+			.iter()
+			.find(|user| user.username == username && user.deleted_at.is_none())
+			.cloned()
+			.ok_or(RepositoryError::NotFound)
+	}
+
+	async fn find_view(&self, id: i32) -> Result<UserView, RepositoryError> {
+		self.find_by_id(id).await.map(|user| UserView::from(&user))
+	}
+
+	async fn find_batch(&self, after_id: i32, limit: i64) -> Result<Vec<UserView>, RepositoryError> {
+		let mut users: Vec<UserView> = self.users.lock().unwrap()
+			.iter()
+			.filter(|user| user.id > after_id && user.deleted_at.is_none())
+			.map(UserView::from)
+			.collect();
+		users.sort_by_key(|user| user.id);
+		users.truncate(limit as usize);
+
+		Ok(users)
+	}
+
+	async fn create(&self, body: &UserCreateDto, password_hash: &str) -> Result<UserData, RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		let now = OffsetDateTime::now_utc();
+		ensure_username_free(&users, &body.username, 0)?;
+
+		let user = UserData {
+			id: users.iter().map(|user| user.id).max().unwrap_or(0) + 1,
+			username: body.username.clone(),
+			password: password_hash.to_string(),
+			full_name: body.full_name.clone(),
+			address: body.address.clone(),
+			phone_number: body.phone_number.clone(),
+			photo: body.photo.clone(),
+			role: body.role.clone(),
+			created_at: now,
+			updated_at: now,
+			deleted_at: None,
+			version: 1,
+			language: USER_LANGUAGES[0].to_string(),
+			is_active: true,
+			activated_at: Some(now)
+		};
+		users.push(user.clone());
+
+		Ok(user)
+	}
+
+	async fn update(&self, id: i32, body: &UserReplaceDto, password_hash: Option<&str>, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		ensure_username_free(&users, &body.username, id)?;
+		let user = find_current(&mut users, id, version)?;
+
+		user.username = body.username.clone();
+		user.full_name = body.full_name.clone();
+		user.address = body.address.clone();
+		user.phone_number = body.phone_number.clone();
+		user.role = body.role.clone();
+		user.photo = body.photo.clone();
+		user.language = body.language.clone();
+		if let Some(password_hash) = password_hash { user.password = password_hash.to_string(); }
+		user.updated_at = OffsetDateTime::now_utc();
+		user.version += 1;
+
+		Ok(user.version)
+	}
+
+	async fn patch(&self, id: i32, body: &UserUpdateDto, password_hash: Option<&str>, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		if let Some(username) = &body.username { ensure_username_free(&users, username, id)?; }
+		let user = find_current(&mut users, id, version)?;
+
+		if let Some(username) = &body.username { user.username = username.clone(); }
+		if let Some(full_name) = &body.full_name { user.full_name = full_name.clone(); }
+		if let Some(address) = &body.address { user.address = address.clone(); }
+		if let Some(phone_number) = &body.phone_number { user.phone_number = phone_number.clone(); }
+		if let Some(role) = &body.role { user.role = role.clone(); }
+		if let Some(photo) = &body.photo { user.photo = photo.clone(); }
+		if let Some(language) = &body.language { user.language = language.clone(); }
+		if let Some(password_hash) = password_hash { user.password = password_hash.to_string(); }
+		user.updated_at = OffsetDateTime::now_utc();
+		user.version += 1;
+
+		Ok(user.version)
+	}
+
+	async fn update_password(&self, username: &str, password_hash: &str) -> Result<(), RepositoryError> {
+		if let Some(user) = self.users.lock().unwrap().iter_mut().find(|user| user.username == username && user.deleted_at.is_none()) {
+			user.password = password_hash.to_string();
+			user.updated_at = OffsetDateTime::now_utc();
+			user.version += 1;
+		}
+
+		Ok(())
+	}
+
+	async fn is_active(&self, id: i32) -> Result<bool, RepositoryError> {
+		Ok(self.users.lock().unwrap().iter().any(|user| user.id == id && user.deleted_at.is_none() && user.is_active))
+	}
+
+	async fn set_active(&self, id: i32, is_active: bool, version: Option<i32>) -> Result<i32, RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		let user = find_current(&mut users, id, version)?;
+
+		if user.activated_at.is_none() {
+			return Err(invite_pending());
+		}
+
+		user.is_active = is_active;
+		user.updated_at = OffsetDateTime::now_utc();
+		user.version += 1;
+
+		Ok(user.version)
+	}
+
+	async fn invite(&self, body: &UserInviteDto, token_hash: &str, expires_at: OffsetDateTime) -> Result<UserData, RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		let now = OffsetDateTime::now_utc();
+		ensure_username_free(&users, &body.username, 0)?;
+
+		let user = UserData {
+			id: users.iter().map(|user| user.id).max().unwrap_or(0) + 1,
+			username: body.username.clone(),
+			password: String::new(),
+			full_name: body.full_name.clone(),
+			address: body.address.clone(),
+			phone_number: body.phone_number.clone(),
+			photo: body.photo.clone(),
+			role: body.role.clone(),
+			created_at: now,
+			updated_at: now,
+			deleted_at: None,
+			version: 1,
+			language: USER_LANGUAGES[0].to_string(),
+			is_active: false,
+			activated_at: None
+		};
+		users.push(user.clone());
+		self.push_invite(user.id, token_hash, expires_at);
+
+		Ok(user)
+	}
+
+	async fn resend_invite(&self, id: i32, token_hash: &str, expires_at: OffsetDateTime) -> Result<(), RepositoryError> {
+		let users = self.users.lock().unwrap();
+		let user = users.iter()
+			.find(|user| user.id == id && user.deleted_at.is_none())
+			.ok_or(RepositoryError::NotFound)?;
+
+		if user.activated_at.is_some() {
+			return Err(already_activated());
+		}
+
+		self.invites.lock().unwrap().retain(|invite| invite.user_id != id || invite.used_at.is_some());
+		self.push_invite(id, token_hash, expires_at);
+
+		Ok(())
+	}
+
+	async fn find_invite(&self, token_hash: &str) -> Result<UserInvite, RepositoryError> {
+		self.invites.lock().unwrap()
+			.iter()
+			.find(|invite| invite.token_hash == token_hash)
+			.cloned()
+			.ok_or(RepositoryError::NotFound)
+	}
+
+	async fn accept_invite(&self, token_hash: &str, password_hash: &str) -> Result<UserData, RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		let mut invites = self.invites.lock().unwrap();
+		let now = OffsetDateTime::now_utc();
+
+		let invite = invites.iter_mut()
+			.find(|invite| invite.token_hash == token_hash && invite.used_at.is_none() && invite.expires_at > now)
+			.ok_or(RepositoryError::NotFound)?;
+		let user = users.iter_mut()
+			.find(|user| user.id == invite.user_id && user.deleted_at.is_none() && user.activated_at.is_none())
+			.ok_or(RepositoryError::NotFound)?;
+
+		invite.used_at = Some(now);
+		user.password = password_hash.to_string();
+		user.is_active = true;
+		user.activated_at = Some(now);
+		user.updated_at = now;
+		user.version += 1;
+
+		Ok(user.clone())
+	}
+
+	async fn import(&self, rows: &[UserImportRow], dry_run: bool) -> Result<ImportOutcome<UserData>, RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		let mut imported = users.clone();
+		let mut errors = vec![];
+		let now = OffsetDateTime::now_utc();
+
+		for row in rows {
+			if ensure_username_free(&imported, &row.user.username, 0).is_err() {
+				push_row_error(&mut errors, row.line, "username", username_taken_message(&row.user.username));
+				continue;
+			}
+
+			imported.push(UserData {
+				id: imported.iter().map(|user| user.id).max().unwrap_or(0) + 1,
+				username: row.user.username.clone(),
+				password: row.password_hash.clone(),
+				full_name: row.user.full_name.clone(),
+				address: row.user.address.clone(),
+				phone_number: row.user.phone_number.clone(),
+				photo: row.user.photo.clone(),
+				role: row.user.role.clone(),
+				created_at: now,
+				updated_at: now,
+				deleted_at: None,
+				version: 1,
+				language: USER_LANGUAGES[0].to_string(),
+				is_active: true,
+				activated_at: Some(now)
+			});
+		}
+
+		if !errors.is_empty() || dry_run {
+			return Ok(ImportOutcome { created: vec![], errors });
+		}
+
+		let created = imported[users.len()..].to_vec();
+		*users = imported;
+
+		Ok(ImportOutcome { created, errors })
+	}
+
+	async fn delete(&self, id: i32, version: Option<i32>) -> Result<(), RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		let user = find_current(&mut users, id, version)?;
+		let now = OffsetDateTime::now_utc();
+
+		user.deleted_at = Some(now);
+		user.updated_at = now;
+		user.version += 1;
+
+		Ok(())
+	}
+
+	async fn restore(&self, id: i32) -> Result<i32, RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		let username = users.iter()
+			.find(|user| user.id == id && user.deleted_at.is_some())
+			.map(|user| user.username.clone())
+			.ok_or(RepositoryError::NotFound)?;
+		ensure_username_free(&users, &username, id)?;
+		let user = users.iter_mut().find(|user| user.id == id).unwrap();
+
+		user.deleted_at = None;
+		user.updated_at = OffsetDateTime::now_utc();
+		user.version += 1;
+
+		Ok(user.version)
+	}
+
+	async fn purge_deleted(&self, deleted_before: OffsetDateTime) -> Result<u64, RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+		let before = users.len();
+		users.retain(|user| user.deleted_at.is_none_or(|deleted_at| deleted_at >= deleted_before));
+
+		Ok((before - users.len()) as u64)
+	}
+
+	async fn phone_numbers(&self) -> Result<Vec<(i32, String)>, RepositoryError> {
+		let mut numbers: Vec<(i32, String)> = self.users.lock().unwrap()
+			.iter()
+			.map(|user| (user.id, user.phone_number.clone()))
+			.collect();
+		numbers.sort();
+
+		Ok(numbers)
+	}
+
+	async fn set_phone_numbers(&self, numbers: &[(i32, String)]) -> Result<(), RepositoryError> {
+		let mut users = self.users.lock().unwrap();
+
+		for (id, phone_number) in numbers {
+			if let Some(user) = users.iter_mut().find(|user| user.id == *id) {
+				user.phone_number = phone_number.clone();
+				user.version += 1;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+fn find_current(users: &mut [UserData], id: i32, version: Option<i32>) -> Result<&mut UserData, RepositoryError> {
+	let user = users.iter_mut()
+		.find(|user| user.id == id && user.deleted_at.is_none())
+		.ok_or(RepositoryError::NotFound)?;
+
+	if version.is_some_and(|version| version != user.version) {
+		return Err(RepositoryError::VersionMismatch);
+	}
+
+	Ok(user)
+}
+
+/// Maps the foreign key of `user_system.role` to a 422 and the unique index of live usernames to a 409, any other
+/// error passes through.
+fn write_error(e: sqlx::Error, username: &str) -> RepositoryError {
+	match &e {
+		sqlx::Error::Database(db) if db.constraint() == Some("user_system_role_fkey") => RepositoryError::Invalid(unknown_role_message()),
+		sqlx::Error::Database(db) if db.constraint() == Some("user_system_username_unique_idx") => {
+			RepositoryError::Conflict(username_taken_message(username))
+		},
+		_ => e.into()
+	}
+}
+
+/// Fails with `RepositoryError::Conflict` when a live user other than `id` has the username.
+fn ensure_username_free(users: &[UserData], username: &str, id: i32) -> Result<(), RepositoryError> {
+	match users.iter().any(|user| user.username == username && user.deleted_at.is_none() && user.id != id) {
+		true => Err(RepositoryError::Conflict(username_taken_message(username))),
+		false => Ok(())
+	}
+}
+
+fn unknown_role_message() -> String {
+	"Role Tidak Ditemukan.".to_string()
+}
+
+fn username_taken_message(username: &str) -> String {
+	format!("Username \"{username}\" Sudah Dipakai.")
+}
+
+fn invite_pending() -> RepositoryError {
+	RepositoryError::Conflict("User Belum Mengaktifkan Undangannya.".to_string())
+}
+
+fn already_activated() -> RepositoryError {
+	RepositoryError::Conflict("User Sudah Aktif, Undangan Tidak Dapat Dikirim Ulang.".to_string())
+}
\ No newline at end of file
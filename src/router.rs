@@ -0,0 +1,97 @@
+use axum::{extract::DefaultBodyLimit, http::{ header, HeaderName }, middleware, routing::{ delete, get, patch, post, put }, Router};
+use tower_http::cors::{ Any, CorsLayer };
+use utoipa::OpenApi;
+use utoipa_redoc::{ Redoc, Servable };
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::controller::{audit_controller, auth_controller, category_controller, file_controller, http_controller, role_controller, user_controller};
+use crate::state::AppState;
+use crate::utils::idempotency::{ idempotency_guard, IDEMPOTENT_REPLAYED };
+use crate::utils::openapi::ApiDoc;
+use crate::utils::route_guard::{ auth_guard, permission_guard };
+use crate::utils::upload::FORM_OVERHEAD;
+
+/// Builds the whole application router, `main` only binds it to a listener so tests can drive it directly.
+pub fn build_app(state: AppState) -> Router {
+	// Browsers hide ETag from cross-origin scripts unless it is exposed, the UI needs it for If-Match
+	// and reads Idempotent-Replayed to tell a retried creation from a new one.
+	let cors = CorsLayer::new()
+	.allow_origin(Any)
+	.expose_headers([header::ETAG, HeaderName::from_static(IDEMPOTENT_REPLAYED)]);
+
+	let protected_router = Router::new()
+	/* Category Route */
+	.route("/api/category/search-paginate", get(category_controller::filter_paginate).post(category_controller::search_paginate))
+	.route("/api/category", get(category_controller::find_many))
+	.route("/api/category", post(category_controller::create).route_layer(middleware::from_fn_with_state("category.create", permission_guard)))
+	.route("/api/category/tree", get(category_controller::tree))
+	.route("/api/category/reorder", post(category_controller::reorder).route_layer(middleware::from_fn_with_state("category.update", permission_guard)))
+	.route("/api/category/import", post(category_controller::import).route_layer(middleware::from_fn_with_state("category.manage", permission_guard)))
+	.route("/api/category/export", get(category_controller::export).route_layer(middleware::from_fn_with_state("category.manage", permission_guard)))
+	.route("/api/category/{id}", get(category_controller::find_by_id))
+	.route("/api/category/{id}/breadcrumbs", get(category_controller::breadcrumbs))
+	.route("/api/category/{id}/move", post(category_controller::move_to).route_layer(middleware::from_fn_with_state("category.update", permission_guard)))
+	.route("/api/category/{id}/merge", post(category_controller::merge).route_layer(middleware::from_fn_with_state("category.manage", permission_guard)))
+	.route("/api/category/{id}", put(category_controller::update).route_layer(middleware::from_fn_with_state("category.update", permission_guard)))
+	.route("/api/category/{id}", patch(category_controller::patch).route_layer(middleware::from_fn_with_state("category.update", permission_guard)))
+	.route("/api/category/{id}", delete(category_controller::delete).route_layer(middleware::from_fn_with_state("category.delete", permission_guard)))
+	.route("/api/category/{id}/restore", post(category_controller::restore).route_layer(middleware::from_fn_with_state("category.manage", permission_guard)))
+
+	/* User Route */
+	.route("/api/user/search-paginate", get(user_controller::filter_paginate).post(user_controller::search_paginate))
+	.route("/api/user", post(user_controller::create).route_layer(middleware::from_fn_with_state("user.create", permission_guard)))
+	.route("/api/user/import", post(user_controller::import).route_layer(middleware::from_fn_with_state("user.manage", permission_guard)))
+	.route("/api/user/export", get(user_controller::export).route_layer(middleware::from_fn_with_state("user.manage", permission_guard)))
+	.route("/api/user/invite", post(user_controller::invite).route_layer(middleware::from_fn_with_state("user.manage", permission_guard)))
+	.route("/api/user/me", get(user_controller::me).patch(user_controller::update_me))
+	.route("/api/user/me/permissions", get(role_controller::my_permissions))
+	.route("/api/user/{id}", get(user_controller::find_by_id).route_layer(middleware::from_fn_with_state("user.manage", permission_guard)))
+	.route("/api/user/{id}", put(user_controller::update).route_layer(middleware::from_fn_with_state("user.update", permission_guard)))
+	.route("/api/user/{id}", patch(user_controller::patch).route_layer(middleware::from_fn_with_state("user.update", permission_guard)))
+	.route("/api/user/{id}", delete(user_controller::delete).route_layer(middleware::from_fn_with_state("user.delete", permission_guard)))
+	.route("/api/user/{id}/restore", post(user_controller::restore).route_layer(middleware::from_fn_with_state("user.manage", permission_guard)))
+	.route("/api/user/{id}/invite/resend", post(user_controller::resend_invite).route_layer(middleware::from_fn_with_state("user.manage", permission_guard)))
+	.route("/api/user/{id}/deactivate", post(user_controller::deactivate).route_layer(middleware::from_fn_with_state("user.manage", permission_guard)))
+	.route("/api/user/{id}/reactivate", post(user_controller::reactivate).route_layer(middleware::from_fn_with_state("user.manage", permission_guard)))
+
+	/* Role Route */
+	.route("/api/role", get(role_controller::find_all).post(role_controller::create).route_layer(middleware::from_fn_with_state("role.manage", permission_guard)))
+	.route("/api/role/{id}", get(role_controller::find_by_id).put(role_controller::update).delete(role_controller::delete).route_layer(middleware::from_fn_with_state("role.manage", permission_guard)))
+	.route("/api/permission", get(role_controller::permissions).route_layer(middleware::from_fn_with_state("role.manage", permission_guard)))
+
+	/* Audit Route */
+	.route("/api/audit", get(audit_controller::search_paginate).route_layer(middleware::from_fn_with_state("audit.view", permission_guard)))
+
+	/* Auth Route */
+	.route("/api/auth/authenticated", post(auth_controller::authenticated))
+	.route("/api/auth/change-password", post(auth_controller::change_password))
+	// Route layers wrap the ones added before them, `auth_guard` runs first so keys are scoped to the caller
+	// and the `permission_guard` of each route sees the permissions it resolved.
+	.route_layer(middleware::from_fn_with_state(state.clone(), idempotency_guard))
+
+	/* File Route */
+	// Added after `idempotency_guard`, which would buffer the whole form: the body limit stops oversized forms early
+	// and `upload::store` still checks the file itself as it streams. Uploads are named by content, a retry is harmless.
+	.route("/api/files/user", post(file_controller::upload_user_image).layer(DefaultBodyLimit::max(state.upload_config.user_image.max_bytes + FORM_OVERHEAD)))
+	.route("/api/files/category", post(file_controller::upload_category_icon).layer(DefaultBodyLimit::max(state.upload_config.category_icon.max_bytes + FORM_OVERHEAD)))
+	.route_layer(middleware::from_fn_with_state(state.clone(), auth_guard));
+
+	Router::new()
+	.route("/", get(|| async { "Hello World" }))
+	.merge(protected_router)
+	.route("/api/auth/login", post(auth_controller::login))
+	.route("/api/auth/activate", post(auth_controller::activate))
+
+	/* Http Example Route */
+	.route("/api/http", get(http_controller::get_http_example))
+	.route("/api/http", post(http_controller::post_http_example))
+	/* Upload User File Route */
+	.route("/api/files/user/image/{filename}", get(file_controller::get_user_image))
+	/* Upload Category Icon Route */
+	.route("/api/files/category/image/{filename}", get(file_controller::get_category_icon))
+	/* API Documentation Route */
+	.merge(SwaggerUi::new("/docs").url("/api/openapi.json", ApiDoc::openapi()))
+	.merge(Redoc::with_url("/redoc", ApiDoc::openapi()))
+	.layer(cors)
+	.with_state(state)
+}
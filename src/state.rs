@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+use sqlx::postgres::PgPool;
+
+use crate::repository::audit_repository::{ AuditRepository, InMemoryAuditRepository, PgAuditRepository };
+use crate::repository::category_repository::{ CategoryRepository, InMemoryCategoryRepository, PgCategoryRepository };
+use crate::repository::file_object_repository::{ FileObjectRepository, InMemoryFileObjectRepository, PgFileObjectRepository };
+use crate::repository::idempotency_repository::{ IdempotencyRepository, InMemoryIdempotencyRepository, PgIdempotencyRepository };
+use crate::repository::role_repository::{ InMemoryRoleRepository, PgRoleRepository, RoleRepository };
+use crate::repository::user_repository::{ InMemoryUserRepository, PgUserRepository, UserRepository };
+use crate::storage::{ DynStorage, DEFAULT_LOCAL_ROOT };
+use crate::storage::local::LocalStorage;
+use crate::storage::memory::InMemoryStorage;
+use crate::utils::audit::TrustedProxies;
+use crate::utils::permission::PermissionCache;
+use crate::utils::upload::UploadConfig;
+
+pub type DynAuditRepository = Arc<dyn AuditRepository>;
+pub type DynCategoryRepository = Arc<dyn CategoryRepository>;
+pub type DynFileObjectRepository = Arc<dyn FileObjectRepository>;
+pub type DynIdempotencyRepository = Arc<dyn IdempotencyRepository>;
+pub type DynRoleRepository = Arc<dyn RoleRepository>;
+pub type DynUserRepository = Arc<dyn UserRepository>;
+
+/// Shared state of the router, handlers extract only the repository they need through `FromRef`.
+#[derive(Clone)]
+pub struct AppState {
+	pub audit_repository: DynAuditRepository,
+	pub category_repository: DynCategoryRepository,
+	pub file_object_repository: DynFileObjectRepository,
+	pub idempotency_repository: DynIdempotencyRepository,
+	pub role_repository: DynRoleRepository,
+	pub user_repository: DynUserRepository,
+	pub storage: DynStorage,
+	pub permission_cache: Arc<PermissionCache>,
+	pub upload_config: Arc<UploadConfig>,
+	pub trusted_proxies: Arc<TrustedProxies>
+}
+
+impl AppState {
+	pub fn postgres(pg_pool: PgPool) -> Self {
+		Self {
+			audit_repository: Arc::new(PgAuditRepository::new(pg_pool.clone())),
+			category_repository: Arc::new(PgCategoryRepository::new(pg_pool.clone())),
+			file_object_repository: Arc::new(PgFileObjectRepository::new(pg_pool.clone())),
+			idempotency_repository: Arc::new(PgIdempotencyRepository::new(pg_pool.clone())),
+			role_repository: Arc::new(PgRoleRepository::new(pg_pool.clone())),
+			user_repository: Arc::new(PgUserRepository::new(pg_pool)),
+			storage: Arc::new(LocalStorage::new(DEFAULT_LOCAL_ROOT)),
+			permission_cache: Arc::default(),
+			upload_config: Arc::default(),
+			trusted_proxies: Arc::default()
+		}
+	}
+
+	pub fn in_memory() -> Self {
+		Self {
+			audit_repository: Arc::new(InMemoryAuditRepository::default()),
+			category_repository: Arc::new(InMemoryCategoryRepository::default()),
+			file_object_repository: Arc::new(InMemoryFileObjectRepository::default()),
+			idempotency_repository: Arc::new(InMemoryIdempotencyRepository::default()),
+			role_repository: Arc::new(InMemoryRoleRepository::default()),
+			user_repository: Arc::new(InMemoryUserRepository::default()),
+			storage: Arc::new(InMemoryStorage::default()),
+			permission_cache: Arc::default(),
+			upload_config: Arc::default(),
+			trusted_proxies: Arc::default()
+		}
+	}
+}
+
+impl FromRef<AppState> for DynAuditRepository {
+	fn from_ref(state: &AppState) -> Self {
+		state.audit_repository.clone()
+	}
+}
+
+impl FromRef<AppState> for DynCategoryRepository {
+	fn from_ref(state: &AppState) -> Self {
+		state.category_repository.clone()
+	}
+}
+
+impl FromRef<AppState> for DynFileObjectRepository {
+	fn from_ref(state: &AppState) -> Self {
+		state.file_object_repository.clone()
+	}
+}
+
+impl FromRef<AppState> for DynIdempotencyRepository {
+	fn from_ref(state: &AppState) -> Self {
+		state.idempotency_repository.clone()
+	}
+}
+
+impl FromRef<AppState> for DynRoleRepository {
+	fn from_ref(state: &AppState) -> Self {
+		state.role_repository.clone()
+	}
+}
+
+impl FromRef<AppState> for Arc<PermissionCache> {
+	fn from_ref(state: &AppState) -> Self {
+		state.permission_cache.clone()
+	}
+}
+
+impl FromRef<AppState> for DynStorage {
+	fn from_ref(state: &AppState) -> Self {
+		state.storage.clone()
+	}
+}
+
+impl FromRef<AppState> for Arc<UploadConfig> {
+	fn from_ref(state: &AppState) -> Self {
+		state.upload_config.clone()
+	}
+}
+
+impl FromRef<AppState> for Arc<TrustedProxies> {
+	fn from_ref(state: &AppState) -> Self {
+		state.trusted_proxies.clone()
+	}
+}
+
+impl FromRef<AppState> for DynUserRepository {
+	fn from_ref(state: &AppState) -> Self {
+		state.user_repository.clone()
+	}
+}
@@ -0,0 +1,202 @@
+use std::io::SeekFrom;
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use tokio::fs::File;
+use tokio::io::{ AsyncReadExt, AsyncSeekExt, AsyncWriteExt };
+use tokio_util::io::ReaderStream;
+
+use crate::storage::{ valid_key, ByteStream, ObjectMeta, Storage, StorageError };
+use crate::utils::serve_file::sniff_content_type;
+use crate::utils::token::random_token;
+
+/// Bytes read from the start of a file to recognise its type.
+const SNIFF_LEN: usize = 16;
+
+/// Objects as files under a directory, keys map to paths below it. Only fit for a single instance, or several
+/// sharing a network volume.
+pub struct LocalStorage {
+	root: PathBuf
+}
+
+impl LocalStorage {
+	pub fn new(root: impl Into<PathBuf>) -> Self {
+		Self { root: root.into() }
+	}
+
+	/// Path of the regular file stored under `key`, once `..` segments and symbolic links are resolved it must still
+	/// be inside the root.
+	fn resolve(&self, key: &str) -> Result<PathBuf, StorageError> {
+		if !valid_key(key) {
+			return Err(StorageError::NotFound);
+		}
+
+		let root = self.root.canonicalize()?;
+		let path = root.join(key).canonicalize()?;
+
+		if path.starts_with(&root) && path.is_file() { Ok(path) } else { Err(StorageError::NotFound) }
+	}
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+	async fn put(&self, key: &str, body: Bytes, _content_type: &str) -> Result<(), StorageError> {
+		if !valid_key(key) {
+			return Err(StorageError::NotFound);
+		}
+
+		let path = self.root.join(key);
+		let dir = path.parent().unwrap_or(&self.root);
+		tokio::fs::create_dir_all(dir).await?;
+
+		// Written aside then renamed, the rename replaces the file at once even for readers on other instances.
+		let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+		let partial = dir.join(format!(".{file_name}.{}.part", &random_token()[..8]));
+
+		let written = async {
+			let mut file = File::create(&partial).await?;
+			file.write_all(&body).await?;
+			file.sync_all().await?;
+			tokio::fs::rename(&partial, &path).await
+		}.await;
+
+		if let Err(e) = written {
+			let _ = tokio::fs::remove_file(&partial).await;
+			return Err(StorageError::Io(e));
+		}
+
+		Ok(())
+	}
+
+	async fn get(&self, key: &str, range: Option<(u64, u64)>) -> Result<ByteStream, StorageError> {
+		let mut file = File::open(self.resolve(key)?).await?;
+
+		match range {
+			Some((start, end)) => {
+				file.seek(SeekFrom::Start(start)).await?;
+				Ok(Box::pin(ReaderStream::new(file.take(end - start + 1))))
+			},
+			None => Ok(Box::pin(ReaderStream::new(file)))
+		}
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), StorageError> {
+		if !valid_key(key) {
+			return Err(StorageError::NotFound);
+		}
+
+		let path = match self.resolve(key) {
+			Ok(path) => path,
+			Err(StorageError::NotFound) => return Ok(()),
+			Err(e) => return Err(e)
+		};
+		tokio::fs::remove_file(&path).await?;
+
+		// Directories left empty, such as those of image variants, go too. Removing one that is not empty fails
+		// and stops the walk, the root itself is kept.
+		let root = self.root.canonicalize()?;
+		for dir in path.ancestors().skip(1).take_while(|dir| *dir != root) {
+			if tokio::fs::remove_dir(dir).await.is_err() {
+				break;
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn stat(&self, key: &str) -> Result<ObjectMeta, StorageError> {
+		let path = self.resolve(key)?;
+		let mut file = File::open(&path).await?;
+		let metadata = file.metadata().await?;
+
+		let mut head = [0; SNIFF_LEN];
+		let read = file.read(&mut head).await?;
+
+		Ok(ObjectMeta {
+			content_type: Some(sniff_content_type(&head[..read]).to_string()),
+			..object_meta(key.to_string(), &metadata)
+		})
+	}
+
+	async fn list(&self, prefix: &str) -> Result<Vec<ObjectMeta>, StorageError> {
+		// Only the directory the prefix points into is walked, a missing one lists nothing.
+		let dir = prefix.rsplit_once('/').map_or("", |(dir, _)| dir);
+		if !dir.is_empty() && !valid_key(dir) {
+			return Ok(vec![]);
+		}
+
+		// Resolved like `resolve` does, a link out of the root lists nothing either.
+		let canonical = |path: PathBuf| match path.canonicalize() {
+			Ok(path) => Ok(Some(path)),
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+			Err(e) => Err(StorageError::Io(e))
+		};
+		let (Some(root), Some(start)) = (canonical(self.root.clone())?, canonical(self.root.join(dir))?) else {
+			return Ok(vec![]);
+		};
+		if !start.starts_with(&root) || !start.is_dir() {
+			return Ok(vec![]);
+		}
+
+		let mut objects = tokio::task::spawn_blocking(move || walk(&root, start))
+		.await
+		.map_err(|e| StorageError::Backend(e.to_string()))??;
+
+		objects.retain(|object| object.key.starts_with(prefix));
+		objects.sort_by(|a, b| a.key.cmp(&b.key));
+
+		Ok(objects)
+	}
+}
+
+/// Every regular file below `start`, keyed relative to `root`, without following links to directories and skipping
+/// hidden files such as the partial writes of `put`.
+fn walk(root: &Path, start: PathBuf) -> std::io::Result<Vec<ObjectMeta>> {
+	let mut objects = vec![];
+	let mut dirs = vec![start];
+
+	while let Some(dir) = dirs.pop() {
+		let entries = match std::fs::read_dir(&dir) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+			Err(e) => return Err(e)
+		};
+
+		for entry in entries {
+			let entry = entry?;
+			let file_type = entry.file_type()?;
+			let path = entry.path();
+
+			if entry.file_name().to_string_lossy().starts_with('.') {
+				continue;
+			}
+
+			if file_type.is_dir() {
+				dirs.push(path);
+			} else if file_type.is_file() {
+				let key = path.strip_prefix(root).ok().and_then(|relative| relative.to_str()).map(|relative| relative.replace('\\', "/"));
+				if let Some(key) = key {
+					objects.push(object_meta(key, &entry.metadata()?));
+				}
+			}
+		}
+	}
+
+	Ok(objects)
+}
+
+fn object_meta(key: String, metadata: &std::fs::Metadata) -> ObjectMeta {
+	let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+
+	ObjectMeta { key, size: metadata.len(), last_modified: modified, etag: etag(metadata.len(), modified), content_type: None }
+}
+
+/// Size and modification time, like the ETag of nginx, files are never rewritten in place.
+fn etag(len: u64, modified: SystemTime) -> String {
+	let modified = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+	format!("\"{len:x}-{:x}{:08x}\"", modified.as_secs(), modified.subsec_nanos())
+}
+
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use futures_util::stream;
+
+use crate::storage::{ valid_key, ByteStream, ObjectMeta, Storage, StorageError };
+
+/// Objects kept in a map, for handler unit tests and `AppState::in_memory`.
+#[derive(Default)]
+pub struct InMemoryStorage {
+	objects: Mutex<BTreeMap<String, StoredObject>>
+}
+
+struct StoredObject {
+	body: Bytes,
+	content_type: String,
+	last_modified: SystemTime,
+	/// Bumped on every `put` so the ETag changes with the content.
+	revision: u64
+}
+
+impl StoredObject {
+	fn meta(&self, key: &str) -> ObjectMeta {
+		ObjectMeta {
+			key: key.to_string(),
+			size: self.body.len() as u64,
+			last_modified: self.last_modified,
+			etag: format!("\"{}\"", self.revision),
+			content_type: Some(self.content_type.clone())
+		}
+	}
+}
+
+#[async_trait]
+impl Storage for InMemoryStorage {
+	async fn put(&self, key: &str, body: Bytes, content_type: &str) -> Result<(), StorageError> {
+		if !valid_key(key) {
+			return Err(StorageError::NotFound);
+		}
+
+		let mut objects = self.objects.lock().unwrap();
+		let revision = objects.get(key).map_or(1, |object| object.revision + 1);
+		objects.insert(key.to_string(), StoredObject { body, content_type: content_type.to_string(), last_modified: SystemTime::now(), revision });
+
+		Ok(())
+	}
+
+	async fn get(&self, key: &str, range: Option<(u64, u64)>) -> Result<ByteStream, StorageError> {
+		let objects = self.objects.lock().unwrap();
+		let body = &objects.get(key).ok_or(StorageError::NotFound)?.body;
+
+		let body = match range {
+			Some((start, end)) => body.slice(start as usize..=(end as usize).min(body.len().saturating_sub(1))),
+			None => body.clone()
+		};
+
+		Ok(Box::pin(stream::iter([Ok(body)])))
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), StorageError> {
+		if !valid_key(key) {
+			return Err(StorageError::NotFound);
+		}
+
+		self.objects.lock().unwrap().remove(key);
+
+		Ok(())
+	}
+
+	async fn stat(&self, key: &str) -> Result<ObjectMeta, StorageError> {
+		self.objects.lock().unwrap().get(key).map(|object| object.meta(key)).ok_or(StorageError::NotFound)
+	}
+
+	async fn list(&self, prefix: &str) -> Result<Vec<ObjectMeta>, StorageError> {
+		Ok(
+			self.objects.lock().unwrap()
+			.iter()
+			.filter(|(key, _)| key.starts_with(prefix))
+			.map(|(key, object)| ObjectMeta { content_type: None, ..object.meta(key) })
+			.collect()
+		)
+	}
+}
@@ -0,0 +1,123 @@
+pub mod local;
+pub mod memory;
+pub mod s3;
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use futures_util::Stream;
+
+use crate::storage::local::LocalStorage;
+use crate::storage::s3::{ S3Config, S3Storage };
+
+pub type DynStorage = Arc<dyn Storage>;
+
+/// Body of a stored object, read lazily so large files are never held in memory.
+pub type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// Root of `LocalStorage` when `STORAGE_LOCAL_ROOT` is not set, served files used to live here before storage was
+/// pluggable so existing deployments keep their files.
+pub const DEFAULT_LOCAL_ROOT: &str = "uploads";
+
+/// Error returned by every storage backend so controllers stay backend agnostic.
+#[derive(Debug)]
+pub enum StorageError {
+	/// No object under the key, or the key could escape its prefix, e.g. `user/../../.env`.
+	NotFound,
+	Io(std::io::Error),
+	/// The remote backend answered with an error or could not be reached.
+	Backend(String)
+}
+
+impl StorageError {
+	pub fn status_code(&self) -> StatusCode {
+		match self {
+			StorageError::NotFound => StatusCode::NOT_FOUND,
+			StorageError::Io(_) | StorageError::Backend(_) => StatusCode::INTERNAL_SERVER_ERROR
+		}
+	}
+}
+
+impl fmt::Display for StorageError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			StorageError::NotFound => write!(f, "File Tidak Ditemukan."),
+			StorageError::Io(e) => write!(f, "{e}"),
+			StorageError::Backend(message) => write!(f, "{message}")
+		}
+	}
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<std::io::Error> for StorageError {
+	fn from(e: std::io::Error) -> Self {
+		match e.kind() {
+			std::io::ErrorKind::NotFound => StorageError::NotFound,
+			_ => StorageError::Io(e)
+		}
+	}
+}
+
+/// What `stat` and `list` know about an object.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectMeta {
+	pub key: String,
+	pub size: u64,
+	pub last_modified: SystemTime,
+	/// Quoted validator that changes whenever the content does.
+	pub etag: String,
+	/// Image type of the content, `None` from `list` or when the backend does not know it.
+	pub content_type: Option<String>
+}
+
+/// Where uploaded files live. Keys are `/` separated relative paths such as `user/1741411404_avatar.png`, every
+/// implementation refuses keys with empty, `.` or `..` segments as `NotFound`.
+#[async_trait]
+pub trait Storage: Send + Sync {
+	/// Stores `body` under `key`, replacing any object already there. Readers never see a partly written object.
+	async fn put(&self, key: &str, body: Bytes, content_type: &str) -> Result<(), StorageError>;
+	/// Content of the object, or only the inclusive byte `range` of it.
+	async fn get(&self, key: &str, range: Option<(u64, u64)>) -> Result<ByteStream, StorageError>;
+	/// Removes the object, a missing one is not an error.
+	async fn delete(&self, key: &str) -> Result<(), StorageError>;
+	async fn stat(&self, key: &str) -> Result<ObjectMeta, StorageError>;
+	/// Every object whose key starts with `prefix`, ordered by key.
+	async fn list(&self, prefix: &str) -> Result<Vec<ObjectMeta>, StorageError>;
+}
+
+/// Whether `key` is a relative path that stays under its first segment.
+pub fn valid_key(key: &str) -> bool {
+	!key.is_empty() && !key.contains('\\') && key.split('/').all(|segment| !segment.is_empty() && segment != "." && segment != "..")
+}
+
+/// The backend named by `STORAGE_BACKEND`, `local` by default or `s3` for any S3 compatible service such as MinIO.
+pub fn from_env() -> DynStorage {
+	match std::env::var("STORAGE_BACKEND").as_deref() {
+		Err(_) | Ok("local") => Arc::new(LocalStorage::new(std::env::var("STORAGE_LOCAL_ROOT").unwrap_or(DEFAULT_LOCAL_ROOT.to_string()))),
+		Ok("s3") => Arc::new(S3Storage::new(S3Config::from_env())),
+		Ok(backend) => panic!("STORAGE_BACKEND must be local or s3, got {backend}.")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keys_cannot_leave_their_prefix() {
+		assert!(valid_key("user/avatar.png"));
+		assert!(valid_key("user/1741411404_[Erai-raws] Yohane.mkv_20230807.jpg"));
+		assert!(!valid_key("user/../../.env"));
+		assert!(!valid_key("user/./default_user.png"));
+		assert!(!valid_key("/etc/passwd"));
+		assert!(!valid_key("user//avatar.png"));
+		assert!(!valid_key("user\\..\\avatar.png"));
+		assert!(!valid_key(""));
+	}
+}
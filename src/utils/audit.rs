@@ -0,0 +1,205 @@
+use std::{ convert::Infallible, net::{ IpAddr, SocketAddr }, sync::Arc };
+
+use axum::{
+	extract::{ ConnectInfo, FromRef, FromRequestParts },
+	http::{ request::Parts, HeaderMap }
+};
+use serde::Serialize;
+use serde_json::{ json, Map, Value };
+
+use crate::model::audit_model::AuditEvent;
+use crate::model::user_model::JwtClaims;
+use crate::state::DynAuditRepository;
+
+/// Fields bumped on every write, listing them in each diff would only add noise.
+const IGNORED_FIELDS: &[&str] = &["updated_at", "version"];
+
+/// Fields whose values never reach the audit log, only the fact that they changed.
+const REDACTED_FIELDS: &[&str] = &["password"];
+
+/// Reverse proxies whose `X-Forwarded-For` and `X-Real-IP` are believed, any other client could write them.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies(Vec<IpAddr>);
+
+impl TrustedProxies {
+	pub fn new(addresses: Vec<IpAddr>) -> Self {
+		Self(addresses)
+	}
+
+	/// Comma separated addresses of `TRUSTED_PROXIES`, none when it is unset.
+	pub fn from_env() -> Self {
+		let addresses = std::env::var("TRUSTED_PROXIES")
+		.map(|addresses| addresses.split(',').map(str::trim).filter(|address| !address.is_empty()).map(|address| address.parse().unwrap_or_else(|_| panic!("TRUSTED_PROXIES must list IP addresses, got '{address}'."))).collect())
+		.unwrap_or_default();
+
+		Self(addresses)
+	}
+
+	fn contains(&self, address: &IpAddr) -> bool {
+		self.0.contains(address)
+	}
+}
+
+/// Who is making the request and from where, handlers call `record` after a successful mutation.
+/// The actor comes from the `JwtClaims` set by `auth_guard` and is `None` on public routes.
+pub struct Audit {
+	audit_repository: DynAuditRepository,
+	actor: Option<(i32, String)>,
+	ip_address: Option<String>
+}
+
+impl<S> FromRequestParts<S> for Audit
+where
+	DynAuditRepository: FromRef<S>,
+	Arc<TrustedProxies>: FromRef<S>,
+	S: Send + Sync
+{
+	type Rejection = Infallible;
+
+	async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+		// The extractor also finds the `MockConnectInfo` tests set instead.
+		let peer = ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await.ok().map(|ConnectInfo(address)| address.ip());
+
+		Ok(Self::new(
+			DynAuditRepository::from_ref(state),
+			parts.extensions.get::<JwtClaims>(),
+			peer.map(|peer| client_ip(&parts.headers, peer, &Arc::<TrustedProxies>::from_ref(state)).to_string())
+		))
+	}
+}
+
+impl Audit {
+	pub fn new(audit_repository: DynAuditRepository, claims: Option<&JwtClaims>, ip_address: Option<String>) -> Self {
+		Self {
+			audit_repository,
+			actor: claims.map(|claims| (claims.id, claims.name.clone())),
+			ip_address
+		}
+	}
+
+	/// Stores the fields that differ between `before` and `after`, `None` stands for a row that does not exist on that side.
+	/// Auditing is best effort: the event is written after the mutation is committed, a failure is logged and never undoes
+	/// or fails it.
+	pub async fn record<T: Serialize>(
+		&self,
+		action: &'static str,
+		entity_type: &'static str,
+		entity_id: impl ToString,
+		before: Option<&T>,
+		after: Option<&T>
+	) {
+		let snapshot = |value: Option<&T>| value.and_then(|value| serde_json::to_value(value).ok());
+
+		let event = AuditEvent {
+			actor_id: self.actor.as_ref().map(|(id, _)| *id),
+			actor_username: self.actor.as_ref().map(|(_, username)| username.clone()),
+			action,
+			entity_type,
+			entity_id: Some(entity_id.to_string()),
+			changes: diff(snapshot(before).as_ref(), snapshot(after).as_ref()),
+			ip_address: self.ip_address.clone()
+		};
+
+		if let Err(e) = self.audit_repository.record(&event).await {
+			eprintln!("Failed to record {action} of {entity_type} {}: {e}", event.entity_id.unwrap_or_default());
+		}
+	}
+}
+
+/// The socket address of the peer, unless it is a trusted proxy: then the last address of `X-Forwarded-For` that is not
+/// one of the proxies, or `X-Real-IP`. Proxies append to `X-Forwarded-For`, the addresses before the last untrusted one
+/// are whatever the client sent.
+fn client_ip(headers: &HeaderMap, peer: IpAddr, trusted_proxies: &TrustedProxies) -> IpAddr {
+	if !trusted_proxies.contains(&peer) {
+		return peer;
+	}
+
+	let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+	let forwarded_for = header("x-forwarded-for").and_then(|value| {
+		value.rsplit(',')
+			.map(|address| address.trim().parse::<IpAddr>().ok())
+			.find(|address| !matches!(address, Some(address) if trusted_proxies.contains(address)))
+			.flatten()
+	});
+	let real_ip = header("x-real-ip").and_then(|value| value.trim().parse().ok());
+
+	forwarded_for.or(real_ip).unwrap_or(peer)
+}
+
+/// `{ "field": { "before": .., "after": .. } }` for every top-level field that differs between two JSON objects.
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Value {
+	let empty = Map::new();
+	let before = before.and_then(Value::as_object).unwrap_or(&empty);
+	let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+	let mut changes = Map::new();
+
+	for field in before.keys().chain(after.keys()) {
+		if IGNORED_FIELDS.contains(&field.as_str()) || changes.contains_key(field) {
+			continue;
+		}
+
+		let (old, new) = (before.get(field).unwrap_or(&Value::Null), after.get(field).unwrap_or(&Value::Null));
+
+		if old == new {
+			continue;
+		}
+
+		let change = if REDACTED_FIELDS.contains(&field.as_str()) {
+			json!({ "before": "[REDACTED]", "after": "[REDACTED]" })
+		} else {
+			json!({ "before": old, "after": new })
+		};
+		changes.insert(field.clone(), change);
+	}
+
+	Value::Object(changes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn diff_lists_changed_fields_only() {
+		let before = json!({ "id": 1, "role": "cashier", "full_name": "Budi", "version": 1, "updated_at": "a" });
+		let after = json!({ "id": 1, "role": "admin", "full_name": "Budi", "version": 2, "updated_at": "b" });
+
+		assert_eq!(diff(Some(&before), Some(&after)), json!({ "role": { "before": "cashier", "after": "admin" } }));
+	}
+
+	#[test]
+	fn diff_of_created_and_deleted_rows() {
+		let row = json!({ "id": 1, "name": "Minuman" });
+
+		assert_eq!(diff(None, Some(&row)), json!({ "id": { "before": null, "after": 1 }, "name": { "before": null, "after": "Minuman" } }));
+		assert_eq!(diff(Some(&row), None), json!({ "id": { "before": 1, "after": null }, "name": { "before": "Minuman", "after": null } }));
+	}
+
+	#[test]
+	fn diff_redacts_passwords() {
+		let changes = diff(Some(&json!({ "password": "$2b$old" })), Some(&json!({ "password": "$2b$new" })));
+
+		assert_eq!(changes, json!({ "password": { "before": "[REDACTED]", "after": "[REDACTED]" } }));
+		assert_eq!(diff(Some(&json!({ "password": "$2b$same" })), Some(&json!({ "password": "$2b$same" }))), json!({}));
+	}
+
+	#[test]
+	fn client_ip_believes_trusted_proxies_only() {
+		let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+		let trusted_proxies = TrustedProxies::new(vec![proxy]);
+		let mut headers = HeaderMap::new();
+		assert_eq!(client_ip(&headers, proxy, &trusted_proxies), proxy);
+
+		headers.insert("x-real-ip", "10.0.0.2".parse().unwrap());
+		assert_eq!(client_ip(&headers, proxy, &trusted_proxies).to_string(), "10.0.0.2");
+
+		headers.insert("x-forwarded-for", "198.51.100.9, 203.0.113.7, 10.0.0.1".parse().unwrap());
+		assert_eq!(client_ip(&headers, proxy, &trusted_proxies).to_string(), "203.0.113.7");
+
+		let client: IpAddr = "192.0.2.4".parse().unwrap();
+		assert_eq!(client_ip(&headers, client, &trusted_proxies), client);
+		assert_eq!(client_ip(&headers, proxy, &TrustedProxies::default()), proxy);
+	}
+}
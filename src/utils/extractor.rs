@@ -0,0 +1,165 @@
+use axum::{
+	extract::{ FromRequest, FromRequestParts, Request },
+	http::{ header, request::Parts, StatusCode }
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use validator::{ Validate, ValidationErrors };
+
+use crate::model::utils_model::FieldError;
+
+/// Same as `axum::Json`, but a malformed or mistyped body is answered with the standard error envelope.
+pub struct Json<T>(pub T);
+
+impl<T, S> FromRequest<S> for Json<T>
+where
+	T: DeserializeOwned,
+	S: Send + Sync
+{
+	type Rejection = (StatusCode, String);
+
+	async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+		let axum::Json(value) = axum::Json::<T>::from_request(req, state)
+		.await
+		.map_err(|rejection| {
+			(
+				rejection.status(),
+				json!({ "success": false, "message": format!("Body JSON tidak valid: {}", rejection.body_text()) }).to_string()
+			)
+		})?;
+
+		Ok(Self(value))
+	}
+}
+
+/// Deserializes the JSON body like `Json` and then runs its `Validate` rules, failing with 422 and one entry per invalid field.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+	T: DeserializeOwned + Validate,
+	S: Send + Sync
+{
+	type Rejection = (StatusCode, String);
+
+	async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+		let Json(value) = Json::<T>::from_request(req, state).await?;
+
+		value.validate().map_err(validation_rejection)?;
+
+		Ok(Self(value))
+	}
+}
+
+/// Same as `axum::extract::Path`, with rejections in the standard error envelope.
+pub struct Path<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+	T: DeserializeOwned + Send,
+	S: Send + Sync
+{
+	type Rejection = (StatusCode, String);
+
+	async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+		let axum::extract::Path(value) = axum::extract::Path::<T>::from_request_parts(parts, state)
+		.await
+		.map_err(|rejection| {
+			(
+				rejection.status(),
+				json!({ "success": false, "message": format!("Parameter path tidak valid: {}", rejection.body_text()) }).to_string()
+			)
+		})?;
+
+		Ok(Self(value))
+	}
+}
+
+/// Same as `axum_extra::extract::Query`, repeated keys included, with rejections in the standard error envelope.
+pub struct Query<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+	T: DeserializeOwned,
+	S: Send + Sync
+{
+	type Rejection = (StatusCode, String);
+
+	async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+		let axum_extra::extract::Query(value) = axum_extra::extract::Query::<T>::from_request_parts(parts, state)
+		.await
+		.map_err(|rejection| {
+			(
+				rejection.status(),
+				json!({ "success": false, "message": format!("Query string tidak valid: {}", rejection.body_text()) }).to_string()
+			)
+		})?;
+
+		Ok(Self(value))
+	}
+}
+
+/// Version required by a write through the `If-Match` header, `None` when the client sent `*` to skip the check.
+/// A missing header is rejected with 428 so clients cannot overwrite concurrent edits by accident.
+pub struct IfMatch(pub Option<i32>);
+
+impl<S> FromRequestParts<S> for IfMatch
+where
+	S: Send + Sync
+{
+	type Rejection = (StatusCode, String);
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+		let Some(value) = parts.headers.get(header::IF_MATCH) else {
+			return Err((
+				StatusCode::PRECONDITION_REQUIRED,
+				json!({ "success": false, "message": "Header If-Match Wajib Diisi Dengan ETag Data Terakhir." }).to_string()
+			));
+		};
+
+		let value = value.to_str().unwrap_or_default().trim();
+
+		if value == "*" {
+			return Ok(Self(None));
+		}
+
+		// Weak and strong validators carry the same version, `W/"3"` and `"3"` are equivalent here.
+		value.strip_prefix("W/").unwrap_or(value)
+			.trim_matches('"')
+			.parse()
+			.map(|version| Self(Some(version)))
+			.map_err(|_| {
+				(
+					StatusCode::PRECONDITION_FAILED,
+					json!({ "success": false, "message": format!("If-Match '{value}' Bukan ETag Yang Valid.") }).to_string()
+				)
+			})
+	}
+}
+
+/// Formats a version as the strong ETag `IfMatch` accepts back.
+pub fn etag(version: i32) -> String {
+	format!("\"{version}\"")
+}
+
+/// Flattens validator errors into `{ field, message }` pairs sorted by field name.
+pub fn field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+	let mut field_errors: Vec<FieldError> = errors.field_errors()
+		.into_iter()
+		.flat_map(|(field, errors)| errors.iter().map(move |error| FieldError {
+			field: field.to_string(),
+			message: error.message.as_ref().map(|message| message.to_string()).unwrap_or_else(|| error.code.to_string())
+		}))
+		.collect();
+	field_errors.sort_by(|a, b| a.field.cmp(&b.field));
+
+	field_errors
+}
+
+fn validation_rejection(errors: ValidationErrors) -> (StatusCode, String) {
+	(
+		StatusCode::UNPROCESSABLE_ENTITY,
+		json!({ "success": false, "message": "Data yang dikirim tidak valid.", "errors": field_errors(&errors) }).to_string()
+	)
+}
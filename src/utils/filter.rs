@@ -0,0 +1,254 @@
+use sqlx::{ postgres::Postgres, QueryBuilder };
+use time::{ macros::format_description, Date };
+
+use crate::utils::pagination::{ FieldKind, FieldValue, Sortable };
+
+/// A column clients may filter on, `name` is both the API name and the SQL column.
+#[derive(Clone, Copy, Debug)]
+pub struct FilterField {
+	pub name: &'static str,
+	pub kind: FieldKind
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterOperator {
+	Eq,
+	Ne,
+	Gt,
+	Gte,
+	Lt,
+	Lte,
+	Like,
+	In
+}
+
+impl FilterOperator {
+	fn parse(raw: &str) -> Option<Self> {
+		match raw {
+			"eq" => Some(FilterOperator::Eq),
+			"ne" => Some(FilterOperator::Ne),
+			"gt" => Some(FilterOperator::Gt),
+			"gte" => Some(FilterOperator::Gte),
+			"lt" => Some(FilterOperator::Lt),
+			"lte" => Some(FilterOperator::Lte),
+			"like" => Some(FilterOperator::Like),
+			"in" => Some(FilterOperator::In),
+			_ => None
+		}
+	}
+
+	fn as_sql(self) -> &'static str {
+		match self {
+			FilterOperator::Eq | FilterOperator::In => "=",
+			FilterOperator::Ne => "<>",
+			FilterOperator::Gt => ">",
+			FilterOperator::Gte => ">=",
+			FilterOperator::Lt => "<",
+			FilterOperator::Lte => "<=",
+			FilterOperator::Like => "ILIKE"
+		}
+	}
+}
+
+/// One `field:operator:value` condition, e.g. `role:eq:admin`, `created_at:gte:2026-01-01` or `name:in:a,b`.
+#[derive(Clone, Debug)]
+pub struct Filter {
+	pub field: FilterField,
+	pub operator: FilterOperator,
+	pub values: Vec<FieldValue>
+}
+
+impl Filter {
+	/// Parses a filter against the whitelist of a resource, errors are meant to be shown to the client.
+	pub fn parse(raw: &str, fields: &[FilterField]) -> Result<Self, String> {
+		let mut parts = raw.splitn(3, ':');
+
+		let (Some(field), Some(operator), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+			return Err(format!("Filter '{raw}' harus berformat field:operator:value."));
+		};
+
+		let field = *fields.iter()
+			.find(|allowed| allowed.name == field)
+			.ok_or_else(|| format!(
+				"Filter pada field '{field}' tidak didukung, gunakan salah satu dari: {}.",
+				fields.iter().map(|allowed| allowed.name).collect::<Vec<_>>().join(", ")
+			))?;
+
+		let operator = FilterOperator::parse(operator)
+			.ok_or_else(|| format!("Operator '{operator}' tidak dikenal, gunakan eq, ne, gt, gte, lt, lte, like atau in."))?;
+
+		if operator == FilterOperator::Like && field.kind != FieldKind::Text {
+			return Err(format!("Operator like hanya bisa dipakai pada field teks, bukan '{}'.", field.name));
+		}
+
+		let raw_values: Vec<&str> = match operator {
+			FilterOperator::In => value.split(',').collect(),
+			_ => vec![value]
+		};
+
+		let values = raw_values.into_iter()
+			.map(|value| parse_value(field.kind, value).ok_or_else(|| format!("Nilai '{value}' tidak valid untuk field '{}'.", field.name)))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(Self { field, operator, values })
+	}
+
+	/// Appends the condition as an `AND` clause to a query that already has a `WHERE`.
+	pub fn push_sql(&self, query: &mut QueryBuilder<'_, Postgres>) {
+		query.push(format!(" AND {} ", self.field.name));
+
+		match self.operator {
+			FilterOperator::In => {
+				query.push("IN (");
+				for (index, value) in self.values.iter().enumerate() {
+					if index > 0 {
+						query.push(", ");
+					}
+					value.push_bind(query);
+				}
+				query.push(")");
+			},
+			FilterOperator::Like => {
+				query.push("ILIKE ");
+				if let FieldValue::Text(value) = &self.values[0] {
+					query.push_bind(contains_pattern(value)).push(" ESCAPE '\\'");
+				}
+			},
+			operator => {
+				query.push(format!("{} ", operator.as_sql()));
+				self.values[0].push_bind(query);
+			}
+		}
+	}
+
+	/// Evaluates the condition against a row, used by the in-memory repositories.
+	pub fn matches<T: Sortable>(&self, row: &T) -> bool {
+		let actual = row.field_value(self.field.name);
+		let expected = &self.values[0];
+
+		match self.operator {
+			FilterOperator::Eq => actual == *expected,
+			FilterOperator::Ne => actual != *expected,
+			FilterOperator::Gt => actual > *expected,
+			FilterOperator::Gte => actual >= *expected,
+			FilterOperator::Lt => actual < *expected,
+			FilterOperator::Lte => actual <= *expected,
+			FilterOperator::In => self.values.contains(&actual),
+			FilterOperator::Like => match (&actual, expected) {
+				(FieldValue::Text(actual), FieldValue::Text(expected)) => actual.to_lowercase().contains(&expected.to_lowercase()),
+				_ => false
+			}
+		}
+	}
+}
+
+/// `%value%` with the `\`, `%` and `_` of `value` escaped, so they match themselves in a `LIKE ... ESCAPE '\'`.
+pub fn contains_pattern(value: &str) -> String {
+	let mut pattern = String::with_capacity(value.len() + 2);
+	pattern.push('%');
+	for character in value.chars() {
+		if matches!(character, '\\' | '%' | '_') {
+			pattern.push('\\');
+		}
+		pattern.push(character);
+	}
+	pattern.push('%');
+
+	pattern
+}
+
+/// Timestamps also accept a plain `YYYY-MM-DD` date, read as midnight UTC.
+fn parse_value(kind: FieldKind, raw: &str) -> Option<FieldValue> {
+	match kind {
+		FieldKind::Timestamp => FieldValue::parse(kind, raw).or_else(|| {
+			Date::parse(raw, format_description!("[year]-[month]-[day]"))
+				.ok()
+				.map(|date| FieldValue::Timestamp(date.midnight().assume_utc()))
+		}),
+		kind => FieldValue::parse(kind, raw)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use time::{ macros::datetime, OffsetDateTime };
+
+	const FIELDS: &[FilterField] = &[
+		FilterField { name: "id", kind: FieldKind::Integer },
+		FilterField { name: "role", kind: FieldKind::Text },
+		FilterField { name: "created_at", kind: FieldKind::Timestamp }
+	];
+
+	struct Row {
+		id: i32,
+		role: &'static str,
+		created_at: OffsetDateTime
+	}
+
+	impl Sortable for Row {
+		fn id(&self) -> i32 {
+			self.id
+		}
+
+		fn field_value(&self, field: &str) -> FieldValue {
+			match field {
+				"role" => FieldValue::Text(self.role.to_string()),
+				"created_at" => FieldValue::Timestamp(self.created_at),
+				_ => FieldValue::Integer(self.id as i64)
+			}
+		}
+	}
+
+	fn row() -> Row {
+		Row { id: 7, role: "admin", created_at: datetime!(2026-02-03 10:00 UTC) }
+	}
+
+	#[test]
+	fn parses_and_matches_each_operator() {
+		for (raw, expected) in [
+			("role:eq:admin", true),
+			("role:ne:admin", false),
+			("role:like:ADM", true),
+			("role:in:cashier,admin", true),
+			("id:gt:7", false),
+			("id:gte:7", true),
+			("id:lt:10", true),
+			("created_at:gte:2026-01-01", true),
+			("created_at:lte:2026-02-03T09:00:00Z", false)
+		] {
+			assert_eq!(Filter::parse(raw, FIELDS).unwrap().matches(&row()), expected, "{raw}");
+		}
+	}
+
+	#[test]
+	fn rejects_unknown_fields_operators_and_values() {
+		for raw in ["password:eq:x", "role:between:a", "id:eq:abc", "id:like:1", "created_at:gte:kemarin", "role"] {
+			assert!(Filter::parse(raw, FIELDS).is_err(), "{raw}");
+		}
+	}
+
+	#[test]
+	fn value_may_contain_colons() {
+		let filter = Filter::parse("created_at:lt:2026-02-03T10:30:00+07:00", FIELDS).unwrap();
+
+		assert_eq!(filter.values, [FieldValue::Timestamp(datetime!(2026-02-03 03:30 UTC))]);
+	}
+
+	#[test]
+	fn pushes_parameterized_sql() {
+		let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM user_system WHERE TRUE");
+		Filter::parse("role:in:admin,cashier", FIELDS).unwrap().push_sql(&mut query);
+		Filter::parse("role:like:adm", FIELDS).unwrap().push_sql(&mut query);
+
+		assert_eq!(query.sql(), "SELECT * FROM user_system WHERE TRUE AND role IN ($1, $2) AND role ILIKE $3 ESCAPE '\\'");
+	}
+
+	#[test]
+	fn like_wildcards_in_values_are_literal() {
+		assert_eq!(contains_pattern("kasir_01"), "%kasir\\_01%");
+		assert_eq!(contains_pattern("100%"), "%100\\%%");
+		assert_eq!(contains_pattern("a\\b"), "%a\\\\b%");
+		assert_eq!(contains_pattern(""), "%%");
+	}
+}
@@ -0,0 +1,241 @@
+use std::time::Duration;
+
+use axum::{
+	body::{ to_bytes, Body },
+	extract::State,
+	http::{ HeaderName, HeaderValue, Method, Request, StatusCode },
+	middleware::Next,
+	response::Response
+};
+use serde_json::json;
+use sha2::{ Digest, Sha256 };
+use time::OffsetDateTime;
+
+use crate::model::idempotency_model::{ IdempotencyClaim, StoredResponse };
+use crate::model::user_model::JwtClaims;
+use crate::state::DynIdempotencyRepository;
+
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// Set on a response that was replayed from a previous request with the same key.
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// How long a key is remembered, a retry after that runs the request again.
+pub const IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Bodies above this size are not hashed and get 413, the same as the default body limit of the routes behind this
+/// guard. Uploads have their own limits and are routed outside of it.
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Makes POST requests carrying an `Idempotency-Key` header safe to retry, must run after `auth_guard`.
+/// The first request runs and its response is stored, a retry with the same key and body gets that response back,
+/// the same key with another method, path or body is rejected with 422 and a retry while the first one runs with 409.
+/// Server errors are not stored so the client can retry them, neither is a request dropped before it was answered.
+pub async fn idempotency_guard(
+	State(idempotency_repository): State<DynIdempotencyRepository>,
+	req: Request<Body>,
+	next: Next
+) -> Result<Response, (StatusCode, String)> {
+	let key = match req.headers().get(IDEMPOTENCY_KEY) {
+		Some(key) if req.method() == Method::POST => key.to_str().unwrap_or_default().trim().to_string(),
+		_ => return Ok(next.run(req).await)
+	};
+
+	let Some(user_id) = req.extensions().get::<JwtClaims>().map(|claims| claims.id) else {
+		return Ok(next.run(req).await);
+	};
+
+	if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+		return Err(error(StatusCode::BAD_REQUEST, format!("Idempotency-Key harus berisi 1 sampai {MAX_KEY_LENGTH} karakter.")));
+	}
+
+	let (parts, body) = req.into_parts();
+	let bytes = to_bytes(body, MAX_BODY_BYTES)
+	.await
+	.map_err(|_| error(StatusCode::PAYLOAD_TOO_LARGE, "Body Request Terlalu Besar.".to_string()))?;
+
+	let request_hash = request_hash(&parts.method, parts.uri.path_and_query().map(|path| path.as_str()).unwrap_or_default(), &bytes);
+	let expired_before = OffsetDateTime::now_utc() - IDEMPOTENCY_TTL;
+
+	let claim = idempotency_repository.claim(user_id, &key, &request_hash, expired_before)
+	.await
+	.map_err(|e| error(e.status_code(), e.to_string()))?;
+
+	match claim {
+		IdempotencyClaim::Started => {},
+		IdempotencyClaim::Replay(stored) => return Ok(replay(stored)),
+		IdempotencyClaim::Mismatch => return Err(error(
+			StatusCode::UNPROCESSABLE_ENTITY,
+			"Idempotency-Key Sudah Dipakai Untuk Request Yang Berbeda.".to_string()
+		)),
+		IdempotencyClaim::InProgress => return Err(error(
+			StatusCode::CONFLICT,
+			"Request Dengan Idempotency-Key Ini Masih Diproses, Coba Lagi Nanti.".to_string()
+		))
+	}
+
+	let claim_guard = ClaimGuard { idempotency_repository: Some(idempotency_repository.clone()), user_id, key: key.clone() };
+	let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+
+	if response.status().is_server_error() {
+		claim_guard.disarm();
+		if let Err(e) = idempotency_repository.release(user_id, &key).await {
+			eprintln!("Failed to release idempotency key {key}: {e}");
+		}
+
+		return Ok(response);
+	}
+
+	let (parts, body) = response.into_parts();
+	let body = to_bytes(body, usize::MAX)
+	.await
+	.map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+	let stored = StoredResponse {
+		status: parts.status.as_u16(),
+		headers: parts.headers.iter()
+			.filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+			.collect(),
+		body: body.to_vec()
+	};
+
+	// Stored from its own task, a client leaving now must neither interrupt it nor release a key that is answered.
+	claim_guard.disarm();
+	let _ = tokio::spawn(async move {
+		if let Err(e) = idempotency_repository.complete(user_id, &key, &stored).await {
+			eprintln!("Failed to store the response of idempotency key {key}: {e}");
+		}
+	}).await;
+
+	Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+/// Releases a claimed key when the request is dropped before its response is stored, as happens when the client
+/// disconnects or the handler panics. Without it a retry would get 409 until the key expires.
+struct ClaimGuard {
+	idempotency_repository: Option<DynIdempotencyRepository>,
+	user_id: i32,
+	key: String
+}
+
+impl ClaimGuard {
+	fn disarm(mut self) {
+		self.idempotency_repository = None;
+	}
+}
+
+impl Drop for ClaimGuard {
+	fn drop(&mut self) {
+		let Some(idempotency_repository) = self.idempotency_repository.take() else {
+			return;
+		};
+		let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+			return;
+		};
+
+		let (user_id, key) = (self.user_id, std::mem::take(&mut self.key));
+		runtime.spawn(async move {
+			if let Err(e) = idempotency_repository.release(user_id, &key).await {
+				eprintln!("Failed to release idempotency key {key}: {e}");
+			}
+		});
+	}
+}
+
+/// Hex SHA-256 of the method, path with query and body, a key may only be reused for the exact same request.
+fn request_hash(method: &Method, path: &str, body: &[u8]) -> String {
+	let mut hasher = Sha256::new();
+	hasher.update(method.as_str());
+	hasher.update(b"\n");
+	hasher.update(path);
+	hasher.update(b"\n");
+	hasher.update(body);
+
+	hex::encode(hasher.finalize())
+}
+
+fn replay(stored: StoredResponse) -> Response {
+	let mut response = Response::new(Body::from(stored.body));
+	*response.status_mut() = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+
+	let headers = response.headers_mut();
+	for (name, value) in stored.headers {
+		if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+			headers.append(name, value);
+		}
+	}
+	headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+
+	response
+}
+
+fn error(status: StatusCode, message: String) -> (StatusCode, String) {
+	(status, json!({ "success": false, "message": message }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{ atomic::{ AtomicBool, Ordering }, Arc };
+
+	use axum::{ middleware::from_fn_with_state, routing::post, Router };
+	use tokio::sync::Notify;
+	use tower::ServiceExt;
+
+	use crate::repository::idempotency_repository::InMemoryIdempotencyRepository;
+
+	use super::*;
+
+	#[test]
+	fn request_hash_covers_method_path_and_body() {
+		let hash = request_hash(&Method::POST, "/api/category", br#"{"name":"Minuman"}"#);
+
+		assert_eq!(hash.len(), 64);
+		assert_eq!(hash, request_hash(&Method::POST, "/api/category", br#"{"name":"Minuman"}"#));
+		assert_ne!(hash, request_hash(&Method::POST, "/api/category", br#"{"name":"Snack"}"#));
+		assert_ne!(hash, request_hash(&Method::POST, "/api/user", br#"{"name":"Minuman"}"#));
+	}
+
+	fn keyed_request() -> Request<Body> {
+		let mut request = Request::post("/api/category").header(IDEMPOTENCY_KEY, "flaky-wifi").body(Body::from("{}")).unwrap();
+		request.extensions_mut().insert(JwtClaims { id: 1, role: "admin".to_string(), name: "admin".to_string(), exp: 0 });
+
+		request
+	}
+
+	#[tokio::test]
+	async fn retry_after_a_dropped_request_runs_again() {
+		let idempotency_repository: DynIdempotencyRepository = Arc::new(InMemoryIdempotencyRepository::default());
+		let hang = Arc::new(AtomicBool::new(true));
+		let entered = Arc::new(Notify::new());
+
+		let app = Router::new()
+		.route("/api/category", post({
+			let (hang, entered) = (hang.clone(), entered.clone());
+			move || async move {
+				if hang.swap(false, Ordering::SeqCst) {
+					entered.notify_one();
+					std::future::pending::<()>().await;
+				}
+
+				StatusCode::CREATED
+			}
+		}))
+		.layer(from_fn_with_state(idempotency_repository.clone(), idempotency_guard));
+
+		// The client gives up once the handler runs, which drops the request future.
+		tokio::select! {
+			_ = app.clone().oneshot(keyed_request()) => panic!("the first request should still be running"),
+			_ = entered.notified() => {}
+		}
+		tokio::task::yield_now().await;
+
+		let response = app.clone().oneshot(keyed_request()).await.unwrap();
+		assert_eq!(response.status(), StatusCode::CREATED);
+		assert!(response.headers().get(IDEMPOTENT_REPLAYED).is_none());
+
+		let replayed = app.oneshot(keyed_request()).await.unwrap();
+		assert_eq!(replayed.headers()[IDEMPOTENT_REPLAYED], "true");
+	}
+}
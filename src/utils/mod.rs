@@ -1,2 +1,20 @@
+#[allow(clippy::module_inception)]
 pub mod utils;
-pub mod route_guard;
\ No newline at end of file
+pub mod route_guard;
+pub mod openapi;
+pub mod pagination;
+pub mod filter;
+pub mod extractor;
+pub mod validation;
+pub mod patch;
+pub mod purge;
+pub mod audit;
+pub mod idempotency;
+pub mod slug;
+pub mod tabular;
+pub mod token;
+pub mod permission;
+pub mod phone;
+pub mod serve_file;
+pub mod upload;
+pub mod thumbnail;
\ No newline at end of file
@@ -0,0 +1,109 @@
+use utoipa::{
+	openapi::security::{ Http, HttpAuthScheme, SecurityScheme },
+	Modify, OpenApi
+};
+
+use crate::controller::{ audit_controller, auth_controller, category_controller, file_controller, http_controller, role_controller, user_controller };
+use crate::model::audit_model::AuditData;
+use crate::model::auth_model::{ ActivateBody, AuthResponse, ChangePasswordBody, LoginBody };
+use crate::model::category_model::{ CategoryCreateBody, CategoryData, CategoryMergeBody, CategoryMoveBody, CategoryNode, CategoryReorderBody, CategoryReplaceBody, CategoryUpdateBody };
+use crate::model::file_model::{ UploadFileForm, UploadFileResponse };
+use crate::model::role_model::{ PermissionData, PermissionSummary, RoleBody, RoleData };
+use crate::model::user_model::{ UserCreateDto, UserInviteData, UserInviteDto, UserInviteResponse, UserProfileDto, UserView, UserReplaceDto, UserUpdateDto };
+use crate::model::utils_model::{ ErrorResponse, FieldError, ImportReport, ImportResponse, ImportRowError, MessageResponse, PaginationBody, PaginationResponse, ValidationErrorResponse };
+use crate::utils::tabular::TabularFormat;
+
+#[derive(OpenApi)]
+#[openapi(
+	info(
+		title = "Rust Axum POS Backend",
+		description = "REST API of the POS back office. Every failed request answers with the `ErrorResponse` envelope, validation failures (422) add a per-field `errors` list."
+	),
+	paths(
+		category_controller::search_paginate,
+		category_controller::filter_paginate,
+		category_controller::find_many,
+		category_controller::tree,
+		category_controller::find_by_id,
+		category_controller::breadcrumbs,
+		category_controller::create,
+		category_controller::update,
+		category_controller::patch,
+		category_controller::delete,
+		category_controller::reorder,
+		category_controller::move_to,
+		category_controller::merge,
+		category_controller::import,
+		category_controller::export,
+		category_controller::restore,
+		user_controller::search_paginate,
+		user_controller::filter_paginate,
+		user_controller::me,
+		user_controller::update_me,
+		role_controller::my_permissions,
+		user_controller::find_by_id,
+		user_controller::create,
+		user_controller::update,
+		user_controller::patch,
+		user_controller::delete,
+		user_controller::restore,
+		user_controller::invite,
+		user_controller::resend_invite,
+		user_controller::deactivate,
+		user_controller::reactivate,
+		user_controller::import,
+		user_controller::export,
+		role_controller::find_all,
+		role_controller::find_by_id,
+		role_controller::create,
+		role_controller::update,
+		role_controller::delete,
+		role_controller::permissions,
+		audit_controller::search_paginate,
+		auth_controller::login,
+		auth_controller::authenticated,
+		auth_controller::change_password,
+		auth_controller::activate,
+		http_controller::get_http_example,
+		http_controller::post_http_example,
+		file_controller::upload_user_image,
+		file_controller::get_user_image,
+		file_controller::upload_category_icon,
+		file_controller::get_category_icon
+	),
+	components(schemas(
+		AuditData,
+		ActivateBody, AuthResponse, ChangePasswordBody, LoginBody,
+		CategoryCreateBody, CategoryData, CategoryMergeBody, CategoryMoveBody, CategoryNode, CategoryReorderBody, CategoryReplaceBody, CategoryUpdateBody,
+		UploadFileForm, UploadFileResponse,
+		PermissionData, PermissionSummary, RoleBody, RoleData,
+		UserCreateDto, UserInviteData, UserInviteDto, UserInviteResponse, UserProfileDto, UserView, UserReplaceDto, UserUpdateDto,
+		ErrorResponse, FieldError, ImportReport, ImportResponse, ImportRowError, MessageResponse, PaginationBody, PaginationResponse, ValidationErrorResponse,
+		TabularFormat
+	)),
+	modifiers(&BearerSecurity),
+	tags(
+		(name = "Auth", description = "Login and token handling"),
+		(name = "Category", description = "Product categories"),
+		(name = "User", description = "Back office users"),
+		(name = "Role", description = "Roles and the permissions they grant"),
+		(name = "File", description = "Uploaded user images and category icons"),
+		(name = "Audit", description = "Who changed what, holders of audit.view only. Best effort: an event is written after its change is committed and a failure to write it is logged, never undoing or failing the request"),
+		(name = "Http Example", description = "Outgoing HTTP client examples")
+	)
+)]
+pub struct ApiDoc;
+
+/// Registers the JWT issued by `/api/auth/login` as the `bearer_auth` scheme.
+struct BearerSecurity;
+
+impl Modify for BearerSecurity {
+	fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+		let components = openapi.components.get_or_insert_with(Default::default);
+
+		components.add_security_scheme(
+			"bearer_auth",
+			SecurityScheme::Http(Http::builder().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build())
+		);
+	}
+}
@@ -0,0 +1,397 @@
+use std::cmp::Ordering;
+
+use base64::{ engine::general_purpose::URL_SAFE_NO_PAD, Engine };
+use serde::Deserialize;
+use sqlx::{ postgres::Postgres, QueryBuilder };
+use time::{ format_description::well_known::Rfc3339, OffsetDateTime };
+use utoipa::ToSchema;
+
+use crate::model::utils_model::{ PaginationBody, PaginationResponse };
+use crate::utils::filter::{ Filter, FilterField };
+
+pub const DEFAULT_PER_PAGE: i64 = 10;
+pub const MAX_PER_PAGE: i64 = 100;
+
+/// Type of a column that can be sorted on, decides how cursor values are parsed and bound.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldKind {
+	Integer,
+	Text,
+	Timestamp
+}
+
+/// A column value taken from a row, compared in memory and bound in SQL.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub enum FieldValue {
+	Integer(i64),
+	Text(String),
+	Timestamp(OffsetDateTime)
+}
+
+impl FieldValue {
+	pub fn parse(kind: FieldKind, raw: &str) -> Option<Self> {
+		match kind {
+			FieldKind::Integer => raw.parse().ok().map(FieldValue::Integer),
+			FieldKind::Text => Some(FieldValue::Text(raw.to_string())),
+			FieldKind::Timestamp => OffsetDateTime::parse(raw, &Rfc3339).ok().map(FieldValue::Timestamp)
+		}
+	}
+
+	fn encode(&self) -> String {
+		match self {
+			FieldValue::Integer(value) => value.to_string(),
+			FieldValue::Text(value) => value.clone(),
+			FieldValue::Timestamp(value) => value.format(&Rfc3339).unwrap_or_default()
+		}
+	}
+
+	pub fn push_bind(&self, query: &mut QueryBuilder<'_, Postgres>) {
+		match self {
+			FieldValue::Integer(value) => query.push_bind(*value),
+			FieldValue::Text(value) => query.push_bind(value.clone()),
+			FieldValue::Timestamp(value) => query.push_bind(*value)
+		};
+	}
+}
+
+/// A column clients may sort on, `name` is both the API name and the SQL column.
+#[derive(Clone, Copy, Debug)]
+pub struct SortField {
+	pub name: &'static str,
+	pub kind: FieldKind
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+	#[default]
+	Asc,
+	Desc
+}
+
+impl SortDirection {
+	fn as_sql(self) -> &'static str {
+		match self {
+			SortDirection::Asc => "ASC",
+			SortDirection::Desc => "DESC"
+		}
+	}
+}
+
+/// Position after the last row of the previous page, ties on the sort column are broken by id.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cursor {
+	pub value: FieldValue,
+	pub id: i32
+}
+
+/// Validated pagination parameters handed to the repositories.
+#[derive(Clone, Debug)]
+pub struct PageRequest {
+	pub term: String,
+	pub filters: Vec<Filter>,
+	pub per_page: i64,
+	pub sort_by: SortField,
+	pub sort_dir: SortDirection,
+	/// Offset mode page, `None` when the request continues from a cursor.
+	pub page: Option<i64>,
+	pub cursor: Option<Cursor>,
+	/// Whether soft-deleted rows are listed, the controller only allows it for admins.
+	pub include_deleted: bool
+}
+
+/// Implemented by rows that can be paginated, exposes the column values used for sorting, cursors and filters.
+pub trait Sortable {
+	fn id(&self) -> i32;
+
+	fn field_value(&self, field: &str) -> FieldValue;
+}
+
+/// One page of rows together with the number of rows matching the search and filters.
+pub struct Page<T> {
+	pub data: Vec<T>,
+	pub count: i64,
+	pub next_cursor: Option<String>
+}
+
+impl PageRequest {
+	/// Resolves the body against the sortable and filterable fields of a resource, the first sort field is the default.
+	pub fn new(body: PaginationBody, sort_fields: &[SortField], filter_fields: &[FilterField]) -> Result<Self, String> {
+		let per_page = body.per_page.unwrap_or(DEFAULT_PER_PAGE);
+
+		if !(1..=MAX_PER_PAGE).contains(&per_page) {
+			return Err(format!("per_page harus di antara 1 dan {MAX_PER_PAGE}."));
+		}
+
+		let sort_by = match body.sort_by.as_deref() {
+			Some(sort_by) => *sort_fields.iter()
+				.find(|field| field.name == sort_by)
+				.ok_or_else(|| format!(
+					"sort_by '{sort_by}' tidak didukung, gunakan salah satu dari: {}.",
+					sort_fields.iter().map(|field| field.name).collect::<Vec<_>>().join(", ")
+				))?,
+			None => sort_fields[0]
+		};
+		let sort_dir = body.sort_dir.unwrap_or_default();
+
+		let filters = body.filter.iter()
+			.map(|filter| Filter::parse(filter, filter_fields))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let cursor = body.cursor.as_deref()
+			.map(|cursor| decode_cursor(cursor, sort_by, sort_dir).ok_or_else(|| "Cursor tidak valid.".to_string()))
+			.transpose()?;
+
+		let page = match cursor {
+			Some(_) => None,
+			None if body.page < 1 => return Err("page minimal 1.".to_string()),
+			// The offset must fit an i64, (page - 1) * per_page overflows for absurd pages.
+			None if (body.page - 1).checked_mul(per_page).is_none() => return Err(format!("page maksimal {}.", i64::MAX / per_page + 1)),
+			None => Some(body.page)
+		};
+
+		Ok(Self { term: body.term, filters, per_page, sort_by, sort_dir, page, cursor, include_deleted: body.include_deleted })
+	}
+
+	/// Rows to fetch, one more than a page so the repository knows whether another page exists.
+	pub fn fetch_limit(&self) -> i64 {
+		self.per_page + 1
+	}
+
+	pub fn offset(&self) -> i64 {
+		self.page.map(|page| (page - 1) * self.per_page).unwrap_or(0)
+	}
+
+	/// Appends `AND deleted_at IS NULL` unless `include_deleted`, used by resources that soft-delete.
+	pub fn push_soft_delete(&self, query: &mut QueryBuilder<'_, Postgres>) {
+		if !self.include_deleted {
+			query.push(" AND deleted_at IS NULL");
+		}
+	}
+
+	/// Appends every filter as an `AND` clause to a query that already has a `WHERE`, used by both the count and the page query.
+	pub fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>) {
+		for filter in &self.filters {
+			filter.push_sql(query);
+		}
+	}
+
+	/// Appends the keyset condition, if any, as an `AND` clause to a query that already has a `WHERE`.
+	pub fn push_keyset(&self, query: &mut QueryBuilder<'_, Postgres>) {
+		if let Some(cursor) = &self.cursor {
+			let operator = match self.sort_dir {
+				SortDirection::Asc => ">",
+				SortDirection::Desc => "<"
+			};
+
+			query.push(format!(" AND ({}, id) {operator} (", self.sort_by.name));
+			cursor.value.push_bind(query);
+			query.push(", ").push_bind(cursor.id).push(")");
+		}
+	}
+
+	/// Appends `ORDER BY`, `LIMIT` and, in offset mode, `OFFSET`.
+	pub fn push_order_and_limit(&self, query: &mut QueryBuilder<'_, Postgres>) {
+		let direction = self.sort_dir.as_sql();
+
+		query.push(format!(" ORDER BY {} {direction}, id {direction}", self.sort_by.name));
+		query.push(" LIMIT ").push_bind(self.fetch_limit());
+
+		if self.cursor.is_none() {
+			query.push(" OFFSET ").push_bind(self.offset());
+		}
+	}
+
+	/// Trims the extra row fetched by `fetch_limit` and turns it into the next cursor.
+	pub fn into_page<T: Sortable>(&self, mut rows: Vec<T>, count: i64) -> Page<T> {
+		let has_more = rows.len() as i64 > self.per_page;
+		rows.truncate(self.per_page as usize);
+
+		let next_cursor = match rows.last() {
+			Some(last) if has_more => Some(encode_cursor(
+				&Cursor { value: last.field_value(self.sort_by.name), id: last.id() },
+				self.sort_by,
+				self.sort_dir
+			)),
+			_ => None
+		};
+
+		Page { data: rows, count, next_cursor }
+	}
+
+	/// Filters, sorts and slices rows that already match the term, used by the in-memory repositories.
+	pub fn paginate_in_memory<T: Sortable>(&self, mut rows: Vec<T>) -> Page<T> {
+		rows.retain(|row| self.filters.iter().all(|filter| filter.matches(row)));
+		let count = rows.len() as i64;
+
+		rows.sort_by(|a, b| self.compare(a, b));
+
+		if let Some(cursor) = &self.cursor {
+			rows.retain(|row| {
+				let position = (row.field_value(self.sort_by.name), row.id())
+					.partial_cmp(&(cursor.value.clone(), cursor.id))
+					.unwrap_or(Ordering::Equal);
+
+				match self.sort_dir {
+					SortDirection::Asc => position == Ordering::Greater,
+					SortDirection::Desc => position == Ordering::Less
+				}
+			});
+		}
+
+		let rows = rows.into_iter()
+			.skip(self.offset() as usize)
+			.take(self.fetch_limit() as usize)
+			.collect();
+
+		self.into_page(rows, count)
+	}
+
+	fn compare<T: Sortable>(&self, a: &T, b: &T) -> Ordering {
+		let ordering = (a.field_value(self.sort_by.name), a.id())
+			.partial_cmp(&(b.field_value(self.sort_by.name), b.id()))
+			.unwrap_or(Ordering::Equal);
+
+		match self.sort_dir {
+			SortDirection::Asc => ordering,
+			SortDirection::Desc => ordering.reverse()
+		}
+	}
+}
+
+impl PaginationResponse {
+	pub fn new<T>(request: &PageRequest, page: &Page<T>) -> Self {
+		Self {
+			per_page: request.per_page,
+			total_page: (page.count + request.per_page - 1) / request.per_page,
+			count: page.count,
+			current_page: request.page,
+			next_cursor: page.next_cursor.clone()
+		}
+	}
+}
+
+/// Cursors carry the sort they were issued for so they cannot be replayed against another ordering.
+fn encode_cursor(cursor: &Cursor, sort_by: SortField, sort_dir: SortDirection) -> String {
+	URL_SAFE_NO_PAD.encode(format!("{}|{}|{}|{}", sort_by.name, sort_dir.as_sql(), cursor.value.encode(), cursor.id))
+}
+
+fn decode_cursor(raw: &str, sort_by: SortField, sort_dir: SortDirection) -> Option<Cursor> {
+	let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(raw).ok()?).ok()?;
+	let mut parts = decoded.splitn(3, '|');
+
+	if parts.next()? != sort_by.name || parts.next()? != sort_dir.as_sql() {
+		return None;
+	}
+
+	let (value, id) = parts.next()?.rsplit_once('|')?;
+
+	Some(Cursor { value: FieldValue::parse(sort_by.kind, value)?, id: id.parse().ok()? })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FIELDS: &[SortField] = &[
+		SortField { name: "id", kind: FieldKind::Integer },
+		SortField { name: "name", kind: FieldKind::Text }
+	];
+
+	#[derive(Clone, Debug)]
+	struct Row {
+		id: i32,
+		name: &'static str
+	}
+
+	impl Sortable for Row {
+		fn id(&self) -> i32 {
+			self.id
+		}
+
+		fn field_value(&self, field: &str) -> FieldValue {
+			match field {
+				"name" => FieldValue::Text(self.name.to_string()),
+				_ => FieldValue::Integer(self.id as i64)
+			}
+		}
+	}
+
+	fn body(page: i64, per_page: Option<i64>, sort_by: Option<&str>, sort_dir: Option<SortDirection>, cursor: Option<String>) -> PaginationBody {
+		PaginationBody {
+			term: String::new(),
+			page,
+			per_page,
+			sort_by: sort_by.map(str::to_string),
+			sort_dir,
+			cursor,
+			filter: vec![],
+			include_deleted: false
+		}
+	}
+
+	fn rows() -> Vec<Row> {
+		["kopi", "teh", "susu", "air", "jus"].iter().enumerate()
+			.map(|(index, name)| Row { id: index as i32 + 1, name })
+			.collect()
+	}
+
+	#[test]
+	fn total_page_rounds_up() {
+		let request = PageRequest::new(body(1, Some(10), None, None, None), FIELDS, &[]).unwrap();
+
+		for (count, total_page) in [(0, 0), (1, 1), (10, 1), (11, 2), (25, 3)] {
+			let page: Page<Row> = Page { data: vec![], count, next_cursor: None };
+			assert_eq!(PaginationResponse::new(&request, &page).total_page, total_page);
+		}
+	}
+
+	#[test]
+	fn rejects_unknown_sort_and_out_of_range_per_page() {
+		assert!(PageRequest::new(body(1, None, Some("password"), None, None), FIELDS, &[]).is_err());
+		assert!(PageRequest::new(body(1, Some(0), None, None, None), FIELDS, &[]).is_err());
+		assert!(PageRequest::new(body(1, Some(MAX_PER_PAGE + 1), None, None, None), FIELDS, &[]).is_err());
+		assert!(PageRequest::new(body(0, None, None, None, None), FIELDS, &[]).is_err());
+	}
+
+	#[test]
+	fn offset_mode_sorts_and_slices() {
+		let request = PageRequest::new(body(2, Some(2), Some("name"), Some(SortDirection::Desc), None), FIELDS, &[]).unwrap();
+		let page = request.paginate_in_memory(rows());
+
+		assert_eq!(page.count, 5);
+		assert_eq!(page.data.iter().map(|row| row.name).collect::<Vec<_>>(), ["kopi", "jus"]);
+
+		assert!(PageRequest::new(body(i64::MAX, Some(2), None, None, None), FIELDS, &[]).is_err());
+		let last = PageRequest::new(body(i64::MAX / 2 + 1, Some(2), None, None, None), FIELDS, &[]).unwrap();
+		assert_eq!(last.offset(), i64::MAX - 1);
+	}
+
+	#[test]
+	fn keyset_mode_walks_every_row_once() {
+		let mut names = vec![];
+		let mut cursor = None;
+
+		loop {
+			let request = PageRequest::new(body(1, Some(2), Some("name"), None, cursor), FIELDS, &[]).unwrap();
+			let page = request.paginate_in_memory(rows());
+			names.extend(page.data.iter().map(|row| row.name));
+
+			match page.next_cursor {
+				Some(next_cursor) => cursor = Some(next_cursor),
+				None => break
+			}
+		}
+
+		assert_eq!(names, ["air", "jus", "kopi", "susu", "teh"]);
+	}
+
+	#[test]
+	fn cursor_is_bound_to_its_sort() {
+		let request = PageRequest::new(body(1, Some(2), Some("name"), None, None), FIELDS, &[]).unwrap();
+		let cursor = request.paginate_in_memory(rows()).next_cursor.unwrap();
+
+		assert!(PageRequest::new(body(1, Some(2), Some("id"), None, Some(cursor.clone())), FIELDS, &[]).is_err());
+		assert!(PageRequest::new(body(1, Some(2), Some("name"), Some(SortDirection::Desc), Some(cursor)), FIELDS, &[]).is_err());
+	}
+}
@@ -0,0 +1,82 @@
+use serde::{ Deserialize, Deserializer };
+use sqlx::{ postgres::Postgres, Encode, QueryBuilder, Type };
+
+/// `deserialize_with` for PATCH fields backed by a NOT NULL column, paired with `#[serde(default)]`
+/// an absent field stays `None` while an explicit `null` is rejected instead of being read as absent.
+pub fn non_null<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de>
+{
+	T::deserialize(deserializer).map(Some)
+}
+
+/// Builds `UPDATE <table> SET ... WHERE id = $n RETURNING version` out of the fields a PATCH body carries,
+/// `updated_at` and `version` are always bumped and soft-deleted rows are never matched.
+pub struct PatchQuery<'args> {
+	query: QueryBuilder<'args, Postgres>
+}
+
+impl<'args> PatchQuery<'args> {
+	pub fn new(table: &str) -> Self {
+		Self { query: QueryBuilder::new(format!("UPDATE {table} SET updated_at = NOW(), version = version + 1")) }
+	}
+
+	/// Sets `column` when the field was sent, `Some(None)` on a nullable column writes NULL.
+	pub fn set<T>(&mut self, column: &str, value: Option<T>) -> &mut Self
+	where
+		T: 'args + Encode<'args, Postgres> + Type<Postgres>
+	{
+		if let Some(value) = value {
+			self.query.push(format!(", {column} = ")).push_bind(value);
+		}
+
+		self
+	}
+
+	/// Only matches the row while it is still at `version`, `None` skips the check.
+	pub fn build(mut self, id: i32, version: Option<i32>) -> QueryBuilder<'args, Postgres> {
+		self.query.push(" WHERE id = ").push_bind(id).push(" AND deleted_at IS NULL");
+		if let Some(version) = version {
+			self.query.push(" AND version = ").push_bind(version);
+		}
+		self.query.push(" RETURNING version");
+
+		self.query
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Deserialize)]
+	struct Body {
+		#[serde(default, deserialize_with = "non_null")]
+		name: Option<String>,
+		#[serde(default, with = "::serde_with::rust::double_option")]
+		address: Option<Option<String>>
+	}
+
+	#[test]
+	fn tells_absent_from_null() {
+		let body: Body = serde_json::from_str("{}").unwrap();
+		assert_eq!((body.name, body.address), (None, None));
+
+		let body: Body = serde_json::from_str(r#"{ "name": "Budi", "address": null }"#).unwrap();
+		assert_eq!((body.name, body.address), (Some("Budi".to_string()), Some(None)));
+
+		assert!(serde_json::from_str::<Body>(r#"{ "name": null }"#).is_err());
+	}
+
+	#[test]
+	fn sets_only_present_fields() {
+		let mut patch = PatchQuery::new("user_system");
+		patch.set("full_name", Some("Budi")).set("phone_number", None::<&str>).set("address", Some(None::<&str>));
+
+		assert_eq!(
+			patch.build(7, Some(3)).sql(),
+			"UPDATE user_system SET updated_at = NOW(), version = version + 1, full_name = $1, address = $2 WHERE id = $3 AND deleted_at IS NULL AND version = $4 RETURNING version"
+		);
+	}
+}
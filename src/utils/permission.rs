@@ -0,0 +1,107 @@
+use std::collections::{ HashMap, HashSet };
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use crate::model::role_model::ADMIN_ROLE;
+
+/// How long resolved permissions of a token are reused before the role is looked up again.
+pub const PERMISSION_TTL: Duration = Duration::from_secs(60);
+
+/// Entries kept before expired ones are pruned on insert.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// Permissions of the caller, handed to handlers as a request extension by `auth_guard`.
+#[derive(Clone, Debug)]
+pub struct Permissions {
+	pub role: String,
+	codes: Arc<HashSet<String>>
+}
+
+impl Permissions {
+	pub fn new(role: impl Into<String>, codes: impl IntoIterator<Item = String>) -> Self {
+		Self { role: role.into(), codes: Arc::new(codes.into_iter().collect()) }
+	}
+
+	/// Admins hold every permission, even one added after their token was resolved.
+	pub fn has(&self, code: &str) -> bool {
+		self.role == ADMIN_ROLE || self.codes.contains(code)
+	}
+
+	/// Fails with 403 naming the missing permission.
+	pub fn require(&self, code: &str) -> Result<(), (StatusCode, String)> {
+		if !self.has(code) {
+			return Err((
+				StatusCode::FORBIDDEN,
+				json!({ "success": false, "message": format!("Anda Tidak Memiliki Izin {code}.") }).to_string()
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Granted codes, sorted.
+	pub fn codes(&self) -> Vec<String> {
+		let mut codes: Vec<String> = self.codes.iter().cloned().collect();
+		codes.sort();
+
+		codes
+	}
+}
+
+/// Permissions resolved per token, keyed by the token hash, so the role tables are not read on every request.
+/// Cleared whenever a role changes or a user is given another one.
+#[derive(Default)]
+pub struct PermissionCache {
+	entries: Mutex<HashMap<String, (Instant, Permissions)>>
+}
+
+impl PermissionCache {
+	pub fn get(&self, token_hash: &str) -> Option<Permissions> {
+		let entries = self.entries.lock().unwrap();
+
+		entries.get(token_hash)
+			.filter(|(resolved_at, _)| resolved_at.elapsed() < PERMISSION_TTL)
+			.map(|(_, permissions)| permissions.clone())
+	}
+
+	pub fn insert(&self, token_hash: String, permissions: Permissions) {
+		let mut entries = self.entries.lock().unwrap();
+
+		if entries.len() >= PRUNE_THRESHOLD {
+			entries.retain(|_, (resolved_at, _)| resolved_at.elapsed() < PERMISSION_TTL);
+		}
+		entries.insert(token_hash, (Instant::now(), permissions));
+	}
+
+	pub fn clear(&self) {
+		self.entries.lock().unwrap().clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn admins_hold_every_permission_and_others_only_theirs() {
+		let admin = Permissions::new(ADMIN_ROLE, Vec::new());
+		let cashier = Permissions::new("cashier", vec!["category.create".to_string()]);
+
+		assert!(admin.has("sale.void"));
+		assert!(cashier.has("category.create"));
+		assert_eq!(cashier.require("sale.void").unwrap_err().0, StatusCode::FORBIDDEN);
+	}
+
+	#[test]
+	fn cache_forgets_everything_on_clear() {
+		let cache = PermissionCache::default();
+		cache.insert("hash".to_string(), Permissions::new("cashier", Vec::new()));
+
+		assert_eq!(cache.get("hash").unwrap().role, "cashier");
+		cache.clear();
+		assert!(cache.get("hash").is_none());
+	}
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+
+use crate::repository::RepositoryError;
+use crate::state::DynUserRepository;
+
+/// Calling code assumed for numbers written without one.
+pub const COUNTRY_CODE: &str = "62";
+
+/// Mobile prefixes following the country code, grouped by operator.
+const CARRIER_PREFIXES: &[&str] = &[
+	// Telkomsel
+	"811", "812", "813", "821", "822", "823", "851", "852", "853",
+	// Indosat
+	"814", "815", "816", "855", "856", "857", "858",
+	// XL
+	"817", "818", "819", "859", "877", "878",
+	// Axis
+	"831", "832", "833", "838",
+	// Tri
+	"895", "896", "897", "898", "899",
+	// Smartfren
+	"881", "882", "883", "884", "885", "886", "887", "888", "889"
+];
+
+/// Characters people type between digits, ignored when reading a number.
+const SEPARATORS: &[char] = &[' ', '-', '.', '(', ')'];
+
+#[derive(Debug, PartialEq)]
+pub enum PhoneError {
+	/// Not made of 9 to 12 digits after the country code, or contains other characters.
+	Format,
+	/// Well formed but no Indonesian operator uses the prefix, landlines included.
+	Prefix(String)
+}
+
+impl fmt::Display for PhoneError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			PhoneError::Format => write!(f, "Nomor telepon harus berupa nomor seluler, contoh 0812-3456-7890 atau +6281234567890."),
+			PhoneError::Prefix(prefix) => write!(f, "Prefix operator 0{prefix} tidak dikenal.")
+		}
+	}
+}
+
+/// Reads an Indonesian mobile number written as `0812...`, `812...`, `62812...`, `+62812...` or `0062812...`,
+/// with any spaces, dashes, dots or parentheses, and returns it in E.164, e.g. `+6281234567890`.
+pub fn normalize(value: &str) -> Result<String, PhoneError> {
+	let cleaned = value.trim().replace(SEPARATORS, "");
+	let digits = match cleaned.strip_prefix('+').or_else(|| cleaned.strip_prefix("00")) {
+		Some(international) => international.strip_prefix(COUNTRY_CODE).ok_or(PhoneError::Format)?,
+		None => cleaned.strip_prefix(COUNTRY_CODE).or_else(|| cleaned.strip_prefix('0')).unwrap_or(&cleaned)
+	};
+
+	if !(9..=12).contains(&digits.len()) || !digits.chars().all(|c| c.is_ascii_digit()) {
+		return Err(PhoneError::Format);
+	}
+
+	let prefix = &digits[..3];
+	if !CARRIER_PREFIXES.contains(&prefix) {
+		return Err(PhoneError::Prefix(prefix.to_string()));
+	}
+
+	Ok(format!("+{COUNTRY_CODE}{digits}"))
+}
+
+/// `normalize` for values that already passed validation, anything else is returned unchanged.
+pub fn e164(value: &str) -> String {
+	normalize(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// Digits to look for in stored numbers when a search term looks like a phone number, in any format and possibly
+/// partial: `0812-34` becomes `6281234`, `812 34` stays `81234`. `None` for terms with letters or fewer than 3 digits.
+pub fn search_digits(term: &str) -> Option<String> {
+	let digits = term.trim().replace(SEPARATORS, "");
+	let digits = digits.strip_prefix('+').or_else(|| digits.strip_prefix("00")).unwrap_or(&digits);
+
+	if digits.len() < 3 || !digits.chars().all(|c| c.is_ascii_digit()) {
+		return None;
+	}
+
+	match digits.strip_prefix('0') {
+		Some(national) => Some(format!("{COUNTRY_CODE}{national}")),
+		None => Some(digits.to_string())
+	}
+}
+
+/// Outcome of `normalize_stored`.
+#[derive(Debug, Default, PartialEq)]
+pub struct NormalizeReport {
+	/// Rows whose number was rewritten, or would be on a dry run.
+	pub normalized: usize,
+	pub unchanged: usize,
+	/// Id and number of the rows that cannot be read as a mobile number, left as they are for a human to fix.
+	pub invalid: Vec<(i32, String)>
+}
+
+/// Rewrites the number of every user, soft-deleted ones included, to E.164. Run once through
+/// `cargo run -- normalize-phone-numbers [--dry-run]` for rows stored before numbers were normalized.
+pub async fn normalize_stored(user_repository: &DynUserRepository, dry_run: bool) -> Result<NormalizeReport, RepositoryError> {
+	let mut report = NormalizeReport::default();
+	let mut changes = vec![];
+
+	for (id, phone_number) in user_repository.phone_numbers().await? {
+		match normalize(&phone_number) {
+			Ok(normalized) if normalized == phone_number => report.unchanged += 1,
+			Ok(normalized) => changes.push((id, normalized)),
+			Err(_) => report.invalid.push((id, phone_number))
+		}
+	}
+
+	report.normalized = changes.len();
+	if !dry_run && !changes.is_empty() {
+		user_repository.set_phone_numbers(&changes).await?;
+	}
+
+	Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn every_local_and_international_format_reads_the_same() {
+		for value in ["081234567890", "81234567890", "6281234567890", "+6281234567890", "006281234567890", "62 812-3456-7890", "(0812) 3456.7890"] {
+			assert_eq!(normalize(value), Ok("+6281234567890".to_string()), "{value}");
+		}
+	}
+
+	#[test]
+	fn unknown_prefixes_and_foreign_numbers_are_rejected() {
+		assert_eq!(normalize("0800 1234 5678"), Err(PhoneError::Prefix("800".to_string())));
+		assert_eq!(normalize("021 5550 1234"), Err(PhoneError::Prefix("215".to_string())));
+		assert_eq!(normalize("+6591234567"), Err(PhoneError::Format));
+		assert_eq!(normalize("0812"), Err(PhoneError::Format));
+	}
+
+	#[test]
+	fn search_terms_are_read_as_partial_numbers() {
+		assert_eq!(search_digits("0812-34").as_deref(), Some("6281234"));
+		assert_eq!(search_digits("+62 812").as_deref(), Some("62812"));
+		assert_eq!(search_digits("812").as_deref(), Some("812"));
+		assert_eq!(search_digits("budi"), None);
+		assert_eq!(search_digits("08"), None);
+	}
+}
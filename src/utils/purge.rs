@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+
+use crate::repository::RepositoryError;
+use crate::utils::audit::Audit;
+use crate::utils::idempotency::IDEMPOTENCY_TTL;
+use crate::utils::upload;
+use crate::state::AppState;
+
+pub const DEFAULT_RETENTION_DAYS: u64 = 365;
+
+/// How long an upload may stay unreferenced, long enough for a client to upload a photo and then save the form.
+pub const DEFAULT_FILE_GRACE_HOURS: u64 = 24;
+
+/// Removes categories and users soft-deleted more than `retention` ago, returns how many of each were removed.
+pub async fn purge_deleted(state: &AppState, retention: Duration) -> Result<(u64, u64), RepositoryError> {
+	let deleted_before = OffsetDateTime::now_utc() - retention;
+
+	let categories = state.category_repository.purge_deleted(deleted_before).await?;
+	let users = state.user_repository.purge_deleted(deleted_before).await?;
+
+	Ok((categories, users))
+}
+
+/// Removes uploads nothing has referenced for `grace`, with their variants, returns how many were removed. Purging
+/// users and categories first releases their files, which are then removed once past the grace period too.
+/// Each removal is audited as a `delete` of the `file` without an actor.
+pub async fn collect_unreferenced_files(state: &AppState, grace: Duration) -> Result<u64, RepositoryError> {
+	let unreferenced_before = OffsetDateTime::now_utc() - grace;
+	let remove_blobs = upload::blob_remover(&state.storage);
+	let audit = Audit::new(state.audit_repository.clone(), None, None);
+	let mut removed = 0;
+
+	loop {
+		let collected = state.file_object_repository.collect_unreferenced(unreferenced_before, &remove_blobs).await?;
+		if collected.is_empty() {
+			return Ok(removed);
+		}
+
+		for file_object in &collected {
+			audit.record("delete", "file", format!("{}/{}", file_object.prefix, file_object.file_name), Some(file_object), None).await;
+		}
+		removed += collected.len() as u64;
+	}
+}
+
+/// Runs `purge_deleted` right away and then every `every` for as long as the server lives, expired idempotency keys
+/// and files unreferenced for `file_grace` are removed on the same schedule.
+pub fn spawn_purge_job(state: AppState, retention: Duration, file_grace: Duration, every: Duration) -> JoinHandle<()> {
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(every);
+
+		loop {
+			interval.tick().await;
+
+			match purge_deleted(&state, retention).await {
+				Ok((0, 0)) => {},
+				Ok((categories, users)) => println!("Purged {categories} categories and {users} users past the soft-delete retention."),
+				Err(e) => eprintln!("Failed to purge soft-deleted rows: {e}")
+			}
+
+			if let Err(e) = state.idempotency_repository.purge_expired(OffsetDateTime::now_utc() - IDEMPOTENCY_TTL).await {
+				eprintln!("Failed to purge expired idempotency keys: {e}");
+			}
+
+			match collect_unreferenced_files(&state, file_grace).await {
+				Ok(0) => {},
+				Ok(files) => println!("Removed {files} unreferenced files."),
+				Err(e) => eprintln!("Failed to remove unreferenced files: {e}")
+			}
+		}
+	})
+}
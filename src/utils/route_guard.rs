@@ -1,5 +1,6 @@
 use axum:: {
 	body:: { Body },
+	extract::State,
 	http:: { StatusCode, Request }, 
 	middleware::Next,
 	response::Response,
@@ -8,9 +9,23 @@ use axum:: {
 use jsonwebtoken::{ decode, DecodingKey, Validation };
 use serde_json::json;
 use crate::model::user_model::JwtClaims;
+use crate::repository::RepositoryError;
+use crate::state::AppState;
+use crate::utils::permission::Permissions;
+use crate::utils::token::token_hash;
 use crate::utils::utils::JWT_SECRET;
 
-pub async fn auth_guard(req: Request<Body>, next: Next) -> Result<Response, (StatusCode, String)> {
+/// Answer to a login or token of a user who cannot use the API any more.
+pub const INACTIVE_MESSAGE: &str = "Akun Anda Tidak Aktif, Hubungi Admin.";
+
+/// Rejects requests without a valid bearer token or whose user was deactivated or deleted since the token was issued,
+/// and hands the decoded `JwtClaims` and the `Permissions` of the current role of the user to handlers as request
+/// extensions. Permissions are resolved once per token and cached, activation is checked on every request.
+pub async fn auth_guard(
+	State(state): State<AppState>,
+	mut req: Request<Body>,
+	next: Next
+) -> Result<Response, (StatusCode, String)> {
 
 	if let Some(extracted_header_value) = req.headers().get("Authorization") {
 		if let Ok(header_value) = extracted_header_value.to_str() {
@@ -23,7 +38,36 @@ pub async fn auth_guard(req: Request<Body>, next: Next) -> Result<Response, (Sta
 				);
 
 				match decoded_token {
-					Ok(_) => Ok(next.run(req).await),
+					Ok(token_data) => {
+						let is_active = state.user_repository.is_active(token_data.claims.id)
+						.await
+						.map_err(|e| {
+							(
+								e.status_code(),
+								json!({ "success": false, "message": e.to_string() }).to_string()
+							)
+						})?;
+
+						if !is_active {
+							return Err((
+								StatusCode::UNAUTHORIZED,
+								json!({ "success": false, "message": INACTIVE_MESSAGE }).to_string()
+							));
+						}
+
+						let permissions = resolve_permissions(&state, jwt_token, token_data.claims.id)
+						.await
+						.map_err(|e| {
+							(
+								e.status_code(),
+								json!({ "success": false, "message": e.to_string() }).to_string()
+							)
+						})?;
+
+						req.extensions_mut().insert(token_data.claims);
+						req.extensions_mut().insert(permissions);
+						Ok(next.run(req).await)
+					},
 					Err(e) => Err((
 						StatusCode::UNAUTHORIZED,
 						json!({ "success": false, "message": e.to_string() }).to_string()
@@ -47,4 +91,38 @@ pub async fn auth_guard(req: Request<Body>, next: Next) -> Result<Response, (Sta
 			json!({ "success": false, "message": "Invalid Credentials" }).to_string()
 		))	
 	}
-}
\ No newline at end of file
+}
+
+/// Reads the role from the user rather than the token so a role change applies to tokens already issued.
+async fn resolve_permissions(state: &AppState, jwt_token: &str, user_id: i32) -> Result<Permissions, RepositoryError> {
+	let key = token_hash(jwt_token);
+	if let Some(permissions) = state.permission_cache.get(&key) {
+		return Ok(permissions);
+	}
+
+	let user = state.user_repository.find_view(user_id).await?;
+	let codes = state.role_repository.permissions_of(&user.role).await?;
+	let permissions = Permissions::new(user.role, codes);
+	state.permission_cache.insert(key, permissions.clone());
+
+	Ok(permissions)
+}
+
+/// Rejects with 403 unless the caller holds the permission, layered per route after `auth_guard` with
+/// `from_fn_with_state("category.delete", permission_guard)`.
+pub async fn permission_guard(
+	State(code): State<&'static str>,
+	req: Request<Body>,
+	next: Next
+) -> Result<Response, (StatusCode, String)> {
+	let permissions = req.extensions()
+		.get::<Permissions>()
+		.ok_or_else(|| (
+			StatusCode::UNAUTHORIZED,
+			json!({ "success": false, "message": "Invalid Credentials" }).to_string()
+		))?;
+
+	permissions.require(code)?;
+
+	Ok(next.run(req).await)
+}
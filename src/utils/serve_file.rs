@@ -0,0 +1,175 @@
+use std::time::SystemTime;
+
+use axum::{
+	body::Body,
+	http::{ header, HeaderMap, HeaderValue, StatusCode },
+	response::{ IntoResponse, Response }
+};
+
+use crate::storage::{ DynStorage, StorageError };
+
+/// Stored names are unique per upload and never rewritten, clients may reuse a copy for a day without asking.
+pub const CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// Every type `sniff_content_type` can tell, the only ones served as something else than a download.
+const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp", "image/bmp", "image/x-icon", "image/avif"];
+
+/// Content type told by the first bytes of a file, whatever its extension claims. Anything unrecognised, SVG
+/// included since it can carry scripts, is served as `application/octet-stream`.
+pub fn sniff_content_type(head: &[u8]) -> &'static str {
+	match head {
+		[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => "image/png",
+		[0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+		[b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => "image/gif",
+		[b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+		[b'B', b'M', ..] => "image/bmp",
+		[0x00, 0x00, 0x01, 0x00, ..] => "image/x-icon",
+		[_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f', ..] => "image/avif",
+		_ => "application/octet-stream"
+	}
+}
+
+/// Streams the object stored under `key`, honouring conditional and single-range requests: `If-None-Match` or
+/// `If-Modified-Since` answer 304, `Range` answers 206 with the requested bytes or 416, and `If-Range` falls back to
+/// the whole object once it changed.
+pub async fn serve(storage: &DynStorage, key: &str, request_headers: &HeaderMap) -> Response {
+	let meta = match storage.stat(key).await {
+		Ok(meta) => meta,
+		Err(e) => return error(e)
+	};
+
+	let len = meta.size;
+	let modified = meta.last_modified;
+	let last_modified = httpdate::fmt_http_date(modified);
+	// Whatever type the backend recorded, only known images are rendered inline.
+	let content_type = IMAGE_TYPES.iter().find(|image_type| meta.content_type.as_deref() == Some(**image_type)).copied().unwrap_or("application/octet-stream");
+
+	let mut response_headers = HeaderMap::new();
+	response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+	response_headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+	response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(CACHE_CONTROL));
+	response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+	if let Ok(etag) = HeaderValue::from_str(&meta.etag) {
+		response_headers.insert(header::ETAG, etag);
+	}
+	response_headers.insert(header::LAST_MODIFIED, HeaderValue::from_str(&last_modified).unwrap());
+
+	if not_modified(request_headers, &meta.etag, modified) {
+		response_headers.remove(header::CONTENT_TYPE);
+		return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+	}
+
+	let range = match request_headers.get(header::RANGE).and_then(|value| value.to_str().ok()) {
+		Some(range) if if_range_matches(request_headers, &meta.etag, &last_modified) => parse_range(range, len),
+		_ => Ok(None)
+	};
+
+	let (status, range, count) = match range {
+		Ok(Some((start, end))) => {
+			response_headers.insert(header::CONTENT_RANGE, HeaderValue::from_str(&format!("bytes {start}-{end}/{len}")).unwrap());
+			(StatusCode::PARTIAL_CONTENT, Some((start, end)), end - start + 1)
+		},
+		Ok(None) => (StatusCode::OK, None, len),
+		Err(()) => {
+			response_headers.insert(header::CONTENT_RANGE, HeaderValue::from_str(&format!("bytes */{len}")).unwrap());
+			return (StatusCode::RANGE_NOT_SATISFIABLE, response_headers).into_response();
+		}
+	};
+
+	let body = match storage.get(key, range).await {
+		Ok(body) => body,
+		Err(e) => return error(e)
+	};
+	response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(count));
+
+	(status, response_headers, Body::from_stream(body)).into_response()
+}
+
+fn error(e: StorageError) -> Response {
+	match e {
+		StorageError::NotFound => not_found(),
+		e => (e.status_code(), e.to_string()).into_response()
+	}
+}
+
+pub fn not_found() -> Response {
+	(StatusCode::NOT_FOUND, "File Not Found.").into_response()
+}
+
+/// `If-None-Match` wins over `If-Modified-Since` when both are sent.
+fn not_modified(request_headers: &HeaderMap, etag: &str, modified: SystemTime) -> bool {
+	if let Some(if_none_match) = request_headers.get(header::IF_NONE_MATCH).and_then(|value| value.to_str().ok()) {
+		return if_none_match.split(',').map(str::trim).any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag);
+	}
+
+	request_headers.get(header::IF_MODIFIED_SINCE)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| httpdate::parse_http_date(value).ok())
+		.is_some_and(|since| modified.duration_since(since).map_or(true, |newer_by| newer_by.as_secs() == 0))
+}
+
+/// Without `If-Range` the range always applies, with it only while the file still has that ETag or date.
+fn if_range_matches(request_headers: &HeaderMap, etag: &str, last_modified: &str) -> bool {
+	request_headers.get(header::IF_RANGE)
+		.and_then(|value| value.to_str().ok())
+		.is_none_or(|if_range| if_range == etag || if_range == last_modified)
+}
+
+/// Reads `bytes=start-end`, `bytes=start-` or `bytes=-suffix` into inclusive offsets. `Ok(None)` for anything else,
+/// several ranges included, which is then answered with the whole file, and `Err` when the range starts past the end.
+fn parse_range(range: &str, len: u64) -> Result<Option<(u64, u64)>, ()> {
+	let Some(spec) = range.trim().strip_prefix("bytes=") else {
+		return Ok(None);
+	};
+	let Some((start, end)) = spec.split_once('-') else {
+		return Ok(None);
+	};
+	if spec.contains(',') {
+		return Ok(None);
+	}
+
+	let (start, end) = match (start.trim(), end.trim()) {
+		("", suffix) => match suffix.parse::<u64>() {
+			Ok(0) => return Err(()),
+			Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+			Err(_) => return Ok(None)
+		},
+		(start, "") => match start.parse::<u64>() {
+			Ok(start) => (start, len.saturating_sub(1)),
+			Err(_) => return Ok(None)
+		},
+		(start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+			(Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+			_ => return Ok(None)
+		}
+	};
+
+	if len == 0 || start >= len {
+		return Err(());
+	}
+
+	Ok(Some((start, end)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ranges_are_clamped_to_the_file() {
+		assert_eq!(parse_range("bytes=0-3", 10), Ok(Some((0, 3))));
+		assert_eq!(parse_range("bytes=4-", 10), Ok(Some((4, 9))));
+		assert_eq!(parse_range("bytes=-4", 10), Ok(Some((6, 9))));
+		assert_eq!(parse_range("bytes=8-100", 10), Ok(Some((8, 9))));
+		assert_eq!(parse_range("bytes=10-", 10), Err(()));
+		assert_eq!(parse_range("bytes=0-1,4-5", 10), Ok(None));
+		assert_eq!(parse_range("items=0-1", 10), Ok(None));
+	}
+
+	#[test]
+	fn content_type_comes_from_magic_bytes() {
+		assert_eq!(sniff_content_type(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]), "image/png");
+		assert_eq!(sniff_content_type(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+		assert_eq!(sniff_content_type(b"<svg onload=alert(1)>"), "application/octet-stream");
+	}
+}
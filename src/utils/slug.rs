@@ -0,0 +1,106 @@
+/// Longest slug produced by `slugify`, leaves room for a `-N` suffix in the 120 character column.
+pub const MAX_SLUG_LENGTH: usize = 100;
+
+/// Symbols that carry meaning in product names, spelled out in Indonesian instead of being dropped.
+const WORDS: &[(char, &str)] = &[
+	('&', "dan"),
+	('+', "plus"),
+	('%', "persen"),
+	('@', "at")
+];
+
+/// Lowercase ASCII words joined by `-`, e.g. `Kopi & Teh Manis` becomes `kopi-dan-teh-manis` and `Café Olé` becomes
+/// `cafe-ole`. Accented letters lose their accent and other symbols separate words, a name without any letter or digit
+/// gives `kategori`.
+pub fn slugify(name: &str) -> String {
+	let mut slug = String::new();
+
+	for c in name.chars().flat_map(char::to_lowercase) {
+		if let Some((_, word)) = WORDS.iter().find(|(symbol, _)| *symbol == c) {
+			push_word(&mut slug, word);
+		} else if let Some(ascii) = fold(c) {
+			slug.push_str(ascii);
+		} else if c.is_ascii_alphanumeric() {
+			slug.push(c);
+		} else if !slug.ends_with('-') && !slug.is_empty() {
+			slug.push('-');
+		}
+	}
+
+	let mut slug = slug.trim_end_matches('-').to_string();
+
+	if slug.len() > MAX_SLUG_LENGTH {
+		slug.truncate(MAX_SLUG_LENGTH);
+		slug = slug.trim_end_matches('-').to_string();
+	}
+
+	if slug.is_empty() {
+		return "kategori".to_string();
+	}
+
+	slug
+}
+
+/// `base` when it is free, otherwise `base-2`, `base-3`, ... whichever comes first, `taken` holds the slugs in use.
+pub fn pick_slug(base: &str, taken: &[String]) -> String {
+	if !taken.iter().any(|slug| slug == base) {
+		return base.to_string();
+	}
+
+	(2..)
+		.map(|suffix| format!("{base}-{suffix}"))
+		.find(|slug| !taken.contains(slug))
+		.unwrap()
+}
+
+fn push_word(slug: &mut String, word: &str) {
+	if !slug.is_empty() && !slug.ends_with('-') {
+		slug.push('-');
+	}
+	slug.push_str(word);
+	slug.push('-');
+}
+
+/// ASCII spelling of lowercase Latin letters with diacritics, as found in loanwords like `café` or `crème`.
+fn fold(c: char) -> Option<&'static str> {
+	let ascii = match c {
+		'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' => "a",
+		'è' | 'é' | 'ê' | 'ë' | 'ē' => "e",
+		'ì' | 'í' | 'î' | 'ï' | 'ī' => "i",
+		'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' => "o",
+		'ù' | 'ú' | 'û' | 'ü' | 'ū' => "u",
+		'ý' | 'ÿ' => "y",
+		'ñ' => "n",
+		'ç' => "c",
+		'ß' => "ss",
+		'æ' => "ae",
+		'œ' => "oe",
+		_ => return None
+	};
+
+	Some(ascii)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn slugify_spells_out_symbols_and_folds_accents() {
+		assert_eq!(slugify("Minuman"), "minuman");
+		assert_eq!(slugify("  Kopi & Teh  Manis "), "kopi-dan-teh-manis");
+		assert_eq!(slugify("Diskon 50%"), "diskon-50-persen");
+		assert_eq!(slugify("Café Crème Brûlée"), "cafe-creme-brulee");
+		assert_eq!(slugify("Makanan/Ringan (Snack)"), "makanan-ringan-snack");
+		assert_eq!(slugify("!!!"), "kategori");
+		assert_eq!(slugify(&"a".repeat(150)).len(), MAX_SLUG_LENGTH);
+	}
+
+	#[test]
+	fn pick_slug_appends_the_first_free_suffix() {
+		let taken = ["kopi".to_string(), "kopi-2".to_string(), "kopi-4".to_string()];
+
+		assert_eq!(pick_slug("teh", &taken), "teh");
+		assert_eq!(pick_slug("kopi", &taken), "kopi-3");
+	}
+}
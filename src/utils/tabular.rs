@@ -0,0 +1,331 @@
+use std::{ collections::HashMap, io::Cursor };
+
+use axum::{
+	body::{ Body, Bytes },
+	extract::Multipart,
+	http::{ header, StatusCode },
+	response::{ IntoResponse, Response }
+};
+use calamine::{ open_workbook_from_rs, Data, Reader, Xlsx };
+use futures_util::{ Stream, StreamExt, TryStreamExt };
+use rust_xlsxwriter::Workbook;
+use serde::Deserialize;
+use serde_json::json;
+use utoipa::{ IntoParams, ToSchema };
+
+use crate::model::utils_model::{ FieldError, ImportReport, ImportRowError };
+use crate::repository::RepositoryError;
+
+/// Data rows an import may contain, larger files are split by the caller.
+pub const MAX_IMPORT_ROWS: usize = 5000;
+
+/// Rows fetched per round trip while streaming an export.
+pub const EXPORT_BATCH_SIZE: i64 = 500;
+
+#[derive(Deserialize, ToSchema, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TabularFormat {
+	#[default]
+	Csv,
+	Xlsx
+}
+
+impl TabularFormat {
+	/// Guessed from the extension of an uploaded file name.
+	pub fn from_file_name(file_name: &str) -> Option<Self> {
+		let extension = file_name.rsplit_once('.')?.1.to_ascii_lowercase();
+
+		match extension.as_str() {
+			"csv" => Some(Self::Csv),
+			"xlsx" => Some(Self::Xlsx),
+			_ => None
+		}
+	}
+
+	fn extension(self) -> &'static str {
+		match self {
+			Self::Csv => "csv",
+			Self::Xlsx => "xlsx"
+		}
+	}
+
+	fn content_type(self) -> &'static str {
+		match self {
+			Self::Csv => "text/csv; charset=utf-8",
+			Self::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+		}
+	}
+}
+
+/// Query string of the import routes.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+	/// Validates every row and reports what would happen without writing anything.
+	#[serde(default)]
+	pub dry_run: bool,
+	/// Defaults to the extension of the uploaded file name.
+	pub format: Option<TabularFormat>
+}
+
+/// Query string of the export routes.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+	/// Defaults to `csv`.
+	#[serde(default)]
+	pub format: TabularFormat
+}
+
+/// One data row of an imported sheet, keyed by the lowercased header of each column.
+pub struct Row {
+	/// Row number as shown by a spreadsheet, the header is row 1.
+	pub line: usize,
+	values: HashMap<String, String>
+}
+
+impl Row {
+	/// Trimmed value of the column, `None` when the column is missing or the cell is empty.
+	pub fn get(&self, column: &str) -> Option<&str> {
+		self.values.get(column).map(|value| value.trim()).filter(|value| !value.is_empty())
+	}
+}
+
+/// Format and contents of the `file` field of an import request, `format` overrides the extension of the file name.
+pub async fn read_upload(mut multipart: Multipart, format: Option<TabularFormat>) -> Result<(TabularFormat, Bytes), (StatusCode, String)> {
+	while let Some(field) = multipart.next_field().await.map_err(|e| error(StatusCode::BAD_REQUEST, e.body_text()))? {
+		if field.name() != Some("file") {
+			continue;
+		}
+
+		let format = format
+			.or_else(|| field.file_name().and_then(TabularFormat::from_file_name))
+			.ok_or_else(|| error(StatusCode::UNPROCESSABLE_ENTITY, "Format File Harus csv Atau xlsx.".to_string()))?;
+		let bytes = field.bytes().await.map_err(|e| error(StatusCode::BAD_REQUEST, e.body_text()))?;
+
+		return Ok((format, bytes));
+	}
+
+	Err(error(StatusCode::BAD_REQUEST, "Field file Wajib Diisi.".to_string()))
+}
+
+/// 422 listing the failed rows, otherwise 200 for a dry run and 201 once the rows were written.
+pub fn import_response(report: ImportReport) -> (StatusCode, String) {
+	let (status, message) = match (report.errors.is_empty(), report.dry_run) {
+		(false, _) => (StatusCode::UNPROCESSABLE_ENTITY, format!("{} Dari {} Baris Tidak Valid, Tidak Ada Data Yang Disimpan.", report.errors.len(), report.rows)),
+		(true, true) => (StatusCode::OK, format!("{} Baris Valid Dan Siap Diimport.", report.rows)),
+		(true, false) => (StatusCode::CREATED, format!("{} Baris Berhasil Diimport.", report.created))
+	};
+
+	(
+		status,
+		json!({ "success": report.errors.is_empty(), "message": message, "data": report }).to_string()
+	)
+}
+
+/// Rows of the first sheet below the header row, empty rows are skipped. A CSV may start with a byte order mark
+/// and use `;` instead of `,`, as spreadsheets set to an Indonesian locale save them.
+pub fn read_rows(format: TabularFormat, bytes: &[u8]) -> Result<Vec<Row>, String> {
+	let lines = match format {
+		TabularFormat::Csv => read_csv(bytes)?,
+		TabularFormat::Xlsx => read_xlsx(bytes)?
+	};
+
+	let mut lines = lines.into_iter().enumerate();
+	let Some((_, header)) = lines.next() else {
+		return Err("File Tidak Memiliki Baris Header.".to_string());
+	};
+	let header: Vec<String> = header.iter().map(|column| column.trim().to_lowercase()).collect();
+
+	let rows: Vec<Row> = lines
+		.filter(|(_, cells)| cells.iter().any(|cell| !cell.trim().is_empty()))
+		.map(|(index, cells)| Row {
+			line: index + 1,
+			values: header.iter().cloned().zip(cells).collect()
+		})
+		.collect();
+
+	if rows.len() > MAX_IMPORT_ROWS {
+		return Err(format!("File Berisi {} Baris, Maksimal {MAX_IMPORT_ROWS} Baris Per Import.", rows.len()));
+	}
+
+	Ok(rows)
+}
+
+fn read_csv(bytes: &[u8]) -> Result<Vec<Vec<String>>, String> {
+	let bytes = bytes.strip_prefix("\u{feff}".as_bytes()).unwrap_or(bytes);
+	let first_line = bytes.split(|byte| *byte == b'\n').next().unwrap_or_default();
+	let delimiter = if !first_line.contains(&b',') && first_line.contains(&b';') { b';' } else { b',' };
+
+	csv::ReaderBuilder::new()
+		.has_headers(false)
+		.flexible(true)
+		.delimiter(delimiter)
+		.from_reader(bytes)
+		.records()
+		.map(|record| {
+			record
+				.map(|record| record.iter().map(|cell| uncsv_cell(cell).to_string()).collect())
+				.map_err(|e| format!("File CSV Tidak Valid: {e}"))
+		})
+		.collect()
+}
+
+/// Undoes `csv_cell`.
+fn uncsv_cell(cell: &str) -> &str {
+	match cell.strip_prefix('\'') {
+		Some(value) if value.starts_with(FORMULA_PREFIXES) => value,
+		_ => cell
+	}
+}
+
+fn read_xlsx(bytes: &[u8]) -> Result<Vec<Vec<String>>, String> {
+	let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(bytes)).map_err(|e| format!("File XLSX Tidak Valid: {e}"))?;
+
+	let range = workbook.worksheet_range_at(0)
+		.ok_or_else(|| "File XLSX Tidak Memiliki Sheet.".to_string())?
+		.map_err(|e| format!("File XLSX Tidak Valid: {e}"))?;
+
+	Ok(range.rows().map(|cells| cells.iter().map(cell_text).collect()).collect())
+}
+
+/// Numbers typed into a spreadsheet come back as floats, whole ones are written without the trailing `.0`.
+fn cell_text(cell: &Data) -> String {
+	match cell {
+		Data::Empty => String::new(),
+		Data::Float(value) if value.fract() == 0.0 && value.abs() < 1e15 => format!("{}", *value as i64),
+		cell => cell.to_string()
+	}
+}
+
+/// Adds a failed rule of a row to `errors`, keeping one entry per row.
+pub fn push_row_error(errors: &mut Vec<ImportRowError>, line: usize, field: &str, message: impl Into<String>) {
+	let error = FieldError { field: field.to_string(), message: message.into() };
+
+	match errors.iter_mut().find(|row_error| row_error.row == line) {
+		Some(row_error) => row_error.errors.push(error),
+		None => errors.push(ImportRowError { row: line, errors: vec![error] })
+	}
+}
+
+/// Sends `batches` as a file download named after `file_stem` and today's date. A CSV is streamed batch by batch,
+/// an XLSX is built in memory first since the zip container is only complete once every row is written.
+pub async fn export<S>(format: TabularFormat, file_stem: &str, columns: &[&str], batches: S) -> Result<Response, (StatusCode, String)>
+where
+	S: Stream<Item = Result<Vec<Vec<String>>, RepositoryError>> + Send + 'static
+{
+	let file_name = format!("{file_stem}-{}.{}", time::OffsetDateTime::now_utc().date(), format.extension());
+	let headers = [
+		(header::CONTENT_TYPE, format.content_type().to_string()),
+		(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{file_name}\""))
+	];
+
+	match format {
+		TabularFormat::Csv => {
+			let header = csv_lines(&[columns.iter().map(|column| column.to_string()).collect()]);
+			let body = futures_util::stream::once(async move { Ok(header) })
+				.chain(batches.map_ok(|rows| csv_lines(&rows)));
+
+			Ok((headers, Body::from_stream(body)).into_response())
+		},
+		TabularFormat::Xlsx => {
+			let rows: Vec<Vec<String>> = batches.try_concat()
+				.await
+				.map_err(|e| error(e.status_code(), e.to_string()))?;
+
+			let bytes = xlsx(columns, &rows).map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+			Ok((headers, bytes).into_response())
+		}
+	}
+}
+
+fn csv_lines(rows: &[Vec<String>]) -> Bytes {
+	let mut writer = csv::Writer::from_writer(vec![]);
+	for row in rows {
+		// Writing into a Vec cannot fail.
+		writer.write_record(row.iter().map(|cell| csv_cell(cell))).unwrap();
+	}
+
+	Bytes::from(writer.into_inner().unwrap_or_default())
+}
+
+/// First characters a spreadsheet reads as the start of a formula.
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+/// Prefixes a cell a spreadsheet would run as a formula with `'`, which shows it as text. `read_csv` drops the
+/// prefix again so an export can be imported as is.
+fn csv_cell(cell: &str) -> String {
+	if cell.starts_with(FORMULA_PREFIXES) {
+		format!("'{cell}")
+	} else {
+		cell.to_string()
+	}
+}
+
+fn xlsx(columns: &[&str], rows: &[Vec<String>]) -> Result<Vec<u8>, rust_xlsxwriter::XlsxError> {
+	let mut workbook = Workbook::new();
+	let worksheet = workbook.add_worksheet();
+
+	for (column, name) in columns.iter().enumerate() {
+		worksheet.write_string(0, column as u16, *name)?;
+	}
+
+	// Every value is written as text so phone numbers keep their leading zero.
+	for (row, values) in rows.iter().enumerate() {
+		for (column, value) in values.iter().enumerate() {
+			worksheet.write_string(row as u32 + 1, column as u16, value)?;
+		}
+	}
+
+	workbook.save_to_buffer()
+}
+
+fn error(status: StatusCode, message: String) -> (StatusCode, String) {
+	(status, json!({ "success": false, "message": message }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn csv_rows_are_keyed_by_lowercased_header() {
+		let rows = read_rows(TabularFormat::Csv, "\u{feff}Name;Parent\nMinuman;\n;\nKopi; Minuman \n".as_bytes()).unwrap();
+
+		assert_eq!(rows.len(), 2);
+		assert_eq!((rows[0].line, rows[0].get("name"), rows[0].get("parent")), (2, Some("Minuman"), None));
+		assert_eq!((rows[1].line, rows[1].get("parent")), (4, Some("Minuman")));
+	}
+
+	#[test]
+	fn csv_cells_cannot_start_a_formula() {
+		let rows = [vec!["=HYPERLINK(\"http://contoh.test\")".to_string(), "+6281234567890".to_string(), "'kutip".to_string()]];
+		let bytes = csv_lines(&rows);
+		assert_eq!(bytes, "\"'=HYPERLINK(\"\"http://contoh.test\"\")\",'+6281234567890,'kutip\n");
+
+		let header = csv_lines(&[vec!["full_name".to_string(), "phone_number".to_string(), "note".to_string()]]);
+		let rows = read_rows(TabularFormat::Csv, &[header, bytes].concat()).unwrap();
+		assert_eq!(rows[0].get("full_name"), Some("=HYPERLINK(\"http://contoh.test\")"));
+		assert_eq!(rows[0].get("phone_number"), Some("+6281234567890"));
+		assert_eq!(rows[0].get("note"), Some("'kutip"));
+	}
+
+	#[test]
+	fn xlsx_round_trips_through_export() {
+		let bytes = xlsx(&["username", "phone_number"], &[vec!["budi".to_string(), "081234567890".to_string()]]).unwrap();
+		let rows = read_rows(TabularFormat::Xlsx, &bytes).unwrap();
+
+		assert_eq!(rows[0].get("username"), Some("budi"));
+		assert_eq!(rows[0].get("phone_number"), Some("081234567890"));
+		assert_eq!(cell_text(&Data::Float(12.0)), "12");
+	}
+
+	#[test]
+	fn format_follows_the_file_extension() {
+		assert_eq!(TabularFormat::from_file_name("kategori.XLSX"), Some(TabularFormat::Xlsx));
+		assert_eq!(TabularFormat::from_file_name("kategori.csv"), Some(TabularFormat::Csv));
+		assert_eq!(TabularFormat::from_file_name("kategori"), None);
+		assert!(read_rows(TabularFormat::Csv, b"").is_err());
+	}
+}
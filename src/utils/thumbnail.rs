@@ -0,0 +1,232 @@
+use std::io::Cursor;
+
+use axum::http::{ header, HeaderMap };
+use image::{ imageops::FilterType, DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult, Limits };
+use image::metadata::Orientation;
+
+/// Widest or tallest image decoded, larger ones are refused before their pixels are allocated.
+const MAX_DIMENSION: u32 = 8192;
+
+/// Quality of the lossy WebP variants of JPEG uploads, photos lose nothing visible at it.
+const WEBP_QUALITY: f32 = 80.0;
+
+/// One resized copy of an upload.
+pub struct Variant {
+	/// Longest side the copy fits in, smaller images are re-encoded without being enlarged.
+	pub size: u32,
+	pub ext: &'static str,
+	pub content_type: &'static str,
+	pub bytes: Vec<u8>
+}
+
+/// An upload decoded, turned upright and encoded again, which drops EXIF, GPS and every other metadata chunk.
+pub struct ProcessedImage {
+	/// Same format as the upload, at full size.
+	pub original: Vec<u8>,
+	pub variants: Vec<Variant>
+}
+
+/// Whether `process` can decode and encode the content type, other allowed types are stored as uploaded.
+pub fn processable(content_type: &str) -> bool {
+	format(content_type).is_some()
+}
+
+/// Decodes the upload, applies its EXIF orientation and encodes it again with one variant per size in the same
+/// format and one in WebP. JPEG uploads are photos and get lossy WebP at `WEBP_QUALITY`, PNG keeps every pixel.
+pub fn process(bytes: &[u8], content_type: &str, sizes: &[u32]) -> ImageResult<ProcessedImage> {
+	let format = format(content_type).ok_or_else(|| image::ImageError::Unsupported(image::error::ImageFormatHint::Unknown.into()))?;
+
+	let mut limits = Limits::default();
+	limits.max_image_width = Some(MAX_DIMENSION);
+	limits.max_image_height = Some(MAX_DIMENSION);
+
+	let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+	reader.limits(limits);
+	let mut decoder = reader.into_decoder()?;
+	// A broken EXIF block only loses the rotation, the pixels are still fine.
+	let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+	let mut image = DynamicImage::from_decoder(decoder)?;
+	image.apply_orientation(orientation);
+
+	let mut variants = vec![];
+	for &size in sizes {
+		let resized = if image.width() > size || image.height() > size { image.resize(size, size, FilterType::CatmullRom) } else { image.clone() };
+		let same_format = encode(&resized, format)?;
+
+		if format != ImageFormat::WebP {
+			let quality = (format == ImageFormat::Jpeg).then_some(WEBP_QUALITY);
+			variants.push(Variant { size, ext: "webp", content_type: "image/webp", bytes: encode_webp(&resized, quality) });
+		}
+
+		variants.push(Variant { size, ext: extension(format), content_type: content_type_of(format), bytes: same_format });
+	}
+
+	Ok(ProcessedImage { original: encode(&image, format)?, variants })
+}
+
+/// Storage key of a variant of the upload stored under `{prefix}/{file_name}`.
+pub fn variant_key(prefix: &str, file_name: &str, size: u32, ext: &str) -> String {
+	format!("{prefix}/variants/{file_name}/{size}.{ext}")
+}
+
+/// Prefix of every variant of an upload, to remove them with it.
+pub fn variants_prefix(prefix: &str, file_name: &str) -> String {
+	format!("{prefix}/variants/{file_name}/")
+}
+
+/// The smallest configured size at least as large as `requested`, `None` when only the original is large enough.
+pub fn pick_size(sizes: &[u32], requested: u32) -> Option<u32> {
+	sizes.iter().copied().filter(|size| *size >= requested).min()
+}
+
+/// Whether `Accept` lists `image/webp` without `q=0`. Wildcards do not count, old browsers send `*/*` too.
+pub fn accepts_webp(request_headers: &HeaderMap) -> bool {
+	request_headers.get_all(header::ACCEPT)
+	.iter()
+	.filter_map(|value| value.to_str().ok())
+	.flat_map(|value| value.split(','))
+	.any(|media_range| {
+		let mut parts = media_range.split(';').map(str::trim);
+		parts.next().is_some_and(|media_type| media_type.eq_ignore_ascii_case("image/webp"))
+			&& parts.all(|parameter| parameter.strip_prefix("q=").is_none_or(|q| q.parse::<f32>().is_ok_and(|q| q > 0.0)))
+	})
+}
+
+fn format(content_type: &str) -> Option<ImageFormat> {
+	match content_type {
+		"image/png" => Some(ImageFormat::Png),
+		"image/jpeg" => Some(ImageFormat::Jpeg),
+		"image/webp" => Some(ImageFormat::WebP),
+		_ => None
+	}
+}
+
+fn extension(format: ImageFormat) -> &'static str {
+	match format {
+		ImageFormat::Jpeg => "jpg",
+		ImageFormat::WebP => "webp",
+		_ => "png"
+	}
+}
+
+fn content_type_of(format: ImageFormat) -> &'static str {
+	match format {
+		ImageFormat::Jpeg => "image/jpeg",
+		ImageFormat::WebP => "image/webp",
+		_ => "image/png"
+	}
+}
+
+/// JPEG has no alpha channel and the WebP encoder only takes 8-bit pixels, both get converted first.
+fn encode(image: &DynamicImage, format: ImageFormat) -> ImageResult<Vec<u8>> {
+	let converted = match format {
+		ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()),
+		ImageFormat::WebP if image.color().has_alpha() => DynamicImage::ImageRgba8(image.to_rgba8()),
+		ImageFormat::WebP => DynamicImage::ImageRgb8(image.to_rgb8()),
+		_ => image.clone()
+	};
+
+	let mut bytes = Cursor::new(Vec::new());
+	converted.write_to(&mut bytes, format)?;
+
+	Ok(bytes.into_inner())
+}
+
+/// Lossy at `quality` when given, lossless otherwise. The `image` encoder only writes lossless WebP.
+fn encode_webp(image: &DynamicImage, quality: Option<f32>) -> Vec<u8> {
+	let converted = if image.color().has_alpha() { DynamicImage::ImageRgba8(image.to_rgba8()) } else { DynamicImage::ImageRgb8(image.to_rgb8()) };
+	let encoder = match &converted {
+		DynamicImage::ImageRgba8(pixels) => webp::Encoder::from_rgba(pixels, converted.width(), converted.height()),
+		_ => webp::Encoder::from_rgb(converted.as_bytes(), converted.width(), converted.height())
+	};
+
+	match quality {
+		Some(quality) => encoder.encode(quality).to_vec(),
+		None => encoder.encode_lossless().to_vec()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use image::{ Rgb, RgbImage };
+
+	use super::*;
+
+	/// A 4x2 JPEG whose EXIF says it must be rotated 90° clockwise, as phones store portrait photos.
+	fn rotated_jpeg() -> Vec<u8> {
+		let mut jpeg = Cursor::new(Vec::new());
+		DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 2, Rgb([200, 30, 30]))).write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+		let jpeg = jpeg.into_inner();
+
+		// APP1 with a big-endian TIFF header and a single IFD entry, Orientation (0x0112) = 6.
+		let tiff: &[u8] = &[b'M', b'M', 0, 42, 0, 0, 0, 8, 0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, 0, 0, 0, 0];
+		let mut app1 = vec![0xFF, 0xE1];
+		app1.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+		app1.extend_from_slice(b"Exif\0\0");
+		app1.extend_from_slice(tiff);
+
+		[&jpeg[..2], &app1, &jpeg[2..]].concat()
+	}
+
+	#[test]
+	fn uploads_are_turned_upright_and_lose_their_metadata() {
+		let upload = rotated_jpeg();
+		assert!(upload.windows(4).any(|window| window == b"Exif"));
+
+		let processed = process(&upload, "image/jpeg", &[1]).unwrap();
+		let original = image::load_from_memory(&processed.original).unwrap();
+
+		assert_eq!((original.width(), original.height()), (2, 4));
+		assert!(!processed.original.windows(4).any(|window| window == b"Exif"));
+		assert_eq!(processed.variants.iter().map(|variant| variant.size).max(), Some(1));
+	}
+
+	#[test]
+	fn photos_get_lossy_webp_variants_fitting_their_size() {
+		// Smooth shading with sensor-like noise, which lossless encoders cannot shrink much.
+		let mut noise = 0x2545_F491_u32;
+		let photo = RgbImage::from_fn(300, 150, |x, y| {
+			noise ^= noise << 13;
+			noise ^= noise >> 17;
+			noise ^= noise << 5;
+			let grain = (noise % 24) as u8;
+			Rgb([(x * 255 / 300) as u8 / 2 + grain, (y * 255 / 150) as u8 / 2 + grain, 90 + grain])
+		});
+		let mut jpeg = Cursor::new(Vec::new());
+		DynamicImage::ImageRgb8(photo).write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+
+		let processed = process(&jpeg.into_inner(), "image/jpeg", &[64, 1024]).unwrap();
+		let variant = |size: u32, ext: &str| processed.variants.iter().find(|variant| variant.size == size && variant.ext == ext).unwrap();
+		let dimensions = |size: u32, ext: &str| {
+			let image = image::load_from_memory(&variant(size, ext).bytes).unwrap();
+			(image.width(), image.height())
+		};
+
+		assert_eq!(dimensions(64, "jpg"), (64, 32));
+		assert_eq!(dimensions(1024, "jpg"), (300, 150));
+		for size in [64, 1024] {
+			let webp = variant(size, "webp");
+			// A `VP8 ` chunk holds lossy data, lossless would be `VP8L`.
+			assert_eq!(&webp.bytes[12..16], b"VP8 ");
+			assert_eq!(webp.content_type, "image/webp");
+		}
+		assert_eq!(dimensions(64, "webp"), (64, 32));
+		assert_eq!(dimensions(1024, "webp"), (300, 150));
+		assert!(variant(1024, "webp").bytes.len() < variant(1024, "jpg").bytes.len());
+	}
+
+	#[test]
+	fn webp_is_sent_only_to_clients_asking_for_it() {
+		let accept = |value: &'static str| HeaderMap::from_iter([(header::ACCEPT, value.parse().unwrap())]);
+
+		assert!(accepts_webp(&accept("image/avif,image/webp,*/*;q=0.8")));
+		assert!(accepts_webp(&accept("image/webp;q=0.5")));
+		assert!(!accepts_webp(&accept("image/webp;q=0")));
+		assert!(!accepts_webp(&accept("image/*,*/*")));
+		assert!(!accepts_webp(&HeaderMap::new()));
+
+		assert_eq!(pick_size(&[64, 256, 1024], 100), Some(256));
+		assert_eq!(pick_size(&[64, 256, 1024], 64), Some(64));
+		assert_eq!(pick_size(&[64, 256, 1024], 2000), None);
+	}
+}
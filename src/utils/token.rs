@@ -0,0 +1,29 @@
+use sha2::{ Digest, Sha256 };
+
+/// 32 random bytes from the operating system as hex, for one-time links that must not be guessable.
+pub fn random_token() -> String {
+	let mut bytes = [0u8; 32];
+	getrandom::getrandom(&mut bytes).expect("Failed to Read Random Bytes");
+
+	hex::encode(bytes)
+}
+
+/// Hex SHA-256 of a token, what gets stored so a leaked table cannot be used to activate accounts.
+pub fn token_hash(token: &str) -> String {
+	hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tokens_are_unique_and_hashed_to_hex() {
+		let (first, second) = (random_token(), random_token());
+
+		assert_eq!(first.len(), 64);
+		assert_ne!(first, second);
+		assert_eq!(token_hash(&first), token_hash(&format!(" {first}\n")));
+		assert_ne!(token_hash(&first), first);
+	}
+}
@@ -0,0 +1,227 @@
+use axum::{
+	body::Bytes,
+	extract::multipart::{ Field, MultipartError },
+	http::StatusCode
+};
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use sha2::{ Digest, Sha256 };
+
+use crate::model::file_model::{ FileObject, NewFileObject };
+use crate::state::DynFileObjectRepository;
+use crate::storage::{ DynStorage, StorageError };
+use crate::utils::serve_file::sniff_content_type;
+use crate::utils::thumbnail;
+
+/// Bytes read before the content type is checked, enough for every signature `sniff_content_type` knows.
+const SNIFF_LEN: usize = 16;
+
+/// Room for the multipart boundaries and part headers on top of the file itself.
+pub const FORM_OVERHEAD: usize = 16 * 1024;
+
+/// What one upload endpoint accepts.
+#[derive(Clone, Debug)]
+pub struct UploadPolicy {
+	/// Content types the first bytes of the file must match, whatever the extension or part header say.
+	pub allowed_types: Vec<String>,
+	pub max_bytes: usize,
+	/// Longest sides of the resized copies made of every upload, see `thumbnail::process`.
+	pub variants: Vec<u32>
+}
+
+impl UploadPolicy {
+	fn new(allowed_types: &[&str], max_bytes: usize, variants: &[u32]) -> Self {
+		Self { allowed_types: allowed_types.iter().map(|content_type| content_type.to_string()).collect(), max_bytes, variants: variants.to_vec() }
+	}
+
+	/// Overrides the defaults with `{prefix}_TYPES` and `{prefix}_VARIANTS`, comma separated lists, and `{prefix}_MAX_BYTES`.
+	fn with_env(self, prefix: &str) -> Self {
+		let allowed_types = std::env::var(format!("{prefix}_TYPES"))
+		.map(|types| types.split(',').map(|content_type| content_type.trim().to_string()).filter(|content_type| !content_type.is_empty()).collect())
+		.unwrap_or(self.allowed_types);
+		let max_bytes = std::env::var(format!("{prefix}_MAX_BYTES"))
+		.map(|bytes| bytes.parse().unwrap_or_else(|_| panic!("{prefix}_MAX_BYTES must be a number of bytes.")))
+		.unwrap_or(self.max_bytes);
+		let variants = std::env::var(format!("{prefix}_VARIANTS"))
+		.map(|sizes| sizes.split(',').map(str::trim).filter(|size| !size.is_empty()).map(|size| size.parse().unwrap_or_else(|_| panic!("{prefix}_VARIANTS must list sizes in pixels."))).collect())
+		.unwrap_or(self.variants);
+
+		Self { allowed_types, max_bytes, variants }
+	}
+}
+
+/// Upload rules of each endpoint, shared through `AppState`.
+#[derive(Clone, Debug)]
+pub struct UploadConfig {
+	pub user_image: UploadPolicy,
+	pub category_icon: UploadPolicy
+}
+
+impl Default for UploadConfig {
+	fn default() -> Self {
+		Self {
+			user_image: UploadPolicy::new(&["image/png", "image/jpeg", "image/webp"], 2 * 1024 * 1024, &[64, 256, 1024]),
+			category_icon: UploadPolicy::new(&["image/png", "image/jpeg", "image/webp"], 512 * 1024, &[64, 256])
+		}
+	}
+}
+
+impl UploadConfig {
+	/// Defaults overridden by the `USER_IMAGE_*` and `CATEGORY_ICON_*` variables read by `UploadPolicy::with_env`.
+	pub fn from_env() -> Self {
+		let defaults = Self::default();
+
+		Self {
+			user_image: defaults.user_image.with_env("USER_IMAGE"),
+			category_icon: defaults.category_icon.with_env("CATEGORY_ICON")
+		}
+	}
+}
+
+/// Extension stored files get for a content type `sniff_content_type` recognises.
+pub fn extension(content_type: &str) -> &'static str {
+	match content_type {
+		"image/png" => "png",
+		"image/jpeg" => "jpg",
+		"image/gif" => "gif",
+		"image/webp" => "webp",
+		"image/bmp" => "bmp",
+		"image/x-icon" => "ico",
+		"image/avif" => "avif",
+		_ => "bin"
+	}
+}
+
+/// Reads the file part and stores it under `{prefix}/{sha256}.{extension}`, returning that name with its extension.
+/// The size is checked on every chunk and the content type once the first bytes arrived, so a refused file is never
+/// read to the end. Accepted files are at most `max_bytes`, they are held in memory until the storage has them.
+/// Images `thumbnail` can decode are stored upright and without metadata, next to their variants, and the hash is
+/// taken of what is stored. Content stored before is not written again, the upload gets the same name.
+pub async fn store(
+	mut field: Field<'_>,
+	storage: &DynStorage,
+	file_object_repository: &DynFileObjectRepository,
+	prefix: &str,
+	policy: &UploadPolicy
+) -> Result<(String, &'static str), (StatusCode, String)> {
+	let mut body = Vec::new();
+	let mut checked = None;
+
+	while let Some(chunk) = field.chunk().await.map_err(|e| multipart_error(e, policy))? {
+		body.extend_from_slice(&chunk);
+		if body.len() > policy.max_bytes {
+			return Err(too_large(policy));
+		}
+
+		if checked.is_none() && body.len() >= SNIFF_LEN {
+			checked = Some(check_type(&body, policy)?);
+		}
+	}
+
+	let content_type = match checked {
+		Some(content_type) => content_type,
+		None => check_type(&body, policy)?
+	};
+
+	let (original, variants) = if thumbnail::processable(content_type) {
+		let sizes = policy.variants.clone();
+		// Decoding and resizing take long enough to stall other requests on this worker.
+		let processed = tokio::task::spawn_blocking(move || thumbnail::process(&body, content_type, &sizes))
+		.await
+		.map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+		.map_err(|_| error(StatusCode::UNPROCESSABLE_ENTITY, "Gambar Rusak Atau Terlalu Besar Untuk Diproses.".to_string()))?;
+
+		(processed.original, processed.variants)
+	} else {
+		(body, vec![])
+	};
+
+	let ext = extension(content_type);
+	let sha256 = hex::encode(Sha256::digest(&original));
+	let file_name = format!("{sha256}.{ext}");
+	let key = format!("{prefix}/{file_name}");
+
+	// Registered before the blobs are written, a collection of the same content holds the row until its blobs are
+	// gone and they are written again below.
+	file_object_repository.register(&NewFileObject {
+		prefix: prefix.to_string(),
+		file_name: file_name.clone(),
+		sha256,
+		content_type: content_type.to_string(),
+		size: original.len() as i64
+	})
+	.await
+	.map_err(|e| error(e.status_code(), e.to_string()))?;
+
+	if storage.stat(&key).await.is_ok() {
+		return Ok((file_name, ext));
+	}
+
+	for variant in variants {
+		storage.put(&thumbnail::variant_key(prefix, &file_name, variant.size, variant.ext), Bytes::from(variant.bytes), variant.content_type)
+		.await
+		.map_err(|e| error(e.status_code(), e.to_string()))?;
+	}
+
+	// Stored last, an original in the storage always has its variants.
+	storage.put(&key, Bytes::from(original), content_type)
+	.await
+	.map_err(|e| error(e.status_code(), e.to_string()))?;
+
+	Ok((file_name, ext))
+}
+
+/// Deletes the upload stored under `{prefix}/{file_name}` and its variants. The original goes first, so an
+/// interrupted removal never leaves an original without its variants.
+async fn delete_blobs(storage: &DynStorage, prefix: &str, file_name: &str) -> Result<(), StorageError> {
+	storage.delete(&format!("{prefix}/{file_name}")).await?;
+
+	for variant in storage.list(&thumbnail::variants_prefix(prefix, file_name)).await? {
+		storage.delete(&variant.key).await?;
+	}
+
+	Ok(())
+}
+
+/// `delete_blobs` for the file object repository.
+pub fn blob_remover(storage: &DynStorage) -> impl Fn(&FileObject) -> BoxFuture<'static, Result<(), StorageError>> + Send + Sync {
+	let storage = storage.clone();
+
+	move |object| {
+		let (storage, prefix, file_name) = (storage.clone(), object.prefix.clone(), object.file_name.clone());
+		Box::pin(async move { delete_blobs(&storage, &prefix, &file_name).await })
+	}
+}
+
+/// Content type of the first bytes, refused unless the policy allows it.
+fn check_type(head: &[u8], policy: &UploadPolicy) -> Result<&'static str, (StatusCode, String)> {
+	if head.is_empty() {
+		return Err(error(StatusCode::BAD_REQUEST, "File Kosong.".to_string()));
+	}
+
+	let content_type = sniff_content_type(head);
+	if !policy.allowed_types.iter().any(|allowed| allowed == content_type) {
+		return Err(error(
+			StatusCode::UNSUPPORTED_MEDIA_TYPE,
+			format!("Tipe File Tidak Diizinkan, Gunakan: {}.", policy.allowed_types.join(", "))
+		));
+	}
+
+	Ok(content_type)
+}
+
+/// The body limit of the route surfaces as a multipart error, it gets the same answer as a file over the policy.
+pub fn multipart_error(e: MultipartError, policy: &UploadPolicy) -> (StatusCode, String) {
+	match e.status() {
+		StatusCode::PAYLOAD_TOO_LARGE => too_large(policy),
+		status => error(status, e.body_text())
+	}
+}
+
+fn too_large(policy: &UploadPolicy) -> (StatusCode, String) {
+	error(StatusCode::PAYLOAD_TOO_LARGE, format!("Ukuran File Melebihi Batas {} KB.", policy.max_bytes / 1024))
+}
+
+pub fn error(status: StatusCode, message: String) -> (StatusCode, String) {
+	(status, json!({ "success": false, "message": message }).to_string())
+}
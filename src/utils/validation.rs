@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+
+use validator::ValidationError;
+
+use crate::model::user_model::USER_LANGUAGES;
+use crate::utils::phone;
+
+fn error(code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+	ValidationError::new(code).with_message(message.into())
+}
+
+/// Rejects values made only of whitespace, `length(min = 1)` alone lets `"  "` through.
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+	if value.trim().is_empty() {
+		return Err(error("blank", "Tidak boleh kosong."));
+	}
+
+	Ok(())
+}
+
+/// Letters, digits, `.` and `_` only, so usernames stay usable in URLs and filters.
+pub fn username(value: &str) -> Result<(), ValidationError> {
+	if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_') {
+		return Err(error("username", "Hanya boleh berisi huruf, angka, titik dan garis bawah."));
+	}
+
+	Ok(())
+}
+
+/// An Indonesian mobile number in any format `phone::normalize` reads, handlers store the normalized form.
+pub fn phone_number(value: &str) -> Result<(), ValidationError> {
+	phone::normalize(value).map_err(|e| error("phone_number", e.to_string()))?;
+
+	Ok(())
+}
+
+/// A bare stored file name, anything that could point outside the upload directory is rejected.
+pub fn file_name(value: &str) -> Result<(), ValidationError> {
+	if value.is_empty() || value.len() > 255 || value.contains(['/', '\\']) || value.starts_with('.') {
+		return Err(error("file_name", "Nama file tidak valid."));
+	}
+
+	Ok(())
+}
+
+/// 2 to 50 lowercase letters, digits or `_`, whether the role exists is checked against the `role` table.
+pub fn role(value: &str) -> Result<(), ValidationError> {
+	if !(2..=50).contains(&value.len()) || !value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+		return Err(error("role", "Role hanya boleh berisi 2 sampai 50 huruf kecil, angka dan garis bawah."));
+	}
+
+	Ok(())
+}
+
+pub fn language(value: &str) -> Result<(), ValidationError> {
+	if !USER_LANGUAGES.contains(&value) {
+		return Err(error("language", format!("Bahasa harus salah satu dari: {}.", USER_LANGUAGES.join(", "))));
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn not_blank_rejects_whitespace() {
+		assert!(not_blank("Minuman").is_ok());
+		assert!(not_blank(" \t").is_err());
+	}
+
+	#[test]
+	fn phone_number_accepts_local_and_international_formats() {
+		for valid in ["081234567890", "+6281234567890", "0812-3456-7890", "0812 3456 7890", "62 899 1234 5678"] {
+			assert!(phone_number(valid).is_ok(), "{valid}");
+		}
+
+		for invalid in ["0812", "08123456789012345", "0812abc67890", "++6281234567890", "0800 1234 5678", "021 5550 1234"] {
+			assert!(phone_number(invalid).is_err(), "{invalid}");
+		}
+	}
+
+	#[test]
+	fn role_and_username_follow_whitelists() {
+		assert!(role("cashier").is_ok());
+		assert!(role("Super User").is_err());
+		assert!(language("en").is_ok());
+		assert!(language("jv").is_err());
+		assert!(username("budi.santoso_1").is_ok());
+		assert!(username("budi santoso").is_err());
+	}
+
+	#[test]
+	fn file_name_rejects_paths() {
+		assert!(file_name("1760000000_kopi.png").is_ok());
+
+		for invalid in ["", "../secret.env", "icons/kopi.png", "..\\kopi.png", ".env"] {
+			assert!(file_name(invalid).is_err(), "{invalid}");
+		}
+	}
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use std::{ net::SocketAddr, sync::Arc };
+
+use axum::{ extract::connect_info::MockConnectInfo, http::{ Method, StatusCode } };
+use serde_json::json;
+use sqlx::postgres::PgPool;
+
+use common::{ admin_token, app, login, seed_category, seed_user, send, send_with };
+use rust_axum_pos_backend::{ build_app, AppState };
+use rust_axum_pos_backend::utils::audit::TrustedProxies;
+
+#[sqlx::test]
+async fn role_change_is_recorded_with_actor_diff_and_ip(pg_pool: PgPool) {
+	// Requests come through the proxy at 10.0.0.1, which appends the address it got them from.
+	let state = AppState {
+		trusted_proxies: Arc::new(TrustedProxies::new(vec!["10.0.0.1".parse().unwrap()])),
+		..AppState::postgres(pg_pool.clone())
+	};
+	let app = build_app(state).layer(MockConnectInfo(SocketAddr::from(([10, 0, 0, 1], 41000))));
+	let token = admin_token(&app, &pg_pool).await;
+	let id = seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+
+	let (status, _, body) = send_with(
+		&app,
+		Method::PATCH,
+		&format!("/api/user/{id}"),
+		Some(&token),
+		&[("If-Match", "*"), ("X-Forwarded-For", "198.51.100.9, 203.0.113.7")],
+		Some(json!({ "role": "admin" }))
+	).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let (status, body) = send(&app, Method::GET, "/api/audit?filter=entity_type:eq:user&filter=action:eq:update", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let event = &body["data"][0];
+	assert_eq!(body["paginate"]["count"], 1);
+	assert_eq!(event["actor_username"], "admin");
+	assert_eq!(event["entity_id"], id.to_string());
+	assert_eq!(event["ip_address"], "203.0.113.7");
+	assert_eq!(event["changes"], json!({ "role": { "before": "cashier", "after": "admin" } }));
+}
+
+#[sqlx::test]
+async fn forwarded_headers_of_untrusted_peers_are_ignored(pg_pool: PgPool) {
+	let app = app(&pg_pool).layer(MockConnectInfo(SocketAddr::from(([192, 0, 2, 4], 41000))));
+	let token = admin_token(&app, &pg_pool).await;
+	let id = seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+
+	let (status, _, body) = send_with(
+		&app,
+		Method::PATCH,
+		&format!("/api/user/{id}"),
+		Some(&token),
+		&[("If-Match", "*"), ("X-Forwarded-For", "203.0.113.7"), ("X-Real-IP", "203.0.113.7")],
+		Some(json!({ "full_name": "Budi Santoso" }))
+	).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let (_, body) = send(&app, Method::GET, "/api/audit?filter=entity_type:eq:user&filter=action:eq:update", Some(&token), None).await;
+	assert_eq!(body["data"][0]["ip_address"], "192.0.2.4");
+}
+
+#[sqlx::test]
+async fn category_lifecycle_and_password_change_are_recorded(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Minuman" }))).await;
+	let id = seed_category(&pg_pool, "Snack").await;
+	send_with(&app, Method::PUT, &format!("/api/category/{id}"), Some(&token), &[("If-Match", "*")], Some(json!({ "name": "Makanan Ringan" }))).await;
+	send_with(&app, Method::DELETE, &format!("/api/category/{id}"), Some(&token), &[("If-Match", "*")], None).await;
+	send(&app, Method::POST, &format!("/api/category/{id}/restore"), Some(&token), None).await;
+	send(
+		&app,
+		Method::POST,
+		"/api/auth/change-password",
+		Some(&token),
+		Some(json!({ "username": "admin", "old_password": "admin12345", "new_password": "admin67890" }))
+	).await;
+
+	let (status, body) = send(&app, Method::GET, "/api/audit?sort_by=id", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let actions: Vec<(&str, &str)> = body["data"].as_array().unwrap().iter()
+		.map(|event| (event["action"].as_str().unwrap(), event["entity_type"].as_str().unwrap()))
+		.collect();
+	assert_eq!(actions, [
+		("create", "category"),
+		("update", "category"),
+		("delete", "category"),
+		("restore", "category"),
+		("change_password", "user")
+	]);
+
+	let data = body["data"].as_array().unwrap();
+	assert_eq!(data[1]["changes"]["name"], json!({ "before": "Snack", "after": "Makanan Ringan" }));
+	assert_eq!(data[4]["changes"]["password"]["before"], "[REDACTED]");
+	assert!(!data[4]["changes"].to_string().contains("$2"));
+}
+
+#[sqlx::test]
+async fn failed_mutation_is_not_recorded(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, _, _) = send_with(&app, Method::DELETE, "/api/category/42", Some(&token), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+
+	let (_, body) = send(&app, Method::GET, "/api/audit", Some(&token), None).await;
+	assert_eq!(body["paginate"]["count"], 0);
+}
+
+#[sqlx::test]
+async fn audit_is_admin_only(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	seed_user(&pg_pool, "kasir01", "rahasia123", "cashier").await;
+	let token = login(&app, "kasir01", "rahasia123").await;
+
+	let (status, _) = send(&app, Method::GET, "/api/audit", Some(&token), None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+
+	let (status, _) = send(&app, Method::GET, "/api/audit", None, None).await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use axum::http::{ Method, StatusCode };
+use base64::{ engine::general_purpose::URL_SAFE_NO_PAD, Engine };
+use serde_json::json;
+use sqlx::postgres::PgPool;
+
+use common::{ admin_token, app, login, seed_user, send, ADMIN_PASSWORD, ADMIN_USERNAME };
+
+#[sqlx::test]
+async fn login_returns_token_for_valid_credentials(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	seed_user(&pg_pool, "kasir", "rahasia123", "cashier").await;
+
+	let (status, body) = send(
+		&app,
+		Method::POST,
+		"/api/auth/login",
+		None,
+		Some(json!({ "username": "kasir", "password": "rahasia123" }))
+	).await;
+
+	assert_eq!(status, StatusCode::ACCEPTED);
+	assert_eq!(body["success"], true);
+	assert_eq!(body["data"]["username"], "kasir");
+	assert!(body["data"].get("password").is_none());
+
+	// The claims are readable by anyone holding the token, they must not carry more than the guards need.
+	let payload = body["token"].as_str().unwrap().split('.').nth(1).unwrap();
+	let claims: serde_json::Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+	let mut fields: Vec<&str> = claims.as_object().unwrap().keys().map(String::as_str).collect();
+	fields.sort();
+	assert_eq!(fields, ["exp", "id", "name", "role"]);
+	assert_eq!((claims["name"].as_str(), claims["role"].as_str()), (Some("kasir"), Some("cashier")));
+}
+
+#[sqlx::test]
+async fn login_rejects_wrong_password(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	seed_user(&pg_pool, "kasir", "rahasia123", "cashier").await;
+
+	let (status, body) = send(
+		&app,
+		Method::POST,
+		"/api/auth/login",
+		None,
+		Some(json!({ "username": "kasir", "password": "salah" }))
+	).await;
+
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+	assert_eq!(body["success"], false);
+}
+
+#[sqlx::test]
+async fn login_rejects_unknown_username(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+
+	let (status, _) = send(
+		&app,
+		Method::POST,
+		"/api/auth/login",
+		None,
+		Some(json!({ "username": "tidak-ada", "password": "rahasia123" }))
+	).await;
+
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn guarded_route_requires_bearer_token(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+
+	let (status, body) = send(&app, Method::GET, "/api/category", None, None).await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+	assert_eq!(body["success"], false);
+
+	let (status, _) = send(&app, Method::GET, "/api/category", Some("not-a-jwt"), None).await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn authenticated_echoes_user_and_refreshes_token(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, body) = send(&app, Method::POST, "/api/auth/authenticated", Some(&token), None).await;
+
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(body["data"]["username"], ADMIN_USERNAME);
+	assert!(body["data"].get("password").is_none());
+	assert!(body["token"].is_string());
+}
+
+#[sqlx::test]
+async fn change_password_allows_login_with_new_password(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, _) = send(
+		&app,
+		Method::POST,
+		"/api/auth/change-password",
+		Some(&token),
+		Some(json!({ "username": ADMIN_USERNAME, "old_password": "salah", "new_password": "baru12345" }))
+	).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+
+	let (status, _) = send(
+		&app,
+		Method::POST,
+		"/api/auth/change-password",
+		Some(&token),
+		Some(json!({ "username": ADMIN_USERNAME, "old_password": ADMIN_PASSWORD, "new_password": "baru12345" }))
+	).await;
+	assert_eq!(status, StatusCode::OK);
+
+	login(&app, ADMIN_USERNAME, "baru12345").await;
+}
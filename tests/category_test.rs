@@ -0,0 +1,601 @@
+mod common;
+
+use axum::http::{ Method, StatusCode };
+use serde_json::json;
+use sqlx::postgres::PgPool;
+
+use common::{ admin_token, app, download, login, seed_category, seed_user, send, send_raw, send_with, upload };
+
+#[sqlx::test]
+async fn create_then_find_many_orders_by_name(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	for name in ["Snack", "Minuman", "Makanan"] {
+		let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": name }))).await;
+		assert_eq!(status, StatusCode::CREATED, "{body}");
+	}
+
+	let (status, body) = send(&app, Method::GET, "/api/category", Some(&token), None).await;
+
+	assert_eq!(status, StatusCode::OK);
+	let names: Vec<&str> = body["data"].as_array().unwrap().iter().map(|category| category["name"].as_str().unwrap()).collect();
+	assert_eq!(names, ["Makanan", "Minuman", "Snack"]);
+}
+
+#[sqlx::test]
+async fn update_and_delete_category(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let id = seed_category(&pg_pool, "Minuman").await;
+
+	let (status, _, _) = send_with(&app, Method::PUT, &format!("/api/category/{id}"), Some(&token), &[("If-Match", "\"1\"")], Some(json!({ "name": "Minuman Dingin" }))).await;
+	assert_eq!(status, StatusCode::OK);
+
+	// PUT replaces the whole category, the name cannot be left out.
+	let (status, _, body) = send_with(&app, Method::PUT, &format!("/api/category/{id}"), Some(&token), &[("If-Match", "*")], Some(json!({}))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+	let name: String = sqlx::query_scalar("SELECT name FROM category WHERE id = $1").bind(id).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(name, "Minuman Dingin");
+
+	let (status, _, _) = send_with(&app, Method::DELETE, &format!("/api/category/{id}"), Some(&token), &[("If-Match", "\"2\"")], None).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let deleted: bool = sqlx::query_scalar("SELECT deleted_at IS NOT NULL FROM category WHERE id = $1").bind(id).fetch_one(&pg_pool).await.unwrap();
+	assert!(deleted);
+
+	let (_, body) = send(&app, Method::GET, "/api/category", Some(&token), None).await;
+	assert_eq!(body["data"].as_array().unwrap().len(), 0);
+}
+
+#[sqlx::test]
+async fn search_paginate_filters_by_term_and_pages(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	for index in 1..=12 {
+		seed_category(&pg_pool, &format!("Minuman {index:02}")).await;
+	}
+	seed_category(&pg_pool, "Snack").await;
+
+	let (status, body) = send(
+		&app,
+		Method::POST,
+		"/api/category/search-paginate",
+		Some(&token),
+		Some(json!({ "term": "minuman", "page": 1 }))
+	).await;
+
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(body["data"].as_array().unwrap().len(), 10);
+	assert_eq!(body["paginate"]["per_page"], 10);
+	assert_eq!(body["paginate"]["current_page"], 1);
+	assert_eq!(body["paginate"]["count"], 12);
+	assert_eq!(body["paginate"]["total_page"], 2);
+
+	let (_, body) = send(
+		&app,
+		Method::POST,
+		"/api/category/search-paginate",
+		Some(&token),
+		Some(json!({ "term": "minuman", "page": 2 }))
+	).await;
+
+	assert_eq!(body["data"].as_array().unwrap().len(), 2);
+	assert_eq!(body["paginate"]["current_page"], 2);
+}
+
+#[sqlx::test]
+async fn search_paginate_sorts_by_whitelisted_field(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	for name in ["Kopi", "Air Mineral", "Teh", "Jus"] {
+		seed_category(&pg_pool, name).await;
+	}
+
+	let (status, body) = send(
+		&app,
+		Method::POST,
+		"/api/category/search-paginate",
+		Some(&token),
+		Some(json!({ "term": "", "page": 1, "per_page": 3, "sort_by": "name", "sort_dir": "desc" }))
+	).await;
+
+	assert_eq!(status, StatusCode::OK);
+	let names: Vec<&str> = body["data"].as_array().unwrap().iter().map(|category| category["name"].as_str().unwrap()).collect();
+	assert_eq!(names, ["Teh", "Kopi", "Jus"]);
+	assert_eq!(body["paginate"]["total_page"], 2);
+
+	let (status, _) = send(
+		&app,
+		Method::POST,
+		"/api/category/search-paginate",
+		Some(&token),
+		Some(json!({ "term": "", "page": 1, "sort_by": "name; DROP TABLE category" }))
+	).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+
+	let (status, _) = send(
+		&app,
+		Method::POST,
+		"/api/category/search-paginate",
+		Some(&token),
+		Some(json!({ "term": "", "page": 1, "per_page": 1000 }))
+	).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn search_paginate_follows_keyset_cursor(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	for name in ["Kopi", "Air Mineral", "Teh", "Jus", "Susu"] {
+		seed_category(&pg_pool, name).await;
+	}
+
+	let mut names = vec![];
+	let mut cursor = serde_json::Value::Null;
+
+	loop {
+		let (status, body) = send(
+			&app,
+			Method::POST,
+			"/api/category/search-paginate",
+			Some(&token),
+			Some(json!({ "per_page": 2, "sort_by": "name", "cursor": cursor }))
+		).await;
+		assert_eq!(status, StatusCode::OK, "{body}");
+
+		names.extend(body["data"].as_array().unwrap().iter().map(|category| category["name"].as_str().unwrap().to_string()));
+		cursor = body["paginate"]["next_cursor"].clone();
+
+		if cursor.is_null() {
+			break;
+		}
+	}
+
+	assert_eq!(names, ["Air Mineral", "Jus", "Kopi", "Susu", "Teh"]);
+}
+
+#[sqlx::test]
+async fn search_paginate_applies_filters_to_data_and_count(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let old = seed_category(&pg_pool, "Rokok").await;
+	sqlx::query("UPDATE category SET created_at = '2025-06-01T00:00:00Z' WHERE id = $1").bind(old).execute(&pg_pool).await.unwrap();
+	seed_category(&pg_pool, "Kopi").await;
+	seed_category(&pg_pool, "Teh").await;
+
+	let (status, body) = send(
+		&app,
+		Method::POST,
+		"/api/category/search-paginate",
+		Some(&token),
+		Some(json!({ "filter": ["created_at:gte:2026-01-01", "name:in:Kopi,Rokok"] }))
+	).await;
+
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(body["paginate"]["count"], 1);
+	assert_eq!(body["data"][0]["name"], "Kopi");
+
+	let (status, _) = send(&app, Method::GET, "/api/category/search-paginate?filter=name:like:o&sort_by=name", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn create_rejects_blank_name_with_field_errors(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "   " }))).await;
+
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+	assert_eq!(body["success"], false);
+	assert_eq!(body["errors"][0]["field"], "name");
+
+	let count: i64 = sqlx::query_scalar("SELECT COUNT(id) FROM category").fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(count, 0);
+}
+
+#[sqlx::test]
+async fn malformed_json_and_path_use_error_envelope(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, body) = send_raw(&app, Method::POST, "/api/category", Some(&token), "{ \"name\": ").await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+	assert_eq!(body["success"], false);
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": 12 }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+	assert_eq!(body["success"], false);
+
+	let (status, body) = send(&app, Method::DELETE, "/api/category/abc", Some(&token), None).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+	assert_eq!(body["success"], false);
+}
+
+#[sqlx::test]
+async fn writes_require_the_current_etag(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let id = seed_category(&pg_pool, "Minuman").await;
+	let uri = format!("/api/category/{id}");
+
+	let (status, headers, body) = send_with(&app, Method::GET, &uri, Some(&token), &[], None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(body["data"]["version"], 1);
+	let etag = headers["etag"].to_str().unwrap().to_string();
+
+	let (status, _) = send(&app, Method::PATCH, &uri, Some(&token), Some(json!({ "name": "Snack" }))).await;
+	assert_eq!(status, StatusCode::PRECONDITION_REQUIRED);
+
+	let (status, headers, _) = send_with(&app, Method::PATCH, &uri, Some(&token), &[("If-Match", &etag)], Some(json!({ "name": "Snack" }))).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(headers["etag"], "\"2\"");
+
+	// A second editor still holding the first ETag loses instead of overwriting.
+	let (status, _, body) = send_with(&app, Method::PUT, &uri, Some(&token), &[("If-Match", &etag)], Some(json!({ "name": "Makanan" }))).await;
+	assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+	assert_eq!(body["success"], false);
+
+	let (status, _, _) = send_with(&app, Method::DELETE, &uri, Some(&token), &[("If-Match", &etag)], None).await;
+	assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+	let name: String = sqlx::query_scalar("SELECT name FROM category WHERE id = $1").bind(id).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(name, "Snack");
+}
+
+#[sqlx::test]
+async fn deleted_categories_are_listed_for_admins_and_restorable(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	seed_user(&pg_pool, "kasir", "rahasia123", "cashier").await;
+	let cashier_token = login(&app, "kasir", "rahasia123").await;
+	let id = seed_category(&pg_pool, "Minuman").await;
+	seed_category(&pg_pool, "Snack").await;
+
+	let (status, _, _) = send_with(&app, Method::DELETE, &format!("/api/category/{id}"), Some(&token), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let (_, body) = send(&app, Method::POST, "/api/category/search-paginate", Some(&token), Some(json!({}))).await;
+	assert_eq!(body["paginate"]["count"], 1);
+
+	let (_, body) = send(&app, Method::POST, "/api/category/search-paginate", Some(&token), Some(json!({ "include_deleted": true }))).await;
+	assert_eq!(body["paginate"]["count"], 2);
+
+	let (status, _) = send(&app, Method::GET, "/api/category?include_deleted=true", Some(&cashier_token), None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+
+	let (status, _) = send(&app, Method::POST, &format!("/api/category/{id}/restore"), Some(&cashier_token), None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+
+	let (status, headers, _) = send_with(&app, Method::POST, &format!("/api/category/{id}/restore"), Some(&token), &[], None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(headers["etag"], "\"3\"");
+
+	let (_, body) = send(&app, Method::GET, "/api/category", Some(&token), None).await;
+	assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+	let (status, _) = send(&app, Method::POST, &format!("/api/category/{id}/restore"), Some(&token), None).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn sub_category_is_restored_after_its_parent(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let minuman = seed_category(&pg_pool, "Minuman").await;
+	let kopi = seed_category(&pg_pool, "Kopi").await;
+	sqlx::query("UPDATE category SET parent_id = $1 WHERE id = $2").bind(minuman).bind(kopi).execute(&pg_pool).await.unwrap();
+
+	for id in [kopi, minuman] {
+		let (status, _, body) = send_with(&app, Method::DELETE, &format!("/api/category/{id}"), Some(&token), &[("If-Match", "*")], None).await;
+		assert_eq!(status, StatusCode::OK, "{body}");
+	}
+
+	let (status, body) = send(&app, Method::POST, &format!("/api/category/{kopi}/restore"), Some(&token), None).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+	for id in [minuman, kopi] {
+		let (status, body) = send(&app, Method::POST, &format!("/api/category/{id}/restore"), Some(&token), None).await;
+		assert_eq!(status, StatusCode::OK, "{body}");
+	}
+
+	let (_, body) = send(&app, Method::GET, "/api/category/tree", Some(&token), None).await;
+	assert_eq!(body["data"].as_array().unwrap().len(), 1);
+	assert_eq!(body["data"][0]["children"][0]["name"], "Kopi");
+}
+
+#[sqlx::test]
+async fn nested_categories_form_a_tree_with_breadcrumbs(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let minuman = seed_category(&pg_pool, "Minuman").await;
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Kopi", "parent_id": minuman }))).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+	let kopi: i32 = sqlx::query_scalar("SELECT id FROM category WHERE name = 'Kopi'").fetch_one(&pg_pool).await.unwrap();
+	send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Kemasan Botol", "parent_id": kopi }))).await;
+	seed_category(&pg_pool, "Snack").await;
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Teh", "parent_id": 999 }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+	let (status, body) = send(&app, Method::GET, "/api/category/tree", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(body["data"][0]["name"], "Minuman");
+	assert_eq!(body["data"][0]["children"][0]["children"][0]["name"], "Kemasan Botol");
+	assert_eq!(body["data"][1]["name"], "Snack");
+
+	let botol: i32 = sqlx::query_scalar("SELECT id FROM category WHERE name = 'Kemasan Botol'").fetch_one(&pg_pool).await.unwrap();
+	let (status, body) = send(&app, Method::GET, &format!("/api/category/{botol}/breadcrumbs"), Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let names: Vec<&str> = body["data"].as_array().unwrap().iter().map(|category| category["name"].as_str().unwrap()).collect();
+	assert_eq!(names, ["Minuman", "Kopi", "Kemasan Botol"]);
+
+	let (status, body) = send(&app, Method::GET, &format!("/api/category/search-paginate?subtree_of={kopi}&sort_by=name"), Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let names: Vec<&str> = body["data"].as_array().unwrap().iter().map(|category| category["name"].as_str().unwrap()).collect();
+	assert_eq!(names, ["Kemasan Botol", "Kopi"]);
+
+	let (status, body) = send(&app, Method::POST, &format!("/api/category/search-paginate?subtree_of={minuman}"), Some(&token), Some(json!({ "term": "botol" }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(body["paginate"]["count"], 1);
+}
+
+#[sqlx::test]
+async fn move_prevents_cycles_and_parents_keep_their_children(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let minuman = seed_category(&pg_pool, "Minuman").await;
+	let kopi = seed_category(&pg_pool, "Kopi").await;
+	let snack = seed_category(&pg_pool, "Snack").await;
+
+	let (status, headers, body) = send_with(&app, Method::POST, &format!("/api/category/{kopi}/move"), Some(&token), &[("If-Match", "\"1\"")], Some(json!({ "parent_id": minuman }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(headers["etag"], "\"2\"");
+
+	let (status, _, body) = send_with(&app, Method::POST, &format!("/api/category/{minuman}/move"), Some(&token), &[("If-Match", "*")], Some(json!({ "parent_id": kopi }))).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+	let (status, _, _) = send_with(&app, Method::POST, &format!("/api/category/{kopi}/move"), Some(&token), &[("If-Match", "\"1\"")], Some(json!({ "parent_id": snack }))).await;
+	assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+	let (status, _, body) = send_with(&app, Method::DELETE, &format!("/api/category/{minuman}"), Some(&token), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+	let (status, _, _) = send_with(&app, Method::POST, &format!("/api/category/{kopi}/move"), Some(&token), &[("If-Match", "*")], Some(json!({ "parent_id": null }))).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let (status, _, _) = send_with(&app, Method::DELETE, &format!("/api/category/{minuman}"), Some(&token), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn nesting_stops_at_the_deepest_level(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	// A chain of 100 levels, "Level 1" at the top.
+	sqlx::query("INSERT INTO category (name, slug) SELECT 'Level ' || n, 'level-' || n FROM generate_series(1, 100) n").execute(&pg_pool).await.unwrap();
+	sqlx::query(
+		"UPDATE category SET parent_id = parent.id FROM category parent
+		WHERE category.name LIKE 'Level %' AND parent.name = 'Level ' || (SUBSTRING(category.name FROM 7)::INTEGER - 1)"
+	).execute(&pg_pool).await.unwrap();
+	let level = |n: i32| {
+		let pg_pool = pg_pool.clone();
+		async move { sqlx::query_scalar::<_, i32>("SELECT id FROM category WHERE name = $1").bind(format!("Level {n}")).fetch_one(&pg_pool).await.unwrap() }
+	};
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Terlalu Dalam", "parent_id": level(100).await }))).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+	let kopi = seed_category(&pg_pool, "Kopi").await;
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Kemasan Botol", "parent_id": kopi }))).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+
+	// Kopi brings its child along, two levels do not fit below level 99.
+	let (status, _, body) = send_with(&app, Method::POST, &format!("/api/category/{kopi}/move"), Some(&token), &[("If-Match", "*")], Some(json!({ "parent_id": level(99).await }))).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+	let (status, _, body) = send_with(&app, Method::POST, &format!("/api/category/{kopi}/move"), Some(&token), &[("If-Match", "*")], Some(json!({ "parent_id": level(98).await }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	// Merging moves the children of the source one level below the target, none fit below level 100.
+	let teh = seed_category(&pg_pool, "Teh").await;
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Teh Botol", "parent_id": teh }))).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+
+	let (status, _, body) = send_with(&app, Method::POST, &format!("/api/category/{}/merge", level(100).await), Some(&token), &[("If-Match", "*")], Some(json!({ "source_ids": [teh] }))).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+	let (status, _, body) = send_with(&app, Method::POST, &format!("/api/category/{}/merge", level(99).await), Some(&token), &[("If-Match", "*")], Some(json!({ "source_ids": [teh] }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+#[sqlx::test]
+async fn delete_waits_for_a_child_being_added(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let minuman = seed_category(&pg_pool, "Minuman").await;
+
+	// Stands in for a create that locked its parent and has not inserted the child yet.
+	let mut tx = pg_pool.begin().await.unwrap();
+	sqlx::query("SELECT id FROM category WHERE id = $1 FOR SHARE").bind(minuman).execute(&mut *tx).await.unwrap();
+
+	let delete = tokio::spawn({
+		let (app, token) = (app.clone(), token.clone());
+		async move { send_with(&app, Method::DELETE, &format!("/api/category/{minuman}"), Some(&token), &[("If-Match", "*")], None).await }
+	});
+	tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+	sqlx::query("INSERT INTO category (name, slug, parent_id) VALUES ('Kopi', 'kopi', $1)").bind(minuman).execute(&mut *tx).await.unwrap();
+	tx.commit().await.unwrap();
+
+	let (status, _, body) = delete.await.unwrap();
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+}
+
+#[sqlx::test]
+async fn slugs_are_unique_and_reorder_drives_find_many(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	for name in ["Kopi & Teh", "Kopi dan Teh", "Makanan"] {
+		let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": name }))).await;
+		assert_eq!(status, StatusCode::CREATED, "{body}");
+	}
+
+	let (_, body) = send(&app, Method::GET, "/api/category", Some(&token), None).await;
+	let categories = body["data"].as_array().unwrap();
+	let slugs: Vec<&str> = categories.iter().map(|category| category["slug"].as_str().unwrap()).collect();
+	assert_eq!(slugs, ["kopi-dan-teh", "kopi-dan-teh-2", "makanan"]);
+
+	let ids: Vec<i64> = categories.iter().map(|category| category["id"].as_i64().unwrap()).collect();
+	let (status, body) = send(&app, Method::POST, "/api/category/reorder", Some(&token), Some(json!({ "ids": [ids[2], ids[1], ids[0]] }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let (_, body) = send(&app, Method::GET, "/api/category", Some(&token), None).await;
+	let names: Vec<&str> = body["data"].as_array().unwrap().iter().map(|category| category["name"].as_str().unwrap()).collect();
+	assert_eq!(names, ["Makanan", "Kopi dan Teh", "Kopi & Teh"]);
+	assert_eq!(body["data"][0]["sort_order"], 1);
+	assert_eq!(body["data"][0]["version"], 2);
+
+	let (_, body) = send(&app, Method::GET, "/api/audit?filter=action:eq:reorder&sort_by=id", Some(&token), None).await;
+	let reordered: Vec<String> = body["data"].as_array().unwrap().iter().map(|event| event["entity_id"].as_str().unwrap().to_string()).collect();
+	assert_eq!(reordered, [ids[2].to_string(), ids[1].to_string(), ids[0].to_string()]);
+	assert_eq!(body["data"][0]["changes"]["sort_order"]["after"], 1, "{body}");
+
+	let (status, body) = send(&app, Method::POST, "/api/category/reorder", Some(&token), Some(json!({ "ids": [ids[0], 999] }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	assert!(body["message"].as_str().unwrap().contains("999"));
+
+	let (_, body) = send(&app, Method::GET, "/api/category", Some(&token), None).await;
+	assert_eq!(body["data"][0]["name"], "Makanan");
+}
+
+#[sqlx::test]
+async fn slug_taken_meanwhile_conflicts(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	// Another name with the same slug, written but not committed while the create picks its slug.
+	let mut tx = pg_pool.begin().await.unwrap();
+	sqlx::query("INSERT INTO category (name, slug) VALUES ('Kopi!', 'kopi')").execute(&mut *tx).await.unwrap();
+
+	let create = tokio::spawn({
+		let (app, token) = (app.clone(), token.clone());
+		async move { send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Kopi" }))).await }
+	});
+	tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+	tx.commit().await.unwrap();
+
+	let (status, body) = create.await.unwrap();
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Kopi" }))).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+	let slug: String = sqlx::query_scalar("SELECT slug FROM category WHERE name = 'Kopi'").fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(slug, "kopi-2");
+}
+
+#[sqlx::test]
+async fn duplicate_names_conflict_and_duplicates_can_be_merged(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": " Minuman  Dingin " }))).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "minuman dingin" }))).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+	assert!(body["message"].as_str().unwrap().contains("\"Minuman Dingin\""), "{body}");
+
+	// "Cold Drinks" is the same category under an English name, its sub category must follow the merge.
+	let minuman: i32 = sqlx::query_scalar("SELECT id FROM category WHERE name = 'Minuman Dingin'").fetch_one(&pg_pool).await.unwrap();
+	let drinks = seed_category(&pg_pool, "Cold Drinks").await;
+	let kopi = seed_category(&pg_pool, "Kopi Susu").await;
+	sqlx::query("UPDATE category SET parent_id = $1 WHERE id = $2").bind(drinks).bind(kopi).execute(&pg_pool).await.unwrap();
+
+	let uri = format!("/api/category/{minuman}/merge");
+	seed_user(&pg_pool, "kasir01", "rahasia123", "cashier").await;
+	let cashier = login(&app, "kasir01", "rahasia123").await;
+	let (status, _, body) = send_with(&app, Method::POST, &uri, Some(&cashier), &[("If-Match", "*")], Some(json!({ "source_ids": [drinks] }))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+
+	let (status, _, body) = send_with(&app, Method::POST, &uri, Some(&token), &[("If-Match", "*")], Some(json!({ "source_ids": [drinks, 999] }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+	let (status, headers, body) = send_with(&app, Method::POST, &uri, Some(&token), &[("If-Match", "\"1\"")], Some(json!({ "source_ids": [drinks] }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(headers["etag"], "\"2\"");
+
+	let parent_id: Option<i32> = sqlx::query_scalar("SELECT parent_id FROM category WHERE id = $1").bind(kopi).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(parent_id, Some(minuman));
+
+	let (_, body) = send(&app, Method::GET, "/api/category/tree", Some(&token), None).await;
+	assert_eq!(body["data"].as_array().unwrap().len(), 1);
+	assert_eq!(body["data"][0]["children"][0]["name"], "Kopi Susu");
+
+	let (status, body) = send(&app, Method::GET, "/api/audit", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK);
+	let mut merged: Vec<i32> = body["data"].as_array().unwrap().iter()
+		.filter(|event| event["action"] == "merge")
+		.map(|event| event["entity_id"].as_str().unwrap().parse().unwrap())
+		.collect();
+	merged.sort();
+	assert_eq!(merged, [minuman, drinks, kopi], "{body}");
+}
+
+#[sqlx::test]
+async fn import_reports_every_invalid_row_then_commits_and_exports(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	seed_category(&pg_pool, "Minuman").await;
+	let count = || async { sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM category").fetch_one(&pg_pool).await.unwrap() };
+
+	let invalid = "name,parent,sort_order\nKopi,Minuman,1\nminuman,,\n,,x\nTeh,Jus,\n";
+	let (status, body) = upload(&app, "/api/category/import", Some(&token), "kategori.csv", invalid.as_bytes()).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	let rows: Vec<i64> = body["data"]["errors"].as_array().unwrap().iter().map(|error| error["row"].as_i64().unwrap()).collect();
+	assert_eq!(rows, [3, 4, 5]);
+	assert_eq!(body["data"]["errors"][1]["errors"].as_array().unwrap().len(), 2);
+	assert_eq!(body["data"]["errors"][2]["errors"][0]["field"], "parent");
+	assert_eq!(count().await, 1);
+
+	let valid = "Name;Parent;Sort_Order\nKopi;Minuman;1\nKopi Susu;Kopi;2\nTeh;;\n";
+	let (status, body) = upload(&app, "/api/category/import?dry_run=true", Some(&token), "kategori.csv", valid.as_bytes()).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(body["data"]["created"], 0);
+	assert_eq!(count().await, 1);
+
+	let (status, body) = upload(&app, "/api/category/import", Some(&token), "kategori.csv", valid.as_bytes()).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+	assert_eq!(body["data"]["created"], 3);
+	assert_eq!(count().await, 4);
+
+	let (_, body) = send(&app, Method::GET, "/api/audit?filter=action:eq:import&sort_by=id", Some(&token), None).await;
+	let imported: Vec<(i32, &str)> = body["data"].as_array().unwrap().iter()
+		.map(|event| (event["entity_id"].as_str().unwrap().parse().unwrap(), event["changes"]["name"]["after"].as_str().unwrap()))
+		.collect();
+	let kopi_susu: i32 = sqlx::query_scalar("SELECT id FROM category WHERE name = 'Kopi Susu'").fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(imported.len(), 3, "{body}");
+	assert!(imported.contains(&(kopi_susu, "Kopi Susu")), "{body}");
+
+	let (status, headers, bytes) = download(&app, "/api/category/export?format=csv", Some(&token)).await;
+	assert_eq!(status, StatusCode::OK);
+	assert!(headers["content-disposition"].to_str().unwrap().contains(".csv"));
+	let csv = String::from_utf8(bytes).unwrap();
+	assert!(csv.starts_with("id,name,slug,parent,sort_order,icon,created_at,updated_at\n"), "{csv}");
+	assert!(csv.lines().any(|line| line.contains(",Kopi Susu,kopi-susu,Kopi,2,")), "{csv}");
+
+	seed_user(&pg_pool, "kasir01", "rahasia123", "cashier").await;
+	let cashier = login(&app, "kasir01", "rahasia123").await;
+	let (status, _, _) = download(&app, "/api/category/export", Some(&cashier)).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,212 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use axum::{
+	body::Body,
+	http::{ header, HeaderMap, Method, Request, StatusCode },
+	Router
+};
+use bcrypt::{ hash, DEFAULT_COST };
+use http_body_util::BodyExt;
+use serde_json::{ json, Value };
+use sqlx::postgres::PgPool;
+use tower::ServiceExt;
+
+use rust_axum_pos_backend::{ build_app, AppState };
+use rust_axum_pos_backend::storage::memory::InMemoryStorage;
+use rust_axum_pos_backend::utils::slug::slugify;
+use rust_axum_pos_backend::utils::token::random_token;
+
+pub const ADMIN_USERNAME: &str = "admin";
+pub const ADMIN_PASSWORD: &str = "admin12345";
+
+/// Postgres state with uploads kept in memory, so tests neither leave files behind nor see each other's.
+pub fn state(pg_pool: &PgPool) -> AppState {
+	AppState {
+		storage: Arc::new(InMemoryStorage::default()),
+		..AppState::postgres(pg_pool.clone())
+	}
+}
+
+pub fn app(pg_pool: &PgPool) -> Router {
+	build_app(state(pg_pool))
+}
+
+/// Creates the role first when it is not one of the seeded ones, without permissions.
+pub async fn seed_user(pg_pool: &PgPool, username: &str, password: &str, role: &str) -> i32 {
+	sqlx::query("INSERT INTO role (name) VALUES ($1) ON CONFLICT DO NOTHING")
+	.bind(role)
+	.execute(pg_pool)
+	.await
+	.unwrap();
+
+	sqlx::query_scalar(
+		"INSERT INTO user_system (username, password, full_name, address, phone_number, photo, role)
+		VALUES ($1, $2, $3, 'Jl. Merdeka 1', '081234567890', 'default_user.png', $4) RETURNING id"
+	)
+	.bind(username)
+	.bind(hash(password, DEFAULT_COST).unwrap())
+	.bind(format!("{username} full name"))
+	.bind(role)
+	.fetch_one(pg_pool)
+	.await
+	.unwrap()
+}
+
+pub async fn seed_category(pg_pool: &PgPool, name: &str) -> i32 {
+	sqlx::query_scalar("INSERT INTO category (name, slug) VALUES ($1, $2) RETURNING id")
+	.bind(name)
+	.bind(slugify(name))
+	.fetch_one(pg_pool)
+	.await
+	.unwrap()
+}
+
+/// Seeds the admin account and returns a bearer token obtained through the login route.
+pub async fn admin_token(app: &Router, pg_pool: &PgPool) -> String {
+	seed_user(pg_pool, ADMIN_USERNAME, ADMIN_PASSWORD, "admin").await;
+
+	login(app, ADMIN_USERNAME, ADMIN_PASSWORD).await
+}
+
+pub async fn login(app: &Router, username: &str, password: &str) -> String {
+	let (status, body) = send(
+		app,
+		Method::POST,
+		"/api/auth/login",
+		None,
+		Some(json!({ "username": username, "password": password }))
+	).await;
+
+	assert_eq!(status, StatusCode::ACCEPTED, "login failed: {body}");
+
+	body["token"].as_str().unwrap().to_string()
+}
+
+pub async fn send(app: &Router, method: Method, uri: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+	let (status, _, body) = send_with(app, method, uri, token, &[], body).await;
+
+	(status, body)
+}
+
+/// Like `send` with extra request headers, also returns the response headers.
+pub async fn send_with(
+	app: &Router,
+	method: Method,
+	uri: &str,
+	token: Option<&str>,
+	headers: &[(&str, &str)],
+	body: Option<Value>
+) -> (StatusCode, HeaderMap, Value) {
+	let mut request = Request::builder().method(method).uri(uri);
+
+	if let Some(token) = token {
+		request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+	}
+
+	for (name, value) in headers {
+		request = request.header(*name, *value);
+	}
+
+	let request = match body {
+		Some(body) => request
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(Body::from(body.to_string()))
+			.unwrap(),
+		None => request.body(Body::empty()).unwrap()
+	};
+
+	let response = app.clone().oneshot(request).await.unwrap();
+	let headers = response.headers().clone();
+	let (status, body) = into_json(response).await;
+
+	(status, headers, body)
+}
+
+/// Sends `body` verbatim as JSON, for requests `send` cannot build such as malformed bodies.
+pub async fn send_raw(app: &Router, method: Method, uri: &str, token: Option<&str>, body: &str) -> (StatusCode, Value) {
+	let mut request = Request::builder()
+	.method(method)
+	.uri(uri)
+	.header(header::CONTENT_TYPE, "application/json");
+
+	if let Some(token) = token {
+		request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+	}
+
+	into_json(app.clone().oneshot(request.body(Body::from(body.to_string())).unwrap()).await.unwrap()).await
+}
+
+/// A PNG of noise, which barely compresses, encoded the way uploads are encoded again so the bytes survive it. The
+/// noise differs on every call, tests running at once never share a stored file.
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+	let mut seed = u32::from_str_radix(&random_token()[..8], 16).unwrap() | 1;
+	let image = image::RgbImage::from_fn(width, height, |_, _| {
+		seed ^= seed << 13;
+		seed ^= seed >> 17;
+		seed ^= seed << 5;
+		image::Rgb([seed as u8, (seed >> 8) as u8, (seed >> 16) as u8])
+	});
+
+	let mut bytes = std::io::Cursor::new(Vec::new());
+	image::DynamicImage::ImageRgb8(image).write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+	bytes.into_inner()
+}
+
+/// Posts `bytes` as the `file` field of a multipart form.
+pub async fn upload(app: &Router, uri: &str, token: Option<&str>, file_name: &str, bytes: &[u8]) -> (StatusCode, Value) {
+	upload_with(app, uri, token, &[], file_name, bytes).await
+}
+
+/// Like `upload` with extra request headers.
+pub async fn upload_with(
+	app: &Router,
+	uri: &str,
+	token: Option<&str>,
+	headers: &[(&str, &str)],
+	file_name: &str,
+	bytes: &[u8]
+) -> (StatusCode, Value) {
+	let boundary = "pos-test-boundary";
+	let mut form = format!(
+		"--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+	).into_bytes();
+	form.extend_from_slice(bytes);
+	form.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+	let mut request = Request::builder()
+	.method(Method::POST)
+	.uri(uri)
+	.header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={boundary}"));
+
+	if let Some(token) = token {
+		request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+	}
+	for (name, value) in headers {
+		request = request.header(*name, *value);
+	}
+
+	into_json(app.clone().oneshot(request.body(Body::from(form)).unwrap()).await.unwrap()).await
+}
+
+/// GETs `uri` and returns the raw body, for file downloads.
+pub async fn download(app: &Router, uri: &str, token: Option<&str>) -> (StatusCode, HeaderMap, Vec<u8>) {
+	let mut request = Request::builder().uri(uri);
+
+	if let Some(token) = token {
+		request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+	}
+
+	let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+	let (status, headers) = (response.status(), response.headers().clone());
+
+	(status, headers, response.into_body().collect().await.unwrap().to_bytes().to_vec())
+}
+
+pub async fn into_json(response: axum::response::Response) -> (StatusCode, Value) {
+	let status = response.status();
+	let bytes = response.into_body().collect().await.unwrap().to_bytes();
+
+	(status, serde_json::from_slice(&bytes).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned())))
+}
@@ -0,0 +1,304 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::{
+	body::Body,
+	http::{ Method, Request, StatusCode }
+};
+use http_body_util::BodyExt;
+use sqlx::postgres::PgPool;
+use tower::ServiceExt;
+
+use serde_json::json;
+use sha2::{ Digest, Sha256 };
+
+use rust_axum_pos_backend::build_app;
+use rust_axum_pos_backend::storage::StorageError;
+use rust_axum_pos_backend::utils::purge::collect_unreferenced_files;
+use rust_axum_pos_backend::utils::thumbnail::variants_prefix;
+
+use common::{ admin_token, app, download, png, seed_user, send, send_with, state, upload };
+
+/// Passes the magic byte check but cannot be decoded.
+const PNG_HEADER_ONLY: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0];
+
+async fn ref_count(pg_pool: &PgPool, prefix: &str, file_name: &str) -> Option<i32> {
+	sqlx::query_scalar("SELECT ref_count FROM file_object WHERE prefix = $1 AND file_name = $2")
+	.bind(prefix)
+	.bind(file_name)
+	.fetch_optional(pg_pool)
+	.await
+	.unwrap()
+}
+
+#[sqlx::test]
+async fn uploaded_user_image_is_served_until_collected(pg_pool: PgPool) {
+	let state = state(&pg_pool);
+	let app = build_app(state.clone());
+	let token = admin_token(&app, &pg_pool).await;
+	let png = png(8, 8);
+
+	let (status, body) = upload(&app, "/api/files/user", Some(&token), "avatar.png", &png).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let file_name = body["file_name"].as_str().unwrap().to_string();
+	assert_eq!(file_name, format!("{}.png", hex::encode(Sha256::digest(&png))));
+	assert_eq!(body["file_extension"], "png");
+
+	let request = Request::builder()
+	.uri(format!("/api/files/user/image/{file_name}"))
+	.body(Body::empty())
+	.unwrap();
+
+	let response = app.clone().oneshot(request).await.unwrap();
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.into_body().collect().await.unwrap().to_bytes().as_ref(), png);
+
+	// Files are only removed by the purge job, once nothing refers to them.
+	let (status, _) = send(&app, Method::DELETE, &format!("/api/files/user/delete/{file_name}"), Some(&token), None).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+
+	assert_eq!(collect_unreferenced_files(&state, Duration::ZERO).await.unwrap(), 1);
+
+	let (status, _) = send(&app, Method::GET, &format!("/api/files/user/image/{file_name}"), None, None).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn category_icon_is_uploaded_then_referenced(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let png = png(8, 8);
+	let (status, body) = upload(&app, "/api/files/category", Some(&token), "kopi.png", &png).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let file_name = body["file_name"].as_str().unwrap().to_string();
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Kopi", "icon": file_name }))).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+	assert_eq!(ref_count(&pg_pool, "category", &file_name).await, Some(1));
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Teh", "icon": "never_uploaded.png" }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Teh", "icon": "../../.env" }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	assert_eq!(body["errors"][0]["field"], "icon");
+
+	let response = app.clone().oneshot(Request::builder().uri(format!("/api/files/category/image/{file_name}")).body(Body::empty()).unwrap()).await.unwrap();
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.into_body().collect().await.unwrap().to_bytes().as_ref(), png);
+}
+
+#[sqlx::test]
+async fn served_files_are_typed_by_content_cached_and_ranged(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	// The extension lies, the bytes decide the content type.
+	let png = png(8, 8);
+	let (status, body) = upload(&app, "/api/files/user", Some(&token), "avatar.gif", &png).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let uri = format!("/api/files/user/image/{}", body["file_name"].as_str().unwrap());
+
+	let (status, headers, bytes) = download(&app, &uri, None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(bytes, png);
+	assert_eq!(headers["content-type"], "image/png");
+	assert_eq!(headers["x-content-type-options"], "nosniff");
+	assert_eq!(headers["cache-control"], "public, max-age=86400");
+	let etag = headers["etag"].to_str().unwrap().to_string();
+	let last_modified = headers["last-modified"].to_str().unwrap().to_string();
+
+	let get = |name: &'static str, value: String| {
+		let app = app.clone();
+		let uri = uri.clone();
+		async move {
+			let response = app.oneshot(Request::builder().uri(uri).header(name, value).body(Body::empty()).unwrap()).await.unwrap();
+			let (status, headers) = (response.status(), response.headers().clone());
+			(status, headers, response.into_body().collect().await.unwrap().to_bytes())
+		}
+	};
+
+	let (status, _, bytes) = get("if-none-match", etag.clone()).await;
+	assert_eq!(status, StatusCode::NOT_MODIFIED);
+	assert!(bytes.is_empty());
+	let (status, _, _) = get("if-modified-since", last_modified).await;
+	assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+	let (status, headers, bytes) = get("range", "bytes=1-3".to_string()).await;
+	assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+	assert_eq!(bytes.as_ref(), b"PNG");
+	assert_eq!(headers["content-range"], format!("bytes 1-3/{}", png.len()));
+
+	let (status, headers, _) = get("range", format!("bytes={}-", png.len())).await;
+	assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+	assert_eq!(headers["content-range"], format!("bytes */{}", png.len()));
+}
+
+#[sqlx::test]
+async fn paths_outside_the_upload_directory_are_not_found(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+
+	for uri in ["/api/files/user/image/..%2F..%2FCargo.toml", "/api/files/category/image/..%2Fuser", "/api/files/user/image/%2Fetc%2Fpasswd"] {
+		let (status, _, _) = download(&app, uri, None).await;
+		assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+	}
+}
+
+#[sqlx::test]
+async fn uploads_need_a_token_and_an_allowed_image_within_the_limit(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, body) = upload(&app, "/api/files/user", None, "avatar.png", &png(8, 8)).await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED, "{body}");
+
+	// A renamed video or script is refused whatever its name says.
+	let (status, body) = upload(&app, "/api/files/user", Some(&token), "avatar.png", b"\x1aE\xdf\xa3 matroska video").await;
+	assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE, "{body}");
+	assert_eq!(body["success"], false);
+	assert_eq!(body["message"], "Tipe File Tidak Diizinkan, Gunakan: image/png, image/jpeg, image/webp.");
+
+	let (status, body) = upload(&app, "/api/files/user", Some(&token), "avatar.png", b"").await;
+	assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+	assert_eq!(body["message"], "File Kosong.");
+
+	let (status, body) = upload(&app, "/api/files/user", Some(&token), "avatar.png", PNG_HEADER_ONLY).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+	let large = png(500, 500);
+	assert!(large.len() > 512 * 1024);
+	let (status, body) = upload(&app, "/api/files/category", Some(&token), "kopi.png", &large).await;
+	assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE, "{body}");
+	assert_eq!(body["message"], "Ukuran File Melebihi Batas 512 KB.");
+
+	// The same file fits the larger limit of user images, the original name plays no part in the stored one.
+	let (status, body) = upload(&app, "/api/files/user", Some(&token), "../Foto Profil (1).gif", &large).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let file_name = body["file_name"].as_str().unwrap();
+	assert_eq!(file_name, format!("{}.png", hex::encode(Sha256::digest(&large))));
+}
+
+#[sqlx::test]
+async fn images_are_served_resized_and_as_webp_when_accepted(pg_pool: PgPool) {
+	let state = state(&pg_pool);
+	let app = build_app(state.clone());
+	let token = admin_token(&app, &pg_pool).await;
+
+	let gradient = image::RgbImage::from_fn(600, 300, |x, y| image::Rgb([(x / 3) as u8, (y / 2) as u8, 128]));
+	let mut png = std::io::Cursor::new(Vec::new());
+	image::DynamicImage::ImageRgb8(gradient).write_to(&mut png, image::ImageFormat::Png).unwrap();
+
+	let (status, body) = upload(&app, "/api/files/user", Some(&token), "avatar.png", png.get_ref()).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let file_name = body["file_name"].as_str().unwrap().to_string();
+	let uri = format!("/api/files/user/image/{file_name}");
+	let dimensions = |bytes: &[u8]| {
+		let image = image::load_from_memory(bytes).unwrap();
+		(image.width(), image.height())
+	};
+
+	let (status, headers, bytes) = download(&app, &format!("{uri}?size=100"), None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(headers["content-type"], "image/png");
+	assert_eq!(headers["vary"], "Accept");
+	assert_eq!(dimensions(&bytes), (256, 128));
+
+	let (status, headers, bytes) = download(&app, &format!("{uri}?size=2000"), None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(headers["content-type"], "image/png");
+	assert_eq!(dimensions(&bytes), (600, 300));
+
+	let request = Request::builder().uri(format!("{uri}?size=64")).header("accept", "image/webp,*/*").body(Body::empty()).unwrap();
+	let response = app.clone().oneshot(request).await.unwrap();
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.headers()["content-type"], "image/webp");
+	let bytes = response.into_body().collect().await.unwrap().to_bytes();
+	assert_eq!(dimensions(&bytes), (64, 32));
+
+	assert_eq!(collect_unreferenced_files(&state, Duration::ZERO).await.unwrap(), 1);
+	assert!(state.storage.list(&variants_prefix("user", &file_name)).await.unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn identical_uploads_share_a_blob_removed_once_nothing_refers_to_it(pg_pool: PgPool) {
+	let state = state(&pg_pool);
+	let app = build_app(state.clone());
+	let token = admin_token(&app, &pg_pool).await;
+	let png = png(8, 8);
+
+	let (status, first) = upload(&app, "/api/files/user", Some(&token), "avatar.png", &png).await;
+	assert_eq!(status, StatusCode::OK, "{first}");
+	let (status, second) = upload(&app, "/api/files/user", Some(&token), "copy of avatar.png", &png).await;
+	assert_eq!(status, StatusCode::OK, "{second}");
+
+	let file_name = first["file_name"].as_str().unwrap().to_string();
+	assert_eq!(second["file_name"], file_name);
+	assert_eq!(ref_count(&pg_pool, "user", &file_name).await, Some(0));
+
+	let kasir = seed_user(&pg_pool, "kasir", "rahasia123", "cashier").await;
+	let budi = seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+	sqlx::query("UPDATE user_system SET photo = $1 WHERE id IN ($2, $3)").bind(&file_name).bind(kasir).bind(budi).execute(&pg_pool).await.unwrap();
+	assert_eq!(ref_count(&pg_pool, "user", &file_name).await, Some(2));
+
+	assert_eq!(collect_unreferenced_files(&state, Duration::ZERO).await.unwrap(), 0);
+
+	sqlx::query("UPDATE user_system SET photo = 'default_user.png' WHERE id = $1").bind(kasir).execute(&pg_pool).await.unwrap();
+	sqlx::query("DELETE FROM user_system WHERE id = $1").bind(budi).execute(&pg_pool).await.unwrap();
+	assert_eq!(ref_count(&pg_pool, "user", &file_name).await, Some(0));
+
+	// Still within the grace period a client gets to save the form the upload was for.
+	assert_eq!(collect_unreferenced_files(&state, Duration::from_secs(60 * 60)).await.unwrap(), 0);
+	let (status, _, _) = download(&app, &format!("/api/files/user/image/{file_name}?size=64"), None).await;
+	assert_eq!(status, StatusCode::OK);
+
+	assert_eq!(collect_unreferenced_files(&state, Duration::ZERO).await.unwrap(), 1);
+	assert_eq!(ref_count(&pg_pool, "user", &file_name).await, None);
+
+	let (status, body) = send(&app, Method::GET, "/api/audit?filter=entity_type:eq:file", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(body["data"][0]["action"], "delete");
+	assert_eq!(body["data"][0]["actor_id"], json!(null));
+	assert_eq!(body["data"][0]["entity_id"], format!("user/{file_name}"));
+	assert_eq!(body["data"][0]["changes"]["sha256"]["after"], json!(null));
+	assert!(matches!(state.storage.stat(&format!("user/{file_name}")).await, Err(StorageError::NotFound)));
+	assert!(state.storage.list(&variants_prefix("user", &file_name)).await.unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn photos_must_be_uploaded_and_kept_until_referenced(pg_pool: PgPool) {
+	let state = state(&pg_pool);
+	let app = build_app(state.clone());
+	let token = admin_token(&app, &pg_pool).await;
+	let kasir = seed_user(&pg_pool, "kasir", "rahasia123", "cashier").await;
+	let uri = format!("/api/user/{kasir}");
+
+	let (status, _, body) = send_with(&app, Method::PATCH, &uri, Some(&token), &[("If-Match", "*")], Some(json!({ "photo": "never_uploaded.png" }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+	let (status, body) = upload(&app, "/api/files/user", Some(&token), "avatar.png", &png(8, 8)).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let file_name = body["file_name"].as_str().unwrap().to_string();
+
+	// The purge job removed the row while this write waited on its lock, the blob is about to go.
+	sqlx::query("DELETE FROM file_object WHERE prefix = 'user' AND file_name = $1").bind(&file_name).execute(&pg_pool).await.unwrap();
+	let (status, _, body) = send_with(&app, Method::PATCH, &uri, Some(&token), &[("If-Match", "*")], Some(json!({ "photo": file_name }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	assert!(body["message"].as_str().unwrap().contains(&file_name), "{body}");
+
+	let photo: String = sqlx::query_scalar("SELECT photo FROM user_system WHERE id = $1").bind(kasir).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(photo, "default_user.png");
+	state.storage.delete(&format!("user/{file_name}")).await.unwrap();
+
+	let (status, body) = upload(&app, "/api/files/user", Some(&token), "avatar.png", &png(8, 8)).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let file_name = body["file_name"].as_str().unwrap().to_string();
+	let (status, _, body) = send_with(&app, Method::PATCH, &uri, Some(&token), &[("If-Match", "*")], Some(json!({ "photo": file_name }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(ref_count(&pg_pool, "user", &file_name).await, Some(1));
+
+	sqlx::query("UPDATE user_system SET photo = 'default_user.png' WHERE id = $1").bind(kasir).execute(&pg_pool).await.unwrap();
+	assert_eq!(collect_unreferenced_files(&state, Duration::ZERO).await.unwrap(), 1);
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use axum::http::{ Method, StatusCode };
+use serde_json::json;
+use sqlx::postgres::PgPool;
+
+use common::{ admin_token, app, login, png, seed_user, send, send_with, upload_with };
+
+async fn category_count(pg_pool: &PgPool) -> i64 {
+	sqlx::query_scalar("SELECT COUNT(*) FROM category").fetch_one(pg_pool).await.unwrap()
+}
+
+#[sqlx::test]
+async fn retry_with_same_key_replays_the_first_response(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let key = [("Idempotency-Key", "3f1c9a7e-create-minuman")];
+
+	let (status, headers, first) = send_with(&app, Method::POST, "/api/category", Some(&token), &key, Some(json!({ "name": "Minuman" }))).await;
+	assert_eq!(status, StatusCode::CREATED, "{first}");
+	assert!(headers.get("idempotent-replayed").is_none());
+
+	let (status, headers, retry) = send_with(&app, Method::POST, "/api/category", Some(&token), &key, Some(json!({ "name": "Minuman" }))).await;
+	assert_eq!(status, StatusCode::CREATED);
+	assert_eq!(headers["idempotent-replayed"], "true");
+	assert_eq!(retry, first);
+
+	assert_eq!(category_count(&pg_pool).await, 1);
+}
+
+#[sqlx::test]
+async fn same_key_with_another_body_is_rejected(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let key = [("Idempotency-Key", "reused-key")];
+
+	send_with(&app, Method::POST, "/api/category", Some(&token), &key, Some(json!({ "name": "Minuman" }))).await;
+	let (status, _, body) = send_with(&app, Method::POST, "/api/category", Some(&token), &key, Some(json!({ "name": "Snack" }))).await;
+
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	assert_eq!(body["success"], false);
+	assert_eq!(category_count(&pg_pool).await, 1);
+}
+
+#[sqlx::test]
+async fn keys_are_scoped_per_user_and_expire(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let admin = admin_token(&app, &pg_pool).await;
+	seed_user(&pg_pool, "kasir01", "rahasia123", "cashier").await;
+	let cashier = login(&app, "kasir01", "rahasia123").await;
+	let key = [("Idempotency-Key", "shared-key")];
+
+	for (token, name) in [(&admin, "Minuman"), (&cashier, "Snack")] {
+		let (status, headers, _) = send_with(&app, Method::POST, "/api/category", Some(token), &key, Some(json!({ "name": name }))).await;
+		assert_eq!(status, StatusCode::CREATED);
+		assert!(headers.get("idempotent-replayed").is_none());
+	}
+
+	sqlx::query("UPDATE idempotency_key SET created_at = NOW() - INTERVAL '2 days'").execute(&pg_pool).await.unwrap();
+
+	let (_, headers, _) = send_with(&app, Method::POST, "/api/category", Some(&admin), &key, Some(json!({ "name": "Makanan" }))).await;
+	assert!(headers.get("idempotent-replayed").is_none());
+	assert_eq!(category_count(&pg_pool).await, 3);
+}
+
+#[sqlx::test]
+async fn requests_without_a_key_are_not_deduplicated(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	// Without a key the retry runs again instead of replaying the 201, and hits the unique name.
+	for expected in [StatusCode::CREATED, StatusCode::CONFLICT] {
+		let (status, _) = send(&app, Method::POST, "/api/category", Some(&token), Some(json!({ "name": "Minuman" }))).await;
+		assert_eq!(status, expected);
+	}
+
+	assert_eq!(category_count(&pg_pool).await, 1);
+}
+
+/// CRC-32 of a PNG chunk, over its type and data.
+fn crc32(bytes: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for &byte in bytes {
+		crc ^= byte as u32;
+		for _ in 0..8 {
+			crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+		}
+	}
+
+	!crc
+}
+
+#[sqlx::test]
+async fn keyed_uploads_are_limited_by_their_route_only(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	// A PNG of exactly the 2 MiB allowed for user images, padded with a private chunk before `IEND`. With the form
+	// around it the body is larger than the guard would buffer.
+	let image = png(8, 8);
+	let (head, iend) = image.split_at(image.len() - 12);
+	let padding = vec![0; 2 * 1024 * 1024 - image.len() - 12];
+	let chunk = [b"prVt".as_slice(), &padding].concat();
+	let crc = crc32(&chunk).to_be_bytes();
+	let large = [head, &(padding.len() as u32).to_be_bytes(), &chunk, &crc, iend].concat();
+	assert_eq!(large.len(), 2 * 1024 * 1024);
+
+	let key = [("Idempotency-Key", "avatar-upload")];
+	let (status, body) = upload_with(&app, "/api/files/user", Some(&token), &key, "avatar.png", &large).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+}
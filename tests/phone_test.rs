@@ -0,0 +1,29 @@
+mod common;
+
+use sqlx::postgres::PgPool;
+
+use rust_axum_pos_backend::AppState;
+use rust_axum_pos_backend::utils::phone::normalize_stored;
+
+use common::seed_user;
+
+#[sqlx::test]
+async fn stored_numbers_are_rewritten_once_and_invalid_ones_reported(pg_pool: PgPool) {
+	let state = AppState::postgres(pg_pool.clone());
+	let local = seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+	let landline = seed_user(&pg_pool, "siti", "rahasia123", "cashier").await;
+	sqlx::query("UPDATE user_system SET phone_number = '021-5550-1234' WHERE id = $1").bind(landline).execute(&pg_pool).await.unwrap();
+
+	let report = normalize_stored(&state.user_repository, true).await.unwrap();
+	assert_eq!((report.normalized, report.unchanged), (1, 0));
+	assert_eq!(report.invalid, [(landline, "021-5550-1234".to_string())]);
+	let stored: String = sqlx::query_scalar("SELECT phone_number FROM user_system WHERE id = $1").bind(local).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(stored, "081234567890");
+
+	normalize_stored(&state.user_repository, false).await.unwrap();
+	let (stored, version): (String, i32) = sqlx::query_as("SELECT phone_number, version FROM user_system WHERE id = $1").bind(local).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!((stored.as_str(), version), ("+6281234567890", 2));
+
+	let report = normalize_stored(&state.user_repository, false).await.unwrap();
+	assert_eq!((report.normalized, report.unchanged), (0, 1));
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use std::time::Duration;
+
+use sqlx::postgres::PgPool;
+
+use rust_axum_pos_backend::AppState;
+use rust_axum_pos_backend::utils::purge::purge_deleted;
+
+use common::{ seed_category, seed_user };
+
+#[sqlx::test]
+async fn purges_only_rows_deleted_before_the_retention(pg_pool: PgPool) {
+	let state = AppState::postgres(pg_pool.clone());
+	let old_category = seed_category(&pg_pool, "Lama").await;
+	let recent_category = seed_category(&pg_pool, "Baru").await;
+	seed_category(&pg_pool, "Aktif").await;
+	let old_user = seed_user(&pg_pool, "lama", "rahasia123", "cashier").await;
+
+	sqlx::query("UPDATE category SET deleted_at = NOW() - INTERVAL '400 days' WHERE id = $1").bind(old_category).execute(&pg_pool).await.unwrap();
+	sqlx::query("UPDATE category SET deleted_at = NOW() - INTERVAL '10 days' WHERE id = $1").bind(recent_category).execute(&pg_pool).await.unwrap();
+	sqlx::query("UPDATE user_system SET deleted_at = NOW() - INTERVAL '400 days' WHERE id = $1").bind(old_user).execute(&pg_pool).await.unwrap();
+
+	let purged = purge_deleted(&state, Duration::from_secs(365 * 24 * 60 * 60)).await.unwrap();
+	assert_eq!(purged, (1, 1));
+
+	let names: Vec<String> = sqlx::query_scalar("SELECT name FROM category ORDER BY name").fetch_all(&pg_pool).await.unwrap();
+	assert_eq!(names, ["Aktif", "Baru"]);
+}
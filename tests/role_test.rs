@@ -0,0 +1,122 @@
+mod common;
+
+use axum::http::{ Method, StatusCode };
+use serde_json::json;
+use sqlx::postgres::PgPool;
+
+use common::{ admin_token, app, login, seed_category, seed_user, send, send_with };
+
+#[sqlx::test]
+async fn admins_manage_roles_and_changes_apply_to_issued_tokens(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, body) = send(&app, Method::POST, "/api/role", Some(&token), Some(json!({ "name": "supervisor", "permissions": ["category.delete", "refund.all"] }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	assert_eq!(body["message"], "Permission Tidak Dikenal: refund.all.");
+
+	let (status, headers, body) = send_with(
+		&app,
+		Method::POST,
+		"/api/role",
+		Some(&token),
+		&[],
+		Some(json!({ "name": "supervisor", "description": "Kepala toko", "permissions": ["report.view", "category.delete"] }))
+	).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+	assert_eq!(body["data"]["permissions"], json!(["category.delete", "report.view"]));
+	assert_eq!(headers["etag"], "\"1\"");
+	let role_id = body["data"]["id"].as_i64().unwrap();
+
+	let (status, _) = send(&app, Method::POST, "/api/role", Some(&token), Some(json!({ "name": "supervisor" }))).await;
+	assert_eq!(status, StatusCode::CONFLICT);
+
+	seed_user(&pg_pool, "siti", "rahasia123", "supervisor").await;
+	let supervisor = login(&app, "siti", "rahasia123").await;
+
+	let (status, body) = send(&app, Method::GET, "/api/user/me/permissions", Some(&supervisor), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(body["data"], json!({ "role": "supervisor", "permissions": ["category.delete", "report.view"] }));
+
+	let category = seed_category(&pg_pool, "Minuman").await;
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&supervisor), Some(json!({ "name": "Makanan" }))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+	assert_eq!(body["message"], "Anda Tidak Memiliki Izin category.create.");
+	let (status, _, body) = send_with(&app, Method::DELETE, &format!("/api/category/{category}"), Some(&supervisor), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	// The cached permissions of the supervisor token are dropped as soon as the role changes.
+	let (status, _, body) = send_with(
+		&app,
+		Method::PUT,
+		&format!("/api/role/{role_id}"),
+		Some(&token),
+		&[("If-Match", "\"1\"")],
+		Some(json!({ "name": "supervisor", "permissions": ["category.create"] }))
+	).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let (status, body) = send(&app, Method::POST, "/api/category", Some(&supervisor), Some(json!({ "name": "Makanan" }))).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+
+	let (status, _, body) = send_with(&app, Method::DELETE, &format!("/api/role/{role_id}"), Some(&token), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+	let (status, body) = send(&app, Method::GET, "/api/audit?filter=entity_type:eq:role", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	assert_eq!(body["paginate"]["count"], 2);
+}
+
+#[sqlx::test]
+async fn demoted_users_lose_their_permissions_on_the_next_request(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let id = seed_user(&pg_pool, "rina", "rahasia123", "admin").await;
+	let rina = login(&app, "rina", "rahasia123").await;
+
+	let (status, body) = send(&app, Method::GET, "/api/audit", Some(&rina), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let (status, _, body) = send_with(&app, Method::PATCH, &format!("/api/user/{id}"), Some(&token), &[("If-Match", "*")], Some(json!({ "role": "cashier" }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let (status, body) = send(&app, Method::GET, "/api/audit", Some(&rina), None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+}
+
+#[sqlx::test]
+async fn built_in_roles_keep_their_names_and_admins_hold_every_permission(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, body) = send(&app, Method::GET, "/api/role", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let cashier = body["data"].as_array().unwrap().iter().find(|role| role["name"] == "cashier").unwrap().clone();
+	assert_eq!(cashier["is_system"], true);
+
+	let (status, _, body) = send_with(&app, Method::PUT, &format!("/api/role/{}", cashier["id"]), Some(&token), &[("If-Match", "*")], Some(json!({ "name": "kasir" }))).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+	let (status, _, body) = send_with(&app, Method::DELETE, &format!("/api/role/{}", cashier["id"]), Some(&token), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+	let (_, catalog) = send(&app, Method::GET, "/api/permission", Some(&token), None).await;
+	let codes: Vec<&str> = catalog["data"].as_array().unwrap().iter().map(|permission| permission["code"].as_str().unwrap()).collect();
+	assert!(codes.contains(&"sale.void"));
+
+	let (_, body) = send(&app, Method::GET, "/api/user/me/permissions", Some(&token), None).await;
+	assert_eq!(body["data"]["permissions"], json!(codes));
+}
+
+#[sqlx::test]
+async fn cashiers_cannot_manage_roles_or_read_the_audit_log(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+	let token = login(&app, "budi", "rahasia123").await;
+
+	for uri in ["/api/role", "/api/permission", "/api/audit"] {
+		let (status, body) = send(&app, Method::GET, uri, Some(&token), None).await;
+		assert_eq!(status, StatusCode::FORBIDDEN, "{uri}: {body}");
+	}
+
+	let (status, _) = send(&app, Method::POST, "/api/role", Some(&token), Some(json!({ "name": "kasir_senior" }))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+use std::sync::{ Arc, Mutex };
+use std::time::SystemTime;
+
+use axum::{
+	body::Bytes,
+	extract::State,
+	http::{ header, HeaderMap, Method, StatusCode, Uri },
+	response::{ IntoResponse, Response },
+	Router
+};
+use futures_util::TryStreamExt;
+use sha2::{ Digest, Sha256 };
+use tokio::net::TcpListener;
+
+use rust_axum_pos_backend::storage::{ ByteStream, Storage, StorageError };
+use rust_axum_pos_backend::storage::local::LocalStorage;
+use rust_axum_pos_backend::storage::memory::InMemoryStorage;
+use rust_axum_pos_backend::storage::s3::{ S3Config, S3Storage };
+use rust_axum_pos_backend::utils::token::random_token;
+
+const PNG_BYTES: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 1, 2, 3];
+
+async fn read(stream: ByteStream) -> Vec<u8> {
+	stream.try_fold(Vec::new(), |mut bytes, chunk| async move {
+		bytes.extend_from_slice(&chunk);
+		Ok(bytes)
+	}).await.unwrap()
+}
+
+/// What every backend must do, objects go under a random prefix so runs against a shared bucket do not collide.
+async fn exercise(storage: &dyn Storage) {
+	let prefix = format!("test-{}", &random_token()[..8]);
+	let avatar = format!("{prefix}/user/avatar.png");
+	let icon = format!("{prefix}/category/kopi.png");
+
+	storage.put(&avatar, Bytes::from_static(PNG_BYTES), "image/png").await.unwrap();
+	storage.put(&icon, Bytes::from_static(PNG_BYTES), "image/png").await.unwrap();
+
+	assert_eq!(read(storage.get(&avatar, None).await.unwrap()).await, PNG_BYTES);
+	assert_eq!(read(storage.get(&avatar, Some((1, 3))).await.unwrap()).await, b"PNG");
+
+	let meta = storage.stat(&avatar).await.unwrap();
+	assert_eq!(meta.size, PNG_BYTES.len() as u64);
+	assert_eq!(meta.content_type.as_deref(), Some("image/png"));
+	assert!(!meta.etag.is_empty());
+
+	let keys: Vec<String> = storage.list(&format!("{prefix}/user/")).await.unwrap().into_iter().map(|object| object.key).collect();
+	assert_eq!(keys, [avatar.as_str()]);
+	assert_eq!(storage.list(&prefix).await.unwrap().len(), 2);
+	assert_eq!(storage.list(&format!("{prefix}/user/ava")).await.unwrap().len(), 1);
+	assert!(storage.list(&format!("{prefix}/missing/")).await.unwrap().is_empty());
+	assert!(storage.list(&format!("{prefix}/user/../category/")).await.unwrap().is_empty());
+
+	for key in [format!("{prefix}/user/../category/kopi.png"), format!("{prefix}/user/./avatar.png"), "../../etc/passwd".to_string(), format!("{prefix}/user")] {
+		assert!(matches!(storage.stat(&key).await, Err(StorageError::NotFound)), "{key}");
+		assert!(matches!(storage.get(&key, None).await, Err(StorageError::NotFound)), "{key}");
+	}
+
+	storage.delete(&avatar).await.unwrap();
+	storage.delete(&avatar).await.unwrap();
+	assert!(matches!(storage.stat(&avatar).await, Err(StorageError::NotFound)));
+
+	storage.delete(&icon).await.unwrap();
+	assert!(storage.list(&prefix).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn local_storage_keeps_objects_as_files_under_its_root() {
+	let root = std::env::temp_dir().join(format!("pos-storage-{}", random_token()));
+
+	exercise(&LocalStorage::new(&root)).await;
+
+	std::fs::remove_dir_all(root).unwrap();
+}
+
+#[tokio::test]
+async fn in_memory_storage_behaves_like_the_others() {
+	exercise(&InMemoryStorage::default()).await;
+}
+
+/// Start one with `docker run -p 9000:9000 minio/minio server /data`, create the bucket, then run
+/// `S3_ENDPOINT=http://localhost:9000 S3_BUCKET=pos S3_ACCESS_KEY_ID=minioadmin S3_SECRET_ACCESS_KEY=minioadmin cargo test -- --ignored`.
+#[tokio::test]
+#[ignore = "needs an S3 compatible service configured through S3_* variables"]
+async fn s3_storage_works_against_minio() {
+	exercise(&S3Storage::new(S3Config::from_env())).await;
+}
+
+/// Objects of the mock bucket, body and content type by key.
+type MockBucket = Arc<Mutex<BTreeMap<String, (Bytes, String)>>>;
+
+const MOCK_BUCKET: &str = "pos";
+
+fn percent_decode(value: &str) -> String {
+	let mut decoded = vec![];
+	let mut bytes = value.bytes();
+
+	while let Some(byte) = bytes.next() {
+		if byte == b'%' {
+			let hex: String = bytes.by_ref().take(2).map(char::from).collect();
+			decoded.push(u8::from_str_radix(&hex, 16).unwrap());
+		} else {
+			decoded.push(byte);
+		}
+	}
+
+	String::from_utf8(decoded).unwrap()
+}
+
+/// Answers the few S3 calls `S3Storage` makes, like MinIO would. Lists one key per page so continuation tokens are
+/// followed, and refuses requests whose signature headers are missing or whose payload hash is wrong.
+async fn mock_s3(State(bucket): State<MockBucket>, method: Method, uri: Uri, headers: HeaderMap, body: Bytes) -> Response {
+	let authorization = headers.get(header::AUTHORIZATION).and_then(|value| value.to_str().ok()).unwrap_or_default();
+	let payload_hash = headers.get("x-amz-content-sha256").and_then(|value| value.to_str().ok()).unwrap_or_default();
+	let signed_headers = authorization.split(',').find_map(|part| part.strip_prefix("SignedHeaders=")).unwrap_or_default();
+	let signs = |name: &str| signed_headers.split(';').any(|signed| signed == name) && headers.contains_key(name);
+	if !authorization.starts_with("AWS4-HMAC-SHA256 Credential=minioadmin/") || !["host", "x-amz-content-sha256", "x-amz-date"].into_iter().all(signs) {
+		return StatusCode::FORBIDDEN.into_response();
+	}
+	if payload_hash != hex::encode(Sha256::digest(&body)) {
+		return (StatusCode::BAD_REQUEST, "XAmzContentSHA256Mismatch").into_response();
+	}
+
+	let path = uri.path().strip_prefix(&format!("/{MOCK_BUCKET}")).unwrap_or_default();
+	let query: BTreeMap<String, String> = uri.query().unwrap_or_default()
+	.split('&')
+	.filter_map(|pair| pair.split_once('='))
+	.map(|(name, value)| (percent_decode(name), percent_decode(value)))
+	.collect();
+	let mut objects = bucket.lock().unwrap();
+
+	let Some(key) = path.strip_prefix('/').map(percent_decode).filter(|key| !key.is_empty()) else {
+		let prefix = query.get("prefix").cloned().unwrap_or_default();
+		let after = query.get("continuation-token").cloned().unwrap_or_default();
+		let mut matching = objects.iter().filter(|(key, _)| key.starts_with(&prefix) && **key > after);
+
+		let mut xml = "<ListBucketResult>".to_string();
+		if let Some((key, (body, _))) = matching.next() {
+			xml += &format!("<Contents><Key>{}</Key><LastModified>2026-10-19T08:00:00.000Z</LastModified><ETag>&quot;{}&quot;</ETag><Size>{}</Size></Contents>", key.replace('&', "&amp;"), body.len(), body.len());
+			if matching.next().is_some() {
+				xml += &format!("<IsTruncated>true</IsTruncated><NextContinuationToken>{}</NextContinuationToken>", key.replace('&', "&amp;"));
+			}
+		}
+
+		return (xml + "</ListBucketResult>").into_response();
+	};
+
+	match method {
+		Method::PUT => {
+			let content_type = headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()).unwrap_or_default().to_string();
+			objects.insert(key, (body, content_type));
+			StatusCode::OK.into_response()
+		},
+		Method::DELETE => {
+			objects.remove(&key);
+			StatusCode::NO_CONTENT.into_response()
+		},
+		Method::GET | Method::HEAD => {
+			let Some((body, content_type)) = objects.get(&key).cloned() else {
+				return StatusCode::NOT_FOUND.into_response();
+			};
+			let meta = [
+				(header::CONTENT_TYPE, content_type),
+				(header::ETAG, format!("\"{}\"", body.len())),
+				(header::LAST_MODIFIED, httpdate::fmt_http_date(SystemTime::now()))
+			];
+
+			let range = headers.get(header::RANGE).and_then(|value| value.to_str().ok()).and_then(|range| range.strip_prefix("bytes="));
+			match range.and_then(|range| range.split_once('-')) {
+				Some((start, end)) => {
+					let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse().unwrap());
+					(StatusCode::PARTIAL_CONTENT, meta, body.slice(start..=end)).into_response()
+				},
+				// axum drops the body of HEAD answers but keeps its length, as S3 does.
+				None => (meta, body).into_response()
+			}
+		},
+		_ => StatusCode::METHOD_NOT_ALLOWED.into_response()
+	}
+}
+
+#[tokio::test]
+async fn s3_storage_works_against_a_mock_service() {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let endpoint = format!("http://{}", listener.local_addr().unwrap());
+	let app = Router::new().fallback(mock_s3).with_state(MockBucket::default());
+	tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+	exercise(&S3Storage::new(S3Config {
+		endpoint,
+		region: "us-east-1".to_string(),
+		bucket: MOCK_BUCKET.to_string(),
+		access_key_id: "minioadmin".to_string(),
+		secret_access_key: "minioadmin".to_string()
+	})).await;
+}
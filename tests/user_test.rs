@@ -0,0 +1,554 @@
+mod common;
+
+use axum::http::{ Method, StatusCode };
+use serde_json::json;
+use sqlx::postgres::PgPool;
+
+use common::{ admin_token, app, download, login, seed_user, send, send_with, upload };
+
+#[sqlx::test]
+async fn created_user_can_log_in(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, body) = send(
+		&app,
+		Method::POST,
+		"/api/user",
+		Some(&token),
+		Some(json!({
+			"username": "kasir01",
+			"password": "rahasia123",
+			"full_name": "Kasir Satu",
+			"address": "Jl. Sudirman 10",
+			"phone_number": "081298765432",
+			"photo": "default_user.png",
+			"role": "cashier"
+		}))
+	).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+
+	login(&app, "kasir01", "rahasia123").await;
+}
+
+#[sqlx::test]
+async fn search_paginate_matches_username_or_full_name(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+	seed_user(&pg_pool, "siti", "rahasia123", "cashier").await;
+
+	let (status, body) = send(
+		&app,
+		Method::POST,
+		"/api/user/search-paginate",
+		Some(&token),
+		Some(json!({ "term": "bud", "page": 1 }))
+	).await;
+
+	assert_eq!(status, StatusCode::OK);
+	let data = body["data"].as_array().unwrap();
+	assert_eq!(data.len(), 1);
+	assert_eq!(data[0]["username"], "budi");
+	assert!(data[0].get("password").is_none());
+	assert_eq!(body["paginate"]["count"], 1);
+	assert_eq!(body["paginate"]["total_page"], 1);
+
+	// `_` is a LIKE wildcard, a term holding one must only match it literally.
+	seed_user(&pg_pool, "kasir_01", "rahasia123", "cashier").await;
+	seed_user(&pg_pool, "kasirx01", "rahasia123", "cashier").await;
+	let (status, body) = send(&app, Method::POST, "/api/user/search-paginate", Some(&token), Some(json!({ "term": "r_0", "page": 1 }))).await;
+	assert_eq!(status, StatusCode::OK);
+	let usernames: Vec<&str> = body["data"].as_array().unwrap().iter().map(|user| user["username"].as_str().unwrap()).collect();
+	assert_eq!(usernames, ["kasir_01"], "{body}");
+}
+
+#[sqlx::test]
+async fn update_and_delete_user(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let id = seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+
+	let (status, headers, _) = send_with(
+		&app,
+		Method::PUT,
+		&format!("/api/user/{id}"),
+		Some(&token),
+		&[("If-Match", "\"1\"")],
+		Some(json!({
+			"username": "budi",
+			"full_name": "Budi Santoso",
+			"address": "Jl. Sudirman 10",
+			"phone_number": "081298765432",
+			"photo": "default_user.png",
+			"role": "admin",
+			"language": "id"
+		}))
+	).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(headers["etag"], "\"2\"");
+
+	let role: String = sqlx::query_scalar("SELECT role FROM user_system WHERE id = $1").bind(id).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(role, "admin");
+
+	let (status, _, _) = send_with(&app, Method::DELETE, &format!("/api/user/{id}"), Some(&token), &[("If-Match", "\"2\"")], None).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let deleted: bool = sqlx::query_scalar("SELECT deleted_at IS NOT NULL FROM user_system WHERE id = $1").bind(id).fetch_one(&pg_pool).await.unwrap();
+	assert!(deleted);
+
+	let (status, _) = send(&app, Method::POST, "/api/auth/login", None, Some(json!({ "username": "budi", "password": "rahasia123" }))).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn live_usernames_are_unique(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let budi = seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+	let siti = seed_user(&pg_pool, "siti", "rahasia123", "cashier").await;
+	let body = json!({
+		"username": "budi",
+		"password": "rahasia123",
+		"full_name": "Budi Lain",
+		"phone_number": "081298765432",
+		"photo": "default_user.png",
+		"role": "cashier"
+	});
+
+	let (status, response) = send(&app, Method::POST, "/api/user", Some(&token), Some(body.clone())).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{response}");
+	assert_eq!(response["message"], "Username \"budi\" Sudah Dipakai.");
+
+	let (status, _, response) = send_with(&app, Method::PATCH, &format!("/api/user/{siti}"), Some(&token), &[("If-Match", "*")], Some(json!({ "username": "budi" }))).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{response}");
+
+	// Once deleted the username is free, until the deleted user is restored.
+	send_with(&app, Method::DELETE, &format!("/api/user/{budi}"), Some(&token), &[("If-Match", "*")], None).await;
+	let (status, response) = send(&app, Method::POST, "/api/user", Some(&token), Some(body)).await;
+	assert_eq!(status, StatusCode::CREATED, "{response}");
+
+	let (status, response) = send(&app, Method::POST, &format!("/api/user/{budi}/restore"), Some(&token), None).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{response}");
+}
+
+#[sqlx::test]
+async fn users_edit_their_own_profile_and_only_admins_read_others(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let admin = admin_token(&app, &pg_pool).await;
+	let id = seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+	let token = login(&app, "budi", "rahasia123").await;
+
+	let (status, body) = send(&app, Method::GET, "/api/user/me", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!((body["data"]["id"].as_i64(), body["data"]["language"].as_str()), (Some(id as i64), Some("id")));
+	assert!(body["data"].get("password").is_none());
+
+	let (status, _, body) = send_with(&app, Method::PATCH, "/api/user/me", Some(&token), &[("If-Match", "\"1\"")], Some(json!({ "language": "jv" }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+
+	let (status, headers, _) = send_with(
+		&app,
+		Method::PATCH,
+		"/api/user/me",
+		Some(&token),
+		&[("If-Match", "\"1\"")],
+		Some(json!({ "full_name": "Budi Santoso", "language": "en", "role": "admin" }))
+	).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(headers["etag"], "\"2\"");
+
+	// `role` is not part of the profile body and is ignored there, through the user route it needs an admin.
+	let (status, body) = send(&app, Method::GET, "/api/user/me", Some(&token), None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!((body["data"]["full_name"].as_str(), body["data"]["language"].as_str(), body["data"]["role"].as_str()), (Some("Budi Santoso"), Some("en"), Some("cashier")));
+
+	let (status, _, _) = send_with(&app, Method::PATCH, &format!("/api/user/{id}"), Some(&token), &[("If-Match", "*")], Some(json!({ "role": "admin" }))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+
+	let (status, _) = send(&app, Method::GET, &format!("/api/user/{id}"), Some(&token), None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+
+	let (status, body) = send(&app, Method::GET, &format!("/api/user/{id}"), Some(&admin), None).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(body["data"]["username"], "budi");
+	assert!(body["data"].get("password").is_none());
+}
+
+#[sqlx::test]
+async fn filter_paginate_reads_filters_from_query_string(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+	seed_user(&pg_pool, "siti", "rahasia123", "manager").await;
+	seed_user(&pg_pool, "joko", "rahasia123", "cashier").await;
+
+	let (status, body) = send(
+		&app,
+		Method::GET,
+		"/api/user/search-paginate?filter=role:in:cashier,manager&filter=username:ne:joko&sort_by=username",
+		Some(&token),
+		None
+	).await;
+
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let usernames: Vec<&str> = body["data"].as_array().unwrap().iter().map(|user| user["username"].as_str().unwrap()).collect();
+	assert_eq!(usernames, ["budi", "siti"]);
+	assert_eq!(body["paginate"]["count"], 2);
+
+	let (status, body) = send(&app, Method::GET, "/api/user/search-paginate?filter=password:like:a", Some(&token), None).await;
+	assert_eq!(status, StatusCode::BAD_REQUEST);
+	assert_eq!(body["success"], false);
+}
+
+#[sqlx::test]
+async fn create_reports_every_invalid_field(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let (status, body) = send(
+		&app,
+		Method::POST,
+		"/api/user",
+		Some(&token),
+		Some(json!({
+			"username": "kasir01",
+			"password": "rahasia123",
+			"full_name": "Kasir Satu",
+			"address": "Jl. Sudirman 10",
+			"phone_number": "0812-abc",
+			"photo": "default_user.png",
+			"role": "Super User"
+		}))
+	).await;
+
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	let fields: Vec<&str> = body["errors"].as_array().unwrap().iter().map(|error| error["field"].as_str().unwrap()).collect();
+	assert_eq!(fields, ["phone_number", "role"]);
+
+	let (status, body) = send(
+		&app,
+		Method::POST,
+		"/api/user",
+		Some(&token),
+		Some(json!({
+			"username": "kasir01",
+			"password": "rahasia123",
+			"full_name": "Kasir Satu",
+			"phone_number": "081298765432",
+			"photo": "default_user.png",
+			"role": "superuser"
+		}))
+	).await;
+
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	assert_eq!(body["message"], "Role Tidak Ditemukan.");
+}
+
+#[sqlx::test]
+async fn patch_writes_only_present_fields_and_null_clears_address(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let id = seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+	let uri = format!("/api/user/{id}");
+
+	let (status, _, body) = send_with(&app, Method::PATCH, &uri, Some(&token), &[("If-Match", "\"1\"")], Some(json!({ "full_name": "Budi S." }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let (full_name, address): (String, Option<String>) = sqlx::query_as("SELECT full_name, address FROM user_system WHERE id = $1").bind(id).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!((full_name.as_str(), address.as_deref()), ("Budi S.", Some("Jl. Merdeka 1")));
+
+	let (status, _, _) = send_with(&app, Method::PATCH, &uri, Some(&token), &[("If-Match", "\"2\"")], Some(json!({ "address": null }))).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let address: Option<String> = sqlx::query_scalar("SELECT address FROM user_system WHERE id = $1").bind(id).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(address, None);
+
+	let (status, _, body) = send_with(&app, Method::PATCH, &uri, Some(&token), &[("If-Match", "\"3\"")], Some(json!({ "full_name": null }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+	assert_eq!(body["success"], false);
+}
+
+#[sqlx::test]
+async fn put_replaces_every_field(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+	let id = seed_user(&pg_pool, "budi", "rahasia123", "cashier").await;
+
+	let (status, _, body) = send_with(
+		&app,
+		Method::PUT,
+		&format!("/api/user/{id}"),
+		Some(&token),
+		&[("If-Match", "\"1\"")],
+		Some(json!({
+			"username": "budi",
+			"full_name": "Budi Santoso",
+			"phone_number": "081298765432",
+			"photo": "default_user.png",
+			"role": "cashier",
+			"language": "en"
+		}))
+	).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	let (address, language): (Option<String>, String) = sqlx::query_as("SELECT address, language FROM user_system WHERE id = $1").bind(id).fetch_one(&pg_pool).await.unwrap();
+	assert_eq!((address, language.as_str()), (None, "en"));
+
+	let (status, _, _) = send_with(&app, Method::PUT, &format!("/api/user/{id}"), Some(&token), &[("If-Match", "*")], Some(json!({ "full_name": "Budi" }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[sqlx::test]
+async fn users_are_imported_in_one_transaction_and_exported_without_hashes(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let invalid = "username,password,full_name,phone_number,role\n\
+		kasir01,rahasia123,Budi,081234567890,\n\
+		kasir02,pendek,Sari,081234567891,cashier\n\
+		admin,rahasia123,Admin Baru,081234567892,admin\n\
+		kasir01,rahasia123,Budi Lagi,081234567893,cashier\n";
+	let (status, body) = upload(&app, "/api/user/import", Some(&token), "user.csv", invalid.as_bytes()).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	let fields: Vec<(i64, &str)> = body["data"]["errors"].as_array().unwrap().iter()
+		.map(|error| (error["row"].as_i64().unwrap(), error["errors"][0]["field"].as_str().unwrap()))
+		.collect();
+	assert_eq!(fields, [(3, "password"), (4, "username"), (5, "username")]);
+
+	let valid = "username,password,full_name,phone_number\nkasir01,rahasia123,Budi,081234567890\n";
+	let (status, body) = upload(&app, "/api/user/import", Some(&token), "user.csv", valid.as_bytes()).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+	login(&app, "kasir01", "rahasia123").await;
+
+	let (_, body) = send(&app, Method::GET, "/api/audit?filter=action:eq:import", Some(&token), None).await;
+	let id: i32 = sqlx::query_scalar("SELECT id FROM user_system WHERE username = 'kasir01'").fetch_one(&pg_pool).await.unwrap();
+	assert_eq!(body["paginate"]["count"], 1, "{body}");
+	assert_eq!(body["data"][0]["entity_id"], id.to_string());
+	assert_eq!(body["data"][0]["changes"]["username"]["after"], "kasir01");
+
+	// Cells a spreadsheet would run as a formula are exported as text.
+	sqlx::query("UPDATE user_system SET full_name = '=HYPERLINK(\"http://contoh.test\")', phone_number = '+6281234567890' WHERE username = 'kasir01'")
+	.execute(&pg_pool)
+	.await
+	.unwrap();
+
+	let (status, _, bytes) = download(&app, "/api/user/export", Some(&token)).await;
+	assert_eq!(status, StatusCode::OK);
+	let csv = String::from_utf8(bytes).unwrap();
+	assert!(csv.starts_with("id,username,full_name,address,phone_number,photo,role,created_at,updated_at\n"), "{csv}");
+	assert!(csv.contains(",kasir01,\"'=HYPERLINK(\"\"http://contoh.test\"\")\",,'+6281234567890,default_user.png,cashier,"), "{csv}");
+	assert!(!csv.contains("$2"), "{csv}");
+
+	let (status, headers, bytes) = download(&app, "/api/user/export?format=xlsx", Some(&token)).await;
+	assert_eq!(status, StatusCode::OK);
+	assert!(headers["content-type"].to_str().unwrap().contains("spreadsheetml"));
+
+	let mut workbook: calamine::Xlsx<_> = calamine::open_workbook_from_rs(std::io::Cursor::new(bytes)).unwrap();
+	let range = calamine::Reader::worksheet_range_at(&mut workbook, 0).unwrap().unwrap();
+	assert_eq!(range.height(), 3);
+	assert!(range.rows().all(|row| row.iter().all(|cell| !cell.to_string().starts_with("$2"))));
+}
+
+#[sqlx::test]
+async fn invited_users_activate_once_and_deactivation_blocks_their_tokens(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let admin = admin_token(&app, &pg_pool).await;
+	let invite = json!({ "username": "dewi", "full_name": "Dewi Lestari", "phone_number": "081234567890", "photo": "default_user.png", "role": "cashier" });
+
+	let (status, body) = send(&app, Method::POST, "/api/user/invite", Some(&admin), Some(invite.clone())).await;
+	assert_eq!(status, StatusCode::CREATED);
+	let id = body["data"]["user_id"].as_i64().unwrap();
+	let first_token = body["data"]["token"].as_str().unwrap().to_string();
+
+	let (status, _) = send(&app, Method::POST, "/api/user/invite", Some(&admin), Some(invite)).await;
+	assert_eq!(status, StatusCode::CONFLICT);
+
+	let (status, _) = send(&app, Method::POST, "/api/auth/login", None, Some(json!({ "username": "dewi", "password": "" }))).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+	let (status, _) = send(&app, Method::POST, "/api/auth/login", None, Some(json!({ "username": "dewi", "password": "apa saja" }))).await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+	// Resending replaces the first link.
+	let (status, body) = send(&app, Method::POST, &format!("/api/user/{id}/invite/resend"), Some(&admin), None).await;
+	assert_eq!(status, StatusCode::OK);
+	let token = body["data"]["token"].as_str().unwrap().to_string();
+
+	let activate = |token: &str| json!({ "token": token, "password": "dewi12345" });
+	let (status, _) = send(&app, Method::POST, "/api/auth/activate", None, Some(activate(&first_token))).await;
+	assert_eq!(status, StatusCode::NOT_FOUND);
+
+	sqlx::query("UPDATE user_invite SET expires_at = NOW() - INTERVAL '1 minute'").execute(&pg_pool).await.unwrap();
+	let (status, _) = send(&app, Method::POST, "/api/auth/activate", None, Some(activate(&token))).await;
+	assert_eq!(status, StatusCode::GONE);
+
+	let (_, body) = send(&app, Method::POST, &format!("/api/user/{id}/invite/resend"), Some(&admin), None).await;
+	let token = body["data"]["token"].as_str().unwrap().to_string();
+
+	let (status, body) = send(&app, Method::POST, "/api/auth/activate", None, Some(activate(&token))).await;
+	assert_eq!(status, StatusCode::OK);
+	assert_eq!(body["data"]["is_active"], true);
+	assert!(body["data"]["activated_at"].is_string());
+
+	let (status, _) = send(&app, Method::POST, "/api/auth/activate", None, Some(activate(&token))).await;
+	assert_eq!(status, StatusCode::GONE);
+	let (status, _) = send(&app, Method::POST, &format!("/api/user/{id}/invite/resend"), Some(&admin), None).await;
+	assert_eq!(status, StatusCode::CONFLICT);
+
+	let dewi = login(&app, "dewi", "dewi12345").await;
+
+	let (status, _, _) = send_with(&app, Method::POST, &format!("/api/user/{id}/deactivate"), Some(&admin), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let (status, _) = send(&app, Method::GET, "/api/user/me", Some(&dewi), None).await;
+	assert_eq!(status, StatusCode::UNAUTHORIZED);
+	let (status, _) = send(&app, Method::POST, "/api/auth/login", None, Some(json!({ "username": "dewi", "password": "dewi12345" }))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+
+	let (status, _, _) = send_with(&app, Method::POST, &format!("/api/user/{id}/reactivate"), Some(&admin), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::OK);
+	let (status, _) = send(&app, Method::GET, "/api/user/me", Some(&dewi), None).await;
+	assert_eq!(status, StatusCode::OK);
+
+	let (_, body) = send(&app, Method::GET, "/api/user/me", Some(&admin), None).await;
+	let admin_id = body["data"]["id"].as_i64().unwrap();
+	let (status, _, _) = send_with(&app, Method::POST, &format!("/api/user/{admin_id}/deactivate"), Some(&admin), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[sqlx::test]
+async fn phone_numbers_are_stored_in_e164_and_found_in_any_format(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let token = admin_token(&app, &pg_pool).await;
+
+	let user = json!({ "username": "dewi", "password": "rahasia123", "full_name": "Dewi Lestari", "phone_number": "0857-1122-3344", "photo": "default_user.png", "role": "cashier" });
+	let (status, body) = send(&app, Method::POST, "/api/user", Some(&token), Some(user.clone())).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+
+	for term in ["0857-1122", "+62 857 1122 3344", "6285711", "857112"] {
+		let (status, body) = send(&app, Method::POST, "/api/user/search-paginate", Some(&token), Some(json!({ "term": term, "page": 1 }))).await;
+		assert_eq!(status, StatusCode::OK, "{body}");
+		assert_eq!(body["paginate"]["count"], 1, "{term}");
+		assert_eq!(body["data"][0]["phone_number"], "+6285711223344");
+	}
+
+	let id: i32 = sqlx::query_scalar("SELECT id FROM user_system WHERE username = 'dewi'").fetch_one(&pg_pool).await.unwrap();
+	let (status, _, body) = send_with(&app, Method::PATCH, &format!("/api/user/{id}"), Some(&token), &[("If-Match", "*")], Some(json!({ "phone_number": "62 899 1234 5678" }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	let (_, body) = send(&app, Method::GET, &format!("/api/user/{id}"), Some(&token), None).await;
+	assert_eq!(body["data"]["phone_number"], "+6289912345678");
+
+	let mut landline = user;
+	landline["username"] = json!("rudi");
+	landline["phone_number"] = json!("021 5550 1234");
+	let (status, body) = send(&app, Method::POST, "/api/user", Some(&token), Some(landline)).await;
+	assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+	assert_eq!(body["errors"][0]["message"], "Prefix operator 0215 tidak dikenal.");
+}
+
+#[sqlx::test]
+async fn creating_a_user_with_another_role_needs_user_manage(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let new_user = |username: &str, role: &str| json!({
+		"username": username,
+		"password": "rahasia123",
+		"full_name": "Pengguna Baru",
+		"phone_number": "081298765432",
+		"photo": "default_user.png",
+		"role": role
+	});
+
+	seed_user(&pg_pool, "kasir", "rahasia123", "cashier").await;
+	let cashier = login(&app, "kasir", "rahasia123").await;
+	let (status, body) = send(&app, Method::POST, "/api/user", Some(&cashier), Some(new_user("penyusup", "admin"))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+
+	// Even with `user.create`, only the caller's own role may be handed out.
+	seed_user(&pg_pool, "supervisor", "rahasia123", "supervisor").await;
+	sqlx::query("INSERT INTO role_permission (role_id, permission_code) SELECT id, 'user.create' FROM role WHERE name = 'supervisor'")
+	.execute(&pg_pool)
+	.await
+	.unwrap();
+	let supervisor = login(&app, "supervisor", "rahasia123").await;
+
+	let (status, body) = send(&app, Method::POST, "/api/user", Some(&supervisor), Some(new_user("penyusup", "admin"))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+	assert_eq!(body["message"], "Anda Tidak Memiliki Izin user.manage.");
+
+	let (status, body) = send(&app, Method::POST, "/api/user", Some(&supervisor), Some(new_user("supervisor02", "supervisor"))).await;
+	assert_eq!(status, StatusCode::CREATED, "{body}");
+
+	let (status, _) = send(&app, Method::POST, "/api/auth/login", None, Some(json!({ "username": "penyusup", "password": "rahasia123" }))).await;
+	assert_ne!(status, StatusCode::ACCEPTED);
+}
+
+#[sqlx::test]
+async fn a_cashier_with_user_update_cannot_set_the_password_of_an_admin(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let admin = admin_token(&app, &pg_pool).await;
+	let admin_id: i32 = sqlx::query_scalar("SELECT id FROM user_system WHERE username = 'admin'").fetch_one(&pg_pool).await.unwrap();
+
+	seed_user(&pg_pool, "kasir", "rahasia123", "cashier").await;
+	sqlx::query("INSERT INTO role_permission (role_id, permission_code) SELECT id, 'user.update' FROM role WHERE name = 'cashier'")
+	.execute(&pg_pool)
+	.await
+	.unwrap();
+	let cashier = login(&app, "kasir", "rahasia123").await;
+
+	let uri = format!("/api/user/{admin_id}");
+	let (status, _, body) = send_with(&app, Method::PATCH, &uri, Some(&cashier), &[("If-Match", "*")], Some(json!({ "password": "diambilalih1" }))).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+
+	let (status, _) = send(&app, Method::POST, "/api/auth/login", None, Some(json!({ "username": "admin", "password": "diambilalih1" }))).await;
+	assert_ne!(status, StatusCode::ACCEPTED);
+
+	let (status, _, body) = send_with(&app, Method::PATCH, &uri, Some(&admin), &[("If-Match", "*")], Some(json!({ "password": "gantibaru12" }))).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+	login(&app, "admin", "gantibaru12").await;
+}
+
+#[sqlx::test]
+async fn a_cashier_with_only_user_delete_cannot_delete_an_admin(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let admin = admin_token(&app, &pg_pool).await;
+	let admin_id: i32 = sqlx::query_scalar("SELECT id FROM user_system WHERE username = 'admin'").fetch_one(&pg_pool).await.unwrap();
+
+	seed_user(&pg_pool, "kasir", "rahasia123", "cashier").await;
+	sqlx::query("INSERT INTO role_permission (role_id, permission_code) SELECT id, 'user.delete' FROM role WHERE name = 'cashier'")
+	.execute(&pg_pool)
+	.await
+	.unwrap();
+	let cashier = login(&app, "kasir", "rahasia123").await;
+
+	let uri = format!("/api/user/{admin_id}");
+	let (status, _, body) = send_with(&app, Method::DELETE, &uri, Some(&cashier), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+	assert_eq!(body["message"], "Anda Tidak Memiliki Izin user.manage.");
+
+	let (status, body) = send(&app, Method::GET, &uri, Some(&admin), None).await;
+	assert_eq!(status, StatusCode::OK, "{body}");
+
+	// Not even an admin may delete its own account.
+	let (status, _, body) = send_with(&app, Method::DELETE, &uri, Some(&admin), &[("If-Match", "*")], None).await;
+	assert_eq!(status, StatusCode::CONFLICT, "{body}");
+}
+
+#[sqlx::test]
+async fn user_management_routes_need_user_manage(pg_pool: PgPool) {
+	let app = app(&pg_pool);
+	let id = seed_user(&pg_pool, "kasir", "rahasia123", "cashier").await;
+	let cashier = login(&app, "kasir", "rahasia123").await;
+
+	for uri in [format!("/api/user/{id}/restore"), format!("/api/user/{id}/invite/resend"), format!("/api/user/{id}/deactivate"), format!("/api/user/{id}/reactivate")] {
+		let (status, _, body) = send_with(&app, Method::POST, &uri, Some(&cashier), &[("If-Match", "*")], None).await;
+		assert_eq!(status, StatusCode::FORBIDDEN, "{uri}: {body}");
+	}
+
+	let invite = json!({ "username": "penyusup", "full_name": "Penyusup", "phone_number": "081298765432", "photo": "default_user.png", "role": "cashier" });
+	let (status, body) = send(&app, Method::POST, "/api/user/invite", Some(&cashier), Some(invite)).await;
+	assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+	assert_eq!(body["message"], "Anda Tidak Memiliki Izin user.manage.");
+
+	let (status, _, _) = download(&app, "/api/user/export", Some(&cashier)).await;
+	assert_eq!(status, StatusCode::FORBIDDEN);
+}